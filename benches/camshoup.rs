@@ -0,0 +1,101 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use verenc::{bench_support::*, Precompute};
+
+fn keygen(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("keygen");
+    for size in MODULUS_SIZES.iter() {
+        let group = size.group();
+        for n in MESSAGE_COUNTS.iter() {
+            let id = BenchmarkId::new(format!("{}-bit", size.bits()), n);
+            bench.bench_with_input(id, n, |b, n| b.iter(|| group.new_keys(*n)));
+        }
+    }
+    bench.finish();
+}
+
+fn encrypt(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("encrypt");
+    for scenario in Scenario::all() {
+        bench.bench_function(scenario.label(), |b| b.iter(|| scenario.ciphertext()));
+    }
+    bench.finish();
+}
+
+fn encrypt_precomputed(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("encrypt_precomputed");
+    for scenario in Scenario::all() {
+        let key = scenario.encryption_key.precompute().unwrap();
+        bench.bench_function(scenario.label(), |b| {
+            b.iter(|| key.encrypt(&scenario.domain, &scenario.messages))
+        });
+    }
+    bench.finish();
+}
+
+fn precompute_tuned(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("precompute_tuned");
+    for scenario in Scenario::all() {
+        for num_ops in [1usize, 100, 10_000] {
+            let precompute = Precompute::tuned_for(num_ops);
+            let key = scenario
+                .encryption_key
+                .precompute_tuned(precompute)
+                .unwrap();
+            let id = BenchmarkId::new(
+                format!("{}-{}-bit-window", scenario.label(), key.window_bits()),
+                num_ops,
+            );
+            bench.bench_with_input(id, &precompute, |b, precompute| {
+                b.iter(|| scenario.encryption_key.precompute_tuned(*precompute))
+            });
+        }
+    }
+    bench.finish();
+}
+
+fn encrypt_and_prove(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("encrypt_and_prove");
+    for scenario in Scenario::all() {
+        bench.bench_function(scenario.label(), |b| {
+            b.iter(|| scenario.ciphertext_and_proof())
+        });
+    }
+    bench.finish();
+}
+
+fn verify(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("verify");
+    for scenario in Scenario::all() {
+        let (ciphertext, proof) = scenario.ciphertext_and_proof();
+        bench.bench_function(scenario.label(), |b| {
+            b.iter(|| {
+                scenario
+                    .encryption_key
+                    .verify(&scenario.domain, &ciphertext, &proof)
+            })
+        });
+    }
+    bench.finish();
+}
+
+fn decrypt(c: &mut Criterion) {
+    let mut bench = c.benchmark_group("decrypt");
+    for scenario in Scenario::all() {
+        let ciphertext = scenario.ciphertext();
+        bench.bench_function(scenario.label(), |b| {
+            b.iter(|| {
+                scenario
+                    .decryption_key
+                    .decrypt(&scenario.domain, &ciphertext)
+            })
+        });
+    }
+    bench.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = keygen, encrypt, encrypt_precomputed, precompute_tuned, encrypt_and_prove, verify, decrypt
+}
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Minimal WebAssembly verifier used to measure the size of a
+//! `wasm-verifier` build:
+//!
+//! ```sh
+//! cargo build --example wasm_verifier --target wasm32-unknown-unknown \
+//!     --profile wasm-verifier --no-default-features --features wasm-verifier
+//! ```
+//!
+//! The key, ciphertext and proof are passed in their checked hex encodings
+use std::slice;
+use verenc::{VerifiableCipherText, VerifiableEncryptionProof, VerificationKey};
+
+/// Allocate `len` bytes for the host to write an input into
+#[no_mangle]
+pub extern "C" fn verenc_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// Free a buffer returned by `verenc_alloc`
+///
+/// # Safety
+/// `ptr` must come from `verenc_alloc(len)` and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn verenc_free(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// 1 if the proof verifies, 0 otherwise
+///
+/// # Safety
+/// Each pointer must be valid for reads of its length
+#[no_mangle]
+pub unsafe extern "C" fn verenc_verify(
+    key: *const u8,
+    key_len: usize,
+    context: *const u8,
+    context_len: usize,
+    ciphertext: *const u8,
+    ciphertext_len: usize,
+    proof: *const u8,
+    proof_len: usize,
+) -> u32 {
+    let text = |ptr, len| std::str::from_utf8(slice::from_raw_parts(ptr, len)).ok();
+    let verified = (|| {
+        let key = VerificationKey::from_checked_hex(text(key, key_len)?).ok()?;
+        let ciphertext =
+            VerifiableCipherText::from_checked_hex(text(ciphertext, ciphertext_len)?).ok()?;
+        let proof = VerifiableEncryptionProof::from_checked_hex(text(proof, proof_len)?).ok()?;
+        let context = slice::from_raw_parts(context, context_len);
+        key.verify(context, &ciphertext, &proof).ok()
+    })();
+    u32::from(verified.is_some())
+}
@@ -0,0 +1,367 @@
+use crate::{
+    budget::charge, version::wire_mismatch, VerencError, VerifiableCipherText,
+    VerifiableEncryptionProof,
+};
+use std::io::{ErrorKind, Read, Write};
+use unknown_order::BigNumber;
+
+const MAGIC: &[u8; 8] = b"VEARCH01";
+const CHECKSUM_LEN: usize = 16;
+/// Larger records are rejected before allocating for them
+const MAX_RECORD_LEN: usize = 64 * 1024 * 1024;
+/// Length prefix marking the trailer instead of a record
+const TRAILER: u32 = u32::MAX;
+
+/// One entry in a ciphertext archive
+#[derive(Clone, Debug)]
+pub struct ArchiveRecord {
+    /// The ciphertext
+    pub ciphertext: VerifiableCipherText,
+    /// The proof for the ciphertext if one was stored
+    pub proof: Option<VerifiableEncryptionProof>,
+}
+
+/// Streams ciphertexts and proofs to `W` one record at a time.
+///
+/// The archive starts with a magic header followed by records framed as
+/// a 4 byte big-endian payload length, the payload and a 16 byte checksum.
+/// The checksum covers the record's position so dropped or reordered
+/// records are detected by `CiphertextArchiveReader`.
+/// `finish` ends the archive with a trailer holding the record count and
+/// a hash chained over every record's checksum, so an archive cut short,
+/// even at a record boundary, is detected too.
+#[derive(Debug)]
+pub struct CiphertextArchiveWriter<W: Write> {
+    writer: W,
+    records: u64,
+    head: [u8; CHECKSUM_LEN],
+}
+
+impl<W: Write> CiphertextArchiveWriter<W> {
+    /// Start a new archive by writing the header to `writer`
+    pub fn new(mut writer: W) -> Result<Self, VerencError> {
+        writer.write_all(MAGIC).map_err(io_error)?;
+        Ok(Self {
+            writer,
+            records: 0,
+            head: [0u8; CHECKSUM_LEN],
+        })
+    }
+
+    /// Append a ciphertext and optional proof
+    pub fn write(
+        &mut self,
+        ciphertext: &VerifiableCipherText,
+        proof: Option<&VerifiableEncryptionProof>,
+    ) -> Result<(), VerencError> {
+        let mut payload = Vec::new();
+        put_bignum(&mut payload, &ciphertext.u);
+        put_bignum(&mut payload, &ciphertext.v);
+        put_bignums(&mut payload, &ciphertext.e);
+        match proof {
+            None => payload.push(0),
+            Some(proof) => {
+                payload.push(1);
+                put_bignum(&mut payload, &proof.challenge);
+                put_bignum(&mut payload, &proof.r);
+                put_bignums(&mut payload, &proof.m);
+            }
+        }
+        if payload.len() > MAX_RECORD_LEN {
+            return Err(VerencError::Other(format!(
+                "Record length {} is more than the maximum {}",
+                payload.len(),
+                MAX_RECORD_LEN
+            )));
+        }
+        let checksum = checksum(self.records, &payload);
+        self.writer
+            .write_all(&(payload.len() as u32).to_be_bytes())
+            .and_then(|_| self.writer.write_all(&payload))
+            .and_then(|_| self.writer.write_all(&checksum))
+            .map_err(io_error)?;
+        self.head = chain(&self.head, &checksum);
+        self.records += 1;
+        Ok(())
+    }
+
+    /// The number of records written so far
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Write the trailer, flush and return the underlying writer.
+    /// Archives not ended this way fail to read as truncated
+    pub fn finish(mut self) -> Result<W, VerencError> {
+        self.writer
+            .write_all(&TRAILER.to_be_bytes())
+            .and_then(|_| self.writer.write_all(&self.records.to_be_bytes()))
+            .and_then(|_| {
+                self.writer
+                    .write_all(&trailer_checksum(self.records, &self.head))
+            })
+            .map_err(io_error)?;
+        self.writer.flush().map_err(io_error)?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads records written by `CiphertextArchiveWriter` from `R` one at a time
+/// so only the current record is held in memory.
+/// Records are charged to the `MemoryBudget` being enforced if there is one.
+/// Iterating yields an error for a corrupt record and then stops
+#[derive(Debug)]
+pub struct CiphertextArchiveReader<R: Read> {
+    reader: R,
+    records: u64,
+    head: [u8; CHECKSUM_LEN],
+    finished: bool,
+    failed: bool,
+}
+
+impl<R: Read> CiphertextArchiveReader<R> {
+    /// Open an archive by reading and checking the header from `reader`
+    pub fn new(mut reader: R) -> Result<Self, VerencError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).map_err(io_error)?;
+        if &magic != MAGIC {
+            return Err(VerencError::Other(
+                wire_mismatch(&magic, MAGIC)
+                    .unwrap_or_else(|| "Not a ciphertext archive".to_string()),
+            ));
+        }
+        Ok(Self {
+            reader,
+            records: 0,
+            head: [0u8; CHECKSUM_LEN],
+            finished: false,
+            failed: false,
+        })
+    }
+
+    /// Read the next record or `None` once the trailer has been checked
+    pub fn read_record(&mut self) -> Result<Option<ArchiveRecord>, VerencError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let mut len = [0u8; 4];
+        if !read_or_eof(&mut self.reader, &mut len)? {
+            return Err(VerencError::Other("Archive is truncated".to_string()));
+        }
+        let len = u32::from_be_bytes(len);
+        if len == TRAILER {
+            self.read_trailer()?;
+            return Ok(None);
+        }
+        let len = len as usize;
+        if len > MAX_RECORD_LEN {
+            return Err(VerencError::Other(format!(
+                "Record length {} is more than the maximum {}",
+                len, MAX_RECORD_LEN
+            )));
+        }
+        charge(len)?;
+        let mut payload = vec![0u8; len];
+        let mut expected = [0u8; CHECKSUM_LEN];
+        self.reader
+            .read_exact(&mut payload)
+            .and_then(|_| self.reader.read_exact(&mut expected))
+            .map_err(io_error)?;
+        if checksum(self.records, &payload) != expected {
+            return Err(VerencError::Other(format!(
+                "Record {} checksum does not match",
+                self.records
+            )));
+        }
+        self.head = chain(&self.head, &expected);
+
+        let mut cursor = Cursor::new(&payload);
+        let u = cursor.bignum()?;
+        let v = cursor.bignum()?;
+        let e = cursor.bignums()?;
+        let proof = match cursor.byte()? {
+            0 => None,
+            1 => Some(VerifiableEncryptionProof {
+                challenge: cursor.bignum()?,
+                r: cursor.bignum()?,
+                m: cursor.bignums()?,
+            }),
+            _ => {
+                return Err(VerencError::Other(format!(
+                    "Record {} is not valid",
+                    self.records
+                )))
+            }
+        };
+        if !cursor.is_finished() {
+            return Err(VerencError::Other(format!(
+                "Record {} has trailing bytes",
+                self.records
+            )));
+        }
+        self.records += 1;
+        Ok(Some(ArchiveRecord {
+            ciphertext: VerifiableCipherText { u, v, e },
+            proof,
+        }))
+    }
+
+    /// The number of records read so far
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    fn read_trailer(&mut self) -> Result<(), String> {
+        let mut count = [0u8; 8];
+        let mut expected = [0u8; CHECKSUM_LEN];
+        self.reader
+            .read_exact(&mut count)
+            .and_then(|_| self.reader.read_exact(&mut expected))
+            .map_err(io_error)?;
+        let count = u64::from_be_bytes(count);
+        if count != self.records {
+            return Err(format!(
+                "Archive has {} records but the trailer counts {}",
+                self.records, count
+            ));
+        }
+        if trailer_checksum(count, &self.head) != expected {
+            return Err("Archive trailer checksum does not match".to_string());
+        }
+        if read_or_eof(&mut self.reader, &mut [0u8; 1])? {
+            return Err("Archive has bytes after the trailer".to_string());
+        }
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for CiphertextArchiveReader<R> {
+    type Item = Result<ArchiveRecord, VerencError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let res = self.read_record();
+        self.failed = res.is_err();
+        res.transpose()
+    }
+}
+
+fn checksum(index: u64, payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let mut transcript = merlin::Transcript::new(b"ciphertext archive record");
+    transcript.append_u64(b"index", index);
+    transcript.append_message(b"payload", payload);
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    transcript.challenge_bytes(b"checksum", &mut checksum);
+    checksum
+}
+
+/// Fold a record's checksum into the running hash of every record
+fn chain(head: &[u8; CHECKSUM_LEN], checksum: &[u8; CHECKSUM_LEN]) -> [u8; CHECKSUM_LEN] {
+    let mut transcript = merlin::Transcript::new(b"ciphertext archive chain");
+    transcript.append_message(b"head", head);
+    transcript.append_message(b"checksum", checksum);
+    let mut next = [0u8; CHECKSUM_LEN];
+    transcript.challenge_bytes(b"head", &mut next);
+    next
+}
+
+fn trailer_checksum(count: u64, head: &[u8; CHECKSUM_LEN]) -> [u8; CHECKSUM_LEN] {
+    let mut transcript = merlin::Transcript::new(b"ciphertext archive trailer");
+    transcript.append_u64(b"count", count);
+    transcript.append_message(b"head", head);
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    transcript.challenge_bytes(b"checksum", &mut checksum);
+    checksum
+}
+
+/// Sign byte, 4 byte big-endian length then the magnitude
+pub(crate) fn put_bignum(buffer: &mut Vec<u8>, value: &BigNumber) {
+    let bytes = value.to_bytes();
+    buffer.push(u8::from(value < &BigNumber::zero()));
+    buffer.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(&bytes);
+}
+
+pub(crate) fn put_bignums(buffer: &mut Vec<u8>, values: &[BigNumber]) {
+    buffer.extend_from_slice(&(values.len() as u32).to_be_bytes());
+    for value in values {
+        put_bignum(buffer, value);
+    }
+}
+
+pub(crate) struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// True if every byte has been read
+    pub(crate) fn is_finished(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.pos < len {
+            return Err("Record is truncated".to_string());
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(len) as usize)
+    }
+
+    pub(crate) fn bignum(&mut self) -> Result<BigNumber, String> {
+        let negative = match self.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err("Invalid number sign".to_string()),
+        };
+        let len = self.len()?;
+        let value = BigNumber::from_slice(self.take(len)?);
+        Ok(if negative { -value } else { value })
+    }
+
+    pub(crate) fn bignums(&mut self) -> Result<Vec<BigNumber>, String> {
+        let count = self.len()?;
+        // Every number takes at least 5 bytes
+        if count > (self.bytes.len() - self.pos) / 5 {
+            return Err("Record is truncated".to_string());
+        }
+        (0..count).map(|_| self.bignum()).collect()
+    }
+}
+
+/// Fill `buffer` or return false if the reader is already at its end
+fn read_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<bool, String> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err("Archive is truncated".to_string()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(io_error(e)),
+        }
+    }
+    Ok(true)
+}
+
+fn io_error(e: std::io::Error) -> String {
+    e.to_string()
+}
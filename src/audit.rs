@@ -0,0 +1,106 @@
+use crate::VerencError;
+use std::sync::OnceLock;
+
+static SINK: OnceLock<Box<dyn AuditSink>> = OnceLock::new();
+
+/// An operation worth keeping a record of.
+///
+/// Keys are named by `VerificationKey::fingerprint` and ciphertexts by
+/// `VerifiableCipherText::digest`. No message, randomness or secret key
+/// value is ever part of an event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuditEvent<'a> {
+    /// A `DecryptionKey` was created
+    KeyGenerated {
+        /// The fingerprint of its verification key
+        key_fingerprint: [u8; 32],
+        /// The number of message slots
+        max_messages: usize,
+    },
+    /// Messages were encrypted
+    Encrypted {
+        /// The key encrypted to
+        key_fingerprint: [u8; 32],
+        /// The digest of the ciphertext
+        ciphertext_hash: [u8; 32],
+        /// The domain or nonce bound to the ciphertext
+        domain: &'a [u8],
+        /// The number of messages
+        messages: usize,
+    },
+    /// A proof of verifiable encryption was created
+    ProofIssued {
+        /// The key encrypted to
+        key_fingerprint: [u8; 32],
+        /// The digest of the ciphertext the proof is for
+        ciphertext_hash: [u8; 32],
+        /// The nonce the proof is bound to
+        nonce: &'a [u8],
+    },
+    /// A proof of verifiable encryption didn't verify
+    VerificationFailed {
+        /// The key verified with
+        key_fingerprint: [u8; 32],
+        /// The digest of the ciphertext
+        ciphertext_hash: [u8; 32],
+        /// The nonce verified with
+        nonce: &'a [u8],
+        /// Why it failed
+        error: &'a VerencError,
+    },
+    /// A ciphertext was decrypted, successfully or not
+    Decrypted {
+        /// The key decrypted with
+        key_fingerprint: [u8; 32],
+        /// The digest of the ciphertext
+        ciphertext_hash: [u8; 32],
+        /// The number of slots opened
+        messages: usize,
+        /// Why it failed if it did
+        error: Option<&'a VerencError>,
+    },
+}
+
+/// Receives an `AuditEvent` for every key generation, encryption,
+/// proof, failed verification and decryption in the process,
+/// e.g. to write them to append-only storage.
+///
+/// `record` is called synchronously on the thread doing the operation
+/// so keep it fast and hand slow work to a queue. It does nothing unless
+/// overridden. Implemented for closures taking an `AuditEvent`
+pub trait AuditSink: Send + Sync {
+    /// Record `event`
+    fn record(&self, _event: &AuditEvent<'_>) {}
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditEvent<'_>) + Send + Sync,
+{
+    fn record(&self, event: &AuditEvent<'_>) {
+        self(event)
+    }
+}
+
+/// Send every `AuditEvent` in the process to `sink` from now on.
+///
+/// The sink can be set once so nothing can swap it out after startup.
+/// Without a sink nothing is recorded and events cost nothing.
+/// Naming the key of a decryption computes its verification key
+/// which takes about as long as the decryption itself
+pub fn set_audit_sink(sink: Box<dyn AuditSink>) -> Result<(), VerencError> {
+    SINK.set(sink)
+        .map_err(|_| VerencError::Other("An audit sink is already set".to_string()))
+}
+
+/// True if a sink is set
+pub fn audit_enabled() -> bool {
+    SINK.get().is_some()
+}
+
+/// Give the event created by `event` to the sink if one is set
+pub(crate) fn record<'a, F: FnOnce() -> AuditEvent<'a>>(event: F) {
+    if let Some(sink) = SINK.get() {
+        sink.record(&event());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::fixtures::{from_hex, SAFE_PRIMES_1024, SAFE_PRIMES_1536};
+use crate::{DecryptionKey, EncryptionKey, Group, VerifiableCipherText, VerifiableEncryptionProof};
+use unknown_order::BigNumber;
+
+/// The message counts covered by the benchmark suite
+pub const MESSAGE_COUNTS: [usize; 3] = [1, 10, 50];
+
+/// The modulus sizes covered by the benchmark suite
+pub const MODULUS_SIZES: [ModulusSize; 2] = [ModulusSize::Bits2048, ModulusSize::Bits3072];
+
+/// Size of the group modulus `n` used by a benchmark scenario
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModulusSize {
+    /// `n` is 2048 bits
+    Bits2048,
+    /// `n` is 3072 bits
+    Bits3072,
+}
+
+impl ModulusSize {
+    /// The bit length of `n`
+    pub fn bits(&self) -> usize {
+        match self {
+            Self::Bits2048 => 2048,
+            Self::Bits3072 => 3072,
+        }
+    }
+
+    /// Create a group with this modulus size from fixed safe primes
+    /// so no time is spent generating primes
+    pub fn group(&self) -> Group {
+        let [p, q] = match self {
+            Self::Bits2048 => SAFE_PRIMES_1024,
+            Self::Bits3072 => SAFE_PRIMES_1536,
+        };
+        Group::with_safe_primes_unchecked(&from_hex(p), &from_hex(q))
+            .expect("fixed primes are valid")
+    }
+}
+
+/// The inputs for benchmarking one combination of modulus size and message count
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    /// The modulus size this scenario was created with
+    pub size: ModulusSize,
+    /// The group for the keys
+    pub group: Group,
+    /// The encryption key
+    pub encryption_key: EncryptionKey,
+    /// The decryption key
+    pub decryption_key: DecryptionKey,
+    /// The domain separation tag or nonce
+    pub domain: Vec<u8>,
+    /// The messages to encrypt
+    pub messages: Vec<BigNumber>,
+}
+
+impl Scenario {
+    /// Create a new scenario with random keys and messages
+    pub fn new(size: ModulusSize, num_messages: usize) -> Self {
+        let group = size.group();
+        let (encryption_key, decryption_key) = group
+            .new_keys(num_messages)
+            .expect("num_messages must be at least 1");
+        let messages = (0..num_messages)
+            .map(|_| group.random_for_encrypt())
+            .collect();
+        let domain = format!("verenc benchmark {} {}", size.bits(), num_messages).into_bytes();
+        Self {
+            size,
+            group,
+            encryption_key,
+            decryption_key,
+            domain,
+            messages,
+        }
+    }
+
+    /// Create all scenarios covered by the benchmark suite
+    pub fn all() -> Vec<Self> {
+        MODULUS_SIZES
+            .iter()
+            .flat_map(|size| MESSAGE_COUNTS.iter().map(move |n| Self::new(*size, *n)))
+            .collect()
+    }
+
+    /// A label suitable for naming a benchmark for this scenario
+    pub fn label(&self) -> String {
+        format!("{}-bit/{} messages", self.size.bits(), self.messages.len())
+    }
+
+    /// Encrypt the messages
+    pub fn ciphertext(&self) -> VerifiableCipherText {
+        self.encryption_key
+            .encrypt(&self.domain, &self.messages)
+            .expect("scenario messages are valid")
+    }
+
+    /// Encrypt the messages and prove it
+    pub fn ciphertext_and_proof(&self) -> (VerifiableCipherText, VerifiableEncryptionProof) {
+        self.encryption_key
+            .encrypt_and_prove(&self.domain, &self.messages)
+            .expect("scenario messages are valid")
+    }
+}
+
+/// `tilde - (challenge * value mod nn)` for each pair as proofs compute
+/// their responses, for checking and benchmarking the shared helper
+pub fn schnorr_responses(
+    group: &Group,
+    challenge: &BigNumber,
+    tildes: &[BigNumber],
+    values: &[BigNumber],
+) -> Vec<BigNumber> {
+    group.schnorr_responses(challenge, tildes, values)
+}
+
+/// `tilde - challenge * value` for each pair as proofs with unreduced
+/// responses compute them, for checking and benchmarking the shared helper
+pub fn unreduced_responses(
+    challenge: &BigNumber,
+    tildes: &[BigNumber],
+    values: &[BigNumber],
+) -> Vec<BigNumber> {
+    crate::group::unreduced_responses(challenge, tildes, values)
+}
+
+/// `tilde - challenge * value mod modulus` for each pair, for checking and
+/// benchmarking the shared helper
+pub fn modular_responses(
+    challenge: &BigNumber,
+    tildes: &[BigNumber],
+    values: &[BigNumber],
+    modulus: &BigNumber,
+) -> Vec<BigNumber> {
+    crate::group::modular_responses(challenge, tildes, values, modulus)
+}
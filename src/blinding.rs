@@ -0,0 +1,76 @@
+use crate::{sampling::random_below, DecryptionKey, Group, VerencError, VerifiableCipherText};
+use unknown_order::BigNumber;
+use zeroize::Zeroize;
+
+/// Bits of the random multiple of the order added to each exponent
+const BLINDING_BITS: usize = 64;
+
+/// Randomizes the secret exponents of each decryption by adding a random
+/// multiple of `n * phi(n)`, the exponent of the group mod n^2,
+/// so the exponents a power analysis sees differ every time.
+///
+/// The order is hidden so building this needs the primes of the group's modulus.
+/// Whoever holds it can factor the modulus, keep it with the `DecryptionKey`
+#[derive(Clone, Debug)]
+pub struct ExponentBlinding {
+    order: BigNumber,
+}
+
+impl Drop for ExponentBlinding {
+    fn drop(&mut self) {
+        self.order.zeroize();
+    }
+}
+
+impl ExponentBlinding {
+    /// Blinding for `group` with the primes `p` and `q` of its modulus
+    pub fn new(group: &Group, p: &BigNumber, q: &BigNumber) -> Result<Self, VerencError> {
+        if &(p * q) != group.n() {
+            return Err(VerencError::Other(
+                "Primes do not match the group modulus".to_string(),
+            ));
+        }
+        let one = BigNumber::one();
+        let mut phi = (p - &one) * (q - &one);
+        let order = group.n() * &phi;
+        phi.zeroize();
+        Ok(Self { order })
+    }
+
+    /// `exp + k * n * phi(n)` for a fresh random `k`
+    pub(crate) fn blind(&self, exp: &BigNumber) -> BigNumber {
+        let mut k = random_below(&(BigNumber::one() << BLINDING_BITS));
+        let blinded = exp + &k * &self.order;
+        k.zeroize();
+        blinded
+    }
+}
+
+impl DecryptionKey {
+    /// Decrypt like `decrypt` with every secret exponent blinded by `blinding`
+    pub fn decrypt_blinded(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        blinding: &ExponentBlinding,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        if self.x1.len() < ciphertext.e.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: ciphertext.e.len(),
+                max: self.x1.len(),
+            });
+        }
+        // Blinding only preserves powers of units
+        if ciphertext.u.invert(&self.group.nn).is_none() {
+            return Err(VerencError::InvalidCiphertext);
+        }
+        let slots = (0..ciphertext.e.len()).collect::<Vec<usize>>();
+        let result = self
+            .check_tag_blinded(domain, ciphertext, Some(blinding))
+            .and_then(|_| {
+                self.open_each_blinded(&slots, ciphertext, Some(blinding))
+                    .into_messages()
+            });
+        self.audited(ciphertext, result)
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{
+    de::{value::StrDeserializer, Error, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::cell::Cell;
+use std::fmt;
+use unknown_order::BigNumber;
+
+thread_local! {
+    /// Bytes remaining in the budget enforced on this thread, if any
+    static REMAINING: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// A cap on the bytes of big numbers one operation may deserialize or allocate.
+///
+/// While `enforce` runs, deserializing ciphertexts, proofs, keys and groups
+/// and reading archive records is charged against the budget and fails with
+/// an error once it's exhausted instead of growing without bound.
+/// Without a budget nothing is limited.
+/// The budget is per thread so work `f` moves to other threads,
+/// e.g. a rayon pool, is not charged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryBudget {
+    max_bytes: usize,
+}
+
+impl MemoryBudget {
+    /// Create a budget allowing `max_bytes`
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes }
+    }
+
+    /// The number of bytes allowed
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Run `f` with this budget enforced on the current thread.
+    /// A budget enforced inside `f` replaces this one until it returns
+    pub fn enforce<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let _guard = Guard(REMAINING.with(|r| r.replace(Some(self.max_bytes))));
+        f()
+    }
+}
+
+/// Restores the enclosing budget, even when unwinding
+struct Guard(Option<usize>);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        REMAINING.with(|r| r.set(self.0));
+    }
+}
+
+/// Charge `bytes` against the budget enforced on this thread if there is one
+pub(crate) fn charge(bytes: usize) -> Result<(), String> {
+    REMAINING.with(|r| match r.get() {
+        None => Ok(()),
+        Some(remaining) if bytes <= remaining => {
+            r.set(Some(remaining - bytes));
+            Ok(())
+        }
+        Some(_) => {
+            r.set(Some(0));
+            Err("Memory budget exceeded".to_string())
+        }
+    })
+}
+
+/// Deserialize a `BigNumber` charging its size to the budget.
+/// The size is taken from the hex encoding before it is decoded
+/// so an oversized number fails without being allocated
+pub(crate) fn bignum<'de, D>(deserializer: D) -> Result<BigNumber, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(BigNumberVisitor)
+}
+
+/// Deserialize a `Vec<BigNumber>` charging each element as it is read
+pub(crate) fn bignums<'de, D>(deserializer: D) -> Result<Vec<BigNumber>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_seq(BigNumbersVisitor)
+}
+
+/// Deserialize a `Vec<Vec<BigNumber>>` charging each element as it is read
+#[cfg(not(feature = "verify-only"))]
+pub(crate) fn bignum_rows<'de, D>(deserializer: D) -> Result<Vec<Vec<BigNumber>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let rows = Vec::<BudgetedRow>::deserialize(deserializer)?;
+    Ok(rows.into_iter().map(|row| row.0).collect())
+}
+
+struct Budgeted(BigNumber);
+
+impl<'de> Deserialize<'de> for Budgeted {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        bignum(deserializer).map(Self)
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+struct BudgetedRow(Vec<BigNumber>);
+
+#[cfg(not(feature = "verify-only"))]
+impl<'de> Deserialize<'de> for BudgetedRow {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        bignums(deserializer).map(Self)
+    }
+}
+
+struct BigNumberVisitor;
+
+impl<'de> Visitor<'de> for BigNumberVisitor {
+    type Value = BigNumber;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a hex encoded string")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        // Two hex digits per byte
+        charge(s.len().div_ceil(2)).map_err(E::custom)?;
+        BigNumber::deserialize(StrDeserializer::<E>::new(s))
+    }
+}
+
+struct BigNumbersVisitor;
+
+impl<'de> Visitor<'de> for BigNumbersVisitor {
+    type Value = Vec<BigNumber>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a sequence of big numbers")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(Budgeted(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// Tells long running work such as `VerificationKey::verify_cancellable`
+/// to stop. It's polled between exponentiations so a proof with many
+/// slots gives up soon after it's cancelled instead of running to the end.
+///
+/// Implemented for an `Instant`, which cancels once it's passed,
+/// an `AtomicBool` set from another thread and closures.
+/// wasm32-unknown-unknown has no clock so use one of the others there
+pub trait Cancellation {
+    /// True once the work should stop
+    fn is_cancelled(&self) -> bool;
+}
+
+impl Cancellation for Instant {
+    fn is_cancelled(&self) -> bool {
+        Instant::now() >= *self
+    }
+}
+
+impl Cancellation for AtomicBool {
+    fn is_cancelled(&self) -> bool {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl<F> Cancellation for F
+where
+    F: Fn() -> bool,
+{
+    fn is_cancelled(&self) -> bool {
+        self()
+    }
+}
@@ -0,0 +1,61 @@
+use crate::budget::charge;
+
+/// Bytes of the checksum appended to the payload
+const CHECKSUM_LEN: usize = 4;
+
+/// Encode `payload` as `tag:hex:checksum` where the checksum covers the tag
+/// and payload so a truncated or mistyped paste is rejected instead of
+/// decoding to a different value
+pub(crate) fn to_checked_hex(tag: &str, payload: &[u8]) -> String {
+    format!(
+        "{}:{}:{}",
+        tag,
+        encode_hex(payload),
+        encode_hex(&checksum(tag, payload))
+    )
+}
+
+/// Decode the output of `to_checked_hex` for `tag`.
+/// Whitespace is ignored so line-wrapped text can be pasted back
+pub(crate) fn from_checked_hex(tag: &str, s: &str) -> Result<Vec<u8>, String> {
+    charge(s.len())?;
+    let s = s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    let mut parts = s.split(':');
+    let (found, payload, expected) = match (parts.next(), parts.next(), parts.next(), parts.next())
+    {
+        (Some(found), Some(payload), Some(expected), None) => (found, payload, expected),
+        _ => return Err("Invalid checked hex, expected tag:hex:checksum".to_string()),
+    };
+    if found != tag {
+        return Err(format!("Expected a {} but found a {}", tag, found));
+    }
+    let payload = decode_hex(payload).ok_or_else(|| "Invalid hex".to_string())?;
+    let expected = decode_hex(expected).ok_or_else(|| "Invalid hex".to_string())?;
+    if expected != checksum(tag, &payload) {
+        return Err("Checksum does not match, the value may be truncated".to_string());
+    }
+    Ok(payload)
+}
+
+fn checksum(tag: &str, payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let mut transcript = merlin::Transcript::new(b"checked hex");
+    transcript.append_message(b"tag", tag.as_bytes());
+    transcript.append_message(b"payload", payload);
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    transcript.challenge_bytes(b"checksum", &mut checksum);
+    checksum
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -1,11 +1,26 @@
+#[cfg(feature = "compression")]
+use crate::compression::{compress, decompress};
+use crate::{
+    archive::{put_bignum, put_bignums, Cursor},
+    budget::charge,
+    checked_hex::{from_checked_hex, to_checked_hex},
+    Group, ReducedElement, VerencError,
+};
 use serde::{Deserialize, Serialize};
 use unknown_order::BigNumber;
 
+const CHECKED_HEX_TAG: &str = "veciphertext";
+#[cfg(feature = "compression")]
+const COMPRESSED_MAGIC: &[u8; 8] = b"VECTZS01";
+
 /// Ciphertext that can be used to prove its verifiably encrypted or decrypted
 #[derive(Clone, Debug, Serialize, Deserialize, Eq)]
 pub struct VerifiableCipherText {
+    #[serde(deserialize_with = "crate::budget::bignum")]
     pub(crate) u: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
     pub(crate) v: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
     pub(crate) e: Vec<BigNumber>,
 }
 
@@ -13,6 +28,122 @@ impl PartialEq for VerifiableCipherText {
     fn eq(&self, other: &Self) -> bool {
         self.u == other.u
             && self.v == other.v
+            && self.e.len() == other.e.len()
             && self.e.iter().zip(other.e.iter()).all(|(l, r)| l == r)
     }
 }
+
+impl VerifiableCipherText {
+    /// Create a ciphertext from its parts.
+    /// `v` is taken in absolute form so the result can be decrypted
+    pub fn new(u: BigNumber, e: Vec<BigNumber>, v: ReducedElement) -> Self {
+        Self {
+            u,
+            v: v.into_inner(),
+            e,
+        }
+    }
+
+    /// The number of messages encrypted in this ciphertext
+    pub fn num_messages(&self) -> usize {
+        self.e.len()
+    }
+
+    /// An encryption of the sum mod n of the messages of this and `other`,
+    /// slot by slot, under the key both were encrypted to, e.g. to tally
+    /// escrowed values without decrypting each one.
+    ///
+    /// The tag `v` binds `u` and `e` to the domain of one encryption, it's
+    /// what makes ciphertexts non-malleable, so the result can't carry one
+    /// and it's set to zero. Decrypt it with `DecryptionKey::decrypt_untagged`
+    /// and only combine ciphertexts whose proofs were checked first, since
+    /// the result can't be proven or verified itself. Sums of `n` or more wrap
+    pub fn add(&self, other: &Self, group: &Group) -> Result<Self, VerencError> {
+        if self.e.len() != other.e.len() {
+            return Err(VerencError::Other(format!(
+                "Ciphertexts have {} and {} messages",
+                self.e.len(),
+                other.e.len()
+            )));
+        }
+        Ok(Self {
+            u: group.mul(&self.u, &other.u),
+            v: BigNumber::zero(),
+            e: self
+                .e
+                .iter()
+                .zip(other.e.iter())
+                .map(|(l, r)| group.mul(l, r))
+                .collect(),
+        })
+    }
+
+    /// An encryption of every message times `k` mod n, with no tag like `add`.
+    /// A negative `k` negates the messages mod n
+    pub fn scalar_mul(&self, k: &BigNumber, group: &Group) -> Self {
+        Self {
+            u: group.pow(&self.u, k),
+            v: BigNumber::zero(),
+            e: self.e.iter().map(|e| group.pow(e, k)).collect(),
+        }
+    }
+
+    /// A 32 byte digest identifying this ciphertext
+    pub fn digest(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"verifiable ciphertext digest");
+        transcript.append_message(b"u", &self.u.to_bytes());
+        for e in &self.e {
+            transcript.append_message(b"e", &e.to_bytes());
+        }
+        transcript.append_message(b"v", &self.v.to_bytes());
+        let mut digest = [0u8; 32];
+        transcript.challenge_bytes(b"ciphertext digest", &mut digest);
+        digest
+    }
+
+    /// Encode as hex with a type tag and checksum for pasting into text,
+    /// e.g. tickets, where truncation would otherwise go unnoticed
+    pub fn to_checked_hex(&self) -> String {
+        to_checked_hex(CHECKED_HEX_TAG, &self.encode())
+    }
+
+    /// Decode the output of `to_checked_hex`
+    pub fn from_checked_hex(s: &str) -> Result<Self, VerencError> {
+        Ok(Self::decode(&from_checked_hex(CHECKED_HEX_TAG, s)?)?)
+    }
+
+    /// Encode compressed with zstd for archiving, worthwhile for wide ciphertexts.
+    /// `level` is the zstd level, 0 for the default
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    pub fn to_compressed_bytes(&self, level: i32) -> Result<Vec<u8>, VerencError> {
+        Ok(compress(COMPRESSED_MAGIC, &self.encode(), level)?)
+    }
+
+    /// Decode the output of `to_compressed_bytes`
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    pub fn from_compressed_bytes(bytes: &[u8]) -> Result<Self, VerencError> {
+        Ok(Self::decode(&decompress(COMPRESSED_MAGIC, bytes)?)?)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        put_bignum(&mut payload, &self.u);
+        put_bignum(&mut payload, &self.v);
+        put_bignums(&mut payload, &self.e);
+        payload
+    }
+
+    fn decode(payload: &[u8]) -> Result<Self, String> {
+        charge(payload.len())?;
+        let mut cursor = Cursor::new(payload);
+        let u = cursor.bignum()?;
+        let v = cursor.bignum()?;
+        let e = cursor.bignums()?;
+        if !cursor.is_finished() {
+            return Err("Ciphertext has trailing bytes".to_string());
+        }
+        Ok(Self { u, v, e })
+    }
+}
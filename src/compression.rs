@@ -0,0 +1,45 @@
+use crate::{budget::charge, version::wire_mismatch};
+use std::convert::TryFrom;
+
+/// Larger decompressed payloads are rejected before allocating for them
+const MAX_DECOMPRESSED_LEN: usize = 64 * 1024 * 1024;
+
+/// Compress `payload` with zstd after `magic`, which identifies the
+/// type so one compressed value is not decoded as another
+pub(crate) fn compress(magic: &[u8; 8], payload: &[u8], level: i32) -> Result<Vec<u8>, String> {
+    let compressed = zstd::bulk::compress(payload, level).map_err(|e| e.to_string())?;
+    let mut out = Vec::with_capacity(magic.len() + compressed.len());
+    out.extend_from_slice(magic);
+    out.extend_from_slice(&compressed);
+    Ok(out)
+}
+
+/// Decompress the output of `compress` for `magic`.
+/// The decompressed size is charged to the `MemoryBudget` being enforced
+/// if there is one so small inputs can't expand without bound
+pub(crate) fn decompress(magic: &[u8; 8], bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if bytes.len() < magic.len() || &bytes[..magic.len()] != magic {
+        let found = &bytes[..magic.len().min(bytes.len())];
+        return Err(wire_mismatch(found, magic)
+            .unwrap_or_else(|| "Not a compressed value of this type".to_string()));
+    }
+    let frame = &bytes[magic.len()..];
+    let len = zstd::zstd_safe::get_frame_content_size(frame)
+        .map_err(|_| "Invalid compressed value".to_string())?
+        .ok_or_else(|| "Compressed value does not record its size".to_string())?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= MAX_DECOMPRESSED_LEN)
+        .ok_or_else(|| {
+            format!(
+                "Decompressed length is more than the maximum {}",
+                MAX_DECOMPRESSED_LEN
+            )
+        })?;
+    charge(len)?;
+    let payload = zstd::bulk::decompress(frame, len).map_err(|e| e.to_string())?;
+    if payload.len() != len {
+        return Err("Invalid compressed value".to_string());
+    }
+    Ok(payload)
+}
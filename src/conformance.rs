@@ -0,0 +1,223 @@
+use crate::{
+    checked_hex::{decode_hex, encode_hex},
+    DecryptionKey, VerencError, VerifiableCipherText, VerifiableEncryptionProof, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use unknown_order::BigNumber;
+
+/// One test vector, stored as a JSON file.
+///
+/// Keys use this crate's serde encoding, the ciphertext and proof their
+/// checked hex encoding and the nonce and messages are lower case hex.
+/// Messages are big-endian without leading zeros, zero is the empty string
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConformanceVector {
+    /// What the vector exercises
+    #[serde(default)]
+    pub description: String,
+    /// The key the proof is verified with
+    pub verification_key: VerificationKey,
+    /// The key the ciphertext is decrypted with, if decryption is checked
+    #[serde(default)]
+    pub decryption_key: Option<DecryptionKey>,
+    /// The nonce or domain in hex
+    pub nonce: String,
+    /// The ciphertext
+    pub ciphertext: String,
+    /// The proof, if verification is checked
+    #[serde(default)]
+    pub proof: Option<String>,
+    /// Whether the proof should verify
+    #[serde(default)]
+    pub expect_verify: Option<bool>,
+    /// The messages decryption should return in hex
+    /// or `None` if decryption should fail
+    #[serde(default)]
+    pub expect_messages: Option<Vec<String>>,
+}
+
+/// The outcome of one check of a vector
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CheckResult {
+    /// `parse`, `verify` or `decrypt`
+    pub check: String,
+    /// True if the behavior matched the vector
+    pub passed: bool,
+    /// What happened when it didn't or the error returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// The outcome of every check of one vector
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct VectorResult {
+    /// The vector's file name
+    pub name: String,
+    /// The checks run
+    pub checks: Vec<CheckResult>,
+}
+
+impl VectorResult {
+    /// True if every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// The results of a directory of vectors
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ConformanceReport {
+    /// The crate version that produced the report
+    pub implementation: String,
+    /// Results in file name order
+    pub vectors: Vec<VectorResult>,
+}
+
+impl ConformanceReport {
+    /// True if every vector passed
+    pub fn passed(&self) -> bool {
+        self.vectors.iter().all(VectorResult::passed)
+    }
+
+    /// The number of vectors that failed
+    pub fn failures(&self) -> usize {
+        self.vectors.iter().filter(|v| !v.passed()).count()
+    }
+
+    /// The report as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report serializes")
+    }
+}
+
+impl ConformanceVector {
+    /// The vector as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("vector serializes")
+    }
+
+    /// Run the checks the vector has expectations for
+    pub fn check(&self) -> Vec<CheckResult> {
+        let mut checks = Vec::new();
+        let parsed = decode_hex(&self.nonce)
+            .ok_or_else(|| "Invalid nonce hex".to_string())
+            .and_then(|nonce| {
+                VerifiableCipherText::from_checked_hex(&self.ciphertext)
+                    .map(|ct| (nonce, ct))
+                    .map_err(String::from)
+            });
+        let (nonce, ciphertext) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                checks.push(failed("parse", e));
+                return checks;
+            }
+        };
+
+        if let Some(expected) = self.expect_verify {
+            let outcome = match &self.proof {
+                Some(proof) => VerifiableEncryptionProof::from_checked_hex(proof)
+                    .and_then(|p| self.verification_key.verify(&nonce, &ciphertext, &p))
+                    .map_err(String::from),
+                None => Err("Vector has no proof".to_string()),
+            };
+            checks.push(compare("verify", expected, outcome));
+        }
+
+        if let Some(dk) = &self.decryption_key {
+            let outcome = dk.decrypt(&nonce, &ciphertext).map_err(String::from);
+            let check = match (&self.expect_messages, outcome) {
+                (Some(expected), Ok(actual)) => {
+                    let actual = actual.iter().map(encode_message).collect::<Vec<_>>();
+                    let expected = expected
+                        .iter()
+                        .map(|m| m.trim_start_matches('0').to_ascii_lowercase())
+                        .collect::<Vec<_>>();
+                    if actual == expected {
+                        passed("decrypt")
+                    } else {
+                        failed(
+                            "decrypt",
+                            format!("Expected messages {:?} but got {:?}", expected, actual),
+                        )
+                    }
+                }
+                (Some(_), Err(e)) => failed("decrypt", format!("Expected messages but {}", e)),
+                (None, Ok(_)) => failed("decrypt", "Expected failure but decrypted".to_string()),
+                (None, Err(e)) => CheckResult {
+                    check: "decrypt".to_string(),
+                    passed: true,
+                    detail: Some(e),
+                },
+            };
+            checks.push(check);
+        }
+        checks
+    }
+}
+
+/// Check every `.json` file in `dir` as a `ConformanceVector`.
+/// A file that isn't a valid vector is reported as a failed `parse` check
+pub fn run_dir<P: AsRef<Path>>(dir: P) -> Result<ConformanceReport, VerencError> {
+    let mut paths = fs::read_dir(dir.as_ref())
+        .map_err(|e| e.to_string())?
+        .map(|entry| entry.map(|e| e.path()).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, String>>()?;
+    paths.retain(|p| p.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    let mut report = ConformanceReport {
+        implementation: format!("verenc {}", env!("CARGO_PKG_VERSION")),
+        vectors: Vec::with_capacity(paths.len()),
+    };
+    for path in paths {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let checks = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str::<ConformanceVector>(&s).map_err(|e| e.to_string()))
+            .map_or_else(|e| vec![failed("parse", e)], |v| v.check());
+        report.vectors.push(VectorResult { name, checks });
+    }
+    Ok(report)
+}
+
+/// Encode a message the way vectors do
+pub fn encode_message(m: &BigNumber) -> String {
+    encode_hex(&m.to_bytes())
+        .trim_start_matches('0')
+        .to_string()
+}
+
+fn compare(check: &str, expected: bool, outcome: Result<(), String>) -> CheckResult {
+    match (expected, outcome) {
+        (true, Ok(())) => passed(check),
+        (false, Err(e)) => CheckResult {
+            check: check.to_string(),
+            passed: true,
+            detail: Some(e),
+        },
+        (true, Err(e)) => failed(check, format!("Expected success but {}", e)),
+        (false, Ok(())) => failed(check, "Expected failure but succeeded".to_string()),
+    }
+}
+
+fn passed(check: &str) -> CheckResult {
+    CheckResult {
+        check: check.to_string(),
+        passed: true,
+        detail: None,
+    }
+}
+
+fn failed(check: &str, detail: String) -> CheckResult {
+    CheckResult {
+        check: check.to_string(),
+        passed: false,
+        detail: Some(detail),
+    }
+}
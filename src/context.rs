@@ -0,0 +1,78 @@
+use crate::Label;
+use std::borrow::Cow;
+
+/// The value binding a ciphertext to where it's used, called the label
+/// in the paper. The same context must be given to encrypt, prove,
+/// verify and decrypt. Older versions named it `domain` in some
+/// methods and `nonce` in others although both go into the same hash.
+///
+/// Every method taking a context accepts bytes, e.g. `b"payroll"` or
+/// `&Vec<u8>`, a `Label` or a `Context`. Build the context once and pass
+/// it everywhere so the values can't drift apart
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Context<'a>(Cow<'a, [u8]>);
+
+impl<'a> Context<'a> {
+    /// A context of `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self(Cow::Borrowed(bytes))
+    }
+
+    /// The bytes that are hashed
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// A context that owns its bytes
+    pub fn into_owned(self) -> Context<'static> {
+        Context(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl AsRef<[u8]> for Context<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<'a> From<&'a [u8]> for Context<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl<'a, const N: usize> From<&'a [u8; N]> for Context<'a> {
+    fn from(bytes: &'a [u8; N]) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for Context<'a> {
+    fn from(bytes: &'a Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<Vec<u8>> for Context<'static> {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Cow::Owned(bytes))
+    }
+}
+
+impl<'a, 'b: 'a> From<&'a Context<'b>> for Context<'a> {
+    fn from(context: &'a Context<'b>) -> Self {
+        Self::new(context.as_bytes())
+    }
+}
+
+impl From<&Label> for Context<'static> {
+    fn from(label: &Label) -> Self {
+        Self(Cow::Owned(label.to_bytes()))
+    }
+}
+
+impl From<Label> for Context<'static> {
+    fn from(label: Label) -> Self {
+        Self::from(&label)
+    }
+}
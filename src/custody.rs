@@ -0,0 +1,255 @@
+use crate::{
+    DecryptionKey, Group, VerencError, VerifiableCipherText, VerificationKey, WarrantAuthority,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+use zeroize::Zeroize;
+
+/// Signs the partial results of a custodian or a `SealedRecord`, e.g. with
+/// a key inside an HSM. Signatures are checked with a matching `WarrantAuthority`
+pub trait CustodySigner {
+    /// Sign `message`
+    fn sign(&self, message: &[u8; 32]) -> Vec<u8>;
+}
+
+/// The ciphertext integrity exponents x2 and x3 of a `DecryptionKey`.
+///
+/// Holding this share only allows checking ciphertext tags,
+/// it cannot open any message
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TagKeyShare {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) x2: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) x3: BigNumber,
+    pub(crate) group: Group,
+}
+
+/// The slot exponents x1 of a `DecryptionKey`.
+///
+/// Holding this share allows removing the blinding from each message
+/// but without a `TagAttestation` the messages are not released
+/// by `VerificationKey::decrypt_from_custody`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SlotKeyShare {
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) x1: Vec<BigNumber>,
+    pub(crate) group: Group,
+}
+
+/// A signed statement that a ciphertext tag is valid under a domain
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TagAttestation {
+    pub(crate) ciphertext_hash: [u8; 32],
+    pub(crate) domain: Vec<u8>,
+    pub(crate) signature: Vec<u8>,
+}
+
+/// The signed values u^{x1_i} for each message of a ciphertext
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SlotDecryption {
+    pub(crate) ciphertext_hash: [u8; 32],
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) values: Vec<BigNumber>,
+    pub(crate) signature: Vec<u8>,
+}
+
+impl Zeroize for TagKeyShare {
+    fn zeroize(&mut self) {
+        self.x2.zeroize();
+        self.x3.zeroize();
+    }
+}
+
+impl Zeroize for SlotKeyShare {
+    fn zeroize(&mut self) {
+        self.x1.iter_mut().for_each(|x| x.zeroize());
+    }
+}
+
+impl DecryptionKey {
+    /// Split the key so the integrity exponents and the slot exponents
+    /// can be held by different custodians, e.g. one inside an HSM.
+    /// Decrypting then needs a `TagAttestation` and a `SlotDecryption`
+    /// combined with `VerificationKey::decrypt_from_custody`
+    pub fn split_custody(&self) -> (TagKeyShare, SlotKeyShare) {
+        (
+            TagKeyShare {
+                x2: self.x2.clone(),
+                x3: self.x3.clone(),
+                group: self.group.clone(),
+            },
+            SlotKeyShare {
+                x1: self.x1.clone(),
+                group: self.group.clone(),
+            },
+        )
+    }
+}
+
+impl TagKeyShare {
+    /// Check the tag of `ciphertext` under `domain` like `DecryptionKey::decrypt`
+    /// and sign the result with `signer`
+    pub fn attest<S: CustodySigner>(
+        &self,
+        signer: &S,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<TagAttestation, VerencError> {
+        let group = &self.group;
+        let v = group.reduced(&ciphertext.v)?;
+        let hash = group.hash(&ciphertext.u, &ciphertext.e, domain);
+        let exp = (hash * &self.x3 + &self.x2) << 1;
+        if group.pow(&ciphertext.u, &exp) != group.pow(&v, &BigNumber::from(2)) {
+            return Err(VerencError::Other("u^2 != v^2".to_string()));
+        }
+        let mut attestation = TagAttestation {
+            ciphertext_hash: ciphertext.digest(),
+            domain: domain.to_vec(),
+            signature: Vec::new(),
+        };
+        attestation.signature = signer.sign(&attestation.message());
+        Ok(attestation)
+    }
+}
+
+impl SlotKeyShare {
+    /// Compute u^{x1_i} for each message of `ciphertext`
+    /// and sign the result with `signer`
+    pub fn partial_decrypt<S: CustodySigner>(
+        &self,
+        signer: &S,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<SlotDecryption, VerencError> {
+        if self.x1.len() < ciphertext.e.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                ciphertext.e.len(),
+                self.x1.len()
+            )));
+        }
+        let values = self
+            .x1
+            .iter()
+            .take(ciphertext.e.len())
+            .map(|x| self.group.pow(&ciphertext.u, x))
+            .collect();
+        let mut partial = SlotDecryption {
+            ciphertext_hash: ciphertext.digest(),
+            values,
+            signature: Vec::new(),
+        };
+        partial.signature = signer.sign(&partial.message());
+        Ok(partial)
+    }
+}
+
+impl TagAttestation {
+    /// The digest of the ciphertext that was checked
+    pub fn ciphertext_hash(&self) -> &[u8; 32] {
+        &self.ciphertext_hash
+    }
+
+    /// The domain the tag was checked under
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+
+    /// The custodian's signature
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// The message the custodian signs
+    pub fn message(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"custody tag attestation");
+        transcript.append_message(b"ciphertext_hash", &self.ciphertext_hash);
+        transcript.append_message(b"domain", &self.domain);
+        let mut message = [0u8; 32];
+        transcript.challenge_bytes(b"attestation message", &mut message);
+        message
+    }
+}
+
+impl SlotDecryption {
+    /// The digest of the ciphertext that was partially decrypted
+    pub fn ciphertext_hash(&self) -> &[u8; 32] {
+        &self.ciphertext_hash
+    }
+
+    /// The custodian's signature
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// The message the custodian signs
+    pub fn message(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"custody slot decryption");
+        transcript.append_message(b"ciphertext_hash", &self.ciphertext_hash);
+        transcript.append_u64(b"values.count", self.values.len() as u64);
+        for value in &self.values {
+            transcript.append_message(b"values", &value.to_bytes());
+        }
+        let mut message = [0u8; 32];
+        transcript.challenge_bytes(b"slot decryption message", &mut message);
+        message
+    }
+}
+
+impl VerificationKey {
+    /// Combine the results of both custodians into the messages.
+    ///
+    /// The attestation must be signed by `tag_custodian` for `ciphertext`
+    /// under `domain` and the slot values signed by `slot_custodian`
+    pub fn decrypt_from_custody<T: WarrantAuthority, S: WarrantAuthority>(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        attestation: &TagAttestation,
+        tag_custodian: &T,
+        partial: &SlotDecryption,
+        slot_custodian: &S,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        let digest = ciphertext.digest();
+        if attestation.ciphertext_hash != digest || partial.ciphertext_hash != digest {
+            return Err(VerencError::Other(
+                "Custody results are for a different ciphertext".to_string(),
+            ));
+        }
+        if attestation.domain != domain {
+            return Err(VerencError::Other(
+                "Tag attestation is for a different domain".to_string(),
+            ));
+        }
+        if !tag_custodian.verify_warrant(&attestation.message(), &attestation.signature) {
+            return Err(VerencError::Other(
+                "Invalid tag attestation signature".to_string(),
+            ));
+        }
+        if !slot_custodian.verify_warrant(&partial.message(), &partial.signature) {
+            return Err(VerencError::Other(
+                "Invalid slot decryption signature".to_string(),
+            ));
+        }
+        if partial.values.len() != ciphertext.e.len() {
+            return Err(VerencError::Other(format!(
+                "Number of slot values {} != number of messages {}",
+                partial.values.len(),
+                ciphertext.e.len()
+            )));
+        }
+
+        let group = &self.group;
+        let mut messages = Vec::with_capacity(ciphertext.e.len());
+        for (i, (ee, value)) in ciphertext.e.iter().zip(partial.values.iter()).enumerate() {
+            let inv = value
+                .invert(&group.nn)
+                .ok_or_else(|| "invalid ciphertext".to_string())?;
+            let m = group
+                .paillier_extract(&group.mul(&inv, ee))
+                .ok_or_else(|| format!("decryption failed for message {}", i))?;
+            messages.push(m);
+        }
+        Ok(messages)
+    }
+}
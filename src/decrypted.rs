@@ -0,0 +1,45 @@
+use crate::VerencError;
+use unknown_order::BigNumber;
+
+/// The result of decrypting each slot of a ciphertext on its own,
+/// returned by `DecryptionKey::decrypt_at_indices` and `DecryptionKey::decrypt_each`.
+///
+/// The tag `v` covers every slot so a ciphertext changed after encryption
+/// is still rejected as a whole. This reports slots that were malformed
+/// when encrypted, e.g. by a faulty encryptor, without losing the others
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecryptedMessages {
+    pub(crate) slots: Vec<(usize, Result<BigNumber, VerencError>)>,
+}
+
+impl DecryptedMessages {
+    /// The number of slots
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// True if the ciphertext had no messages
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// The result for key slot `slot` if the ciphertext has one
+    pub fn get(&self, slot: usize) -> Option<&Result<BigNumber, VerencError>> {
+        self.slots.iter().find(|(s, _)| *s == slot).map(|(_, r)| r)
+    }
+
+    /// Every key slot with its result in ciphertext order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Result<BigNumber, VerencError>)> {
+        self.slots.iter().map(|(s, r)| (*s, r))
+    }
+
+    /// True if every slot decrypted
+    pub fn is_complete(&self) -> bool {
+        self.slots.iter().all(|(_, r)| r.is_ok())
+    }
+
+    /// The messages in ciphertext order or the first slot's error
+    pub fn into_messages(self) -> Result<Vec<BigNumber>, VerencError> {
+        self.slots.into_iter().map(|(_, r)| r).collect()
+    }
+}
@@ -1,4 +1,18 @@
-use crate::{Group, VerifiableCipherText};
+use crate::{
+    audit::{self, AuditEvent},
+    encryptionkeyref::first_slots,
+    group::{ct_eq, unreduced_responses},
+    hierarchy::derive_child,
+    proof_element::{element_chunk_count, element_chunk_width, join_chunks},
+    proof_key_generation::KEY_GENERATION_BLINDING_SLACK,
+    sampling::random_below,
+    BatchDecryptionRecord, BatchItemError, ChildDecryptionRecord, Context, DecryptedMessages,
+    DecryptionProof, DecryptionRecord, EncryptionKey, ExponentBlinding, Group, KeyGenerationProof,
+    ReencryptionProof, VerencError, VerifiableCipherText, VerificationKey, Warrant,
+    WarrantAuthority,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use unknown_order::BigNumber;
 use zeroize::Zeroize;
@@ -8,8 +22,11 @@ use zeroize::Zeroize;
 /// <https://shoup.net/papers/verenc.pdf>
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DecryptionKey {
+    #[serde(deserialize_with = "crate::budget::bignums")]
     pub(crate) x1: Vec<BigNumber>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
     pub(crate) x2: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
     pub(crate) x3: BigNumber,
     pub(crate) group: Group,
 }
@@ -23,45 +40,442 @@ impl Zeroize for DecryptionKey {
 }
 
 impl DecryptionKey {
-    /// Create a new random decryption key
+    /// Create a new random decryption key.
+    ///
+    /// A key for zero messages is allowed and encrypts only `u` and `v`,
+    /// a verifiable commitment to the encryption randomness
     pub fn random(num_messages: usize, group: &Group) -> Option<Self> {
-        if num_messages < 1 {
-            return None;
-        }
-
         let mut x1 = Vec::with_capacity(num_messages);
         for _ in 0..num_messages {
-            let x = BigNumber::random(&group.n2d4);
+            let x = random_below(&group.n2d4);
             x1.push(x);
         }
-        let x2 = BigNumber::random(&group.n2d4);
-        let x3 = BigNumber::random(&group.n2d4);
-        Some(Self {
+        let x2 = random_below(&group.n2d4);
+        let x3 = random_below(&group.n2d4);
+        let key = Self {
             x1,
             x2,
             x3,
             group: group.clone(),
+        };
+        audit::record(|| AuditEvent::KeyGenerated {
+            key_fingerprint: VerificationKey::from(&key).fingerprint(),
+            max_messages: num_messages,
+        });
+        Some(key)
+    }
+
+    /// A new version of this key with `additional` random slots appended.
+    /// The existing slots and the tag exponents are kept so everything
+    /// encrypted to this key still decrypts with the new one
+    pub fn extend_slots(&self, additional: usize) -> Result<Self, VerencError> {
+        if additional == 0 {
+            return Err(VerencError::Other("No slots to add".to_string()));
+        }
+        let mut x1 = self.x1.clone();
+        x1.extend((0..additional).map(|_| random_below(&self.group.n2d4)));
+        Ok(Self {
+            x1,
+            x2: self.x2.clone(),
+            x3: self.x3.clone(),
+            group: self.group.clone(),
         })
     }
 
+    /// Prove `y2` and `y3` of this key's `VerificationKey` were generated
+    /// from known exponents in the right range
+    pub fn prove_key_generation(&self, nonce: &[u8]) -> KeyGenerationProof {
+        let group = &self.group;
+        let vk = VerificationKey::from(self);
+        let bound = &group.n2d4 << KEY_GENERATION_BLINDING_SLACK;
+        let t2 = random_below(&bound);
+        let t3 = random_below(&bound);
+        let commitments = [group.g_pow(&(&t2 << 1)), group.g_pow(&(&t3 << 1))];
+        let challenge = vk.key_generation_challenge(nonce, &commitments);
+        // Not reduced so the verifier can bound the exponents
+        KeyGenerationProof {
+            x2: t2 - &challenge * &self.x2,
+            x3: t3 - &challenge * &self.x3,
+            challenge,
+        }
+    }
+
     /// Decrypt verifiable ciphertext as described in section 3.2 in
     /// <https://shoup.net/papers/verenc.pdf>
-    pub fn decrypt(
+    pub fn decrypt<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        let context = context.into();
+        let domain = context.as_bytes();
+        if self.x1.len() < ciphertext.e.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: ciphertext.e.len(),
+                max: self.x1.len(),
+            });
+        }
+        self.decrypt_in_slots(domain, &first_slots(ciphertext.e.len()), ciphertext)
+    }
+
+    /// Decrypt a ciphertext created by `EncryptionKey::encrypt_and_prove_element`
+    /// and reconstruct the element less than `modulus` from its chunks
+    pub fn decrypt_element<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        modulus: &BigNumber,
+    ) -> Result<BigNumber, VerencError> {
+        let width = element_chunk_width(&self.group.n).map_err(VerencError::Other)?;
+        if modulus <= &BigNumber::one() || ciphertext.e.len() != element_chunk_count(modulus, width)
+        {
+            return Err(VerencError::InvalidCiphertext);
+        }
+        // Chunks above n / 2 were encrypted as negative numbers
+        let half = &self.group.n >> 1;
+        let chunks = self
+            .decrypt(context, ciphertext)?
+            .into_iter()
+            .map(|m| if m > half { m - &self.group.n } else { m })
+            .collect::<Vec<_>>();
+        let element = join_chunks(&chunks, width);
+        if element < BigNumber::zero() || &element >= modulus {
+            return Err(VerencError::InvalidCiphertext);
+        }
+        Ok(element)
+    }
+
+    /// Decrypt every slot of `ciphertext` on its own so one malformed
+    /// slot doesn't hide the others. The tag is still checked first
+    pub fn decrypt_each<'c>(
         &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<DecryptedMessages, VerencError> {
+        let context = context.into();
+        let domain = context.as_bytes();
+        if self.x1.len() < ciphertext.e.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: ciphertext.e.len(),
+                max: self.x1.len(),
+            });
+        }
+        let slots = (0..ciphertext.e.len()).collect::<Vec<usize>>();
+        let result = self
+            .check_tag(domain, ciphertext)
+            .map(|_| self.open_each(&slots, ciphertext));
+        self.audited(ciphertext, result)
+    }
+
+    /// Decrypt a ciphertext created by `EncryptionKey::encrypt_at_indices`.
+    /// The slots are returned in the same order as `indices`, each
+    /// with its own result
+    pub fn decrypt_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        indices: &[usize],
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<DecryptedMessages, VerencError> {
+        let context = context.into();
+        let domain = context.as_bytes();
+        if indices.len() != ciphertext.e.len() {
+            return Err(VerencError::IndexCountMismatch {
+                indices: indices.len(),
+                messages: ciphertext.e.len(),
+            });
+        }
+        for (i, slot) in indices.iter().enumerate() {
+            if *slot >= self.x1.len() {
+                return Err(VerencError::IndexOutOfRange {
+                    index: *slot,
+                    max: self.x1.len(),
+                });
+            }
+            if i > 0 && indices[i - 1] >= *slot {
+                return Err(VerencError::IndexNotIncreasing { index: *slot });
+            }
+        }
+        let result = self
+            .check_tag(domain, ciphertext)
+            .map(|_| self.open_each(indices, ciphertext));
+        self.audited(ciphertext, result)
+    }
+
+    /// Decrypt a ciphertext without checking its tag `v`,
+    /// e.g. the output of `EncryptionKey::rerandomize` or `VerifiableCipherText::add`.
+    /// The tag provides chosen ciphertext security so only use this
+    /// when the ciphertext is known to have been modified on purpose.
+    pub fn decrypt_untagged(
+        &self,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        if self.x1.len() < ciphertext.e.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: ciphertext.e.len(),
+                max: self.x1.len(),
+            });
+        }
+        let slots = (0..ciphertext.e.len()).collect::<Vec<usize>>();
+        let result = self.open_slots(&slots, ciphertext);
+        self.audited(ciphertext, result)
+    }
+
+    /// Derive the child key at `path`. Each step is hardened so the child
+    /// key can be given out without revealing this key while this key
+    /// can still open everything encrypted to the child.
+    /// Give children their `EncryptionKey::from(&child)` for encrypting
+    pub fn derive_child(&self, path: &[u32]) -> DecryptionKey {
+        path.iter()
+            .fold(self.clone(), |key, index| derive_child(&key, *index))
+    }
+
+    /// Decrypt a ciphertext made with the key of the child at `path`
+    /// and return a record of which child was opened
+    pub fn decrypt_for_child(
+        &self,
+        path: &[u32],
         domain: &[u8],
         ciphertext: &VerifiableCipherText,
+    ) -> Result<(Vec<BigNumber>, ChildDecryptionRecord), VerencError> {
+        let msgs = self.derive_child(path).decrypt(domain, ciphertext)?;
+        Ok((
+            msgs,
+            ChildDecryptionRecord {
+                path: path.to_vec(),
+                ciphertext_hash: ciphertext.digest(),
+                domain: domain.to_vec(),
+            },
+        ))
+    }
+
+    /// Decrypt `ciphertext` only if `warrant` names it and is signed by `authority`.
+    /// The record proves the messages are the correct decryption and binds the warrant
+    /// so the opening can be shown to be authorized.
+    /// Check it with `VerificationKey::verify_decryption_record`
+    pub fn decrypt_with_warrant<A: WarrantAuthority>(
+        &self,
+        authority: &A,
+        warrant: &Warrant,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<DecryptionRecord, VerencError> {
+        warrant.check(authority, ciphertext)?;
+        let messages = self.decrypt(&warrant.label, ciphertext)?;
+
+        let group = &self.group;
+        // Hides c * x1 where c is 256 bits
+        let bound = &group.n2d4 << (256 + 128);
+        let mut tildes = Vec::with_capacity(messages.len());
+        let mut test_values = Vec::with_capacity(messages.len());
+        for ((e, m), x) in ciphertext.e.iter().zip(messages.iter()).zip(self.x1.iter()) {
+            if group.mul(&group.pow(&ciphertext.u, x), &group.h_pow(m)) != *e {
+                return Err(VerencError::Other(
+                    "ciphertext is not well formed".to_string(),
+                ));
+            }
+            let tilde = random_below(&bound);
+            test_values.push((group.g_pow(&tilde), group.pow(&ciphertext.u, &tilde)));
+            tildes.push(tilde);
+        }
+        let challenge = VerificationKey::from(self).decryption_challenge(
+            warrant,
+            ciphertext,
+            &messages,
+            &test_values,
+        );
+        let x = unreduced_responses(&challenge, &tildes, &self.x1);
+        Ok(DecryptionRecord {
+            warrant: warrant.clone(),
+            messages,
+            proof: DecryptionProof { challenge, x },
+        })
+    }
+
+    /// Decrypt each of `ciphertexts` only if the warrant at the same position
+    /// names it and is signed by `authority`, with one proof covering every
+    /// decryption. The proof has one response per key slot however many
+    /// ciphertexts there are.
+    /// Check it with `VerificationKey::verify_batch_decryption_record`
+    pub fn decrypt_batch_with_warrants<A: WarrantAuthority>(
+        &self,
+        authority: &A,
+        warrants: &[Warrant],
+        ciphertexts: &[VerifiableCipherText],
+    ) -> Result<BatchDecryptionRecord, VerencError> {
+        if warrants.len() != ciphertexts.len() {
+            return Err(VerencError::Other(format!(
+                "Number of warrants {} != number of ciphertexts {}",
+                warrants.len(),
+                ciphertexts.len()
+            )));
+        }
+        let group = &self.group;
+        let mut messages = Vec::with_capacity(ciphertexts.len());
+        for (i, (warrant, ciphertext)) in warrants.iter().zip(ciphertexts.iter()).enumerate() {
+            let opened = self
+                .open_with_warrant(authority, warrant, ciphertext)
+                .map_err(|e| BatchItemError::new(i, ciphertext.digest(), &warrant.label, e))?;
+            messages.push(opened);
+        }
+
+        let vk = VerificationKey::from(self);
+        let (mut transcript, aggregates) =
+            vk.batch_decryption_statement(warrants, ciphertexts, &messages)?;
+        // Hides c * x1 where c is 256 bits
+        let bound = &group.n2d4 << (256 + 128);
+        let tildes = aggregates
+            .iter()
+            .map(|_| random_below(&bound))
+            .collect::<Vec<_>>();
+        let test_values = tildes
+            .iter()
+            .zip(aggregates.iter())
+            .map(|(tilde, (u, _))| (group.g_pow(tilde), group.pow(u, tilde)))
+            .collect::<Vec<_>>();
+        let challenge = VerificationKey::batch_decryption_challenge(&mut transcript, &test_values);
+        let x = unreduced_responses(&challenge, &tildes, &self.x1);
+        Ok(BatchDecryptionRecord {
+            warrants: warrants.to_vec(),
+            messages,
+            proof: DecryptionProof { challenge, x },
+        })
+    }
+
+    fn open_with_warrant<A: WarrantAuthority>(
+        &self,
+        authority: &A,
+        warrant: &Warrant,
+        ciphertext: &VerifiableCipherText,
     ) -> Result<Vec<BigNumber>, String> {
-        if self.x1.len() < ciphertext.e.len() {
-            return Err(format!(
-                "Number of messages {} is more than supported by this key {}",
-                ciphertext.e.len(),
-                self.x1.len()
-            ));
+        warrant.check(authority, ciphertext)?;
+        let messages = self.decrypt(&warrant.label, ciphertext)?;
+        let group = &self.group;
+        for ((e, m), x) in ciphertext.e.iter().zip(messages.iter()).zip(self.x1.iter()) {
+            if group.mul(&group.pow(&ciphertext.u, x), &group.h_pow(m)) != *e {
+                return Err("ciphertext is not well formed".to_string());
+            }
         }
+        Ok(messages)
+    }
 
-        if ciphertext.v != self.group.abs(&ciphertext.v) {
-            return Err("Absolute check failed".to_string());
+    /// Decrypt `ciphertext` and encrypt the messages again to `new_key`
+    /// under `new_domain`, e.g. after rotating keys, with a proof both
+    /// ciphertexts hold the same messages
+    pub fn reencrypt(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        new_key: &EncryptionKey,
+        new_domain: &[u8],
+    ) -> Result<(VerifiableCipherText, ReencryptionProof), VerencError> {
+        let messages = self.decrypt(domain, ciphertext)?;
+        let group = &self.group;
+        for ((e, m), x) in ciphertext.e.iter().zip(messages.iter()).zip(self.x1.iter()) {
+            if group.mul(&group.pow(&ciphertext.u, x), &group.h_pow(m)) != *e {
+                return Err(VerencError::Other(
+                    "ciphertext is not well formed".to_string(),
+                ));
+            }
+        }
+        if new_key.vk.group.n != group.n {
+            return Err(VerencError::Other(
+                "Both keys must use the same group".to_string(),
+            ));
         }
+        let r = group.random_for_encrypt();
+        let new_ciphertext = new_key
+            .view()
+            .encrypt_with_randomness(new_domain, &messages, &r)?;
+
+        let bound = &group.n2d4 << KEY_GENERATION_BLINDING_SLACK;
+        let x_tilde = messages
+            .iter()
+            .map(|_| random_below(&bound))
+            .collect::<Vec<_>>();
+        let m_tilde = messages
+            .iter()
+            .map(|_| group.random_value())
+            .collect::<Vec<_>>();
+        let r_tilde = group.random_value();
+        let old_key = VerificationKey::from(self);
+        let commitments = old_key.reencryption_commitments(
+            ciphertext,
+            &new_key.vk,
+            new_domain,
+            &new_ciphertext,
+            &x_tilde,
+            &r_tilde,
+            &m_tilde,
+        );
+        let challenge = old_key.reencryption_challenge(
+            domain,
+            ciphertext,
+            &new_key.vk,
+            new_domain,
+            &new_ciphertext,
+            &commitments,
+        );
+        // Not reduced so the verifier can bound the exponents
+        let x = unreduced_responses(&challenge, &x_tilde, &self.x1);
+        Ok((
+            new_ciphertext,
+            ReencryptionProof {
+                x,
+                r: new_key.view().schnorr(&r_tilde, &challenge, &r),
+                m: group.schnorr_responses(&challenge, &m_tilde, &messages),
+                challenge,
+            },
+        ))
+    }
+
+    fn decrypt_in_slots(
+        &self,
+        domain: &[u8],
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        // Open the slots even if the tag is wrong so the time taken
+        // doesn't tell which check failed
+        let tag = self.check_tag(domain, ciphertext);
+        let messages = match (slots, ciphertext.e.as_slice()) {
+            // One message skips collecting per slot results
+            ([slot], [e]) => self
+                .open_slot(0, *slot, &ciphertext.u, e, None)
+                .map(|m| vec![m]),
+            _ => self.open_slots(slots, ciphertext),
+        };
+        self.audited(ciphertext, tag.and(messages))
+    }
+
+    /// Record the decryption of `ciphertext` and pass its result through
+    pub(crate) fn audited<T>(
+        &self,
+        ciphertext: &VerifiableCipherText,
+        result: Result<T, VerencError>,
+    ) -> Result<T, VerencError> {
+        audit::record(|| AuditEvent::Decrypted {
+            key_fingerprint: VerificationKey::from(self).fingerprint(),
+            ciphertext_hash: ciphertext.digest(),
+            messages: ciphertext.e.len(),
+            error: result.as_ref().err(),
+        });
+        result
+    }
+
+    fn check_tag(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<(), VerencError> {
+        self.check_tag_blinded(domain, ciphertext, None)
+    }
+
+    pub(crate) fn check_tag_blinded(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        blinding: Option<&ExponentBlinding>,
+    ) -> Result<(), VerencError> {
+        let v = self.group.reduced(&ciphertext.v)?;
 
         // H(u, e, L)
         let hash = self.group.hash(&ciphertext.u, &ciphertext.e, domain);
@@ -69,33 +483,91 @@ impl DecryptionKey {
         let exp = (hash * &self.x3 + &self.x2) << 1;
 
         let two = BigNumber::from(2);
-        let u = self.group.pow(&ciphertext.u, &exp);
-        let v = self.group.pow(&ciphertext.v, &two);
-
-        if u != v {
-            return Err("u^2 != v^2".to_string());
-        }
-        let mut m = Vec::with_capacity(ciphertext.e.len());
-
-        let one = BigNumber::from(1);
-        for (i, (ee, xx)) in ciphertext.e.iter().zip(self.x1.iter()).enumerate() {
-            // 1/u^x_1
-            let u_x1_inv = self
-                .group
-                .pow(&ciphertext.u, xx)
-                .invert(&self.group.nn)
-                .ok_or_else(|| "invalid ciphertext".to_string())?;
-            let e = self.group.mul(&u_x1_inv, ee);
-            let m_hat = self.group.pow(&e, &self.group.two_inv_two);
-            let check = &m_hat % &self.group.n;
-            if check != one {
-                return Err(format!("decryption failed for message {}", i));
-            }
+        let u = self.secret_pow(&ciphertext.u, &exp, blinding);
+        let v = self.group.pow(&v, &two);
 
-            let m_i: BigNumber = (m_hat - 1) / &self.group.n;
-            m.push(m_i);
+        if !ct_eq(&u, &v) {
+            return Err(VerencError::InvalidTag);
         }
+        Ok(())
+    }
 
-        Ok(m)
+    fn open_slots(
+        &self,
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        self.open_each(slots, ciphertext).into_messages()
+    }
+
+    fn open_each(&self, slots: &[usize], ciphertext: &VerifiableCipherText) -> DecryptedMessages {
+        self.open_each_blinded(slots, ciphertext, None)
+    }
+
+    pub(crate) fn open_each_blinded(
+        &self,
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+        blinding: Option<&ExponentBlinding>,
+    ) -> DecryptedMessages {
+        let open = |(i, (ee, slot)): (usize, (&BigNumber, &usize))| {
+            (*slot, self.open_slot(i, *slot, &ciphertext.u, ee, blinding))
+        };
+        // Slots open independently, across rayon's pool with `parallel`
+        #[cfg(feature = "parallel")]
+        let slots = ciphertext
+            .e
+            .par_iter()
+            .zip(slots.par_iter())
+            .enumerate()
+            .map(open)
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let slots = ciphertext
+            .e
+            .iter()
+            .zip(slots.iter())
+            .enumerate()
+            .map(open)
+            .collect();
+        DecryptedMessages { slots }
+    }
+
+    /// `base^exp` with `exp` blinded by `blinding` if given
+    fn secret_pow(
+        &self,
+        base: &BigNumber,
+        exp: &BigNumber,
+        blinding: Option<&ExponentBlinding>,
+    ) -> BigNumber {
+        match blinding {
+            Some(blinding) => {
+                let mut blinded = blinding.blind(exp);
+                let result = self.group.pow(base, &blinded);
+                blinded.zeroize();
+                result
+            }
+            None => self.group.pow(base, exp),
+        }
+    }
+
+    fn open_slot(
+        &self,
+        i: usize,
+        slot: usize,
+        u: &BigNumber,
+        ee: &BigNumber,
+        blinding: Option<&ExponentBlinding>,
+    ) -> Result<BigNumber, VerencError> {
+        let xx = &self.x1[slot];
+        // 1/u^x_1
+        let u_x1_inv = self
+            .secret_pow(u, xx, blinding)
+            .invert(&self.group.nn)
+            .ok_or(VerencError::InvalidCiphertext)?;
+        let e = self.group.mul(&u_x1_inv, ee);
+        self.group
+            .paillier_extract(&e)
+            .ok_or(VerencError::DecryptionFailed { index: i })
     }
 }
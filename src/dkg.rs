@@ -0,0 +1,744 @@
+//! Distributed generation of the modulus and decryption key so no single
+//! party ever knows `p`, `q` or the decryption key, after Boneh and Franklin
+//! <https://crypto.stanford.edu/~dabo/pubs/papers/sharing.pdf>.
+//!
+//! Each party `i` picks shares `p_i` and `q_i` of the primes. The parties
+//! multiply `p = sum p_i` and `q = sum q_i` with BGW over a prime field and
+//! reveal only `n = p * q`, then run the biprimality test on `n` with
+//! exponents derived from their shares. Alongside it they reveal
+//! `(p + q - 1) * r + n * s` for shared random `r` and `s`, which reduced
+//! mod `n` shows only whether `gcd(n, p + q - 1) = 1`, the check that
+//! rules out the moduli the test alone accepts. A candidate that fails
+//! either, or has a small factor, is discarded and the parties start
+//! another attempt. Once `n` passes every party deals
+//! its own random decryption key with integer Shamir sharing and Feldman
+//! commitments `g^a`, and each ends up with a `threshold::DecryptionKeyShare`
+//! of the sum of the keys. The encryption key is the product of the commitments.
+//!
+//! Every round is driven by `DkgParty`: call its methods in order, deliver
+//! each returned message to the party it names and pass every message of
+//! the round, including the party's own, to the next method.
+//!
+//! The protocol assumes honest but curious parties, an honest majority
+//! and private authenticated channels for `ModulusShare` and `KeyShare`.
+//! Bring your own transport and encryption for those.
+//! The primes are not safe primes, see `Group::from_rsa_private_key_relaxed`.
+//! A candidate is a biprime with probability about `1 / (prime_bits * ln 2 / 2)^2`
+//! so expect hundreds of attempts with 64-bit primes and tens of thousands
+//! with 1024-bit primes, run it ahead of time
+use crate::{
+    audit::{self, AuditEvent},
+    sampling::random_below,
+    threshold::{evaluate, factorial, DecryptionKeyShare, SLACK_BITS},
+    EncryptionKey, Group, VerencError, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+use zeroize::Zeroize;
+
+/// The number of random bases `n` is tested with before it is accepted
+pub const BIPRIMALITY_TESTS: usize = 40;
+
+/// Statistical hiding of `p + q - 1` in the revealed gcd value
+const MASK_BITS: usize = 40;
+
+/// Candidates divisible by an odd number below this are rejected
+/// before testing. Below `2^15` so no prime of 16 bits or more divides it
+const TRIAL_DIVISION_BOUND: u64 = 2000;
+
+/// What every party must agree on before starting
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DkgParameters {
+    parties: usize,
+    threshold: usize,
+    prime_bits: usize,
+    max_messages: usize,
+    field: BigNumber,
+}
+
+impl DkgParameters {
+    /// `parties` parties generating `n` from two `prime_bits` primes and a
+    /// key for `max_messages` messages any `threshold` of them can decrypt with.
+    /// Multiplying the primes needs at least three parties
+    pub fn new(
+        parties: usize,
+        threshold: usize,
+        prime_bits: usize,
+        max_messages: usize,
+    ) -> Result<Self, VerencError> {
+        if parties < 3 {
+            return Err(VerencError::Other(
+                "At least three parties are needed".to_string(),
+            ));
+        }
+        if threshold == 0 || threshold > parties {
+            return Err(VerencError::Other(format!(
+                "Threshold {} must be between 1 and the number of parties {}",
+                threshold, parties
+            )));
+        }
+        if prime_bits < 16 {
+            return Err(VerencError::Other(
+                "Primes must have at least 16 bits".to_string(),
+            ));
+        }
+        if max_messages == 0 {
+            return Err(VerencError::Other(
+                "The key must support at least one message".to_string(),
+            ));
+        }
+        // The smallest prime above every possible n and gcd value
+        let field_bits = 3 * prime_bits + 2 * MASK_BITS + 2 * bits(parties) + 4;
+        let mut field = (BigNumber::one() << field_bits) + BigNumber::one();
+        while !field.is_prime() {
+            field += BigNumber::from(2);
+        }
+        Ok(Self {
+            parties,
+            threshold,
+            prime_bits,
+            max_messages,
+            field,
+        })
+    }
+
+    /// The number of parties
+    pub fn parties(&self) -> usize {
+        self.parties
+    }
+
+    /// The number of parties needed to decrypt
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The bit length of `p` and `q`
+    pub fn prime_bits(&self) -> usize {
+        self.prime_bits
+    }
+
+    /// The number of messages the key supports
+    pub fn max_messages(&self) -> usize {
+        self.max_messages
+    }
+
+    /// The degree of the sharings of `p_i` and `q_i`, low enough that
+    /// their product can be interpolated from every party's point
+    fn degree(&self) -> usize {
+        (self.parties - 1) / 2
+    }
+}
+
+/// Shares of a party's `p_i` and `q_i` for one other party. Send privately
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModulusShare {
+    /// The sending party
+    pub from: usize,
+    /// The receiving party
+    pub to: usize,
+    /// The attempt this belongs to
+    pub attempt: u64,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) p: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) q: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) r: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) s: BigNumber,
+}
+
+/// A party's point on the product polynomial. Broadcast
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModulusProduct {
+    /// The sending party
+    pub from: usize,
+    /// The attempt this belongs to
+    pub attempt: u64,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) value: BigNumber,
+}
+
+/// A party's values for the biprimality test of the candidate `n`. Broadcast
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BiprimalityShare {
+    /// The sending party
+    pub from: usize,
+    /// The attempt this belongs to
+    pub attempt: u64,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) values: Vec<BigNumber>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) gcd: BigNumber,
+}
+
+/// Commitments `g^a` to the coefficients of a party's key sharing. Broadcast
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyCommitment {
+    /// The sending party
+    pub from: usize,
+    #[serde(deserialize_with = "crate::budget::bignum_rows")]
+    pub(crate) commitments: Vec<Vec<BigNumber>>,
+}
+
+/// A party's shares of its key for one other party. Send privately
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyShare {
+    /// The sending party
+    pub from: usize,
+    /// The receiving party
+    pub to: usize,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) values: Vec<BigNumber>,
+}
+
+/// The result of the biprimality test
+#[derive(Clone, Debug)]
+pub enum BiprimalityOutcome {
+    /// `n` isn't a biprime. Call `DkgParty::start` for another attempt
+    Rejected,
+    /// `n` is accepted. Send the commitment to everyone and each share to its party
+    Accepted {
+        /// The commitments to broadcast
+        commitment: KeyCommitment,
+        /// One share per party, in party order
+        shares: Vec<KeyShare>,
+    },
+}
+
+/// What a party ends up with
+#[derive(Clone, Debug)]
+pub struct DkgOutput {
+    /// The joint encryption key, the same for every party
+    pub encryption_key: EncryptionKey,
+    /// This party's share of the joint decryption key
+    pub share: DecryptionKeyShare,
+}
+
+#[derive(Debug)]
+enum State {
+    Idle,
+    Sharing {
+        p: BigNumber,
+        q: BigNumber,
+    },
+    Multiplying {
+        p: BigNumber,
+        q: BigNumber,
+        /// This party's points of `p + q - 1`, `r` and `s`
+        points: [BigNumber; 3],
+    },
+    Testing {
+        p: BigNumber,
+        q: BigNumber,
+        n: BigNumber,
+    },
+    Dealing {
+        group: Group,
+    },
+    Finished,
+}
+
+/// One party's side of the protocol
+#[derive(Debug)]
+pub struct DkgParty {
+    parameters: DkgParameters,
+    index: usize,
+    attempt: u64,
+    state: State,
+}
+
+impl DkgParty {
+    /// Party `index`, from 1 to the number of parties
+    pub fn new(parameters: DkgParameters, index: usize) -> Result<Self, VerencError> {
+        if index == 0 || index > parameters.parties {
+            return Err(VerencError::Other(format!(
+                "Party {} is out of range",
+                index
+            )));
+        }
+        Ok(Self {
+            parameters,
+            index,
+            attempt: 0,
+            state: State::Idle,
+        })
+    }
+
+    /// The party's index
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The current attempt, counting from 1 after the first `start`
+    pub fn attempt(&self) -> u64 {
+        self.attempt
+    }
+
+    /// Start an attempt with fresh `p_i` and `q_i`, returning one share per party
+    /// in party order
+    pub fn start(&mut self) -> Result<Vec<ModulusShare>, VerencError> {
+        if !matches!(self.state, State::Idle) {
+            return Err(VerencError::Other(
+                "An attempt is already running".to_string(),
+            ));
+        }
+        let params = &self.parameters;
+        // p = sum p_i has exactly prime_bits bits and is 3 mod 4
+        let bound = (BigNumber::one() << (params.prime_bits - 2))
+            / BigNumber::from((4 * params.parties) as u64);
+        let sample = || {
+            let share = random_below(&bound) << 2;
+            if self.index == 1 {
+                share + (BigNumber::one() << (params.prime_bits - 1)) + BigNumber::from(3)
+            } else {
+                share
+            }
+        };
+        let (p, q) = (sample(), sample());
+        let p_poly = field_polynomial(&p, params);
+        let q_poly = field_polynomial(&q, params);
+        // r is close to uniform mod n and s hides (p + q - 1) * r / n
+        let lp = bits(params.parties);
+        let mut r_poly = field_polynomial(
+            &random_below(&(BigNumber::one() << (2 * params.prime_bits + MASK_BITS))),
+            params,
+        );
+        let mut s_poly = field_polynomial(
+            &random_below(&(BigNumber::one() << (params.prime_bits + 2 * MASK_BITS + 3 + lp))),
+            params,
+        );
+        self.attempt += 1;
+        let shares = (1..=params.parties)
+            .map(|to| {
+                let x = BigNumber::from(to as u64);
+                ModulusShare {
+                    from: self.index,
+                    to,
+                    attempt: self.attempt,
+                    p: evaluate(&p_poly, &x) % &params.field,
+                    q: evaluate(&q_poly, &x) % &params.field,
+                    r: evaluate(&r_poly, &x) % &params.field,
+                    s: evaluate(&s_poly, &x) % &params.field,
+                }
+            })
+            .collect();
+        r_poly
+            .iter_mut()
+            .chain(s_poly.iter_mut())
+            .for_each(|a| a.zeroize());
+        self.state = State::Sharing { p, q };
+        Ok(shares)
+    }
+
+    /// Multiply the shares of every party, returning this party's point to broadcast
+    pub fn receive_modulus_shares(
+        &mut self,
+        shares: &[ModulusShare],
+    ) -> Result<ModulusProduct, VerencError> {
+        let (p, q) = match std::mem::replace(&mut self.state, State::Idle) {
+            State::Sharing { p, q } => (p, q),
+            state => return self.unexpected(state),
+        };
+        let result = self
+            .check_senders(shares.iter().map(|s| (s.from, s.attempt)))
+            .and_then(|_| {
+                if shares.iter().any(|s| s.to != self.index) {
+                    return Err(VerencError::Other("Share is for another party".to_string()));
+                }
+                Ok(())
+            });
+        if let Err(e) = result {
+            self.state = State::Sharing { p, q };
+            return Err(e);
+        }
+        let field = &self.parameters.field;
+        let sum = |value: fn(&ModulusShare) -> &BigNumber| {
+            shares
+                .iter()
+                .fold(BigNumber::zero(), |acc, s| acc.modadd(value(s), field))
+        };
+        let p_sum = sum(|s| &s.p);
+        let q_sum = sum(|s| &s.q);
+        let value = p_sum.modmul(&q_sum, field);
+        let a = p_sum.modadd(&q_sum, field).modsub(&BigNumber::one(), field);
+        let points = [a, sum(|s| &s.r), sum(|s| &s.s)];
+        self.state = State::Multiplying { p, q, points };
+        Ok(ModulusProduct {
+            from: self.index,
+            attempt: self.attempt,
+            value,
+        })
+    }
+
+    /// Interpolate the candidate `n` and return this party's values for testing it
+    pub fn receive_modulus_products(
+        &mut self,
+        products: &[ModulusProduct],
+    ) -> Result<BiprimalityShare, VerencError> {
+        let (p, q, mut points) = match std::mem::replace(&mut self.state, State::Idle) {
+            State::Multiplying { p, q, points } => (p, q, points),
+            state => return self.unexpected(state),
+        };
+        if let Err(e) = self.check_senders(products.iter().map(|s| (s.from, s.attempt))) {
+            self.state = State::Multiplying { p, q, points };
+            return Err(e);
+        }
+        let field = &self.parameters.field;
+        let n = products.iter().fold(BigNumber::zero(), |acc, product| {
+            let lambda = field_lagrange(field, self.parameters.parties, product.from);
+            acc.modadd(&product.value.modmul(&lambda, field), field)
+        });
+        // g^((n - p_1 - q_1 + 1) / 4) for party 1 and g^((p_i + q_i) / 4) for the others
+        let sum = &p + &q;
+        let exp = if self.index == 1 {
+            (&n - &sum + BigNumber::one()) >> 2
+        } else {
+            sum >> 2
+        };
+        let values = biprimality_bases(&n, self.attempt)
+            .iter()
+            .map(|g| g.modpow(&exp, &n))
+            .collect();
+        // A point of (p + q - 1) * r + n * s
+        let [a, r, s] = &points;
+        let gcd = a.modmul(r, field).modadd(&n.modmul(s, field), field);
+        points.iter_mut().for_each(|p| p.zeroize());
+        self.state = State::Testing { p, q, n };
+        Ok(BiprimalityShare {
+            from: self.index,
+            attempt: self.attempt,
+            values,
+            gcd,
+        })
+    }
+
+    /// Test the candidate. If it passes deal this party's key
+    pub fn receive_biprimality_shares(
+        &mut self,
+        shares: &[BiprimalityShare],
+    ) -> Result<BiprimalityOutcome, VerencError> {
+        let (mut p, mut q, n) = match std::mem::replace(&mut self.state, State::Idle) {
+            State::Testing { p, q, n } => (p, q, n),
+            state => return self.unexpected(state),
+        };
+        let result = self
+            .check_senders(shares.iter().map(|s| (s.from, s.attempt)))
+            .and_then(|_| {
+                if shares.iter().any(|s| s.values.len() != BIPRIMALITY_TESTS) {
+                    return Err(VerencError::Other(
+                        "Biprimality share has the wrong number of values".to_string(),
+                    ));
+                }
+                Ok(())
+            });
+        if let Err(e) = result {
+            self.state = State::Testing { p, q, n };
+            return Err(e);
+        }
+        p.zeroize();
+        q.zeroize();
+        if has_small_factor(&n) {
+            return Ok(BiprimalityOutcome::Rejected);
+        }
+        let (first, rest) = shares.iter().partition::<Vec<_>, _>(|s| s.from == 1);
+        let biprime = (0..BIPRIMALITY_TESTS).all(|t| {
+            let product = rest
+                .iter()
+                .fold(BigNumber::one(), |acc, s| acc.modmul(&s.values[t], &n));
+            let v = &first[0].values[t];
+            *v == product || *v == product.modneg(&n)
+        });
+        if !biprime {
+            return Ok(BiprimalityOutcome::Rejected);
+        }
+        let field = &self.parameters.field;
+        let z = shares.iter().fold(BigNumber::zero(), |acc, share| {
+            let lambda = field_lagrange(field, self.parameters.parties, share.from);
+            acc.modadd(&share.gcd.modmul(&lambda, field), field)
+        });
+        if (z % &n).gcd(&n) != BigNumber::one() {
+            return Ok(BiprimalityOutcome::Rejected);
+        }
+        let group = joint_group(&n)?;
+        Ok(self.deal(group))
+    }
+
+    /// Check every share against its commitments and combine them
+    /// into this party's share of the joint key
+    pub fn receive_key_material(
+        &mut self,
+        commitments: &[KeyCommitment],
+        shares: &[KeyShare],
+    ) -> Result<DkgOutput, VerencError> {
+        let group = match &self.state {
+            State::Dealing { group } => group.clone(),
+            _ => return Err(VerencError::Other("No key was dealt".to_string())),
+        };
+        let attempt = self.attempt;
+        self.check_senders(commitments.iter().map(|c| (c.from, attempt)))?;
+        self.check_senders(shares.iter().map(|s| (s.from, attempt)))?;
+        let secrets = self.parameters.max_messages + 2;
+        let x = BigNumber::from(self.index as u64);
+        let mut sum = vec![BigNumber::zero(); secrets];
+        for share in shares {
+            if share.to != self.index {
+                return Err(VerencError::Other("Share is for another party".to_string()));
+            }
+            let commitment = commitments
+                .iter()
+                .find(|c| c.from == share.from)
+                .expect("every party sent a commitment");
+            if share.values.len() != secrets
+                || commitment.commitments.len() != secrets
+                || commitment
+                    .commitments
+                    .iter()
+                    .any(|c| c.len() != self.parameters.threshold)
+            {
+                return Err(VerencError::Other(format!(
+                    "Key material of party {} is malformed",
+                    share.from
+                )));
+            }
+            for ((value, coefficients), total) in share
+                .values
+                .iter()
+                .zip(commitment.commitments.iter())
+                .zip(sum.iter_mut())
+            {
+                // g^f(x) = prod (g^a_k)^(x^k)
+                let expected = coefficients.iter().rev().fold(BigNumber::one(), |acc, c| {
+                    group.mul(&group.pow(&acc, &x), c)
+                });
+                if group.g_pow(value) != expected {
+                    return Err(VerencError::Other(format!(
+                        "Key share of party {} is invalid",
+                        share.from
+                    )));
+                }
+                *total += value;
+            }
+        }
+        let mut public = (0..secrets).map(|s| {
+            commitments.iter().fold(BigNumber::one(), |acc, c| {
+                group.mul(&acc, &c.commitments[s][0])
+            })
+        });
+        let y1 = (&mut public)
+            .take(self.parameters.max_messages)
+            .collect::<Vec<_>>();
+        let y2 = public.next().expect("y2 is committed");
+        let y3 = public.next().expect("y3 is committed");
+        let x3 = sum.pop().expect("x3 is shared");
+        let x2 = sum.pop().expect("x2 is shared");
+        let share = DecryptionKeyShare {
+            index: self.index,
+            threshold: self.parameters.threshold,
+            trustees: self.parameters.parties,
+            x1: sum,
+            x2,
+            x3,
+            group: group.clone(),
+        };
+        let vk = VerificationKey {
+            y1,
+            y2,
+            y3,
+            group,
+            lifecycle: None,
+            schema: None,
+        };
+        audit::record(|| AuditEvent::KeyGenerated {
+            key_fingerprint: vk.fingerprint(),
+            max_messages: vk.max_messages(),
+        });
+        self.state = State::Finished;
+        Ok(DkgOutput {
+            encryption_key: EncryptionKey { vk },
+            share,
+        })
+    }
+
+    fn deal(&mut self, group: Group) -> BiprimalityOutcome {
+        let params = &self.parameters;
+        let delta = factorial(params.parties);
+        let bound = (&delta * &delta * &group.n2d4) << SLACK_BITS;
+        let mut polynomials = (0..params.max_messages + 2)
+            .map(|_| {
+                std::iter::once(random_below(&group.n2d4))
+                    .chain((1..params.threshold).map(|_| random_below(&bound)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let commitment = KeyCommitment {
+            from: self.index,
+            commitments: polynomials
+                .iter()
+                .map(|coefficients| coefficients.iter().map(|a| group.g_pow(a)).collect())
+                .collect(),
+        };
+        let shares = (1..=params.parties)
+            .map(|to| {
+                let x = BigNumber::from(to as u64);
+                KeyShare {
+                    from: self.index,
+                    to,
+                    values: polynomials.iter().map(|f| evaluate(f, &x)).collect(),
+                }
+            })
+            .collect();
+        polynomials.iter_mut().flatten().for_each(|a| a.zeroize());
+        self.state = State::Dealing { group };
+        BiprimalityOutcome::Accepted { commitment, shares }
+    }
+
+    /// Each party sent exactly one message for the current attempt
+    fn check_senders(
+        &self,
+        senders: impl Iterator<Item = (usize, u64)>,
+    ) -> Result<(), VerencError> {
+        let mut seen = vec![false; self.parameters.parties];
+        for (from, attempt) in senders {
+            if attempt != self.attempt {
+                return Err(VerencError::Other(format!(
+                    "Message of party {} is for attempt {} not {}",
+                    from, attempt, self.attempt
+                )));
+            }
+            match seen.get_mut(from.wrapping_sub(1)) {
+                Some(seen) if !*seen => *seen = true,
+                Some(_) => return Err(VerencError::Other(format!("Party {} sent twice", from))),
+                None => {
+                    return Err(VerencError::Other(format!(
+                        "Party {} is out of range",
+                        from
+                    )))
+                }
+            }
+        }
+        if let Some(missing) = seen.iter().position(|s| !s) {
+            return Err(VerencError::Other(format!(
+                "Missing message of party {}",
+                missing + 1
+            )));
+        }
+        Ok(())
+    }
+
+    fn unexpected<T>(&mut self, state: State) -> Result<T, VerencError> {
+        self.state = state;
+        Err(VerencError::Other(
+            "Unexpected message for the current round".to_string(),
+        ))
+    }
+}
+
+impl Drop for DkgParty {
+    fn drop(&mut self) {
+        match &mut self.state {
+            State::Sharing { p, q } | State::Testing { p, q, .. } => {
+                p.zeroize();
+                q.zeroize();
+            }
+            State::Multiplying { p, q, points } => {
+                p.zeroize();
+                q.zeroize();
+                points.iter_mut().for_each(|p| p.zeroize());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A random polynomial over the field with constant term `secret`
+fn field_polynomial(secret: &BigNumber, params: &DkgParameters) -> Vec<BigNumber> {
+    std::iter::once(secret.clone())
+        .chain((0..params.degree()).map(|_| random_below(&params.field)))
+        .collect()
+}
+
+/// The Lagrange coefficient at 0 of point `j` among the points 1 to `parties`
+fn field_lagrange(field: &BigNumber, parties: usize, j: usize) -> BigNumber {
+    let (numerator, denominator) = (1..=parties).filter(|k| *k != j).fold(
+        (BigNumber::one(), BigNumber::one()),
+        |(num, den), k| {
+            let k_big = BigNumber::from(k as u64);
+            let diff = k_big.modsub(&BigNumber::from(j as u64), field);
+            (num.modmul(&k_big, field), den.modmul(&diff, field))
+        },
+    );
+    numerator.modmul(&denominator.invert(field).expect("field is prime"), field)
+}
+
+/// The number of bits of `value`
+fn bits(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()) as usize
+}
+
+/// True if an odd number below `TRIAL_DIVISION_BOUND` divides `n`
+fn has_small_factor(n: &BigNumber) -> bool {
+    let zero = BigNumber::zero();
+    (3..TRIAL_DIVISION_BOUND)
+        .step_by(2)
+        .any(|d| n % BigNumber::from(d) == zero)
+}
+
+/// Bases with Jacobi symbol 1 mod `n` that every party derives alike
+fn biprimality_bases(n: &BigNumber, attempt: u64) -> Vec<BigNumber> {
+    let mut transcript = merlin::Transcript::new(b"verenc dkg biprimality");
+    transcript.append_message(b"n", &n.to_bytes());
+    transcript.append_u64(b"attempt", attempt);
+    let mut bases = Vec::with_capacity(BIPRIMALITY_TESTS);
+    while bases.len() < BIPRIMALITY_TESTS {
+        let g = hash_below(&mut transcript, n);
+        if jacobi(&g, n) == 1 {
+            bases.push(g);
+        }
+    }
+    bases
+}
+
+/// The group of `n` with a generator every party derives alike
+fn joint_group(n: &BigNumber) -> Result<Group, String> {
+    let nn = n * n;
+    let mut transcript = merlin::Transcript::new(b"verenc dkg generator");
+    transcript.append_message(b"n", &n.to_bytes());
+    let g = hash_below(&mut transcript, &nn).modpow(&(&nn << 1), &nn);
+    Group::from_parts(g, n.clone()).ok_or_else(|| "n is even".to_string())
+}
+
+fn hash_below(transcript: &mut merlin::Transcript, bound: &BigNumber) -> BigNumber {
+    let mut bytes = vec![0u8; bound.to_bytes().len() + 16];
+    transcript.challenge_bytes(b"value", &mut bytes);
+    BigNumber::from_slice(&bytes) % bound
+}
+
+/// The Jacobi symbol `(a / n)` for odd `n`
+fn jacobi(a: &BigNumber, n: &BigNumber) -> i8 {
+    let (zero, one) = (BigNumber::zero(), BigNumber::one());
+    let (two, four, eight) = (BigNumber::from(2), BigNumber::from(4), BigNumber::from(8));
+    let (three, five) = (BigNumber::from(3), BigNumber::from(5));
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+    while a != zero {
+        while &a % &two == zero {
+            a = a >> 1;
+            let r = &n % &eight;
+            if r == three || r == five {
+                result = -result;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if &a % &four == three && &n % &four == three {
+            result = -result;
+        }
+        a = &a % &n;
+    }
+    if n == one {
+        result
+    } else {
+        0
+    }
+}
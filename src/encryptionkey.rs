@@ -1,17 +1,57 @@
-use crate::{DecryptionKey, Group, VerifiableCipherText, VerifiableEncryptionProof};
-use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "verify-only"))]
+use crate::{
+    escrow::ESCROW_BLINDING_SLACK,
+    group::{modular_responses, unreduced_responses},
+    pack_messages,
+    packing::{bits_le, packed_bit},
+    proof_derivation::{
+        derivation_bases, derivation_challenge, derivation_tweak, DERIVATION_BLINDING_SLACK,
+        TWEAK_BITS,
+    },
+    proof_disclosure::{merkle_challenge, response_leaf},
+    proof_element::{join_chunks, split_element},
+    proof_packed::{challenge_modulus, BitCommitments},
+    sampling::random_below,
+    CommitmentLinkProof, CommittedEncryptionProof, DecryptionKey, DerivationProof,
+    ElementEscrowProof, KeyGenerationProof, LabelOrProof, LessThanProof, Manifest,
+    PackedRangeProof, Precompute, PrecomputedEncryptionKey, PublicKeyGroup, RerandomizationProof,
+    ResponseTree, SecretKeyEscrowProof,
+};
+use crate::{
+    BatchItemError, Context, EncryptionKeyRef, Label, Lifecycle, SlotSchema, VerencError,
+    VerifiableCipherText, VerifiableEncryptionProof, VerificationKey,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{self, Display};
+#[cfg(not(feature = "verify-only"))]
 use unknown_order::BigNumber;
 
 /// Key for Encrypting `VerifiableCipherText`
 /// as described in section 3.2 in
 /// <https://shoup.net/papers/verenc.pdf>
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Relying parties that only check proofs should use `VerificationKey`.
+#[derive(Debug, Clone)]
 pub struct EncryptionKey {
-    y1: Vec<BigNumber>,
-    y2: BigNumber,
-    y3: BigNumber,
-    group: Group,
+    pub(crate) vk: VerificationKey,
+}
+
+impl Serialize for EncryptionKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.vk.serialize(serializer)
+    }
+}
+
+impl<'a> Deserialize<'a> for EncryptionKey {
+    fn deserialize<D>(deserializer: D) -> Result<EncryptionKey, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        let vk = VerificationKey::deserialize(deserializer)?;
+        Ok(Self { vk })
+    }
 }
 
 impl Display for EncryptionKey {
@@ -19,59 +59,224 @@ impl Display for EncryptionKey {
         write!(
             f,
             "EncryptionKey {{ y1: [{}], y2: {}, y3: {}, group: {} }}",
-            self.y1
+            self.vk
+                .y1
                 .iter()
                 .map(|y| format!("{}", y))
                 .collect::<Vec<String>>()
                 .join(", "),
-            self.y2,
-            self.y3,
-            self.group,
+            self.vk.y2,
+            self.vk.y3,
+            self.vk.group,
         )
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl From<&DecryptionKey> for EncryptionKey {
     fn from(dk: &DecryptionKey) -> Self {
-        let y1 = dk.x1.iter().map(|x| dk.group.g_pow(x)).collect();
-        let y2 = dk.group.g_pow(&dk.x2);
-        let y3 = dk.group.g_pow(&dk.x3);
         Self {
-            y1,
-            y2,
-            y3,
-            group: dk.group.clone(),
+            vk: VerificationKey::from(dk),
+        }
+    }
+}
+
+impl EncryptionKey {
+    /// The public values needed to verify proofs created with this key
+    pub fn verification_key(&self) -> &VerificationKey {
+        &self.vk
+    }
+
+    /// Borrowed view of this key
+    pub fn view(&self) -> EncryptionKeyRef<'_> {
+        self.vk.view()
+    }
+
+    /// Build the tables that speed up encrypting and proving with this key,
+    /// see `PrecomputedEncryptionKey`. Fails if `g` or a `y1` is not a unit
+    /// mod n^2, which a key deserialized from an untrusted source may not be
+    #[cfg(not(feature = "verify-only"))]
+    pub fn precompute(&self) -> Result<PrecomputedEncryptionKey, VerencError> {
+        PrecomputedEncryptionKey::new(self).map_err(VerencError::from)
+    }
+
+    /// Build the tables like `precompute` with the window size
+    /// `precompute` picks for `g` and every `y1`
+    #[cfg(not(feature = "verify-only"))]
+    pub fn precompute_tuned(
+        &self,
+        precompute: Precompute,
+    ) -> Result<PrecomputedEncryptionKey, VerencError> {
+        PrecomputedEncryptionKey::tuned(self, precompute).map_err(VerencError::from)
+    }
+
+    /// This key with `lifecycle` attached, see `VerificationKey::with_lifecycle`
+    pub fn with_lifecycle(self, lifecycle: Lifecycle) -> Self {
+        Self {
+            vk: self.vk.with_lifecycle(lifecycle),
         }
     }
+
+    /// The key's lifecycle metadata if any is attached
+    pub fn lifecycle(&self) -> Option<&Lifecycle> {
+        self.vk.lifecycle()
+    }
+
+    /// This key with `schema` attached, see `VerificationKey::with_schema`
+    pub fn with_schema(self, schema: SlotSchema) -> Result<Self, VerencError> {
+        Ok(Self {
+            vk: self.vk.with_schema(schema)?,
+        })
+    }
+
+    /// What the key's slots hold if a schema is attached
+    pub fn schema(&self) -> Option<&SlotSchema> {
+        self.vk.schema()
+    }
+
+    /// A key restricted to the `y1` slots at `indices`, see `VerificationKey::project`
+    pub fn project(&self, indices: &[usize]) -> Result<EncryptionKey, VerencError> {
+        Ok(Self {
+            vk: self.vk.project(indices)?,
+        })
+    }
+
+    /// Derive a key for `label` at the slots `indices` of this master key,
+    /// e.g. one per relying party, and a proof it is derived from this key.
+    ///
+    /// Every value including `g` is raised to a tweak hashed from the label
+    /// so derived keys look unrelated to each other and to this key. They
+    /// share its modulus so only tell apart keys whose group is shared by
+    /// other keys too, such as a `ParameterPreset`. This key's `DecryptionKey`
+    /// opens what's encrypted to a derived key with
+    /// `DecryptionKey::decrypt_at_indices(indices)` and needs no label.
+    /// Give the proof only to those who should be able to link the keys
+    #[cfg(not(feature = "verify-only"))]
+    pub fn derive(
+        &self,
+        label: &[u8],
+        indices: &[usize],
+    ) -> Result<(EncryptionKey, DerivationProof), VerencError> {
+        let master = &self.vk;
+        let derived = master.derive(label, indices)?;
+        let group = &master.group;
+        let tweak = derivation_tweak(master, label, indices);
+        let t = random_below(&(BigNumber::one() << (TWEAK_BITS + DERIVATION_BLINDING_SLACK)));
+        let commitments = derivation_bases(master, indices)
+            .map(|base| group.pow(base, &(&t << 1)))
+            .collect::<Vec<_>>();
+        let challenge = derivation_challenge(master, &derived, indices, &commitments);
+        // Not reduced so the verifier can bound the tweak
+        let proof = DerivationProof {
+            indices: indices.to_vec(),
+            response: t - &challenge * &tweak,
+            challenge,
+        };
+        Ok((EncryptionKey { vk: derived }, proof))
+    }
+
+    /// Verify a proof of verifiable encryption
+    /// See section 6.2.19 in
+    /// <https://dominoweb.draco.res.ibm.com/reports/rz3730_revised.pdf>
+    pub fn verify<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.vk.verify(context, ciphertext, proof)
+    }
+
+    /// Verify each of many proofs, see `EncryptionKeyRef::verify_all`
+    pub fn verify_all(
+        &self,
+        items: &[(&[u8], &VerifiableCipherText, &VerifiableEncryptionProof)],
+    ) -> Result<(), Vec<BatchItemError>> {
+        self.vk.verify_all(items)
+    }
+
+    /// Verify a proof made with a `Label` that has not expired at time `now`
+    pub fn verify_with_label(
+        &self,
+        label: &Label,
+        now: u64,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.vk.verify_with_label(label, now, ciphertext, proof)
+    }
+
+    /// Verify a proof created by `encrypt_and_prove_at_indices`
+    pub fn verify_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        indices: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.vk
+            .verify_at_indices(context, indices, ciphertext, proof)
+    }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl EncryptionKey {
+    /// Append `additional` slots to this key with its `key`, producing new
+    /// versions of both. The new `VerificationKey::fingerprint` differs so
+    /// distribute the new encryption key like any rotated key
+    pub fn extend_slots(
+        &self,
+        key: &DecryptionKey,
+        additional: usize,
+    ) -> Result<(EncryptionKey, DecryptionKey), VerencError> {
+        if VerificationKey::from(key).fingerprint() != self.vk.fingerprint() {
+            return Err(VerencError::Other(
+                "Decryption key does not match this key".to_string(),
+            ));
+        }
+        let dk = key.extend_slots(additional)?;
+        Ok((EncryptionKey::from(&dk), dk))
+    }
+
     /// Encrypt multiple messages as described in
     /// section 3.2 in
     /// <https://shoup.net/papers/verenc.pdf>
-    /// `domain` represents a domain separation tag or nonce.
+    /// `context` is the label of the paper, see `Context`.
     /// `msgs` values must be less than `self.group.n`
-    pub fn encrypt(
+    pub fn encrypt<'c>(
         &self,
-        domain: &[u8],
+        context: impl Into<Context<'c>>,
         msgs: &[BigNumber],
-    ) -> Result<VerifiableCipherText, String> {
-        if msgs.len() > self.y1.len() {
-            return Err(format!(
-                "Number of messages {} is more than supported by this key {}",
-                msgs.len(),
-                self.y1.len()
-            ));
-        }
-        for (i, m) in msgs.iter().enumerate() {
-            if m > &self.group.n {
-                return Err(format!("message {} is not valid", i));
-            }
-        }
+    ) -> Result<VerifiableCipherText, VerencError> {
+        self.view().encrypt(context, msgs)
+    }
 
-        let r = self.group.random_for_encrypt();
+    /// Encrypt byte strings into the first slots, one per message.
+    /// Each is read as a big-endian unsigned integer that must be less than `n`.
+    /// Leading zero bytes don't change the message so decrypted messages
+    /// convert back with `BigNumber::to_bytes` without them
+    pub fn encrypt_bytes_iter<I, B>(
+        &self,
+        domain: &[u8],
+        msgs: I,
+    ) -> Result<VerifiableCipherText, VerencError>
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        self.view().encrypt_bytes_iter(domain, msgs)
+    }
 
-        Ok(self.encrypt_with_blinding_factor(domain, msgs, &r))
+    /// Encrypt messages into specific key slots instead of the first `msgs.len()`.
+    /// Each entry is the index of the `y1` value to use and the message.
+    /// Indices must be strictly increasing and the same indices must be used to
+    /// verify and decrypt.
+    pub fn encrypt_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[(usize, BigNumber)],
+    ) -> Result<VerifiableCipherText, VerencError> {
+        self.view().encrypt_at_indices(context, msgs)
     }
 
     /// Encrypts and returns a NIZK where the ciphertext and commitments are computed (t values).
@@ -80,16 +285,24 @@ impl EncryptionKey {
     /// Not using t = g^m*h^s as the Idemix protocol does not use it,
     /// possibly because the knowledge of `m` is proved in the credential attribute protocol.
     /// Use this if the proof is by itself and not part of another protocol.
-    pub fn encrypt_and_prove(
+    pub fn encrypt_and_prove<'c>(
         &self,
-        nonce: &[u8],
+        context: impl Into<Context<'c>>,
         msgs: &[BigNumber],
-    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), String> {
-        let group = &self.group;
-        let blindings = (0..msgs.len())
-            .map(|_| group.random_for_encrypt())
-            .collect::<Vec<BigNumber>>();
-        self.encrypt_and_prove_blindings(nonce, msgs, blindings.as_slice())
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        self.view().encrypt_and_prove(context, msgs)
+    }
+
+    /// Encrypts messages into specific key slots and returns a NIZK
+    /// like `encrypt_and_prove`.
+    /// Each entry is the index of the `y1` value to use and the message.
+    /// Indices must be strictly increasing and the same indices must be used to verify.
+    pub fn encrypt_and_prove_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[(usize, BigNumber)],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        self.view().encrypt_and_prove_at_indices(context, msgs)
     }
 
     /// Encrypts and returns a NIZK where the ciphertext and commitments are computed (t values).
@@ -98,231 +311,710 @@ impl EncryptionKey {
     /// Not using t = g^m*h^s as the Idemix protocol does not use it,
     /// possibly because the knowledge of `m` is proved in the credential attribute protocol.
     /// Use this if the proof is part of other ZKPs.
-    pub fn encrypt_and_prove_blindings(
+    /// Each blinding must be between 0 and n / 4 like `Group::random_for_encrypt`.
+    pub fn encrypt_and_prove_blindings<'c>(
         &self,
-        nonce: &[u8],
+        context: impl Into<Context<'c>>,
         msgs: &[BigNumber],
         blindings: &[BigNumber],
-    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), String> {
-        if msgs.len() != blindings.len() {
-            return Err(format!(
-                "Number of messages {} != number of blindings {}",
-                msgs.len(),
-                blindings.len()
-            ));
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        self.view()
+            .encrypt_and_prove_blindings(context, msgs, blindings)
+    }
+
+    /// Encrypt `msgs` with the randomness `r` instead of generating it,
+    /// e.g. when `r` is produced jointly by an MPC protocol.
+    /// `r` must be secret, uniform below n / 4 and never reused.
+    pub fn encrypt_with_randomness<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+        r: &BigNumber,
+    ) -> Result<VerifiableCipherText, VerencError> {
+        self.view().encrypt_with_randomness(context, msgs, r)
+    }
+
+    /// Encrypt `msgs` with the randomness `r` and prove it like
+    /// `encrypt_and_prove_blindings`, see `encrypt_with_randomness`.
+    /// Given the same inputs the ciphertext is always the same
+    pub fn encrypt_and_prove_with_randomness<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+        r: &BigNumber,
+        blindings: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        self.view()
+            .encrypt_and_prove_with_randomness(context, msgs, r, blindings)
+    }
+
+    /// Pack `values` into a single message with `pack_messages`, encrypt and prove it
+    /// like `encrypt_and_prove` and also prove every value fits in `bits_per_value` bits.
+    /// Verify with `VerificationKey::verify_packed`
+    pub fn encrypt_and_prove_packed(
+        &self,
+        nonce: &[u8],
+        values: &[u64],
+        bits_per_value: usize,
+    ) -> Result<
+        (
+            VerifiableCipherText,
+            VerifiableEncryptionProof,
+            PackedRangeProof,
+        ),
+        String,
+    > {
+        let msg = pack_messages(values, bits_per_value)?;
+        let width = self.vk.check_packed_width(values.len(), bits_per_value)?;
+        let group = &self.vk.group;
+        let r = group.random_for_encrypt();
+        let blinding = group.random_for_encrypt();
+        let (ciphertext, proof) =
+            self.view()
+                .prove_in_slots(nonce, &[0], &[msg], &[blinding], &r)?;
+
+        let bits = (0..width)
+            .map(|k| packed_bit(values, bits_per_value, k))
+            .collect::<Vec<bool>>();
+        let commitments = BitCommitments::new(group, &self.vk.y1[0], &r, &bits)?;
+        let challenge = self.vk.packed_challenge(
+            nonce,
+            &ciphertext,
+            &commitments.bits,
+            &commitments.test_values,
+        );
+        let bits = commitments.respond(&challenge);
+        Ok((ciphertext, proof, PackedRangeProof { challenge, bits }))
+    }
+
+    /// Encrypt `msg` and prove it like `encrypt_and_prove`
+    /// and also prove it is less than the public `bound`.
+    /// Verify with `VerificationKey::verify_less_than`
+    pub fn encrypt_and_prove_less_than(
+        &self,
+        nonce: &[u8],
+        msg: &BigNumber,
+        bound: &BigNumber,
+    ) -> Result<
+        (
+            VerifiableCipherText,
+            VerifiableEncryptionProof,
+            LessThanProof,
+        ),
+        String,
+    > {
+        let width = self.vk.check_less_than_bound(bound)?;
+        if msg < &BigNumber::zero() || msg >= bound {
+            return Err("message is not less than the bound".to_string());
         }
-        if msgs.len() > self.y1.len() {
-            return Err(format!(
+        let group = &self.vk.group;
+        let r = group.random_for_encrypt();
+        let blinding = group.random_for_encrypt();
+        let (ciphertext, proof) =
+            self.view()
+                .prove_in_slots(nonce, &[0], std::slice::from_ref(msg), &[blinding], &r)?;
+
+        let y1 = &self.vk.y1[0];
+        let shifted = msg + (BigNumber::one() << width) - bound;
+        let lower = BitCommitments::new(group, y1, &r, &bits_le(msg, width))?;
+        let upper = BitCommitments::new(group, y1, &r, &bits_le(&shifted, width))?;
+        let challenge = self.vk.less_than_challenge(
+            nonce,
+            &ciphertext,
+            bound,
+            (&lower.bits, &lower.test_values),
+            (&upper.bits, &upper.test_values),
+        );
+        let lower = lower.respond(&challenge);
+        let upper = upper.respond(&challenge);
+        Ok((
+            ciphertext,
+            proof,
+            LessThanProof {
+                challenge,
+                lower,
+                upper,
+            },
+        ))
+    }
+
+    /// Encrypt `msgs` under `labels[index]` and prove the ciphertext is a valid
+    /// verifiable encryption under one of `labels` without revealing which.
+    /// The decryptor must use the real label.
+    /// Verify with `VerificationKey::verify_label_or`
+    pub fn encrypt_and_prove_label_or(
+        &self,
+        labels: &[&[u8]],
+        index: usize,
+        msgs: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, LabelOrProof), VerencError> {
+        if index >= labels.len() {
+            return Err(VerencError::Other(format!(
+                "Index {} is more than the number of labels {}",
+                index,
+                labels.len()
+            )));
+        }
+        if msgs.len() > self.vk.y1.len() {
+            return Err(VerencError::Other(format!(
                 "Number of messages {} is more than supported by this key {}",
                 msgs.len(),
-                self.y1.len()
-            ));
+                self.vk.y1.len()
+            )));
         }
+        let view = self.view();
+        let group = &self.vk.group;
+        let modulus = challenge_modulus();
+        let slots = (0..msgs.len()).collect::<Vec<usize>>();
+        let r = group.random_for_encrypt();
+        let ciphertext = view.encrypt_with_blinding_factor(labels[index], &slots, msgs, &r);
 
-        for (i, b) in blindings.iter().enumerate() {
-            if b.is_zero() {
-                return Err(format!("Invalid blinding factor at index {}", i));
+        // Simulate every other branch by choosing its challenge and responses,
+        // drawn like the real ones, and recomputing the test values from them
+        let mut branches = Vec::with_capacity(labels.len());
+        let mut test_values = Vec::with_capacity(labels.len());
+        let mut simulated = BigNumber::zero();
+        for (i, label) in labels.iter().enumerate() {
+            if i == index {
+                branches.push(VerifiableEncryptionProof {
+                    challenge: BigNumber::zero(),
+                    r: BigNumber::zero(),
+                    m: Vec::new(),
+                });
+                test_values.push(VerifiableCipherText {
+                    u: BigNumber::zero(),
+                    e: Vec::new(),
+                    v: BigNumber::zero(),
+                });
+                continue;
             }
+            let challenge = random_below(&modulus);
+            let tildes = (0..msgs.len())
+                .map(|_| group.random_for_encrypt())
+                .collect::<Vec<_>>();
+            let values = (0..msgs.len())
+                .map(|_| group.random_for_encrypt())
+                .collect::<Vec<_>>();
+            let branch = VerifiableEncryptionProof {
+                r: view.schnorr(
+                    &group.random_for_encrypt(),
+                    &challenge,
+                    &group.random_for_encrypt(),
+                ),
+                m: group.schnorr_responses(&challenge, &tildes, &values),
+                challenge,
+            };
+            test_values.push(view.test_values_from_responses(label, &slots, &ciphertext, &branch));
+            simulated += &branch.challenge;
+            branches.push(branch);
         }
-        let group = &self.group;
 
+        let r_tick = group.random_for_encrypt();
+        let blindings = (0..msgs.len())
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<_>>();
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), labels[index]);
+        test_values[index] = view.ciphertext_test_values(&r_tick, &hash, &slots, &blindings);
+        let challenge = self
+            .vk
+            .label_or_challenge(labels, &ciphertext, &test_values)
+            .modsub(&simulated, &modulus);
+        branches[index] = VerifiableEncryptionProof {
+            r: view.schnorr(&r_tick, &challenge, &r),
+            m: group.schnorr_responses(&challenge, &blindings, msgs),
+            challenge,
+        };
+        Ok((ciphertext, LabelOrProof { branches }))
+    }
+
+    /// Verify a proof created by `encrypt_and_prove_label_or`
+    pub fn verify_label_or(
+        &self,
+        labels: &[&[u8]],
+        ciphertext: &VerifiableCipherText,
+        proof: &LabelOrProof,
+    ) -> Result<(), VerencError> {
+        self.vk.verify_label_or(labels, ciphertext, proof)
+    }
+
+    /// Encrypt the secret key of `public_key` from another scheme
+    /// and prove the ciphertext decrypts to its discrete log.
+    /// `DecryptionKey::decrypt` recovers the secret key as the only message.
+    /// Verify with `VerificationKey::verify_escrowed_secret_key`
+    pub fn escrow_secret_key<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        secret_key: &BigNumber,
+        public_key: &P::PublicKey,
+    ) -> Result<(VerifiableCipherText, SecretKeyEscrowProof), VerencError> {
+        let order = self.vk.check_escrow_order::<P>()?;
+        if secret_key < &BigNumber::zero() || secret_key >= &order {
+            return Err(VerencError::Other(
+                "secret key is not less than the group order".to_string(),
+            ));
+        }
+        let zero = BigNumber::zero();
+        if P::mul_add(secret_key, &zero, public_key) != *public_key {
+            return Err(VerencError::Other(
+                "secret key does not match the public key".to_string(),
+            ));
+        }
+        let view = self.view();
+        let group = &self.vk.group;
         let r = group.random_for_encrypt();
         let r_tick = group.random_for_encrypt();
-        let ciphertext = self.encrypt_with_blinding_factor(nonce, msgs, &r);
+        let m_tilde = random_below(&(&order << ESCROW_BLINDING_SLACK));
+        let ciphertext =
+            view.encrypt_with_blinding_factor(nonce, &[0], std::slice::from_ref(secret_key), &r);
 
         let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
-        let test_values = self.ciphertext_test_values(&r_tick, &hash, blindings);
-        let challenge = self.fiat_shamir(nonce, &ciphertext, &test_values);
+        let test_values =
+            view.ciphertext_test_values(&r_tick, &hash, &[0], std::slice::from_ref(&m_tilde));
+        let commitment = P::mul_add(&(&m_tilde % &order), &zero, public_key);
+        let challenge = self.vk.escrow_challenge::<P>(
+            nonce,
+            &ciphertext,
+            public_key,
+            &test_values,
+            &commitment,
+        );
+
+        // Not reduced so it also answers the challenge in the other group
+        let m_hat = m_tilde - &challenge * secret_key;
+        let proof = VerifiableEncryptionProof {
+            r: view.schnorr(&r_tick, &challenge, &r),
+            m: vec![m_hat],
+            challenge,
+        };
+        Ok((ciphertext, SecretKeyEscrowProof { proof }))
+    }
 
-        let r_hat = self.schnorr(&r_tick, &challenge, &r);
-        let m_hat = msgs
+    /// Escrow `element`, a value less than `modulus` such as a ciphertext
+    /// component or commitment mod N^2 from another group, by splitting it
+    /// into chunks encrypted in the first slots of this key. Also proves the
+    /// chunks reconstruct the element committed to in the proof.
+    /// Verify with `VerificationKey::verify_element` and recover the element
+    /// with `DecryptionKey::decrypt_element`
+    pub fn encrypt_and_prove_element(
+        &self,
+        nonce: &[u8],
+        element: &BigNumber,
+        modulus: &BigNumber,
+    ) -> Result<(VerifiableCipherText, ElementEscrowProof), VerencError> {
+        let (width, count) = self.vk.check_element_modulus(modulus)?;
+        if element < &BigNumber::zero() || element >= modulus {
+            return Err(VerencError::Other(
+                "element is not less than the modulus".to_string(),
+            ));
+        }
+        let view = self.view();
+        let group = &self.vk.group;
+        let chunks = split_element(element, width, count);
+        let slots = (0..count).collect::<Vec<usize>>();
+        let r = group.random_for_encrypt();
+        let r_tick = group.random_for_encrypt();
+        let blinding_bound = BigNumber::one() << (width + ESCROW_BLINDING_SLACK);
+        let m_tilde = chunks
             .iter()
-            .zip(blindings.iter())
-            .map(|(m, b)| self.schnorr(b, &challenge, m))
-            .collect();
+            .map(|_| random_below(&blinding_bound))
+            .collect::<Vec<_>>();
+        let ciphertext = view.encrypt_with_blinding_factor(nonce, &slots, &chunks, &r);
+
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let test_values = view.ciphertext_test_values(&r_tick, &hash, &slots, &m_tilde);
+        let commitment = group.g_pow(element);
+        let test_commitment = group.g_pow(&join_chunks(&m_tilde, width));
+        let challenge = self.vk.element_challenge(
+            nonce,
+            &ciphertext,
+            modulus,
+            &commitment,
+            &test_values,
+            &test_commitment,
+        );
+
+        // Not reduced so they also answer the challenge for the commitment
+        let m_hat = unreduced_responses(&challenge, &m_tilde, &chunks);
+        let proof = VerifiableEncryptionProof {
+            r: view.schnorr(&r_tick, &challenge, &r),
+            m: m_hat,
+            challenge,
+        };
+        Ok((ciphertext, ElementEscrowProof { proof, commitment }))
+    }
 
+    /// Encrypt and prove like `encrypt_and_prove` committing to the
+    /// per-message responses in a Merkle tree. Keep the `ResponseTree`
+    /// and give each verifier a `ResponseTree::disclose` of the slots it checks
+    pub fn encrypt_and_prove_merkle(
+        &self,
+        nonce: &[u8],
+        msgs: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, ResponseTree), VerencError> {
+        let view = self.view();
+        let group = &self.vk.group;
+        let r = group.random_for_encrypt();
+        let slots = view.check_with_randomness(msgs, &r)?;
+        let r_tick = group.random_for_encrypt();
+        let blindings = msgs
+            .iter()
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<_>>();
+        let ciphertext = view.encrypt_with_blinding_factor(nonce, &slots, msgs, &r);
+
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let test_values = view.ciphertext_test_values(&r_tick, &hash, &slots, &blindings);
+        let tree = Manifest::from_hashes(
+            test_values
+                .e
+                .iter()
+                .enumerate()
+                .map(|(i, e)| response_leaf(i, e))
+                .collect(),
+        );
+        let challenge = merkle_challenge(
+            &view,
+            nonce,
+            &ciphertext,
+            &test_values.u,
+            &tree.root(),
+            &test_values.v,
+        );
         Ok((
             ciphertext,
-            VerifiableEncryptionProof {
+            ResponseTree {
+                r: view.schnorr(&r_tick, &challenge, &r),
+                m: group.schnorr_responses(&challenge, &blindings, msgs),
                 challenge,
-                r: r_hat,
-                m: m_hat,
+                tree,
             },
         ))
     }
 
-    /// Verify a proof of verifiable encryption
-    /// See section 6.2.19 in
-    /// <https://dominoweb.draco.res.ibm.com/reports/rz3730_revised.pdf>
-    pub fn verify(
+    /// Encrypt and prove like `encrypt_and_prove` while also proving the message
+    /// at `index` is the one committed to in the Pedersen commitment
+    /// `msgs[index] * G + blinding * H` in `P` with the generator `h`.
+    /// The commitment is made elsewhere, e.g. by a credential system,
+    /// so only its opening is needed here.
+    /// Verify with `VerificationKey::verify_linked`
+    pub fn encrypt_and_prove_linked<P: PublicKeyGroup>(
         &self,
         nonce: &[u8],
-        ciphertext: &VerifiableCipherText,
-        proof: &VerifiableEncryptionProof,
-    ) -> Result<(), String> {
-        if proof.m.len() > self.y1.len() {
-            return Err(format!(
-                "Number of messages {} is more than supported by this key {}",
-                proof.m.len(),
-                self.y1.len()
+        msgs: &[BigNumber],
+        index: usize,
+        h: &P::PublicKey,
+        blinding: &BigNumber,
+    ) -> Result<(VerifiableCipherText, CommitmentLinkProof), VerencError> {
+        let order = self.vk.check_linked_slot::<P>(msgs.len(), index)?;
+        let zero = BigNumber::zero();
+        if msgs[index] < zero || msgs[index] >= order {
+            return Err(VerencError::Other(format!(
+                "message {} is not less than the group order",
+                index
+            )));
+        }
+        if blinding < &zero || blinding >= &order {
+            return Err(VerencError::Other(
+                "blinding is not less than the group order".to_string(),
             ));
         }
-        if proof.m.len() != ciphertext.e.len() {
+        let view = self.view();
+        let group = &self.vk.group;
+        let slots = (0..msgs.len()).collect::<Vec<usize>>();
+        let r = group.random_for_encrypt();
+        let r_tick = group.random_for_encrypt();
+        let m_tilde = slots
+            .iter()
+            .map(|i| {
+                if *i == index {
+                    random_below(&(&order << ESCROW_BLINDING_SLACK))
+                } else {
+                    group.random_for_encrypt()
+                }
+            })
+            .collect::<Vec<_>>();
+        let s_tilde = random_below(&order);
+        let ciphertext = view.encrypt_with_blinding_factor(nonce, &slots, msgs, &r);
+
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let test_values = view.ciphertext_test_values(&r_tick, &hash, &slots, &m_tilde);
+        let commitment = P::mul_add(&msgs[index], blinding, h);
+        let test_commitment = P::mul_add(&(&m_tilde[index] % &order), &s_tilde, h);
+        let challenge = self.vk.linked_challenge::<P>(
+            nonce,
+            &ciphertext,
+            index,
+            h,
+            &commitment,
+            &test_values,
+            &test_commitment,
+        );
+
+        // Not reduced at `index` so it also answers the challenge in the other group
+        let m_hat = m_tilde
+            .into_iter()
+            .zip(msgs.iter())
+            .enumerate()
+            .map(|(i, (m_tilde, m))| {
+                if i == index {
+                    m_tilde - &challenge * m
+                } else {
+                    view.schnorr(&m_tilde, &challenge, m)
+                }
+            })
+            .collect();
+        let c = &challenge % &order;
+        let s_hat = s_tilde.modsub(&c.modmul(blinding, &order), &order);
+        let proof = VerifiableEncryptionProof {
+            r: view.schnorr(&r_tick, &challenge, &r),
+            m: m_hat,
+            challenge,
+        };
+        Ok((ciphertext, CommitmentLinkProof { proof, s: s_hat }))
+    }
+
+    /// Encrypt and prove like `encrypt_and_prove` while also committing to
+    /// each message as `m_i * G + s_i * H` in `P` with the generator `h`.
+    /// Messages must be less than the order of `P`.
+    /// Returns the blindings `s_i` to open the commitments
+    #[allow(clippy::type_complexity)]
+    pub fn encrypt_and_prove_committed<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        msgs: &[BigNumber],
+        h: &P::PublicKey,
+    ) -> Result<
+        (
+            VerifiableCipherText,
+            CommittedEncryptionProof<P::PublicKey>,
+            Vec<BigNumber>,
+        ),
+        String,
+    > {
+        let order = self.vk.check_escrow_order::<P>()?;
+        if msgs.len() > self.vk.y1.len() {
             return Err(format!(
-                "Number of messages {} is equal to ciphertext {}",
-                proof.m.len(),
-                ciphertext.e.len()
+                "Number of messages {} is more than supported by this key {}",
+                msgs.len(),
+                self.vk.y1.len()
             ));
         }
-        let group = &self.group;
-        // Reconstruct u, e, v
-        let two_c = &proof.challenge << 1;
-        let two_r = &proof.r << 1;
-
-        // u^{2c} mod n^2
-        let uc = group.pow(&ciphertext.u, &two_c);
-
-        // g^{2r} mod n^2
-        let gr = group.g_pow(&two_r);
-
-        // u^{2c} * g^{2r} mod n^2
-        let u = group.mul(&uc, &gr);
-
-        let mut e = Vec::with_capacity(proof.m.len());
-        for i in 0..proof.m.len() {
-            let ec = group.pow(&ciphertext.e[i], &two_c);
-            let yr = group.pow(&self.y1[i], &two_r);
-            let hm = group.h_pow(&(&proof.m[i] << 1));
-            e.push(group.mul(&group.mul(&ec, &yr), &hm));
+        let zero = BigNumber::zero();
+        for (i, m) in msgs.iter().enumerate() {
+            if m < &zero || m >= &order {
+                return Err(format!("message {} is not less than the group order", i));
+            }
         }
+        let view = self.view();
+        let group = &self.vk.group;
+        let slots = (0..msgs.len()).collect::<Vec<usize>>();
+        let r = group.random_for_encrypt();
+        let r_tick = group.random_for_encrypt();
+        let m_tilde = msgs
+            .iter()
+            .map(|_| random_below(&(&order << ESCROW_BLINDING_SLACK)))
+            .collect::<Vec<_>>();
+        let s = msgs
+            .iter()
+            .map(|_| random_below(&order))
+            .collect::<Vec<_>>();
+        let s_tilde = msgs
+            .iter()
+            .map(|_| random_below(&order))
+            .collect::<Vec<_>>();
+        let ciphertext = view.encrypt_with_blinding_factor(nonce, &slots, msgs, &r);
 
-        let hs = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
-        let vc = group.pow(&ciphertext.v, &two_c);
-        let y3hs = group.pow(&self.y3, &hs);
-        let y2y3hs = group.mul(&self.y2, &y3hs);
-        let y2y3hsr2 = group.pow(&y2y3hs, &two_r);
-        let v = group.mul(&vc, &y2y3hsr2);
-        let test_values = VerifiableCipherText { u, e, v };
-        let challenge = self.fiat_shamir(nonce, ciphertext, &test_values);
-        if challenge == proof.challenge {
-            Ok(())
-        } else {
-            Err("Invalid proof".to_string())
-        }
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let test_values = view.ciphertext_test_values(&r_tick, &hash, &slots, &m_tilde);
+        let commitments = msgs
+            .iter()
+            .zip(s.iter())
+            .map(|(m, s)| P::mul_add(m, s, h))
+            .collect::<Vec<_>>();
+        let test_commitments = m_tilde
+            .iter()
+            .zip(s_tilde.iter())
+            .map(|(m, s)| P::mul_add(&(m % &order), s, h))
+            .collect::<Vec<_>>();
+        let challenge = self.vk.committed_challenge::<P>(
+            nonce,
+            &ciphertext,
+            h,
+            &commitments,
+            &test_values,
+            &test_commitments,
+        );
+
+        // Not reduced so they also answer the challenge in the other group
+        let m_hat = unreduced_responses(&challenge, &m_tilde, msgs);
+        let s_hat = modular_responses(&challenge, &s_tilde, &s, &order);
+        let proof = VerifiableEncryptionProof {
+            r: view.schnorr(&r_tick, &challenge, &r),
+            m: m_hat,
+            challenge,
+        };
+        Ok((
+            ciphertext,
+            CommittedEncryptionProof {
+                proof,
+                commitments,
+                s: s_hat,
+            },
+            s,
+        ))
     }
 
-    pub(crate) fn fiat_shamir(
+    /// Verify a proof created by `encrypt_and_prove_linked`
+    pub fn verify_linked<P: PublicKeyGroup>(
         &self,
         nonce: &[u8],
         ciphertext: &VerifiableCipherText,
-        test_values: &VerifiableCipherText,
-    ) -> BigNumber {
-        let group = &self.group;
-        let mut transcript =
-            merlin::Transcript::new(b"camenisch-shoup verifiable encryption proof");
-        transcript.append_message(b"nonce", nonce);
-        transcript.append_message(b"n", &group.n.to_bytes());
-        transcript.append_message(b"g", &group.g.to_bytes());
-        transcript.append_message(b"y2", &self.y2.to_bytes());
-        transcript.append_message(b"y3", &self.y3.to_bytes());
-        transcript.append_message(
-            b"y1",
-            &self
-                .y1
-                .iter()
-                .map(|y| y.to_bytes())
-                .flatten()
-                .collect::<Vec<u8>>(),
-        );
-        transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
-        transcript.append_message(
-            b"ciphertext.e",
-            &ciphertext
-                .e
-                .iter()
-                .map(|e| e.to_bytes())
-                .flatten()
-                .collect::<Vec<u8>>(),
-        );
-        transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
-        transcript.append_message(b"ciphertext_test.u", &test_values.u.to_bytes());
-        transcript.append_message(
-            b"ciphertext_test.e",
-            &test_values
-                .e
-                .iter()
-                .map(|e| e.to_bytes())
-                .flatten()
-                .collect::<Vec<u8>>(),
-        );
-        transcript.append_message(b"ciphertext_test.v", &test_values.v.to_bytes());
+        index: usize,
+        h: &P::PublicKey,
+        commitment: &P::PublicKey,
+        proof: &CommitmentLinkProof,
+    ) -> Result<(), VerencError> {
+        self.vk
+            .verify_linked::<P>(nonce, ciphertext, index, h, commitment, proof)
+    }
 
-        let mut challenge_bytes = [0u8; 32];
-        transcript.challenge_bytes(
-            b"verifiable encryption proof challenge",
-            &mut challenge_bytes,
-        );
-        BigNumber::from_slice(&challenge_bytes)
+    /// Verify a proof created by `encrypt_and_prove_committed`
+    pub fn verify_committed<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        h: &P::PublicKey,
+        proof: &CommittedEncryptionProof<P::PublicKey>,
+    ) -> Result<(), VerencError> {
+        self.vk.verify_committed::<P>(nonce, ciphertext, h, proof)
     }
 
-    pub(crate) fn ciphertext_test_values(
+    /// Verify a proof created by `encrypt_and_prove_element`
+    pub fn verify_element(
         &self,
-        r: &BigNumber,
-        hash: &BigNumber,
-        msgs: &[BigNumber],
-    ) -> VerifiableCipherText {
-        let two_r = r << 1;
-        let two_m = msgs.iter().map(|m| m << 1).collect::<Vec<BigNumber>>();
-        let u = self.compute_u(&two_r);
-        let e = self.compute_e(two_m.as_slice(), &two_r);
-        let v = self.compute_v(&two_r, hash, false);
-        VerifiableCipherText { u, e, v }
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        modulus: &BigNumber,
+        proof: &ElementEscrowProof,
+    ) -> Result<(), VerencError> {
+        self.vk.verify_element(nonce, ciphertext, modulus, proof)
     }
 
-    pub(crate) fn schnorr(
+    /// Verify a proof created by `escrow_secret_key`
+    pub fn verify_escrowed_secret_key<P: PublicKeyGroup>(
         &self,
-        tilde: &BigNumber,
-        challenge: &BigNumber,
-        value: &BigNumber,
-    ) -> BigNumber {
-        tilde - self.group.mul(challenge, value)
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        public_key: &P::PublicKey,
+        proof: &SecretKeyEscrowProof,
+    ) -> Result<(), VerencError> {
+        self.vk
+            .verify_escrowed_secret_key::<P>(nonce, ciphertext, public_key, proof)
     }
 
-    pub(crate) fn encrypt_with_blinding_factor(
+    /// Verify a proof created by `DecryptionKey::prove_key_generation`
+    pub fn verify_key_generation(
         &self,
-        domain: &[u8],
-        msgs: &[BigNumber],
-        r: &BigNumber,
-    ) -> VerifiableCipherText {
-        let u = self.compute_u(r);
-        let e = self.compute_e(msgs, r);
-        let hash = self.group.hash(&u, &e, domain);
-        let v = self.compute_v(r, &hash, true);
-        VerifiableCipherText { u, e, v }
+        nonce: &[u8],
+        proof: &KeyGenerationProof,
+    ) -> Result<(), VerencError> {
+        self.vk.verify_key_generation(nonce, proof)
     }
 
-    pub(crate) fn compute_u(&self, r: &BigNumber) -> BigNumber {
-        self.group.g_pow(r)
+    /// Verify a proof created by `encrypt_and_prove_less_than`
+    pub fn verify_less_than(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        less_than_proof: &LessThanProof,
+        bound: &BigNumber,
+    ) -> Result<(), VerencError> {
+        self.vk
+            .verify_less_than(nonce, ciphertext, proof, less_than_proof, bound)
     }
 
-    pub(crate) fn compute_e(&self, msgs: &[BigNumber], r: &BigNumber) -> Vec<BigNumber> {
-        let mut e = Vec::with_capacity(msgs.len());
-        let group = &self.group;
-        for (i, m) in msgs.iter().enumerate() {
-            let ee: BigNumber = group.mul(&group.pow(&self.y1[i], r), &group.h_pow(m));
-            e.push(ee);
-        }
-        e
+    /// Verify a proof created by `encrypt_and_prove_packed`
+    pub fn verify_packed(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        range_proof: &PackedRangeProof,
+        count: usize,
+        bits_per_value: usize,
+    ) -> Result<(), VerencError> {
+        self.vk
+            .verify_packed(nonce, ciphertext, proof, range_proof, count, bits_per_value)
     }
 
-    pub(crate) fn compute_v(&self, r: &BigNumber, hash: &BigNumber, abs: bool) -> BigNumber {
-        let group = &self.group;
-        // (y2 * (y3^H(u, e, L)))^r
-        let v = group.pow(&group.mul(&group.pow(&self.y3, hash), &self.y2), r);
-        if abs {
-            group.abs(&v)
-        } else {
-            v
+    /// Re-randomize `ciphertext` by multiplying in a fresh encryption of zero
+    /// so it can't be linked to the original. Returns the new ciphertext and
+    /// the randomization factor.
+    ///
+    /// The tag `v` binds `u` and `e` to the domain and can't be recomputed without
+    /// the original encryption randomness so it is discarded (set to zero).
+    /// Use `DecryptionKey::decrypt_untagged` to decrypt the result.
+    pub fn rerandomize(
+        &self,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<(VerifiableCipherText, BigNumber), VerencError> {
+        if ciphertext.e.len() > self.vk.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: ciphertext.e.len(),
+                max: self.vk.y1.len(),
+            });
         }
+        let group = &self.vk.group;
+        let factor = group.random_for_encrypt();
+        let u = group.mul(&ciphertext.u, &self.view().compute_u(&factor));
+        let e = ciphertext
+            .e
+            .iter()
+            .zip(self.vk.y1.iter())
+            .map(|(e, y)| group.mul(e, &group.pow(y, &factor)))
+            .collect();
+        Ok((
+            VerifiableCipherText {
+                u,
+                e,
+                v: BigNumber::zero(),
+            },
+            factor,
+        ))
+    }
+
+    /// Prove `rerandomized` is `original` times an encryption of zero
+    /// with randomness `factor` without revealing `factor`
+    pub fn prove_rerandomization(
+        &self,
+        original: &VerifiableCipherText,
+        rerandomized: &VerifiableCipherText,
+        factor: &BigNumber,
+    ) -> Result<RerandomizationProof, VerencError> {
+        self.vk.check_rerandomized(original, rerandomized)?;
+        let group = &self.vk.group;
+        // Large enough to statistically hide challenge * factor
+        let blinding = group.random_value();
+        let two_b: BigNumber = &blinding << 1;
+        let commitments = self
+            .vk
+            .rerandomization_commitments(&two_b, original.e.len());
+        let challenge = self
+            .vk
+            .rerandomization_challenge(original, rerandomized, &commitments);
+        let r = self.view().schnorr(&blinding, &challenge, factor);
+        Ok(RerandomizationProof { challenge, r })
+    }
+
+    /// Verify a proof that `rerandomized` is a re-randomization of `original`
+    pub fn verify_rerandomization(
+        &self,
+        original: &VerifiableCipherText,
+        rerandomized: &VerifiableCipherText,
+        proof: &RerandomizationProof,
+    ) -> Result<(), VerencError> {
+        self.vk
+            .verify_rerandomization(original, rerandomized, proof)
     }
 }
@@ -0,0 +1,979 @@
+#[cfg(not(feature = "verify-only"))]
+use crate::precompute::ProverTables;
+use crate::{
+    audit::{self, AuditEvent},
+    explain::RecordingTranscript,
+    group::ct_eq,
+    precompute::FixedBaseTable,
+    proof_options::VectorDigest,
+    BatchItemError, Cancellation, Context, Group, ProofOptions, ProtocolVersion, VerencError,
+    VerifiableCipherText, VerifiableEncryptionProof, VerificationKey, VerificationTrace,
+};
+#[cfg(all(feature = "parallel", not(feature = "verify-only")))]
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::ops::Deref;
+use std::sync::Arc;
+use unknown_order::BigNumber;
+
+/// The bit length of Fiat-Shamir challenges
+const CHALLENGE_BITS: usize = 256;
+
+/// The domain separator of verifiable encryption proof transcripts
+pub(crate) const PROOF_DOMAIN: &[u8] = b"camenisch-shoup verifiable encryption proof";
+
+/// Somewhere the messages of a Fiat-Shamir transcript are absorbed
+pub(crate) trait TranscriptSink {
+    fn append(&mut self, label: &'static [u8], message: &[u8]);
+}
+
+impl TranscriptSink for merlin::Transcript {
+    fn append(&mut self, label: &'static [u8], message: &[u8]) {
+        self.append_message(label, message);
+    }
+}
+
+/// Slots `0..16` to borrow from instead of allocating for small ciphertexts
+const FIRST_SLOTS: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// The slots `0..count`, borrowed without allocating when `count` is small
+pub(crate) fn first_slots(count: usize) -> Cow<'static, [usize]> {
+    match FIRST_SLOTS.get(..count) {
+        Some(slots) => Cow::Borrowed(slots),
+        None => Cow::Owned((0..count).collect()),
+    }
+}
+
+/// Borrowed view of a `Group`
+#[derive(Copy, Clone, Debug)]
+pub struct GroupRef<'a> {
+    group: &'a Group,
+}
+
+impl<'a> From<&'a Group> for GroupRef<'a> {
+    fn from(group: &'a Group) -> Self {
+        Self { group }
+    }
+}
+
+impl Deref for GroupRef<'_> {
+    type Target = Group;
+
+    fn deref(&self) -> &Group {
+        self.group
+    }
+}
+
+/// Borrowed view of the public key values for encrypting and verifying
+/// without cloning or owning an `EncryptionKey` or `VerificationKey`.
+/// The values can come from any storage, e.g. a key embedded in a larger struct
+#[derive(Copy, Clone, Debug)]
+pub struct EncryptionKeyRef<'a> {
+    pub(crate) y1: &'a [BigNumber],
+    pub(crate) y2: &'a BigNumber,
+    pub(crate) y3: &'a BigNumber,
+    pub(crate) group: GroupRef<'a>,
+    pub(crate) options: ProofOptions,
+    #[cfg(not(feature = "verify-only"))]
+    pub(crate) tables: Option<&'a ProverTables>,
+}
+
+impl<'a> From<&'a VerificationKey> for EncryptionKeyRef<'a> {
+    fn from(vk: &'a VerificationKey) -> Self {
+        vk.view()
+    }
+}
+
+impl<'a> EncryptionKeyRef<'a> {
+    /// Create a view from the key values and group
+    pub fn new(
+        y1: &'a [BigNumber],
+        y2: &'a BigNumber,
+        y3: &'a BigNumber,
+        group: GroupRef<'a>,
+    ) -> Self {
+        Self {
+            y1,
+            y2,
+            y3,
+            group,
+            options: ProofOptions::default(),
+            #[cfg(not(feature = "verify-only"))]
+            tables: None,
+        }
+    }
+
+    /// Use `options` when proving and verifying with this view
+    pub fn with_options(mut self, options: ProofOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The proof options used by this view
+    pub fn options(&self) -> ProofOptions {
+        self.options
+    }
+
+    /// The group used by this key
+    pub fn group(&self) -> GroupRef<'a> {
+        self.group
+    }
+
+    /// The maximum number of messages this key supports
+    pub fn max_messages(&self) -> usize {
+        self.y1.len()
+    }
+
+    /// A 32 byte digest identifying this key and its group,
+    /// see `VerificationKey::fingerprint`
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"verenc key fingerprint");
+        transcript.append_message(b"group", &self.group.fingerprint());
+        for y in self.y1 {
+            transcript.append_message(b"y1", &y.to_bytes());
+        }
+        transcript.append_message(b"y2", &self.y2.to_bytes());
+        transcript.append_message(b"y3", &self.y3.to_bytes());
+        let mut fingerprint = [0u8; 32];
+        transcript.challenge_bytes(b"fingerprint", &mut fingerprint);
+        fingerprint
+    }
+
+    /// Verify a proof of verifiable encryption, see `VerificationKey::verify`
+    pub fn verify<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        if proof.m.len() > self.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: proof.m.len(),
+                max: self.y1.len(),
+            });
+        }
+        self.verify_in_slots(nonce, &first_slots(proof.m.len()), ciphertext, proof, None)
+    }
+
+    /// Verify a proof like `verify`, giving up with `VerencError::Cancelled`
+    /// once `cancellation` is cancelled. It's polled before each
+    /// exponentiation of the ciphertext so a proof with many slots
+    /// stops within a slot's work of the deadline or signal, e.g.
+    /// `&(Instant::now() + budget)` bounds the time spent on one request
+    pub fn verify_cancellable<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        cancellation: &dyn Cancellation,
+    ) -> Result<(), VerencError> {
+        let context = context.into();
+        if proof.m.len() > self.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: proof.m.len(),
+                max: self.y1.len(),
+            });
+        }
+        self.verify_in_slots(
+            context.as_bytes(),
+            &first_slots(proof.m.len()),
+            ciphertext,
+            proof,
+            Some(cancellation),
+        )
+    }
+
+    /// Verify each of many proofs under this key. If any fails every
+    /// failing item is returned with its position, ciphertext digest and nonce.
+    ///
+    /// This is not batch verification: each challenge is a hash of its
+    /// proof's test values and the proof carries only responses, so every
+    /// proof's test values are recomputed in full and a random linear
+    /// combination of them would save nothing. What's shared are the
+    /// fixed-base tables for `g` and the slots in use, built once for
+    /// every item, which pays for itself from two proofs on. A table uses
+    /// about 2 MiB with a 2048-bit modulus and is dropped on return
+    pub fn verify_all(
+        &self,
+        items: &[(&[u8], &VerifiableCipherText, &VerifiableEncryptionProof)],
+    ) -> Result<(), Vec<BatchItemError>> {
+        let group: &Group = &self.group;
+        let (g, y1) = if items.len() > 1 {
+            let widest = items
+                .iter()
+                .map(|(_, _, proof)| proof.m.len().min(self.y1.len()))
+                .max()
+                .unwrap_or(0);
+            let y1 = self.y1[..widest]
+                .iter()
+                .map(|y| Some(Arc::new(FixedBaseTable::new(group, y))))
+                .collect();
+            (Some(FixedBaseTable::new(group, &group.g)), y1)
+        } else {
+            (None, Vec::new())
+        };
+        let slots = (0..self.y1.len()).collect::<Vec<usize>>();
+        let failures = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (nonce, ciphertext, proof))| {
+                let result = if proof.m.len() > self.y1.len() {
+                    Err(VerencError::TooManyMessages {
+                        messages: proof.m.len(),
+                        max: self.y1.len(),
+                    })
+                } else {
+                    self.verify_with_tables(
+                        nonce,
+                        &slots[..proof.m.len()],
+                        ciphertext,
+                        proof,
+                        g.as_ref(),
+                        &y1,
+                        None,
+                    )
+                };
+                result
+                    .err()
+                    .map(|e| BatchItemError::new(i, ciphertext.digest(), nonce, e))
+            })
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Verify a proof of verifiable encryption into the key slots at `indices`
+    pub fn verify_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        indices: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        self.check_slots(indices)?;
+        if indices.len() != proof.m.len() {
+            return Err(VerencError::IndexCountMismatch {
+                indices: indices.len(),
+                messages: proof.m.len(),
+            });
+        }
+        self.verify_in_slots(nonce, indices, ciphertext, proof, None)
+    }
+
+    fn verify_in_slots(
+        &self,
+        nonce: &[u8],
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        cancellation: Option<&dyn Cancellation>,
+    ) -> Result<(), VerencError> {
+        self.verify_with_tables(nonce, slots, ciphertext, proof, None, &[], cancellation)
+    }
+
+    /// Verify using the fixed-base tables given for `g` and each slot of `y1`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn verify_with_tables(
+        &self,
+        nonce: &[u8],
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        g: Option<&FixedBaseTable>,
+        y1: &[Option<Arc<FixedBaseTable>>],
+        cancellation: Option<&dyn Cancellation>,
+    ) -> Result<(), VerencError> {
+        let result = self.check_with_tables(nonce, slots, ciphertext, proof, g, y1, cancellation);
+        if let Err(error) = &result {
+            audit::record(|| AuditEvent::VerificationFailed {
+                key_fingerprint: self.fingerprint(),
+                ciphertext_hash: ciphertext.digest(),
+                nonce,
+                error,
+            });
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn check_with_tables(
+        &self,
+        nonce: &[u8],
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        g: Option<&FixedBaseTable>,
+        y1: &[Option<Arc<FixedBaseTable>>],
+        cancellation: Option<&dyn Cancellation>,
+    ) -> Result<(), VerencError> {
+        self.check_shape(ciphertext, proof)?;
+        let poll = || match cancellation {
+            Some(c) if c.is_cancelled() => Err(VerencError::Cancelled),
+            _ => Ok(()),
+        };
+        let group = &self.group;
+        // Reconstruct u, e, v
+        let two_c = &proof.challenge << 1;
+        let two_r = &proof.r << 1;
+
+        // u^{2c} * g^{2r} mod n^2
+        poll()?;
+        let u = match g {
+            Some(table) => group.mul(
+                &group.pow(&ciphertext.u, &two_c),
+                &table.pow(group, &two_r)?,
+            ),
+            None => self.test_u(&ciphertext.u, &two_c, &two_r),
+        };
+
+        let mut transcript = self.proof_transcript(nonce, ciphertext);
+        transcript.append_message(b"ciphertext_test.u", &u.to_bytes());
+        // Each reconstructed e is absorbed as soon as it's computed
+        // so memory use doesn't grow with the number of messages
+        self.append_count(
+            &mut transcript,
+            b"ciphertext_test.e.count",
+            ciphertext.e.len(),
+        );
+        let mut digest = self
+            .options
+            .prehash_e
+            .then(|| VectorDigest::new(b"ciphertext_test.e"));
+        for ((ee, i), m) in ciphertext.e.iter().zip(slots.iter()).zip(proof.m.iter()) {
+            poll()?;
+            let e = match y1.get(*i).and_then(Option::as_deref) {
+                Some(table) => group.mul(
+                    &group.mul(&group.pow(ee, &two_c), &table.pow(group, &two_r)?),
+                    &group.h_pow(&(m << 1)),
+                ),
+                None => self.test_e(ee, *i, &two_c, &two_r, m),
+            };
+            match digest.as_mut() {
+                Some(digest) => digest.absorb(&e),
+                None => transcript.append_message(b"ciphertext_test.e", &e.to_bytes()),
+            }
+        }
+        if let Some(digest) = digest {
+            transcript.append_message(b"ciphertext_test.e", &digest.finish());
+        }
+
+        let hs = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        poll()?;
+        let v = self.test_v(&ciphertext.v, &two_c, &two_r, &hs);
+        transcript.append_message(b"ciphertext_test.v", &v.to_bytes());
+
+        let challenge = VerificationKey::challenge(&mut transcript);
+        if ct_eq(&challenge, &proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::InvalidProof)
+        }
+    }
+
+    /// Check the proof has a response per ciphertext value and they
+    /// and the challenge are in range
+    fn check_shape(
+        &self,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        if proof.m.len() != ciphertext.e.len() {
+            return Err(VerencError::MessageCountMismatch {
+                messages: proof.m.len(),
+                ciphertext: ciphertext.e.len(),
+            });
+        }
+        self.check_challenge_and_r(&proof.challenge, &proof.r)?;
+        for m in &proof.m {
+            self.check_response(m)?;
+        }
+        Ok(())
+    }
+
+    /// Verify a proof in the first slots like `verify`, returning every
+    /// value computed along the way instead of only the result.
+    /// Values are computed even when an earlier check fails
+    pub fn verify_explain<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> VerificationTrace {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        let checks = if proof.m.len() > self.y1.len() {
+            Err(VerencError::TooManyMessages {
+                messages: proof.m.len(),
+                max: self.y1.len(),
+            })
+        } else {
+            self.check_shape(ciphertext, proof)
+        };
+        let slots = first_slots(proof.m.len().min(self.y1.len()));
+        let group = &self.group;
+        let two_c = &proof.challenge << 1;
+        let two_r = &proof.r << 1;
+        let u = self.test_u(&ciphertext.u, &two_c, &two_r);
+        let e = ciphertext
+            .e
+            .iter()
+            .zip(slots.iter())
+            .zip(proof.m.iter())
+            .map(|((ee, i), m)| self.test_e(ee, *i, &two_c, &two_r, m))
+            .collect::<Vec<_>>();
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let v = self.test_v(&ciphertext.v, &two_c, &two_r, &hash);
+
+        let mut transcript = RecordingTranscript::new(PROOF_DOMAIN);
+        self.append_statement(&mut transcript, nonce, ciphertext);
+        transcript.append(b"ciphertext_test.u", &u.to_bytes());
+        self.append_e(
+            &mut transcript,
+            b"ciphertext_test.e.count",
+            b"ciphertext_test.e",
+            &e,
+        );
+        transcript.append(b"ciphertext_test.v", &v.to_bytes());
+        let challenge = VerificationKey::challenge(&mut transcript.transcript);
+        let failure = match checks {
+            Err(error) => Some(error),
+            Ok(()) if !ct_eq(&challenge, &proof.challenge) => Some(VerencError::InvalidProof),
+            Ok(()) => None,
+        };
+        VerificationTrace {
+            nonce: nonce.to_vec(),
+            slots: slots.to_vec(),
+            u,
+            e,
+            hash,
+            v,
+            transcript: transcript.entries,
+            challenge,
+            proof_challenge: proof.challenge.clone(),
+            failure: failure.map(|error| error.to_string()),
+        }
+    }
+
+    /// Check the challenge and responses are in the ranges honest proofs
+    /// produce so each proof has one encoding. Exponents differing by a
+    /// multiple of an element's order, e.g. `m + n^2` for `h`, would
+    /// otherwise verify the same.
+    /// The challenge is 256 bits and `r` and each `m` are below n / 4
+    /// and above -n^2 since they are `t - (c * x mod n^2)` with `0 < t < n / 4`
+    pub(crate) fn check_challenge_and_r(
+        &self,
+        challenge: &BigNumber,
+        r: &BigNumber,
+    ) -> Result<(), VerencError> {
+        if challenge < &BigNumber::zero() || challenge >= &(BigNumber::one() << CHALLENGE_BITS) {
+            return Err(VerencError::ProofOutOfRange);
+        }
+        self.check_response(r)
+    }
+
+    /// Check a response is in range, see `check_challenge_and_r`
+    pub(crate) fn check_response(&self, response: &BigNumber) -> Result<(), VerencError> {
+        if response <= &-self.group.nn.clone() || response >= &self.group.nd4 {
+            return Err(VerencError::ProofOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// u^{2c} * g^{2r} recomputed from the responses
+    pub(crate) fn test_u(&self, u: &BigNumber, two_c: &BigNumber, two_r: &BigNumber) -> BigNumber {
+        self.group.multi_pow(&[(u, two_c), (&self.group.g, two_r)])
+    }
+
+    /// e^{2c} * y1^{2r} * h^{2m} recomputed from the responses for `slot`
+    pub(crate) fn test_e(
+        &self,
+        e: &BigNumber,
+        slot: usize,
+        two_c: &BigNumber,
+        two_r: &BigNumber,
+        m: &BigNumber,
+    ) -> BigNumber {
+        let group = &self.group;
+        let ey = group.multi_pow(&[(e, two_c), (&self.y1[slot], two_r)]);
+        group.mul(&ey, &group.h_pow(&(m << 1)))
+    }
+
+    /// v^{2c} * (y2 * y3^H)^{2r} recomputed from the responses
+    pub(crate) fn test_v(
+        &self,
+        v: &BigNumber,
+        two_c: &BigNumber,
+        two_r: &BigNumber,
+        hash: &BigNumber,
+    ) -> BigNumber {
+        let group = &self.group;
+        let base = group.mul(&group.pow(self.y3, hash), self.y2);
+        group.multi_pow(&[(v, two_c), (&base, two_r)])
+    }
+
+    /// Check `slots` are strictly increasing indices into `y1`
+    pub(crate) fn check_slots(&self, slots: &[usize]) -> Result<(), VerencError> {
+        for (i, slot) in slots.iter().enumerate() {
+            if *slot >= self.y1.len() {
+                return Err(VerencError::IndexOutOfRange {
+                    index: *slot,
+                    max: self.y1.len(),
+                });
+            }
+            if i > 0 && slots[i - 1] >= *slot {
+                return Err(VerencError::IndexNotIncreasing { index: *slot });
+            }
+        }
+        Ok(())
+    }
+
+    /// Start the proof transcript with the public statement.
+    /// Vector values are absorbed one element at a time
+    /// or `e` as a digest with `ProofOptions::prehash_e`
+    pub(crate) fn proof_transcript(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+    ) -> merlin::Transcript {
+        let mut transcript = merlin::Transcript::new(PROOF_DOMAIN);
+        self.append_statement(&mut transcript, nonce, ciphertext);
+        transcript
+    }
+
+    /// Append the public statement `proof_transcript` starts with to `transcript`
+    pub(crate) fn append_statement(
+        &self,
+        transcript: &mut impl TranscriptSink,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+    ) {
+        let group = &self.group;
+        transcript.append(b"nonce", nonce);
+        transcript.append(b"n", &group.n.to_bytes());
+        transcript.append(b"g", &group.g.to_bytes());
+        transcript.append(b"y2", &self.y2.to_bytes());
+        transcript.append(b"y3", &self.y3.to_bytes());
+        self.append_count(transcript, b"y1.count", self.y1.len());
+        for y in self.y1.iter() {
+            transcript.append(b"y1", &y.to_bytes());
+        }
+        transcript.append(b"ciphertext.u", &ciphertext.u.to_bytes());
+        self.append_e(
+            transcript,
+            b"ciphertext.e.count",
+            b"ciphertext.e",
+            &ciphertext.e,
+        );
+        transcript.append(b"ciphertext.v", &ciphertext.v.to_bytes());
+    }
+
+    pub(crate) fn append_count(
+        &self,
+        transcript: &mut impl TranscriptSink,
+        label: &'static [u8],
+        count: usize,
+    ) {
+        if self.options.version == ProtocolVersion::V2 {
+            // The encoding of `merlin::Transcript::append_u64`
+            transcript.append(label, &(count as u64).to_le_bytes());
+        }
+    }
+
+    pub(crate) fn append_e(
+        &self,
+        transcript: &mut impl TranscriptSink,
+        count_label: &'static [u8],
+        label: &'static [u8],
+        e: &[BigNumber],
+    ) {
+        self.append_count(transcript, count_label, e.len());
+        if self.options.prehash_e {
+            let mut digest = VectorDigest::new(label);
+            e.iter().for_each(|e| digest.absorb(e));
+            transcript.append(label, &digest.finish());
+        } else {
+            for e in e {
+                transcript.append(label, &e.to_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl<'a> EncryptionKeyRef<'a> {
+    /// Encrypt multiple messages, see `EncryptionKey::encrypt`
+    pub fn encrypt<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+    ) -> Result<VerifiableCipherText, VerencError> {
+        let context = context.into();
+        let domain = context.as_bytes();
+        if msgs.len() > self.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: msgs.len(),
+                max: self.y1.len(),
+            });
+        }
+        let slots = (0..msgs.len()).collect::<Vec<usize>>();
+        self.encrypt_in_slots(domain, &slots, msgs)
+    }
+
+    /// Encrypt byte strings as messages, see `EncryptionKey::encrypt_bytes_iter`
+    pub fn encrypt_bytes_iter<I, B>(
+        &self,
+        domain: &[u8],
+        msgs: I,
+    ) -> Result<VerifiableCipherText, VerencError>
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        let mut numbers = Vec::with_capacity(self.y1.len());
+        for (i, bytes) in msgs.into_iter().enumerate() {
+            if i == self.y1.len() {
+                return Err(VerencError::TooManyMessages {
+                    messages: i + 1,
+                    max: self.y1.len(),
+                });
+            }
+            let m = BigNumber::from_slice(bytes.as_ref());
+            if m >= self.group.n {
+                return Err(VerencError::MessageOutOfRange { index: i });
+            }
+            numbers.push(m);
+        }
+        let slots = (0..numbers.len()).collect::<Vec<usize>>();
+        self.encrypt_in_slots(domain, &slots, &numbers)
+    }
+
+    /// Encrypt messages into specific key slots, see `EncryptionKey::encrypt_at_indices`
+    pub fn encrypt_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[(usize, BigNumber)],
+    ) -> Result<VerifiableCipherText, VerencError> {
+        let (slots, msgs) = split_indexed(msgs);
+        self.check_slots(&slots)?;
+        self.encrypt_in_slots(context.into().as_bytes(), &slots, &msgs)
+    }
+
+    pub(crate) fn encrypt_in_slots(
+        &self,
+        domain: &[u8],
+        slots: &[usize],
+        msgs: &[BigNumber],
+    ) -> Result<VerifiableCipherText, VerencError> {
+        for (i, m) in msgs.iter().enumerate() {
+            if m > &self.group.n {
+                return Err(VerencError::MessageOutOfRange { index: i });
+            }
+        }
+
+        let r = self.group.random_for_encrypt();
+
+        Ok(self.encrypt_with_blinding_factor(domain, slots, msgs, &r))
+    }
+
+    /// Encrypt with caller supplied randomness,
+    /// see `EncryptionKey::encrypt_with_randomness`
+    pub fn encrypt_with_randomness<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+        r: &BigNumber,
+    ) -> Result<VerifiableCipherText, VerencError> {
+        let slots = self.check_with_randomness(msgs, r)?;
+        Ok(self.encrypt_with_blinding_factor(context.into().as_bytes(), &slots, msgs, r))
+    }
+
+    /// Encrypt and prove, see `EncryptionKey::encrypt_and_prove`
+    pub fn encrypt_and_prove<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        let group = &self.group;
+        let blindings = (0..msgs.len())
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<BigNumber>>();
+        self.encrypt_and_prove_blindings(nonce, msgs, blindings.as_slice())
+    }
+
+    /// Encrypt into specific key slots and prove,
+    /// see `EncryptionKey::encrypt_and_prove_at_indices`
+    pub fn encrypt_and_prove_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[(usize, BigNumber)],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        let (slots, msgs) = split_indexed(msgs);
+        self.check_slots(&slots)?;
+        let group = &self.group;
+        let blindings = (0..msgs.len())
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<BigNumber>>();
+        let r = group.random_for_encrypt();
+        self.prove_in_slots(nonce, &slots, &msgs, &blindings, &r)
+    }
+
+    /// Encrypt and prove with the given blindings,
+    /// see `EncryptionKey::encrypt_and_prove_blindings`
+    pub fn encrypt_and_prove_blindings<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+        blindings: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        if msgs.len() != blindings.len() {
+            return Err(VerencError::BlindingCountMismatch {
+                messages: msgs.len(),
+                blindings: blindings.len(),
+            });
+        }
+        if msgs.len() > self.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: msgs.len(),
+                max: self.y1.len(),
+            });
+        }
+        let slots = (0..msgs.len()).collect::<Vec<usize>>();
+        let r = self.group.random_for_encrypt();
+        self.prove_in_slots(nonce, &slots, msgs, blindings, &r)
+    }
+
+    /// Encrypt with caller supplied randomness and prove,
+    /// see `EncryptionKey::encrypt_and_prove_with_randomness`
+    pub fn encrypt_and_prove_with_randomness<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+        r: &BigNumber,
+        blindings: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        let context = context.into();
+        let nonce = context.as_bytes();
+        if msgs.len() != blindings.len() {
+            return Err(VerencError::BlindingCountMismatch {
+                messages: msgs.len(),
+                blindings: blindings.len(),
+            });
+        }
+        let slots = self.check_with_randomness(msgs, r)?;
+        self.prove_in_slots(nonce, &slots, msgs, blindings, r)
+    }
+
+    /// Check `msgs` fit in the key and `r` is in the range
+    /// `Group::random_for_encrypt` draws from, returning the slots to use
+    pub(crate) fn check_with_randomness(
+        &self,
+        msgs: &[BigNumber],
+        r: &BigNumber,
+    ) -> Result<Vec<usize>, VerencError> {
+        if msgs.len() > self.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: msgs.len(),
+                max: self.y1.len(),
+            });
+        }
+        for (i, m) in msgs.iter().enumerate() {
+            if m > &self.group.n {
+                return Err(VerencError::MessageOutOfRange { index: i });
+            }
+        }
+        if r <= &BigNumber::zero() || r >= &self.group.nd4 {
+            return Err(VerencError::InvalidRandomness);
+        }
+        Ok((0..msgs.len()).collect())
+    }
+
+    pub(crate) fn prove_in_slots(
+        &self,
+        nonce: &[u8],
+        slots: &[usize],
+        msgs: &[BigNumber],
+        blindings: &[BigNumber],
+        r: &BigNumber,
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        let group = &self.group;
+        let zero = BigNumber::zero();
+        for (i, b) in blindings.iter().enumerate() {
+            if b <= &zero || b >= &group.nd4 {
+                return Err(VerencError::InvalidBlinding { index: i });
+            }
+        }
+
+        let r_tick = group.random_for_encrypt();
+        let ciphertext = self.encrypt_with_blinding_factor(nonce, slots, msgs, r);
+
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let test_values = self.ciphertext_test_values(&r_tick, &hash, slots, blindings);
+        let challenge = self.fiat_shamir(nonce, &ciphertext, &test_values);
+
+        let r_hat = self.schnorr(&r_tick, &challenge, r);
+        let m_hat = group.schnorr_responses(&challenge, blindings, msgs);
+        audit::record(|| AuditEvent::ProofIssued {
+            key_fingerprint: self.fingerprint(),
+            ciphertext_hash: ciphertext.digest(),
+            nonce,
+        });
+
+        Ok((
+            ciphertext,
+            VerifiableEncryptionProof {
+                challenge,
+                r: r_hat,
+                m: m_hat,
+            },
+        ))
+    }
+
+    pub(crate) fn ciphertext_test_values(
+        &self,
+        r: &BigNumber,
+        hash: &BigNumber,
+        slots: &[usize],
+        msgs: &[BigNumber],
+    ) -> VerifiableCipherText {
+        let two_r = r << 1;
+        let two_m = msgs.iter().map(|m| m << 1).collect::<Vec<BigNumber>>();
+        let u = self.compute_u(&two_r);
+        let e = self.compute_e(slots, two_m.as_slice(), &two_r);
+        let v = self.compute_v(&two_r, hash, false);
+        VerifiableCipherText { u, e, v }
+    }
+
+    /// Recompute the test values from the responses in `proof`
+    /// the same way `verify` does. The caller checks the lengths match
+    pub(crate) fn test_values_from_responses(
+        &self,
+        domain: &[u8],
+        slots: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> VerifiableCipherText {
+        let group = &self.group;
+        let two_c = &proof.challenge << 1;
+        let two_r = &proof.r << 1;
+        let u = self.test_u(&ciphertext.u, &two_c, &two_r);
+        let e = ciphertext
+            .e
+            .iter()
+            .zip(slots.iter())
+            .zip(proof.m.iter())
+            .map(|((ee, i), m)| self.test_e(ee, *i, &two_c, &two_r, m))
+            .collect();
+        let hs = group.hash(&ciphertext.u, ciphertext.e.as_slice(), domain);
+        let v = self.test_v(&ciphertext.v, &two_c, &two_r, &hs);
+        VerifiableCipherText { u, e, v }
+    }
+
+    pub(crate) fn compute_v(&self, r: &BigNumber, hash: &BigNumber, abs: bool) -> BigNumber {
+        let group = &self.group;
+        // (y2 * (y3^H(u, e, L)))^r
+        let v = group.pow(&group.mul(&group.pow(self.y3, hash), self.y2), r);
+        if abs {
+            group.abs(&v).into_inner()
+        } else {
+            v
+        }
+    }
+
+    pub(crate) fn schnorr(
+        &self,
+        tilde: &BigNumber,
+        challenge: &BigNumber,
+        value: &BigNumber,
+    ) -> BigNumber {
+        tilde - self.group.mul(challenge, value)
+    }
+
+    pub(crate) fn encrypt_with_blinding_factor(
+        &self,
+        domain: &[u8],
+        slots: &[usize],
+        msgs: &[BigNumber],
+        r: &BigNumber,
+    ) -> VerifiableCipherText {
+        let u = self.compute_u(r);
+        let e = self.compute_e(slots, msgs, r);
+        let hash = self.group.hash(&u, &e, domain);
+        let v = self.compute_v(r, &hash, true);
+        let ciphertext = VerifiableCipherText { u, e, v };
+        audit::record(|| AuditEvent::Encrypted {
+            key_fingerprint: self.fingerprint(),
+            ciphertext_hash: ciphertext.digest(),
+            domain,
+            messages: msgs.len(),
+        });
+        ciphertext
+    }
+
+    pub(crate) fn compute_u(&self, r: &BigNumber) -> BigNumber {
+        match self.tables {
+            Some(tables) => tables.g.pow(&self.group, r),
+            None => self.group.g_pow(r),
+        }
+    }
+
+    pub(crate) fn compute_e(
+        &self,
+        slots: &[usize],
+        msgs: &[BigNumber],
+        r: &BigNumber,
+    ) -> Vec<BigNumber> {
+        let group = &self.group;
+        let encrypt = |(i, m): (&usize, &BigNumber)| {
+            let yr = match self.tables {
+                Some(tables) => tables.y1[*i].pow(group, r),
+                None => group.pow(&self.y1[*i], r),
+            };
+            group.mul(&yr, &group.h_pow(m))
+        };
+        // Each message is independent so with `parallel` they are spread across rayon's pool
+        #[cfg(feature = "parallel")]
+        let e = slots.par_iter().zip(msgs.par_iter()).map(encrypt).collect();
+        #[cfg(not(feature = "parallel"))]
+        let e = slots.iter().zip(msgs.iter()).map(encrypt).collect();
+        e
+    }
+}
+
+#[cfg(any(feature = "parallel", not(feature = "verify-only")))]
+impl EncryptionKeyRef<'_> {
+    pub(crate) fn fiat_shamir(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        test_values: &VerifiableCipherText,
+    ) -> BigNumber {
+        let mut transcript = self.proof_transcript(nonce, ciphertext);
+        transcript.append_message(b"ciphertext_test.u", &test_values.u.to_bytes());
+        self.append_e(
+            &mut transcript,
+            b"ciphertext_test.e.count",
+            b"ciphertext_test.e",
+            &test_values.e,
+        );
+        transcript.append_message(b"ciphertext_test.v", &test_values.v.to_bytes());
+        VerificationKey::challenge(&mut transcript)
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+fn split_indexed(msgs: &[(usize, BigNumber)]) -> (Vec<usize>, Vec<BigNumber>) {
+    msgs.iter().map(|(i, m)| (*i, m.clone())).unzip()
+}
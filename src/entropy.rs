@@ -0,0 +1,310 @@
+use crate::{sampling::sample_below, DecryptionKey, Group, ParameterPreset, VerencError};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use std::fmt::{self, Display};
+use std::num::NonZeroU32;
+
+/// False positive rate of each test, α = 2^-20 as recommended by SP 800-90B
+const ALPHA_LOG2: f64 = 20.0;
+/// Window size of the adaptive proportion test for non-binary samples
+const PROPORTION_WINDOW: usize = 512;
+/// Bytes tested at start up before generating primes, SP 800-90B 4.3 asks for 1024 samples
+const STARTUP_SAMPLES: usize = 1024;
+
+/// Why an entropy health test failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EntropyHealthError {
+    /// The same byte was output `run` times in a row
+    Repetition {
+        /// The length of the run
+        run: usize,
+    },
+    /// One byte was output `count` times in a window of `window` bytes
+    AdaptiveProportion {
+        /// The number of times the byte was seen
+        count: usize,
+        /// The size of the window
+        window: usize,
+    },
+}
+
+impl Display for EntropyHealthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Repetition { run } => write!(
+                f,
+                "Entropy source failed the repetition count test, a byte repeated {} times",
+                run
+            ),
+            Self::AdaptiveProportion { count, window } => write!(
+                f,
+                "Entropy source failed the adaptive proportion test, a byte occurred {} times in {}",
+                count, window
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EntropyHealthError {}
+
+impl From<EntropyHealthError> for String {
+    fn from(e: EntropyHealthError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<EntropyHealthError> for VerencError {
+    fn from(e: EntropyHealthError) -> Self {
+        Self::Other(e.to_string())
+    }
+}
+
+/// The repetition count and adaptive proportion health tests of
+/// NIST SP 800-90B section 4.4 applied to RNG output one byte at a time.
+///
+/// The cutoffs are derived from the min-entropy per byte the source claims.
+/// They catch a source that is stuck or badly biased,
+/// they can't tell a good source from a predictable one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EntropyHealthTests {
+    repetition_cutoff: usize,
+    proportion_cutoff: usize,
+}
+
+impl Default for EntropyHealthTests {
+    /// Tests for a source claiming full entropy such as the operating system's RNG
+    fn default() -> Self {
+        Self::new(8.0)
+    }
+}
+
+impl EntropyHealthTests {
+    /// Tests for a source claiming `min_entropy` bits per byte.
+    /// Panics unless `min_entropy` is in (0, 8]
+    pub fn new(min_entropy: f64) -> Self {
+        assert!(
+            min_entropy > 0.0 && min_entropy <= 8.0,
+            "min-entropy must be in (0, 8] bits per byte"
+        );
+        let repetition_cutoff = 1 + (ALPHA_LOG2 / min_entropy).ceil() as usize;
+        // C = 1 + CRITBINOM(W, 2^-H, 1 - α)
+        let p = (-min_entropy).exp2();
+        let target = 1.0 - (-ALPHA_LOG2).exp2();
+        let mut proportion_cutoff = PROPORTION_WINDOW;
+        if p < 1.0 {
+            let w = PROPORTION_WINDOW as f64;
+            let mut ln_pmf = w * (1.0 - p).ln();
+            let mut cdf = 0.0;
+            for k in 0..PROPORTION_WINDOW {
+                cdf += ln_pmf.exp();
+                if cdf >= target {
+                    proportion_cutoff = k + 1;
+                    break;
+                }
+                let kf = k as f64;
+                ln_pmf += ((w - kf) / (kf + 1.0)).ln() + (p / (1.0 - p)).ln();
+            }
+        }
+        Self {
+            repetition_cutoff,
+            proportion_cutoff,
+        }
+    }
+
+    /// A run of this many equal bytes fails the repetition count test
+    pub fn repetition_cutoff(&self) -> usize {
+        self.repetition_cutoff
+    }
+
+    /// This many occurrences of a byte in a window fails the adaptive proportion test
+    pub fn proportion_cutoff(&self) -> usize {
+        self.proportion_cutoff
+    }
+
+    /// Run both tests over `sample`
+    pub fn check(&self, sample: &[u8]) -> Result<(), EntropyHealthError> {
+        let mut state = HealthState::default();
+        sample.iter().try_for_each(|b| state.feed(self, *b))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct HealthState {
+    last: u8,
+    run: usize,
+    reference: u8,
+    seen: usize,
+    count: usize,
+}
+
+impl HealthState {
+    fn feed(&mut self, tests: &EntropyHealthTests, b: u8) -> Result<(), EntropyHealthError> {
+        if self.run > 0 && b == self.last {
+            self.run += 1;
+            if self.run >= tests.repetition_cutoff {
+                return Err(EntropyHealthError::Repetition { run: self.run });
+            }
+        } else {
+            self.last = b;
+            self.run = 1;
+        }
+
+        if self.seen == 0 {
+            self.reference = b;
+            self.count = 1;
+        } else if b == self.reference {
+            self.count += 1;
+            if self.count >= tests.proportion_cutoff {
+                return Err(EntropyHealthError::AdaptiveProportion {
+                    count: self.count,
+                    window: PROPORTION_WINDOW,
+                });
+            }
+        }
+        self.seen = (self.seen + 1) % PROPORTION_WINDOW;
+        Ok(())
+    }
+}
+
+/// An RNG that runs `EntropyHealthTests` continuously over everything `inner` outputs.
+///
+/// After the first failure `try_fill_bytes` returns an error and
+/// `fill_bytes` returns zeros, so check `status` before using any output
+/// from `fill_bytes` or a function that takes an `RngCore`
+#[derive(Debug)]
+pub struct HealthTestedRng<R> {
+    inner: R,
+    tests: EntropyHealthTests,
+    state: HealthState,
+    failure: Option<EntropyHealthError>,
+}
+
+impl<R: RngCore> HealthTestedRng<R> {
+    /// Test the output of `inner` with `tests`
+    pub fn new(inner: R, tests: EntropyHealthTests) -> Self {
+        Self {
+            inner,
+            tests,
+            state: HealthState::default(),
+            failure: None,
+        }
+    }
+
+    /// The first failed test if any
+    pub fn status(&self) -> Result<(), EntropyHealthError> {
+        self.failure.map_or(Ok(()), Err)
+    }
+
+    /// The tested RNG
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn test(&mut self, dest: &mut [u8]) -> Result<(), EntropyHealthError> {
+        if self.failure.is_none() {
+            let Self {
+                tests,
+                state,
+                failure,
+                ..
+            } = self;
+            *failure = dest.iter().try_for_each(|b| state.feed(tests, *b)).err();
+        }
+        if self.failure.is_some() {
+            dest.iter_mut().for_each(|b| *b = 0);
+        }
+        self.status()
+    }
+}
+
+impl<R: RngCore> RngCore for HealthTestedRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest);
+        let _ = self.test(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.inner.try_fill_bytes(dest)?;
+        self.test(dest).map_err(|_| {
+            let code = NonZeroU32::new(rand_core::Error::CUSTOM_START).expect("non-zero");
+            rand_core::Error::from(code)
+        })
+    }
+}
+
+impl<R: RngCore + CryptoRng> CryptoRng for HealthTestedRng<R> {}
+
+impl DecryptionKey {
+    /// Create a new random decryption key like `random` from the operating
+    /// system's RNG after it passes `tests`. No key is returned if any of the
+    /// bytes sampled for it fail
+    pub fn random_with_health_tests(
+        num_messages: usize,
+        group: &Group,
+        tests: EntropyHealthTests,
+    ) -> Result<Self, EntropyHealthError> {
+        Self::random_with_tested_rng(num_messages, group, &mut HealthTestedRng::new(OsRng, tests))
+    }
+
+    /// Create a new random decryption key from `rng`.
+    /// No key is returned if any of the bytes sampled for it fail the health tests
+    pub fn random_with_tested_rng<R: RngCore + CryptoRng>(
+        num_messages: usize,
+        group: &Group,
+        rng: &mut HealthTestedRng<R>,
+    ) -> Result<Self, EntropyHealthError> {
+        let x1 = (0..num_messages)
+            .map(|_| sample_below(&group.n2d4, rng))
+            .collect();
+        let x2 = sample_below(&group.n2d4, rng);
+        let x3 = sample_below(&group.n2d4, rng);
+        let mut key = Self {
+            x1,
+            x2,
+            x3,
+            group: group.clone(),
+        };
+        if let Err(e) = rng.status() {
+            zeroize::Zeroize::zeroize(&mut key);
+            return Err(e);
+        }
+        Ok(key)
+    }
+}
+
+impl Group {
+    /// Create a random paillier group like `random` after `tests` pass on a
+    /// start up sample from the operating system's RNG.
+    ///
+    /// The primes are generated by the `unknown_order` backend which draws
+    /// from the same source but can't be tested directly
+    pub fn random_with_health_tests(tests: EntropyHealthTests) -> Result<Self, VerencError> {
+        Self::with_preset_and_health_tests(ParameterPreset::Rsa2048, tests)
+    }
+
+    /// Create a random paillier group with the modulus size of `preset`,
+    /// see `random_with_health_tests`
+    pub fn with_preset_and_health_tests(
+        preset: ParameterPreset,
+        tests: EntropyHealthTests,
+    ) -> Result<Self, VerencError> {
+        let mut sample = zeroize::Zeroizing::new(vec![0u8; STARTUP_SAMPLES]);
+        OsRng
+            .try_fill_bytes(&mut sample)
+            .map_err(|e| e.to_string())?;
+        tests.check(&sample)?;
+        Self::with_preset(preset)
+            .ok_or_else(|| VerencError::Other("Unable to generate group".to_string()))
+    }
+}
@@ -0,0 +1,217 @@
+use crate::checked_hex::encode_hex;
+use std::fmt::{self, Display};
+
+/// Why encrypting, proving, verifying or decrypting failed.
+///
+/// Every public fallible function of the crate returns it. `Other`
+/// carries the message of failures without a variant of their own.
+/// Converts into a `String` with the same message for callers that
+/// only log errors
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerencError {
+    /// More messages than the key has slots
+    TooManyMessages {
+        /// The number of messages
+        messages: usize,
+        /// The number of slots of the key
+        max: usize,
+    },
+    /// The proof has a different number of messages than the ciphertext
+    MessageCountMismatch {
+        /// The number of messages in the proof
+        messages: usize,
+        /// The number of messages in the ciphertext
+        ciphertext: usize,
+    },
+    /// A different number of key slots than messages
+    IndexCountMismatch {
+        /// The number of slots
+        indices: usize,
+        /// The number of messages
+        messages: usize,
+    },
+    /// A different number of blindings than messages
+    BlindingCountMismatch {
+        /// The number of messages
+        messages: usize,
+        /// The number of blindings
+        blindings: usize,
+    },
+    /// A key slot the key doesn't have
+    IndexOutOfRange {
+        /// The slot
+        index: usize,
+        /// The number of slots of the key
+        max: usize,
+    },
+    /// Key slots must be strictly increasing
+    IndexNotIncreasing {
+        /// The first slot out of order
+        index: usize,
+    },
+    /// A message isn't less than n
+    MessageOutOfRange {
+        /// The position of the message
+        index: usize,
+    },
+    /// Encryption randomness isn't between 0 and n / 4
+    InvalidRandomness,
+    /// A blinding factor isn't between 0 and n / 4
+    InvalidBlinding {
+        /// The position of the blinding
+        index: usize,
+    },
+    /// The challenge or a response isn't in the range honest proofs use
+    ProofOutOfRange,
+    /// The proof doesn't verify
+    InvalidProof,
+    /// The ciphertext isn't made of valid group elements
+    InvalidCiphertext,
+    /// The tag `v` doesn't match, the ciphertext was changed or
+    /// the context is not the one it was encrypted with
+    InvalidTag,
+    /// A slot didn't decrypt to a message
+    DecryptionFailed {
+        /// The position of the message in the ciphertext
+        index: usize,
+    },
+    /// A `Cancellation` stopped the work before it finished
+    Cancelled,
+    /// Any other failure
+    Other(String),
+}
+
+impl Display for VerencError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyMessages { messages, max } => write!(
+                f,
+                "Number of messages {} is more than supported by this key {}",
+                messages, max
+            ),
+            Self::MessageCountMismatch {
+                messages,
+                ciphertext,
+            } => write!(
+                f,
+                "Number of messages {} is not equal to ciphertext {}",
+                messages, ciphertext
+            ),
+            Self::IndexCountMismatch { indices, messages } => write!(
+                f,
+                "Number of indices {} != number of messages {}",
+                indices, messages
+            ),
+            Self::BlindingCountMismatch {
+                messages,
+                blindings,
+            } => write!(
+                f,
+                "Number of messages {} != number of blindings {}",
+                messages, blindings
+            ),
+            Self::IndexOutOfRange { index, max } => write!(
+                f,
+                "Index {} is more than supported by this key {}",
+                index, max
+            ),
+            Self::IndexNotIncreasing { index } => {
+                write!(f, "Index {} is not in increasing order", index)
+            }
+            Self::MessageOutOfRange { index } => write!(f, "message {} is not valid", index),
+            Self::InvalidRandomness => write!(f, "randomness must be between 0 and n / 4"),
+            Self::InvalidBlinding { index } => {
+                write!(f, "Invalid blinding factor at index {}", index)
+            }
+            Self::ProofOutOfRange => write!(f, "Proof challenge or response is out of range"),
+            Self::InvalidProof => write!(f, "Invalid proof"),
+            Self::InvalidCiphertext => write!(f, "invalid ciphertext"),
+            Self::InvalidTag => write!(f, "u^2 != v^2"),
+            Self::DecryptionFailed { index } => {
+                write!(f, "decryption failed for message {}", index)
+            }
+            Self::Cancelled => write!(f, "Cancelled before finishing"),
+            Self::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerencError {}
+
+impl From<VerencError> for String {
+    fn from(e: VerencError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<String> for VerencError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
+}
+
+/// A failure of one item of a batch with what's needed to find the
+/// record it came from: its position, the digest of its ciphertext
+/// and its label. The failure itself is the `source`.
+///
+/// `Display` shows the context only so error reporters walking the
+/// `source` chain don't repeat the cause. `String::from` joins both
+/// for logging, `VerencError::from` does the same for the functions
+/// that stop at the first failed item
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchItemError {
+    /// The position of the item in the batch
+    pub index: usize,
+    /// `VerifiableCipherText::digest` of the item's ciphertext
+    pub ciphertext: [u8; 32],
+    /// The label, nonce or domain of the item
+    pub label: Vec<u8>,
+    /// Why the item failed
+    pub source: VerencError,
+}
+
+impl BatchItemError {
+    pub(crate) fn new(
+        index: usize,
+        ciphertext: [u8; 32],
+        label: &[u8],
+        source: impl Into<VerencError>,
+    ) -> Self {
+        Self {
+            index,
+            ciphertext,
+            label: label.to_vec(),
+            source: source.into(),
+        }
+    }
+}
+
+impl Display for BatchItemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "batch item {} with ciphertext {} and label \"{}\" failed",
+            self.index,
+            encode_hex(&self.ciphertext),
+            self.label.escape_ascii()
+        )
+    }
+}
+
+impl std::error::Error for BatchItemError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<BatchItemError> for String {
+    fn from(e: BatchItemError) -> Self {
+        format!("{}: {}", e, e.source)
+    }
+}
+
+impl From<BatchItemError> for VerencError {
+    fn from(e: BatchItemError) -> Self {
+        Self::Other(e.into())
+    }
+}
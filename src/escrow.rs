@@ -0,0 +1,40 @@
+use crate::VerifiableEncryptionProof;
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A prime order group whose secret keys can be escrowed with
+/// `EncryptionKey::escrow_secret_key`, e.g. Ed25519, secp256k1 or BLS12-381.
+///
+/// This crate doesn't depend on any curve library so implement this
+/// with the one the keys already come from.
+pub trait PublicKeyGroup {
+    /// The public key type, a group element
+    type PublicKey: Clone + PartialEq;
+
+    /// The order of the group. Secret keys are less than it
+    fn order() -> BigNumber;
+
+    /// Compute `a * G + b * P` where `G` is the generator used for public keys.
+    /// `a` and `b` are always non-negative and less than `order`
+    fn mul_add(a: &BigNumber, b: &BigNumber, public_key: &Self::PublicKey) -> Self::PublicKey;
+
+    /// Compute `lhs + rhs`
+    fn add(lhs: &Self::PublicKey, rhs: &Self::PublicKey) -> Self::PublicKey;
+
+    /// The canonical encoding of a public key
+    fn to_bytes(public_key: &Self::PublicKey) -> Vec<u8>;
+}
+
+/// Proof that a ciphertext encrypts the discrete log of a public key
+/// in a `PublicKeyGroup`, i.e. its secret key.
+///
+/// The message response is not reduced so the same
+/// value answers the challenge in both groups.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SecretKeyEscrowProof {
+    pub(crate) proof: VerifiableEncryptionProof,
+}
+
+/// The message blinding is this many bits longer than the
+/// group order to hide c * x where c is 256 bits
+pub(crate) const ESCROW_BLINDING_SLACK: usize = 256 + 128;
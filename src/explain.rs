@@ -0,0 +1,86 @@
+use crate::encryptionkeyref::TranscriptSink;
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A message absorbed into a Fiat-Shamir transcript
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    /// The merlin label, e.g. `ciphertext_test.u`
+    pub label: String,
+    /// The message bytes, big-endian for numbers
+    pub message: Vec<u8>,
+}
+
+/// Every value `verify` recomputes for a proof in the order it computes
+/// them, from `VerificationKey::verify_explain`, for comparing against
+/// another implementation step by step.
+///
+/// `transcript` is every message of the merlin transcript the challenge
+/// is drawn from, starting with the `dom-sep` message `merlin::Transcript::new`
+/// appends. The challenge is 32 bytes drawn from it under the label
+/// `verifiable encryption proof challenge` read as a big-endian number
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VerificationTrace {
+    /// The nonce the proof is checked under
+    pub nonce: Vec<u8>,
+    /// The key slots of the messages
+    pub slots: Vec<usize>,
+    /// `u^{2c} * g^{2r}`
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub u: BigNumber,
+    /// `e[i]^{2c} * y1[slot]^{2r} * h^{2m[i]}` for each message
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub e: Vec<BigNumber>,
+    /// H(u, e, L) used in the `v` equation
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub hash: BigNumber,
+    /// `v^{2c} * (y2 * y3^H)^{2r}`
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub v: BigNumber,
+    /// The transcript messages in order
+    pub transcript: Vec<TranscriptEntry>,
+    /// The challenge recomputed from the transcript
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub challenge: BigNumber,
+    /// The challenge in the proof
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub proof_challenge: BigNumber,
+    /// Why `verify` rejects the proof, if it does
+    pub failure: Option<String>,
+}
+
+impl VerificationTrace {
+    /// True if `verify` accepts the proof
+    pub fn is_valid(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// A merlin transcript that keeps a copy of every message
+pub(crate) struct RecordingTranscript {
+    pub(crate) transcript: merlin::Transcript,
+    pub(crate) entries: Vec<TranscriptEntry>,
+}
+
+impl RecordingTranscript {
+    pub(crate) fn new(domain: &'static [u8]) -> Self {
+        Self {
+            transcript: merlin::Transcript::new(domain),
+            entries: vec![entry(b"dom-sep", domain)],
+        }
+    }
+}
+
+impl TranscriptSink for RecordingTranscript {
+    fn append(&mut self, label: &'static [u8], message: &[u8]) {
+        self.transcript.append_message(label, message);
+        self.entries.push(entry(label, message));
+    }
+}
+
+fn entry(label: &[u8], message: &[u8]) -> TranscriptEntry {
+    TranscriptEntry {
+        label: String::from_utf8_lossy(label).into_owned(),
+        message: message.to_vec(),
+    }
+}
@@ -0,0 +1,43 @@
+use unknown_order::BigNumber;
+
+#[cfg(feature = "test-params")]
+/// 256-bit safe primes for small test groups
+pub(crate) const SAFE_PRIMES_256: [&str; 6] = [
+    "d20a1f803fd1649237ecb492c32154a2034ac11244baa25ee8e729324629f0df",
+    "c3f70150f7b051577588e3383e3b3a5b02b69df56af9a9631c49000fae10404f",
+    "d03b002a3349491507fb81a0f97579ba3adfa361db377d5a02dfc0c5d5e5b4e7",
+    "dc8de2e7cb04a604f5c294c7e680d89181a63d6a39c345eff0dcacd1945ba9fb",
+    "f9d42081561c7d9aabf4d47679f5e734b011c97060af4cf931532cacdd76c787",
+    "ddd5b4009bc552e29125a1ffeb7e270c7354dfb0a09ce2b3b4ee0da8b3e35b8b",
+];
+
+#[cfg(feature = "test-params")]
+/// 512-bit safe primes for small test groups
+pub(crate) const SAFE_PRIMES_512: [&str; 4] = [
+    "ee10f5f902f37c4c215aef880c5d5e99aa2d07103cdb9627ae3e4025919e5d73b83c72059f66b9f272edfb2cb1bad1ecc6f33e87bf6d1bfe0f29c7bfb6e75a9f",
+    "cb8de663523dfff5012b79d3c036f71b4febbce72be234fcc61f25b425ff197453309e37c9c19ec224d3589407d1b8a0674e0157500a33e07c747ac51553eedf",
+    "e2052da66f8a6cf241fb9a086b702f6caac6b9d9d12ab01ea1207f8bbf5727ac2beda14b9ccaaa3b9e370456ab6949947cb88d792512d3745c9001556079a147",
+    "e4763e45a95ca97c2e10ac9dbb35b6de377344975621dbb698c0908d97f5cbd1ae1efa4f340539cbd10c73d5ce0c0c4c3931e99724e7c5554cb88a413661e3ab",
+];
+
+#[cfg(any(feature = "bench_support", feature = "test-params"))]
+/// 1024-bit safe primes used for a 2048-bit modulus
+pub(crate) const SAFE_PRIMES_1024: [&str; 2] = [
+    "d43d5d49a42e82441695c5f1a57ab656b604342a21689c1fd8ea95da84043ed62b01e670da8d12a0653b84bfaf70bbfb597124d19699c2f516b774d016f6a4b1bd74eafe8c43f1d28bf98149ff60d56a307b7edde1a82a547900a8067d586272db95376f38162ba9c39e26801c10b6c3b7c5265e058f04bd1811297546c7945b",
+    "c91f0a78708167f2e84155b57ec5f6e78b121b7c948e9110a3f52a4437a291530413bb656d1b166c60035254a08c770363312e1994dd62d006c7fe8767c8eb69adddc723eb556292280ee3c98c119ec0e6378c9f7ee02b1123da3b255939087a5ea1144fb95681c97d9461a9846e935b8268f603cb47c2608c3f155d5577ec0b",
+];
+
+#[cfg(feature = "bench_support")]
+/// 1536-bit safe primes used for a 3072-bit modulus
+pub(crate) const SAFE_PRIMES_1536: [&str; 2] = [
+    "f957bd5c0645873caaf041d37c7b516ff3590fd1d31a3b801f372ae86b50d452e755b40cdade029d339ec2272b9e080ebf133f9a8f64c1ec1d94767481e8cfff743c1497967bc3fecd30b0a02151c2a6912c8563ec7d12a8c93d6de4afde2e3bcdfbd405e93830cd8ec3ae05384a851630a772ea55b3751f007e5ddcc3c3dae95efc33451a4ac1fc2ceab49c95f017387d901cbf4aa7d0dcfbcda40c4ad708ddafb9656619f9afa9aad5ac293f9cad2c1e15d5eb272206cd014923c337cf1097",
+    "c63777db848a80807fbc2589ddba0c7e7654941c3ed871f62caf89f7b2adace4252aea6b815a51bcc1e9e4049d2346904387849b8945f37bbdebff8af2cb242673a86741b1b994e62cca06c305c5ae0b078c5ff03d2c990d0c5609d537e9e1fdf3c27ab8014f99809c2606a7c3e97c9797c2d808d1965f95680ad86f9970a7f932bcae084042a6fd5cb0892dcf10b5475fe3bc9426de57b382c749bdbdb4434f87417533a4edef793bd9b749d97a1090a396c7d4d2ad512c13ce2105cc57560b",
+];
+
+pub(crate) fn from_hex(s: &str) -> BigNumber {
+    let bytes = (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("valid hex"))
+        .collect::<Vec<u8>>();
+    BigNumber::from_slice(bytes)
+}
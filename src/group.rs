@@ -1,9 +1,70 @@
-use crate::{DecryptionKey, EncryptionKey};
+#[cfg(not(feature = "verify-only"))]
+use crate::{
+    sampling::{random_below, sample_below},
+    DecryptionKey, EncryptionKey,
+};
+use crate::{Lifecycle, ParameterPreset, VerencError};
+#[cfg(not(feature = "verify-only"))]
+use rand_core::{CryptoRng, OsRng, RngCore};
+#[cfg(all(feature = "parallel", not(feature = "verify-only")))]
+use rayon::prelude::*;
 use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{self, Display};
+use std::ops::Deref;
+use subtle::{Choice, ConstantTimeEq};
 use unknown_order::BigNumber;
 use zeroize::Zeroize;
 
+#[cfg(not(feature = "verify-only"))]
+/// Compute the Schnorr responses `tilde - challenge * value` over the integers
+/// for many values sharing one challenge, for proofs whose verifier bounds the
+/// responses or checks them against a commitment in another group too
+pub(crate) fn unreduced_responses(
+    challenge: &BigNumber,
+    tildes: &[BigNumber],
+    values: &[BigNumber],
+) -> Vec<BigNumber> {
+    respond_each(tildes, values, |tilde, value| tilde - challenge * value)
+}
+
+#[cfg(not(feature = "verify-only"))]
+/// Compute the Schnorr responses `tilde - challenge * value mod modulus`
+/// for many values sharing one challenge, e.g. in a `PublicKeyGroup`'s order
+pub(crate) fn modular_responses(
+    challenge: &BigNumber,
+    tildes: &[BigNumber],
+    values: &[BigNumber],
+    modulus: &BigNumber,
+) -> Vec<BigNumber> {
+    let challenge = challenge % modulus;
+    respond_each(tildes, values, |tilde, value| {
+        tilde.modsub(&challenge.modmul(value, modulus), modulus)
+    })
+}
+
+#[cfg(not(feature = "verify-only"))]
+/// Apply `respond` to each pair of `tildes` and `values`,
+/// in parallel with the `parallel` feature
+fn respond_each<F>(tildes: &[BigNumber], values: &[BigNumber], respond: F) -> Vec<BigNumber>
+where
+    F: Fn(&BigNumber, &BigNumber) -> BigNumber + Send + Sync,
+{
+    debug_assert_eq!(tildes.len(), values.len());
+    #[cfg(feature = "parallel")]
+    let responses = tildes
+        .par_iter()
+        .zip(values.par_iter())
+        .map(|(tilde, value)| respond(tilde, value))
+        .collect();
+    #[cfg(not(feature = "parallel"))]
+    let responses = tildes
+        .iter()
+        .zip(values.iter())
+        .map(|(tilde, value)| respond(tilde, value))
+        .collect();
+    responses
+}
+
 /// Group holds public values for Verifiable Encryption and Decryption
 /// `g` and `h` correspond to the symbols with the same name in the paper.
 /// `n` = p*q, p = 2p'+1, q = 2q'+1, p, q, p', q' are all prime.
@@ -21,6 +82,43 @@ pub struct Group {
     pub(crate) n2d2: BigNumber,
     pub(crate) n2d4: BigNumber,
     pub(crate) two_inv_two: BigNumber,
+    #[zeroize(skip)]
+    pub(crate) lifecycle: Option<Lifecycle>,
+}
+
+/// An element mod n^2 in the absolute form of section 3.2,
+/// i.e. at most n^2 / 2, returned by `Group::abs` and `Group::reduced`.
+///
+/// A ciphertext's `v` must be in this form to decrypt so
+/// `VerifiableCipherText::new` takes one instead of a plain `BigNumber`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReducedElement(pub(crate) BigNumber);
+
+impl ReducedElement {
+    /// The element
+    pub fn into_inner(self) -> BigNumber {
+        self.0
+    }
+}
+
+impl Deref for ReducedElement {
+    type Target = BigNumber;
+
+    fn deref(&self) -> &BigNumber {
+        &self.0
+    }
+}
+
+impl From<ReducedElement> for BigNumber {
+    fn from(a: ReducedElement) -> Self {
+        a.0
+    }
+}
+
+impl PartialEq<BigNumber> for ReducedElement {
+    fn eq(&self, other: &BigNumber) -> bool {
+        self.0 == *other
+    }
 }
 
 impl Display for Group {
@@ -41,6 +139,8 @@ impl Serialize for Group {
         let serdes = GroupSerdes {
             g: self.g.clone(),
             n: self.n.clone(),
+            modulus_bits: Some(self.modulus_bits()),
+            lifecycle: self.lifecycle.clone(),
         };
         serdes.serialize(serializer)
     }
@@ -51,31 +151,247 @@ impl<'a> Deserialize<'a> for Group {
     where
         D: Deserializer<'a>,
     {
-        let GroupSerdes { g, n } = GroupSerdes::deserialize(deserializer)?;
-        let nn = &n * &n;
-        BigNumber::from(2)
-            .invert(&n)
-            .map(|two_inv| {
-                let n2d2: BigNumber = &nn >> 1;
-                let n2d4: BigNumber = &n2d2 >> 1;
-                let nd4: BigNumber = &n >> 2;
-                let h = &n + BigNumber::from(1);
-                let two_inv_two: BigNumber = two_inv << 1;
-                Group {
-                    g,
-                    h,
-                    n,
-                    nn,
-                    n2d2,
-                    n2d4,
-                    nd4,
-                    two_inv_two,
+        let GroupSerdes {
+            g,
+            n,
+            modulus_bits,
+            lifecycle,
+        } = GroupSerdes::deserialize(deserializer)?;
+        if let Some(bits) = modulus_bits {
+            if bits != bit_length(&n) {
+                return Err(D::Error::custom(format!(
+                    "modulus is {} bits, expected {}",
+                    bit_length(&n),
+                    bits
+                )));
+            }
+        }
+        let group =
+            Group::from_parts(g, n).ok_or_else(|| D::Error::custom("Unable to deserialize"))?;
+        Ok(Group { lifecycle, ..group })
+    }
+}
+
+impl Group {
+    /// Computes a mod nn where 0 < a < nn or
+    /// (nn - a) mod nn where a > nn / 2
+    /// See section 3.2
+    pub fn abs(&self, a: &BigNumber) -> ReducedElement {
+        let tv = a % &self.nn;
+
+        if tv > self.n2d2 {
+            ReducedElement(&self.nn - tv)
+        } else {
+            ReducedElement(tv)
+        }
+    }
+
+    /// Check `a` is already in the absolute form returned by `abs`,
+    /// as a ciphertext's `v` must be
+    pub fn reduced(&self, a: &BigNumber) -> Result<ReducedElement, VerencError> {
+        let reduced = self.abs(a);
+        if reduced.0 == *a {
+            Ok(reduced)
+        } else {
+            Err(VerencError::Other(
+                "v is not in absolute form, reduce it with Group::abs".to_string(),
+            ))
+        }
+    }
+
+    /// Computes H(u, e, L) for encryption/decryption
+    pub fn hash(&self, u: &BigNumber, e: &[BigNumber], domain: &[u8]) -> BigNumber {
+        let mut transcript = merlin::Transcript::new(b"encryption hash generation");
+        transcript.append_message(b"u", &u.to_bytes());
+        transcript.append_message(
+            b"e",
+            &e.iter().flat_map(|ee| ee.to_bytes()).collect::<Vec<u8>>(),
+        );
+        transcript.append_message(b"domain", domain);
+
+        let mut hash = [0u8; 64];
+        transcript.challenge_bytes(b"encryption hash output", &mut hash);
+        BigNumber::from_slice(hash)
+    }
+
+    /// Compute the modular exponentiation reduced by the group modulus
+    pub fn pow(&self, base: &BigNumber, exp: &BigNumber) -> BigNumber {
+        base.modpow(exp, &self.nn)
+    }
+
+    /// Compute the modular multiplication reduced by the group modulus
+    pub fn mul(&self, lhs: &BigNumber, rhs: &BigNumber) -> BigNumber {
+        lhs.modmul(rhs, &self.nn)
+    }
+
+    /// Compute modular exponentiation with the base as `g`
+    pub fn g_pow(&self, exp: &BigNumber) -> BigNumber {
+        self.g.modpow(exp, &self.nn)
+    }
+
+    /// Compute modular exponentiation with the base as `h`.
+    /// `h = 1 + n` so `h^exp = 1 + exp * n mod n^2` with no exponentiation
+    pub fn h_pow(&self, exp: &BigNumber) -> BigNumber {
+        BigNumber::one() + exp.modmul(&self.n, &self.nn)
+    }
+
+    /// Compute the product of `base^exp` for every pair reduced by the
+    /// group modulus, sharing the squarings between the exponents of
+    /// similar length (Straus's method). A multiplication costs more here
+    /// than inside `pow` so sharing only pays for itself with three or more
+    /// exponents at least half as long as the longest. Any other terms are
+    /// computed with `pow` and multiplied in.
+    /// Takes time that depends on the exponents so is only for public ones.
+    /// Like `pow` a negative exponent of a base with no inverse gives zero
+    pub fn multi_pow(&self, terms: &[(&BigNumber, &BigNumber)]) -> BigNumber {
+        let longest = terms
+            .iter()
+            .map(|(_, exp)| bit_length(exp))
+            .max()
+            .unwrap_or(0);
+        let (mut shared, mut alone): (Vec<_>, Vec<_>) = terms
+            .iter()
+            .partition(|(_, exp)| 2 * bit_length(exp) >= longest);
+        if shared.len() < 3 {
+            alone.append(&mut shared);
+        }
+        let mut result = if shared.is_empty() {
+            BigNumber::one()
+        } else {
+            self.straus(&shared)
+        };
+        for (base, exp) in alone {
+            result = self.mul(&result, &self.pow(base, exp));
+        }
+        result
+    }
+
+    /// The product of `base^exp` with one chain of squarings and a
+    /// window of 4 bits per exponent
+    fn straus(&self, terms: &[&(&BigNumber, &BigNumber)]) -> BigNumber {
+        let zero = BigNumber::zero();
+        let mut prepared = Vec::with_capacity(terms.len());
+        for (base, exp) in terms {
+            let (base, bytes) = if *exp < &zero {
+                match base.invert(&self.nn) {
+                    Some(inverse) => (inverse, (-(*exp).clone()).to_bytes()),
+                    None => return zero,
+                }
+            } else {
+                (base.modadd(&zero, &self.nn), exp.to_bytes())
+            };
+            // base^0, base^1, ..., base^15
+            let mut powers = Vec::with_capacity(16);
+            powers.push(BigNumber::one());
+            for i in 1..16 {
+                let next = self.mul(&powers[i - 1], &base);
+                powers.push(next);
+            }
+            prepared.push((powers, bytes));
+        }
+        let len = prepared.iter().map(|(_, b)| b.len()).max().unwrap_or(0);
+        let mut result = BigNumber::one();
+        let mut started = false;
+        for i in 0..len {
+            for shift in [4, 0] {
+                if started {
+                    for _ in 0..4 {
+                        result = self.mul(&result, &result);
+                    }
                 }
-            })
-            .ok_or_else(|| D::Error::custom("Unable to deserialize"))
+                for (powers, bytes) in &prepared {
+                    // Exponents are aligned at their least significant byte
+                    let offset = len - bytes.len();
+                    if i < offset {
+                        continue;
+                    }
+                    let digit = (bytes[i - offset] >> shift & 0x0f) as usize;
+                    if digit != 0 {
+                        result = self.mul(&result, &powers[digit]);
+                        started = true;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// The random generator for this group
+    pub fn g(&self) -> &BigNumber {
+        &self.g
+    }
+
+    /// The other generator, h = n + 1
+    pub fn h(&self) -> &BigNumber {
+        &self.h
+    }
+
+    /// The product of two primes
+    pub fn n(&self) -> &BigNumber {
+        &self.n
+    }
+
+    /// The group modulus
+    pub fn nn(&self) -> &BigNumber {
+        &self.nn
+    }
+
+    /// The bit length of `n`
+    pub fn modulus_bits(&self) -> usize {
+        bit_length(&self.n)
+    }
+
+    /// These parameters with `lifecycle` attached
+    pub fn with_lifecycle(mut self, lifecycle: Lifecycle) -> Self {
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// The lifecycle metadata if any is attached
+    pub fn lifecycle(&self) -> Option<&Lifecycle> {
+        self.lifecycle.as_ref()
+    }
+
+    /// Create the group from `g` and `n` computing the derived values
+    pub(crate) fn from_parts(g: BigNumber, n: BigNumber) -> Option<Self> {
+        let nn = &n * &n;
+        BigNumber::from(2).invert(&n).map(|two_inv| {
+            let n2d2: BigNumber = &nn >> 1;
+            let n2d4: BigNumber = &n2d2 >> 1;
+            let nd4: BigNumber = &n >> 2;
+            let h = &n + BigNumber::from(1);
+            let two_inv_two: BigNumber = two_inv << 1;
+            Group {
+                g,
+                h,
+                n,
+                nn,
+                n2d2,
+                n2d4,
+                nd4,
+                two_inv_two,
+                lifecycle: None,
+            }
+        })
+    }
+
+    /// A 32 byte digest identifying the public parameters `g` and `n`
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"verenc group fingerprint");
+        transcript.append_message(b"g", &self.g.to_bytes());
+        transcript.append_message(b"n", &self.n.to_bytes());
+        let mut fingerprint = [0u8; 32];
+        transcript.challenge_bytes(b"fingerprint", &mut fingerprint);
+        fingerprint
+    }
+
+    /// The preset matching the size of `n` if there is one
+    pub fn preset(&self) -> Option<ParameterPreset> {
+        ParameterPreset::for_modulus_bits(self.modulus_bits())
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl Group {
     /// Create new encryption/decryption keys that support up to `max_messages` to encrypt
     pub fn new_keys(&self, max_messages: usize) -> Option<(EncryptionKey, DecryptionKey)> {
@@ -85,10 +401,15 @@ impl Group {
         })
     }
 
-    /// Create a random paillier group
+    /// Create a random paillier group with a 2048-bit modulus
     pub fn random() -> Option<Self> {
-        let mut p = BigNumber::safe_prime(1024);
-        let mut q = BigNumber::safe_prime(1024);
+        Self::with_preset(ParameterPreset::Rsa2048)
+    }
+
+    /// Create a random paillier group with the modulus size of `preset`
+    pub fn with_preset(preset: ParameterPreset) -> Option<Self> {
+        let mut p = BigNumber::safe_prime(preset.prime_bits());
+        let mut q = BigNumber::safe_prime(preset.prime_bits());
         let res = Self::with_safe_primes_unchecked(&p, &q);
         // Make sure the primes are zero'd
         p.zeroize();
@@ -105,6 +426,43 @@ impl Group {
         Self::with_safe_primes_unchecked(p, q)
     }
 
+    /// Create a new group from the primes of an existing RSA private key.
+    /// `p` and `q` must be distinct safe primes
+    pub fn from_rsa_private_key(p: &BigNumber, q: &BigNumber) -> Result<Self, VerencError> {
+        for (name, prime) in [("p", p), ("q", q)] {
+            let sophie_germain: BigNumber = (prime - BigNumber::one()) >> 1;
+            if !prime.is_prime() || !sophie_germain.is_prime() {
+                return Err(VerencError::Other(format!("{} is not a safe prime", name)));
+            }
+        }
+        Self::from_rsa_private_key_relaxed(p, q)
+    }
+
+    /// Create a new group from the primes of an existing RSA private key
+    /// that are not safe primes, as most generated RSA keys aren't.
+    /// `p` and `q` must be distinct primes with gcd(n, (p - 1)(q - 1)) = 1.
+    ///
+    /// Without safe primes the group of squares may have small subgroups,
+    /// which weakens the soundness of proofs created in it but
+    /// not the secrecy of encrypted messages.
+    pub fn from_rsa_private_key_relaxed(p: &BigNumber, q: &BigNumber) -> Result<Self, VerencError> {
+        if p == q {
+            return Err(VerencError::Other("p and q must be different".to_string()));
+        }
+        if !p.is_prime() || !q.is_prime() {
+            return Err(VerencError::Other("p and q must be prime".to_string()));
+        }
+        let n = p * q;
+        let phi = (p - BigNumber::one()) * (q - BigNumber::one());
+        if !n.gcd(&phi).is_one() {
+            return Err(VerencError::Other(
+                "gcd(n, (p - 1)(q - 1)) != 1".to_string(),
+            ));
+        }
+        Self::with_safe_primes_unchecked(p, q)
+            .ok_or_else(|| VerencError::Other("Invalid primes".to_string()))
+    }
+
     #[allow(clippy::many_single_char_names)]
     /// Create a new group from two safe primes,
     /// `p` and `q` are not checked to see if they are safe primes
@@ -116,7 +474,7 @@ impl Group {
 
         let n = p * q;
         let nn = &n * &n;
-        let g_tick = BigNumber::random(&nn);
+        let g_tick = random_below(&nn);
         BigNumber::from(2).invert(&n).map(|two_inv| {
             let two_n2: BigNumber = &nn << 1;
             let n2d2: BigNumber = &nn >> 1;
@@ -134,102 +492,120 @@ impl Group {
                 n2d4,
                 nd4,
                 two_inv_two,
+                lifecycle: None,
             }
         })
     }
 
-    /// Computes a mod nn where 0 < a < nn or
-    /// (nn - a) mod nn where a > nn / 2
-    /// See section 3.2
-    pub fn abs(&self, a: &BigNumber) -> BigNumber {
-        let tv = a % &self.nn;
-
-        if tv > self.n2d2 {
-            &self.nn - tv
-        } else {
-            tv
-        }
-    }
-
     /// Generate random value < n / 4
     pub fn random_for_encrypt(&self) -> BigNumber {
-        let mut r = BigNumber::random(&self.nd4);
+        self.random_for_encrypt_with_rng(&mut OsRng)
+    }
+
+    /// Generate random value < n / 4 with `rng`
+    pub fn random_for_encrypt_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> BigNumber {
+        let mut r = sample_below(&self.nd4, rng);
         while r.is_zero() {
-            r = BigNumber::random(&self.nd4);
+            r = sample_below(&self.nd4, rng);
         }
         r
     }
 
     /// Generate random value < n^2 / 4
     pub fn random_value(&self) -> BigNumber {
-        let mut r = BigNumber::random(&self.n2d4);
+        self.random_value_with_rng(&mut OsRng)
+    }
+
+    /// Generate random value < n^2 / 4 with `rng`
+    pub fn random_value_with_rng(&self, rng: &mut (impl RngCore + CryptoRng)) -> BigNumber {
+        let mut r = sample_below(&self.n2d4, rng);
         while r.is_zero() {
-            r = BigNumber::random(&self.n2d4);
+            r = sample_below(&self.n2d4, rng);
         }
         r
     }
 
-    /// Computes H(u, e, L) for encryption/decryption
-    pub fn hash(&self, u: &BigNumber, e: &[BigNumber], domain: &[u8]) -> BigNumber {
-        let mut transcript = merlin::Transcript::new(b"encryption hash generation");
-        transcript.append_message(b"u", &u.to_bytes());
-        transcript.append_message(
-            b"e",
-            &e.iter()
-                .map(|ee| ee.to_bytes())
-                .flatten()
-                .collect::<Vec<u8>>(),
-        );
-        transcript.append_message(b"domain", domain);
-
-        let mut hash = [0u8; 64];
-        transcript.challenge_bytes(b"encryption hash output", &mut hash);
-        BigNumber::from_slice(&hash)
-    }
-
-    /// Compute the modular exponentiation reduced by the group modulus
-    pub fn pow(&self, base: &BigNumber, exp: &BigNumber) -> BigNumber {
-        base.modpow(exp, &self.nn)
-    }
-
-    /// Compute the modular multiplication reduced by the group modulus
-    pub fn mul(&self, lhs: &BigNumber, rhs: &BigNumber) -> BigNumber {
-        lhs.modmul(rhs, &self.nn)
-    }
-
-    /// Compute modular exponentiation with the base as `g`
-    pub fn g_pow(&self, exp: &BigNumber) -> BigNumber {
-        self.g.modpow(exp, &self.nn)
-    }
-
-    /// Compute modular exponentiation with the base as `h`
-    pub fn h_pow(&self, exp: &BigNumber) -> BigNumber {
-        self.h.modpow(exp, &self.nn)
-    }
-
-    /// The random generator for this group
-    pub fn g(&self) -> &BigNumber {
-        &self.g
+    /// Compute the Schnorr responses `tilde - (challenge * value mod nn)`
+    /// for many values sharing one challenge, reducing the challenge once.
+    /// See `unreduced_responses` for proofs whose responses must not be reduced
+    pub(crate) fn schnorr_responses(
+        &self,
+        challenge: &BigNumber,
+        tildes: &[BigNumber],
+        values: &[BigNumber],
+    ) -> Vec<BigNumber> {
+        let challenge = challenge % &self.nn;
+        respond_each(tildes, values, |tilde, value| {
+            tilde - challenge.modmul(value, &self.nn)
+        })
     }
 
-    /// The other generator, h = n + 1
-    pub fn h(&self) -> &BigNumber {
-        &self.h
+    /// `2 * (1/2 mod n)`, the exponent that maps `±h^m` to `h^m`.
+    /// It's even so the sign of an absolute value cancels and
+    /// it's 1 mod n so the message exponent of `h` is unchanged
+    pub fn two_inv_two(&self) -> &BigNumber {
+        &self.two_inv_two
     }
 
-    /// The product of two primes
-    pub fn n(&self) -> &BigNumber {
-        &self.n
+    /// Recover `m` from `e = ±h^m mod n^2` by raising `e` to `two_inv_two`
+    /// and computing `(m_hat - 1) / n`, see section 3.2.
+    /// Returns `None` if `e` is not of that form
+    pub fn paillier_extract(&self, e: &BigNumber) -> Option<BigNumber> {
+        let m_hat = self.pow(e, &self.two_inv_two);
+        if !ct_eq(&(&m_hat % &self.n), &BigNumber::one()) {
+            return None;
+        }
+        Some((m_hat - 1) / &self.n)
     }
 
-    /// The group modulus
-    pub fn nn(&self) -> &BigNumber {
-        &self.nn
+    /// Recover `m` from one ciphertext component `e = u^x * h^m mod n^2`
+    /// given `u` and the secret exponent `x` with `paillier_extract`.
+    /// Returns `None` if `u` is not invertible or `e` does not encrypt
+    /// a message under `x`
+    pub fn paillier_decrypt_component(
+        &self,
+        e: &BigNumber,
+        u: &BigNumber,
+        x: &BigNumber,
+    ) -> Option<BigNumber> {
+        let u_x_inv = self.pow(u, x).invert(&self.nn)?;
+        self.paillier_extract(&self.mul(&u_x_inv, e))
     }
 }
 
 #[derive(Serialize, Deserialize)]
 struct GroupSerdes {
+    #[serde(deserialize_with = "crate::budget::bignum")]
     g: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
     n: BigNumber,
+    #[serde(default)]
+    modulus_bits: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lifecycle: Option<Lifecycle>,
+}
+
+/// `a == b` in time that depends only on the byte lengths of `a` and `b`,
+/// for comparing a recomputed challenge or a secret-dependent element
+pub(crate) fn ct_eq(a: &BigNumber, b: &BigNumber) -> bool {
+    let (a_bytes, b_bytes) = (a.to_bytes(), b.to_bytes());
+    let width = a_bytes.len().max(b_bytes.len());
+    let pad = |bytes: &[u8]| {
+        let mut padded = vec![0u8; width - bytes.len()];
+        padded.extend_from_slice(bytes);
+        padded
+    };
+    let same_sign = Choice::from(u8::from(
+        (a < &BigNumber::zero()) == (b < &BigNumber::zero()),
+    ));
+    bool::from(pad(&a_bytes).ct_eq(&pad(&b_bytes)) & same_sign)
+}
+
+/// The number of bits needed to represent `a`
+pub(crate) fn bit_length(a: &BigNumber) -> usize {
+    let bytes = a.to_bytes();
+    match bytes.iter().position(|b| *b != 0) {
+        Some(i) => (bytes.len() - i) * 8 - bytes[i].leading_zeros() as usize,
+        None => 0,
+    }
 }
@@ -0,0 +1,200 @@
+use crate::{DecryptionKey, VerencError, VerifiableCipherText, WarrantAuthority};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Limits on decrypting with a `GuardedDecryptionKey`
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DecryptionPolicy {
+    /// The most decryptions allowed in one period
+    pub max_decryptions: u64,
+    /// The length of a period in seconds
+    pub period: u64,
+    /// The number of distinct approvers that must sign each request
+    pub required_approvals: usize,
+}
+
+/// An approver's signature on `GuardedDecryptionKey::request_message`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Approval {
+    /// The index of the approver
+    pub approver: usize,
+    /// The signature
+    pub signature: Vec<u8>,
+}
+
+/// The counters of a `GuardedDecryptionKey` to persist between uses.
+///
+/// `log` chains the digest of every decryption and `mac` authenticates
+/// the whole state with a key derived from the decryption key, so
+/// resetting or editing the counters is detected by `GuardedDecryptionKey::resume`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GuardState {
+    period_start: u64,
+    count: u64,
+    total: u64,
+    log: [u8; 32],
+    mac: [u8; 32],
+}
+
+impl GuardState {
+    /// The number of decryptions in the current period
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The number of decryptions ever made
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The hash chain over every decryption
+    pub fn log(&self) -> &[u8; 32] {
+        &self.log
+    }
+}
+
+/// A `DecryptionKey` that can only be used as allowed by a `DecryptionPolicy`.
+/// The inner key is never exposed
+pub struct GuardedDecryptionKey<A: WarrantAuthority> {
+    key: DecryptionKey,
+    policy: DecryptionPolicy,
+    approvers: Vec<A>,
+    state: GuardState,
+}
+
+impl<A: WarrantAuthority> GuardedDecryptionKey<A> {
+    /// Guard `key` with `policy`, checking approvals with `approvers`
+    pub fn new(key: DecryptionKey, policy: DecryptionPolicy, approvers: Vec<A>) -> Self {
+        let mut guarded = Self {
+            key,
+            policy,
+            approvers,
+            state: GuardState {
+                period_start: 0,
+                count: 0,
+                total: 0,
+                log: [0u8; 32],
+                mac: [0u8; 32],
+            },
+        };
+        guarded.state.mac = guarded.mac(&guarded.state);
+        guarded
+    }
+
+    /// Guard `key` continuing from a previously persisted `state`
+    pub fn resume(
+        key: DecryptionKey,
+        policy: DecryptionPolicy,
+        approvers: Vec<A>,
+        state: GuardState,
+    ) -> Result<Self, VerencError> {
+        let mut guarded = Self::new(key, policy, approvers);
+        if guarded.mac(&state) != state.mac {
+            return Err(VerencError::Other(
+                "Guard state has been tampered with".to_string(),
+            ));
+        }
+        guarded.state = state;
+        Ok(guarded)
+    }
+
+    /// The current state to persist after each use
+    pub fn state(&self) -> &GuardState {
+        &self.state
+    }
+
+    /// The policy this key enforces
+    pub fn policy(&self) -> &DecryptionPolicy {
+        &self.policy
+    }
+
+    /// The message approvers sign to allow decrypting `ciphertext` under `domain`
+    pub fn request_message(ciphertext: &VerifiableCipherText, domain: &[u8]) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"guarded decryption request");
+        transcript.append_message(b"ciphertext", &ciphertext.digest());
+        transcript.append_message(b"domain", domain);
+        let mut message = [0u8; 32];
+        transcript.challenge_bytes(b"request message", &mut message);
+        message
+    }
+
+    /// Decrypt `ciphertext` if `approvals` has enough distinct valid signatures
+    /// and the policy allows another decryption at time `now` in seconds
+    pub fn decrypt(
+        &mut self,
+        now: u64,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        approvals: &[Approval],
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        let message = Self::request_message(ciphertext, domain);
+        let mut approved = Vec::with_capacity(approvals.len());
+        for approval in approvals {
+            let approver = self
+                .approvers
+                .get(approval.approver)
+                .ok_or_else(|| format!("Unknown approver {}", approval.approver))?;
+            if !approver.verify_warrant(&message, &approval.signature) {
+                return Err(VerencError::Other(format!(
+                    "Invalid approval from {}",
+                    approval.approver
+                )));
+            }
+            if !approved.contains(&approval.approver) {
+                approved.push(approval.approver);
+            }
+        }
+        if approved.len() < self.policy.required_approvals {
+            return Err(VerencError::Other(format!(
+                "Number of approvals {} is less than required {}",
+                approved.len(),
+                self.policy.required_approvals
+            )));
+        }
+
+        let mut state = self.state.clone();
+        if now < state.period_start {
+            return Err(VerencError::Other(
+                "Time is before the current period".to_string(),
+            ));
+        }
+        if now - state.period_start >= self.policy.period {
+            state.period_start = now;
+            state.count = 0;
+        }
+        if state.count >= self.policy.max_decryptions {
+            return Err(VerencError::Other(
+                "Decryption limit for this period reached".to_string(),
+            ));
+        }
+
+        let msgs = self.key.decrypt(domain, ciphertext)?;
+        state.count += 1;
+        state.total += 1;
+        let mut transcript = merlin::Transcript::new(b"guarded decryption log");
+        transcript.append_message(b"log", &state.log);
+        transcript.append_u64(b"total", state.total);
+        transcript.append_u64(b"now", now);
+        transcript.append_message(b"request", &message);
+        transcript.challenge_bytes(b"log output", &mut state.log);
+        state.mac = self.mac(&state);
+        self.state = state;
+        Ok(msgs)
+    }
+
+    fn mac(&self, state: &GuardState) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"guarded decryption state");
+        transcript.append_message(b"x2", &self.key.x2.to_bytes());
+        transcript.append_message(b"x3", &self.key.x3.to_bytes());
+        transcript.append_u64(b"max_decryptions", self.policy.max_decryptions);
+        transcript.append_u64(b"period", self.policy.period);
+        transcript.append_u64(b"required_approvals", self.policy.required_approvals as u64);
+        transcript.append_u64(b"period_start", state.period_start);
+        transcript.append_u64(b"count", state.count);
+        transcript.append_u64(b"total", state.total);
+        transcript.append_message(b"log", &state.log);
+        let mut mac = [0u8; 32];
+        transcript.challenge_bytes(b"state mac", &mut mac);
+        mac
+    }
+}
@@ -0,0 +1,43 @@
+use crate::DecryptionKey;
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Record of a parent key opening a ciphertext for one of its children,
+/// returned by `DecryptionKey::decrypt_for_child`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ChildDecryptionRecord {
+    /// The derivation path of the child key used
+    pub path: Vec<u32>,
+    /// `VerifiableCipherText::digest` of the opened ciphertext
+    pub ciphertext_hash: [u8; 32],
+    /// The domain the ciphertext was decrypted under
+    pub domain: Vec<u8>,
+}
+
+/// Derive the child at `index` of `key`.
+/// The tweaks depend on the parent secret so the derivation is hardened:
+/// a child key reveals nothing about its parent or siblings
+pub(crate) fn derive_child(key: &DecryptionKey, index: u32) -> DecryptionKey {
+    let mut transcript = merlin::Transcript::new(b"camenisch-shoup child key derivation");
+    for x in &key.x1 {
+        transcript.append_message(b"x1", &x.to_bytes());
+    }
+    transcript.append_message(b"x2", &key.x2.to_bytes());
+    transcript.append_message(b"x3", &key.x3.to_bytes());
+    transcript.append_u64(b"index", u64::from(index));
+
+    let mut tweak = |label: &'static [u8]| {
+        let mut bytes = [0u8; 64];
+        transcript.challenge_bytes(label, &mut bytes);
+        BigNumber::from_slice(bytes)
+    };
+    let x1 = key.x1.iter().map(|x| x + tweak(b"tweak x1")).collect();
+    let x2 = &key.x2 + tweak(b"tweak x2");
+    let x3 = &key.x3 + tweak(b"tweak x3");
+    DecryptionKey {
+        x1,
+        x2,
+        x3,
+        group: key.group.clone(),
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(not(feature = "verify-only"))]
+use crate::{
+    archive::{put_bignum, put_bignums, Cursor},
+    budget::charge,
+    DecryptionKey, Group,
+};
+use crate::{EncryptionKey, VerificationKey};
+use pkcs8::{
+    der::{asn1::BitStringRef, Document},
+    spki::{AlgorithmIdentifierRef, EncodePublicKey, ObjectIdentifier, SubjectPublicKeyInfoRef},
+};
+#[cfg(not(feature = "verify-only"))]
+use pkcs8::{EncodePrivateKey, PrivateKeyInfo, SecretDocument};
+use std::convert::TryFrom;
+#[cfg(not(feature = "verify-only"))]
+use unknown_order::BigNumber;
+#[cfg(not(feature = "verify-only"))]
+use zeroize::Zeroizing;
+
+/// The algorithm identifier of Camenisch-Shoup keys in SPKI and PKCS#8 documents.
+///
+/// It's in the IANA experimental arc 1.3.6.1.3 and not registered, so other
+/// implementations have to be told it. The key is the payload of the
+/// checked hex encoding: the modulus, generator then key values
+pub const VERENC_KEY_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.3.43553.1");
+
+const ALGORITHM: AlgorithmIdentifierRef<'static> = AlgorithmIdentifierRef {
+    oid: VERENC_KEY_OID,
+    parameters: None,
+};
+
+impl EncodePublicKey for VerificationKey {
+    fn to_public_key_der(&self) -> pkcs8::spki::Result<Document> {
+        let payload = self.to_payload();
+        let info = SubjectPublicKeyInfoRef {
+            algorithm: ALGORITHM,
+            subject_public_key: BitStringRef::from_bytes(&payload)?,
+        };
+        Ok(Document::encode_msg(&info)?)
+    }
+}
+
+impl TryFrom<SubjectPublicKeyInfoRef<'_>> for VerificationKey {
+    type Error = pkcs8::spki::Error;
+
+    fn try_from(info: SubjectPublicKeyInfoRef<'_>) -> pkcs8::spki::Result<Self> {
+        info.algorithm.assert_algorithm_oid(VERENC_KEY_OID)?;
+        let payload = info
+            .subject_public_key
+            .as_bytes()
+            .ok_or(pkcs8::spki::Error::KeyMalformed)?;
+        Self::from_payload(payload).map_err(|_| pkcs8::spki::Error::KeyMalformed)
+    }
+}
+
+impl EncodePublicKey for EncryptionKey {
+    fn to_public_key_der(&self) -> pkcs8::spki::Result<Document> {
+        self.vk.to_public_key_der()
+    }
+}
+
+impl TryFrom<SubjectPublicKeyInfoRef<'_>> for EncryptionKey {
+    type Error = pkcs8::spki::Error;
+
+    fn try_from(info: SubjectPublicKeyInfoRef<'_>) -> pkcs8::spki::Result<Self> {
+        Ok(Self {
+            vk: VerificationKey::try_from(info)?,
+        })
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl EncodePrivateKey for DecryptionKey {
+    fn to_pkcs8_der(&self) -> pkcs8::Result<SecretDocument> {
+        let mut payload = Zeroizing::new(Vec::new());
+        put_bignum(&mut payload, &self.group.n);
+        put_bignum(&mut payload, &self.group.g);
+        put_bignums(&mut payload, &self.x1);
+        put_bignum(&mut payload, &self.x2);
+        put_bignum(&mut payload, &self.x3);
+        SecretDocument::try_from(PrivateKeyInfo::new(ALGORITHM, &payload))
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl TryFrom<PrivateKeyInfo<'_>> for DecryptionKey {
+    type Error = pkcs8::Error;
+
+    fn try_from(info: PrivateKeyInfo<'_>) -> pkcs8::Result<Self> {
+        info.algorithm.assert_algorithm_oid(VERENC_KEY_OID)?;
+        let mut cursor = Cursor::new(info.private_key);
+        let mut read = || -> Result<Self, String> {
+            charge(info.private_key.len())?;
+            let n = cursor.bignum()?;
+            let g = cursor.bignum()?;
+            let x1 = cursor.bignums()?;
+            let x2 = cursor.bignum()?;
+            let x3 = cursor.bignum()?;
+            if !cursor.is_finished() {
+                return Err("Decryption key has trailing bytes".to_string());
+            }
+            let group = Group::from_parts(g, n).ok_or_else(|| "Invalid group".to_string())?;
+            let zero = BigNumber::zero();
+            if x1
+                .iter()
+                .chain([&x2, &x3])
+                .any(|x| x < &zero || x >= &group.nn)
+            {
+                return Err("Decryption key value out of range".to_string());
+            }
+            Ok(Self { x1, x2, x3, group })
+        };
+        read().map_err(|_| pkcs8::Error::KeyMalformed)
+    }
+}
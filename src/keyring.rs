@@ -0,0 +1,134 @@
+use crate::{
+    EncryptionKey, VerencError, VerifiableCipherText, VerifiableEncryptionProof, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+
+/// A proof with the `VerificationKey::fingerprint` of the key it was made with.
+///
+/// The proof transcript already includes the key so the fingerprint only
+/// selects which key to try, changing it can't make a proof verify
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyBoundProof {
+    pub(crate) key: [u8; 32],
+    pub(crate) proof: VerifiableEncryptionProof,
+}
+
+impl KeyBoundProof {
+    /// Attach the fingerprint of `key` to `proof`
+    pub fn new(key: &VerificationKey, proof: VerifiableEncryptionProof) -> Self {
+        Self {
+            key: key.fingerprint(),
+            proof,
+        }
+    }
+
+    /// The fingerprint of the key the proof was made with
+    pub fn key_fingerprint(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    /// The proof
+    pub fn proof(&self) -> &VerifiableEncryptionProof {
+        &self.proof
+    }
+}
+
+/// Current and past encryption keys with the times in seconds each was valid,
+/// so proofs keep verifying across key rotations
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct KeyRing {
+    entries: Vec<KeyRingEntry>,
+}
+
+/// A key in a `KeyRing` and when it was valid
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyRingEntry {
+    /// The key
+    pub key: EncryptionKey,
+    /// The first time the key is valid
+    pub not_before: u64,
+    /// The last time the key is valid, or `None` if it has not been retired
+    pub not_after: Option<u64>,
+}
+
+impl KeyRingEntry {
+    /// True if the key is valid at time `at`
+    pub fn is_valid_at(&self, at: u64) -> bool {
+        at >= self.not_before && !matches!(self.not_after, Some(not_after) if at > not_after)
+    }
+}
+
+impl KeyRing {
+    /// An empty key ring
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `key` valid from `not_before` until `not_after`, returning its fingerprint
+    pub fn insert(
+        &mut self,
+        key: EncryptionKey,
+        not_before: u64,
+        not_after: Option<u64>,
+    ) -> [u8; 32] {
+        let fingerprint = key.verification_key().fingerprint();
+        self.entries
+            .retain(|e| e.key.verification_key().fingerprint() != fingerprint);
+        self.entries.push(KeyRingEntry {
+            key,
+            not_before,
+            not_after,
+        });
+        fingerprint
+    }
+
+    /// Retire the key with `fingerprint` after time `not_after`
+    pub fn retire(&mut self, fingerprint: &[u8; 32], not_after: u64) -> Result<(), VerencError> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.key.verification_key().fingerprint() == *fingerprint)
+            .ok_or_else(|| "Unknown key".to_string())?;
+        entry.not_after = Some(not_after);
+        Ok(())
+    }
+
+    /// The key with `fingerprint` if it is in the ring
+    pub fn get(&self, fingerprint: &[u8; 32]) -> Option<&KeyRingEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.key.verification_key().fingerprint() == *fingerprint)
+    }
+
+    /// The most recently started key valid at time `at`, used for new encryptions
+    pub fn current(&self, at: u64) -> Option<&EncryptionKey> {
+        self.entries
+            .iter()
+            .filter(|e| e.is_valid_at(at))
+            .max_by_key(|e| e.not_before)
+            .map(|e| &e.key)
+    }
+
+    /// Every key in the order they were added
+    pub fn entries(&self) -> &[KeyRingEntry] {
+        &self.entries
+    }
+
+    /// Verify `proof` with the key it names, which must have been valid at time `at`,
+    /// e.g. when the proof was received
+    pub fn verify_any(
+        &self,
+        at: u64,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &KeyBoundProof,
+    ) -> Result<(), VerencError> {
+        let entry = self
+            .get(&proof.key)
+            .ok_or_else(|| "Proof was made with an unknown key".to_string())?;
+        if !entry.is_valid_at(at) {
+            return Err(VerencError::Other(format!("Key was not valid at {}", at)));
+        }
+        entry.key.verify(nonce, ciphertext, &proof.proof)
+    }
+}
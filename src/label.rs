@@ -0,0 +1,289 @@
+use crate::version::wire_mismatch;
+use crate::VerencError;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const LABEL_MAGIC: &[u8; 8] = b"VELABEL1";
+const SESSION_FIELD: &[u8] = b"session";
+const COUNTER_FIELD: &[u8] = b"counter";
+
+/// Builder for structured labels with an unambiguous encoding.
+///
+/// Use `to_bytes` as the nonce or domain when encrypting, proving and
+/// decrypting. A label can carry a not-after time which
+/// `VerificationKey::verify_with_label` checks against the caller's clock
+/// so old proofs replayed later are rejected.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Label {
+    context: Vec<u8>,
+    fields: Vec<(Vec<u8>, Vec<u8>)>,
+    not_after: Option<u64>,
+}
+
+impl Label {
+    /// Start a label for `context`, e.g. the application or protocol name
+    pub fn new(context: &[u8]) -> Self {
+        Self {
+            context: context.to_vec(),
+            fields: Vec::new(),
+            not_after: None,
+        }
+    }
+
+    /// Add a named field. Fields are encoded in the order they are added
+    pub fn field(mut self, name: &[u8], value: &[u8]) -> Self {
+        self.fields.push((name.to_vec(), value.to_vec()));
+        self
+    }
+
+    /// Set the last time in seconds that proofs with this label are accepted
+    pub fn not_after(mut self, timestamp: u64) -> Self {
+        self.not_after = Some(timestamp);
+        self
+    }
+
+    /// The label for encryption `counter` in session `session_id`,
+    /// `base` with the session and counter appended as the last fields.
+    ///
+    /// Every encryption should use a label no other encryption under the
+    /// same key has used. Deriving them from a session id chosen once per
+    /// session and a counter that only goes up makes them unique and
+    /// ordered, see `SessionLabels` and `LabelReuseDetector`
+    pub fn derive_session(base: &Label, session_id: &[u8], counter: u64) -> Self {
+        base.clone()
+            .field(SESSION_FIELD, session_id)
+            .field(COUNTER_FIELD, &counter.to_be_bytes())
+    }
+
+    /// The session id and counter of a label made by `derive_session`
+    pub fn session(&self) -> Option<(&[u8], u64)> {
+        match self.fields.as_slice() {
+            [.., (session, id), (counter, value)]
+                if session == SESSION_FIELD && counter == COUNTER_FIELD && value.len() == 8 =>
+            {
+                let mut be = [0u8; 8];
+                be.copy_from_slice(value);
+                Some((id, u64::from_be_bytes(be)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The context
+    pub fn context(&self) -> &[u8] {
+        &self.context
+    }
+
+    /// The named fields in order
+    pub fn fields(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.fields
+    }
+
+    /// The not-after time if one was set
+    pub fn expiry(&self) -> Option<u64> {
+        self.not_after
+    }
+
+    /// Check the label has not expired at time `now` in seconds
+    pub fn check_fresh(&self, now: u64) -> Result<(), VerencError> {
+        match self.not_after {
+            Some(not_after) if now > not_after => Err(VerencError::Other(format!(
+                "Label expired at {} and it is now {}",
+                not_after, now
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The encoding used as the nonce or domain. Every value is length prefixed
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = LABEL_MAGIC.to_vec();
+        write_bytes(&mut out, &self.context);
+        out.extend_from_slice(&(self.fields.len() as u32).to_be_bytes());
+        for (name, value) in &self.fields {
+            write_bytes(&mut out, name);
+            write_bytes(&mut out, value);
+        }
+        match self.not_after {
+            Some(not_after) => {
+                out.push(1);
+                out.extend_from_slice(&not_after.to_be_bytes());
+            }
+            None => out.push(0),
+        }
+        out
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, VerencError> {
+        let mut reader = Reader(bytes);
+        let magic = reader.take(LABEL_MAGIC.len())?;
+        if magic != LABEL_MAGIC {
+            return Err(VerencError::Other(
+                wire_mismatch(magic, LABEL_MAGIC)
+                    .unwrap_or_else(|| "Invalid label encoding".to_string()),
+            ));
+        }
+        let context = reader.bytes()?.to_vec();
+        let count = reader.u32()?;
+        let mut fields = Vec::new();
+        for _ in 0..count {
+            let name = reader.bytes()?.to_vec();
+            let value = reader.bytes()?.to_vec();
+            fields.push((name, value));
+        }
+        let not_after = match reader.take(1)?[0] {
+            0 => None,
+            1 => {
+                let mut be = [0u8; 8];
+                be.copy_from_slice(reader.take(8)?);
+                Some(u64::from_be_bytes(be))
+            }
+            _ => return Err(VerencError::Other("Invalid label encoding".to_string())),
+        };
+        if !reader.0.is_empty() {
+            return Err(VerencError::Other("Invalid label encoding".to_string()));
+        }
+        Ok(Self {
+            context,
+            fields,
+            not_after,
+        })
+    }
+}
+
+/// Hands out the labels of one session in order starting at counter 0
+#[derive(Clone, Debug)]
+pub struct SessionLabels {
+    base: Label,
+    session_id: Vec<u8>,
+    next: Option<u64>,
+}
+
+impl SessionLabels {
+    /// Start session `session_id` of `base`
+    pub fn new(base: Label, session_id: &[u8]) -> Self {
+        Self::resume(base, session_id, 0)
+    }
+
+    /// Continue session `session_id` of `base` from `counter`,
+    /// e.g. after restoring a persisted counter
+    pub fn resume(base: Label, session_id: &[u8], counter: u64) -> Self {
+        Self {
+            base,
+            session_id: session_id.to_vec(),
+            next: Some(counter),
+        }
+    }
+
+    /// The counter the next label will have
+    pub fn counter(&self) -> Option<u64> {
+        self.next
+    }
+
+    /// The next label of the session.
+    /// Fails once every counter has been used
+    pub fn next_label(&mut self) -> Result<Label, VerencError> {
+        let counter = self
+            .next
+            .ok_or_else(|| "Session labels are exhausted".to_string())?;
+        self.next = counter.checked_add(1);
+        Ok(Label::derive_session(&self.base, &self.session_id, counter))
+    }
+}
+
+/// Records the nonces or domains seen by an encryptor or decryptor and
+/// rejects any seen before.
+///
+/// Labels made by `derive_session` must also arrive in order,
+/// a counter no higher than the last one seen for the same base and
+/// session is rejected even if that exact label hasn't been seen.
+/// Everything recorded is kept in memory
+#[derive(Clone, Debug, Default)]
+pub struct LabelReuseDetector {
+    seen: HashSet<Vec<u8>>,
+    last_counter: HashMap<Vec<u8>, u64>,
+}
+
+impl LabelReuseDetector {
+    /// A detector that has seen nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `nonce` hasn't been seen and is in order then record it
+    pub fn record(&mut self, nonce: &[u8]) -> Result<(), VerencError> {
+        if self.seen.contains(nonce) {
+            return Err(VerencError::Other(
+                "Label has already been used".to_string(),
+            ));
+        }
+        let session = Label::from_bytes(nonce).ok().and_then(|label| {
+            label.session().map(|(id, counter)| {
+                let base = Label {
+                    fields: label.fields[..label.fields.len() - 2].to_vec(),
+                    ..label.clone()
+                };
+                let key = Label::derive_session(&base, id, 0).to_bytes();
+                (key, counter)
+            })
+        });
+        if let Some((key, counter)) = session {
+            if let Some(last) = self.last_counter.get(&key) {
+                if counter <= *last {
+                    return Err(VerencError::Other(format!(
+                        "Session label counter {} is not after {}",
+                        counter, last
+                    )));
+                }
+            }
+            self.last_counter.insert(key, counter);
+        }
+        self.seen.insert(nonce.to_vec());
+        Ok(())
+    }
+
+    /// Whether `nonce` has been recorded
+    pub fn contains(&self, nonce: &[u8]) -> bool {
+        self.seen.contains(nonce)
+    }
+
+    /// The number of distinct nonces recorded
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Whether nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < len {
+            return Err("Invalid label encoding".to_string());
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let mut be = [0u8; 4];
+        be.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(be))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
@@ -19,15 +19,274 @@
 //! Camenisch-Shoup verifiable encryption and decryption based on
 //! <https://www.shoup.net/papers/verenc.pdf> and
 //! <https://dominoweb.draco.res.ibm.com/reports/rz3730_revised.pdf>
+#[cfg(not(any(feature = "os-rng", feature = "verify-only")))]
+compile_error!(
+    "key generation, encryption and decryption need `os-rng`, enable it or `verify-only`"
+);
+#[cfg(all(feature = "wasm-verifier", any(feature = "gmp", feature = "openssl")))]
+compile_error!("`wasm-verifier` uses the pure Rust backend");
+#[cfg(all(feature = "wasm", any(feature = "gmp", feature = "openssl")))]
+compile_error!("`wasm` uses the pure Rust backend");
+#[cfg(all(
+    target_arch = "wasm32",
+    target_os = "unknown",
+    any(feature = "parallel", feature = "timing")
+))]
+compile_error!(
+    "`parallel` needs threads and `timing` a clock, neither of which wasm32-unknown-unknown has"
+);
+mod archive;
+mod audit;
+#[cfg(all(feature = "bench_support", not(feature = "verify-only")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "bench_support")))]
+/// Scenario generators for benchmarking
+pub mod bench_support;
+#[cfg(not(feature = "verify-only"))]
+mod blinding;
+mod budget;
+mod cancellation;
+mod checked_hex;
 mod ciphertext;
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(all(feature = "conformance", not(feature = "verify-only")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "conformance")))]
+/// Checks verification and decryption against test vectors from other implementations
+pub mod conformance;
+mod context;
+#[cfg(not(feature = "verify-only"))]
+mod custody;
+#[cfg(not(feature = "verify-only"))]
+mod decrypted;
+#[cfg(not(feature = "verify-only"))]
 mod decryptionkey;
+#[cfg(not(feature = "verify-only"))]
+/// Dealerless generation of the modulus and threshold key shares
+pub mod dkg;
 mod encryptionkey;
+mod encryptionkeyref;
+#[cfg(not(feature = "verify-only"))]
+mod entropy;
+mod error;
+#[cfg(not(feature = "verify-only"))]
+mod escrow;
+mod explain;
+#[cfg(all(
+    any(feature = "bench_support", feature = "test-params"),
+    not(feature = "verify-only")
+))]
+mod fixtures;
 mod group;
+#[cfg(not(feature = "verify-only"))]
+mod guarded;
+#[cfg(not(feature = "verify-only"))]
+mod hierarchy;
+#[cfg(feature = "pkcs8")]
+mod key_encoding;
+#[cfg(not(feature = "verify-only"))]
+mod keyring;
+mod label;
+mod lifecycle;
+#[cfg(not(feature = "verify-only"))]
+mod manifest;
+#[cfg(not(feature = "verify-only"))]
+mod mpc_export;
+#[cfg(not(feature = "verify-only"))]
+mod packing;
+#[cfg(not(feature = "verify-only"))]
+mod policy;
+mod precompute;
+mod preset;
+#[cfg(not(feature = "verify-only"))]
+mod proof_commitment;
+#[cfg(not(feature = "verify-only"))]
+mod proof_decryption;
+#[cfg(not(feature = "verify-only"))]
+mod proof_derivation;
+#[cfg(not(feature = "verify-only"))]
+mod proof_disclosure;
+#[cfg(not(feature = "verify-only"))]
+mod proof_element;
+#[cfg(not(feature = "verify-only"))]
+mod proof_key_generation;
+#[cfg(not(feature = "verify-only"))]
+mod proof_label_or;
+#[cfg(not(feature = "verify-only"))]
+mod proof_less_than;
+mod proof_options;
+#[cfg(not(feature = "verify-only"))]
+mod proof_packed;
+#[cfg(not(feature = "verify-only"))]
+mod proof_paillier_blum;
+#[cfg(not(feature = "verify-only"))]
+mod proof_reencryption;
+#[cfg(not(feature = "verify-only"))]
+mod proof_rerandomization;
 mod proof_verenc;
+#[cfg(not(feature = "verify-only"))]
+mod protect;
+#[cfg(not(feature = "verify-only"))]
+/// Social recovery of a secret key through threshold escrow to guardians
+pub mod recovery;
+#[cfg(all(feature = "reference", not(feature = "verify-only")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "reference")))]
+/// Direct textbook implementation of the scheme for differential testing.
+/// It does no input validation beyond what's needed to avoid panics
+/// and is slow on purpose
+pub mod reference;
+#[cfg(not(feature = "verify-only"))]
+mod registry;
+#[cfg(not(feature = "verify-only"))]
+/// Generic proofs of knowledge over the group behind verifiable encryption
+pub mod relation;
+#[cfg(not(feature = "verify-only"))]
+/// Escrow of credential revocation handles linked to the commitment shown in a presentation
+pub mod revocation;
+#[cfg(not(feature = "verify-only"))]
+mod rotation;
+#[cfg(not(feature = "verify-only"))]
+mod sampling;
+mod schema;
+#[cfg(not(feature = "verify-only"))]
+mod scoped;
+#[cfg(not(feature = "verify-only"))]
+mod sealed;
+#[cfg(not(feature = "verify-only"))]
+mod search;
+#[cfg(not(feature = "verify-only"))]
+mod secret_serde;
+#[cfg(not(feature = "verify-only"))]
+mod send_sync;
+mod statement;
+mod strict;
+#[cfg(all(feature = "test-params", not(feature = "verify-only")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-params")))]
+/// Fixed public test primes and shared groups and keys for fast test suites.
+/// **Not for production use**
+pub mod test_params;
+#[cfg(all(feature = "testing", not(feature = "verify-only")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+/// Generators and a mock escrow agent for tests using small cached parameters.
+/// **Not for production use**
+pub mod testing;
+#[cfg(not(feature = "verify-only"))]
+/// Threshold decryption with a `DecryptionKey` shared among trustees
+pub mod threshold;
+#[cfg(all(feature = "timing", not(feature = "verify-only")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "timing")))]
+/// dudect style harness for measuring timing leakage of decryption and verification
+/// with the chosen `unknown_order` backend
+pub mod timing;
+mod verification_policy;
+mod verificationkey;
+mod verifier;
+#[cfg(feature = "parallel")]
+mod verifier_pool;
+mod version;
+#[cfg(not(feature = "verify-only"))]
+mod warrant;
+#[cfg(all(
+    feature = "wasm-verifier",
+    target_arch = "wasm32",
+    target_os = "unknown"
+))]
+mod wasm_verifier;
 
+pub use archive::*;
+pub use audit::{audit_enabled, set_audit_sink, AuditEvent, AuditSink};
+#[cfg(not(feature = "verify-only"))]
+pub use blinding::ExponentBlinding;
+pub use budget::MemoryBudget;
+pub use cancellation::Cancellation;
 pub use ciphertext::*;
+pub use context::Context;
+#[cfg(not(feature = "verify-only"))]
+pub use custody::*;
+#[cfg(not(feature = "verify-only"))]
+pub use decrypted::*;
+#[cfg(not(feature = "verify-only"))]
 pub use decryptionkey::*;
 pub use encryptionkey::*;
+pub use encryptionkeyref::*;
+#[cfg(not(feature = "verify-only"))]
+pub use entropy::{EntropyHealthError, EntropyHealthTests, HealthTestedRng};
+pub use error::{BatchItemError, VerencError};
+#[cfg(not(feature = "verify-only"))]
+pub use escrow::*;
+pub use explain::*;
 pub use group::*;
+#[cfg(not(feature = "verify-only"))]
+pub use guarded::*;
+#[cfg(not(feature = "verify-only"))]
+pub use hierarchy::ChildDecryptionRecord;
+#[cfg(feature = "pkcs8")]
+pub use key_encoding::VERENC_KEY_OID;
+#[cfg(not(feature = "verify-only"))]
+pub use keyring::*;
+pub use label::*;
+pub use lifecycle::*;
+#[cfg(not(feature = "verify-only"))]
+pub use manifest::*;
+#[cfg(not(feature = "verify-only"))]
+pub use mpc_export::*;
+#[cfg(not(feature = "verify-only"))]
+pub use packing::{pack_messages, unpack_messages};
+#[cfg(not(feature = "verify-only"))]
+pub use policy::*;
+#[cfg(not(feature = "verify-only"))]
+pub use precompute::PrecomputedEncryptionKey;
+pub use precompute::{BaseId, Precompute, PrecomputedKey};
+pub use preset::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_commitment::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_decryption::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_derivation::DerivationProof;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_disclosure::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_element::ElementEscrowProof;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_key_generation::KeyGenerationProof;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_label_or::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_less_than::*;
+pub use proof_options::{ProofOptions, ProtocolVersion};
+#[cfg(not(feature = "verify-only"))]
+pub use proof_packed::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_paillier_blum::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_reencryption::*;
+#[cfg(not(feature = "verify-only"))]
+pub use proof_rerandomization::*;
 pub use proof_verenc::*;
+#[cfg(not(feature = "verify-only"))]
+pub use protect::*;
+#[cfg(not(feature = "verify-only"))]
+pub use registry::*;
+#[cfg(not(feature = "verify-only"))]
+pub use rotation::*;
+#[cfg(not(feature = "verify-only"))]
+pub use sampling::sample_below;
+pub use schema::*;
+#[cfg(not(feature = "verify-only"))]
+pub use scoped::*;
+#[cfg(not(feature = "verify-only"))]
+pub use sealed::*;
+#[cfg(not(feature = "verify-only"))]
+pub use search::*;
+pub use statement::*;
+pub use strict::StrictVerifyError;
 pub use unknown_order;
+pub use verification_policy::*;
+pub use verificationkey::*;
+pub use verifier::*;
+#[cfg(feature = "parallel")]
+#[cfg_attr(docsrs, doc(cfg(feature = "parallel")))]
+pub use verifier_pool::*;
+pub use version::{compatibility, Compat, Version, PROTOCOL_VERSION, WIRE_VERSION};
+#[cfg(not(feature = "verify-only"))]
+pub use warrant::*;
@@ -0,0 +1,198 @@
+use crate::{Group, VerencError, VerificationKey};
+use serde::{Deserialize, Serialize};
+
+/// When parameters or a key were made, how strong they are meant to be and
+/// when to replace them, attached with `Group::with_lifecycle` or
+/// `VerificationKey::with_lifecycle` and checked with an `ExpiryPolicy`.
+///
+/// Times are seconds since the Unix epoch. It's kept by the serde encoding
+/// of the group or key, not the checked hex or compact ones, and isn't part
+/// of its fingerprint or proofs so treat it as advice from whoever
+/// published the key
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Lifecycle {
+    /// When they were created
+    pub created_at: Option<u64>,
+    /// The intended security level in bits
+    pub security_bits: Option<u32>,
+    /// When they should be retired
+    pub retire_at: Option<u64>,
+}
+
+/// What an `ExpiryPolicy` finding is about
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExpirySubject {
+    /// The group parameters
+    Group,
+    /// The key's own values
+    Key,
+}
+
+/// Why an `ExpiryPolicy` flags parameters or a key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExpiryReason {
+    /// The retirement date has passed
+    Retired {
+        /// When they were due to be retired
+        retire_at: u64,
+    },
+    /// The retirement date is within the policy's warning period
+    RetiringSoon {
+        /// When they are due to be retired
+        retire_at: u64,
+    },
+    /// They are older than the policy's maximum age
+    TooOld {
+        /// When they were created
+        created_at: u64,
+    },
+    /// The security level is below the policy's minimum
+    TooWeak {
+        /// The level from the metadata or estimated from the modulus size
+        security_bits: u32,
+    },
+}
+
+/// One reason to replace a group or key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExpiryFinding {
+    /// What the finding is about
+    pub subject: ExpirySubject,
+    /// Why it's flagged
+    pub reason: ExpiryReason,
+}
+
+impl ExpiryFinding {
+    /// True if it only warns of a coming retirement
+    pub fn is_warning(&self) -> bool {
+        matches!(self.reason, ExpiryReason::RetiringSoon { .. })
+    }
+}
+
+/// Flags aging parameters and keys from their `Lifecycle` metadata.
+///
+/// Groups without a security level in their metadata are rated by their
+/// modulus size as in NIST SP 800-57, 112 bits for 2048 and 128 for 3072
+/// or 4096, so 2048-bit parameters are flagged once the minimum is raised
+#[derive(Clone, Debug)]
+pub struct ExpiryPolicy {
+    min_security_bits: u32,
+    max_age: Option<u64>,
+    warning: u64,
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpiryPolicy {
+    /// A policy requiring 112-bit security with no maximum age or warning period
+    pub fn new() -> Self {
+        Self {
+            min_security_bits: 112,
+            max_age: None,
+            warning: 0,
+        }
+    }
+
+    /// Flag anything below `bits` of security
+    pub fn with_min_security_bits(mut self, bits: u32) -> Self {
+        self.min_security_bits = bits;
+        self
+    }
+
+    /// Flag anything created more than `seconds` ago
+    pub fn with_max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Warn of retirements due within `seconds`
+    pub fn with_warning(mut self, seconds: u64) -> Self {
+        self.warning = seconds;
+        self
+    }
+
+    /// Every finding for `group` at time `now`
+    pub fn check_group(&self, group: &Group, now: u64) -> Vec<ExpiryFinding> {
+        let default = Lifecycle::default();
+        let lifecycle = group.lifecycle().unwrap_or(&default);
+        let security_bits = lifecycle
+            .security_bits
+            .unwrap_or_else(|| estimated_security_bits(group.modulus_bits()));
+        let mut findings = self.check(ExpirySubject::Group, lifecycle, now);
+        if security_bits < self.min_security_bits {
+            findings.push(ExpiryFinding {
+                subject: ExpirySubject::Group,
+                reason: ExpiryReason::TooWeak { security_bits },
+            });
+        }
+        findings
+    }
+
+    /// Every finding for `key` and then its group at time `now`
+    pub fn check_key(&self, key: &VerificationKey, now: u64) -> Vec<ExpiryFinding> {
+        let default = Lifecycle::default();
+        let lifecycle = key.lifecycle().unwrap_or(&default);
+        let mut findings = self.check(ExpirySubject::Key, lifecycle, now);
+        if let Some(security_bits) = lifecycle.security_bits {
+            if security_bits < self.min_security_bits {
+                findings.push(ExpiryFinding {
+                    subject: ExpirySubject::Key,
+                    reason: ExpiryReason::TooWeak { security_bits },
+                });
+            }
+        }
+        findings.extend(self.check_group(key.group(), now));
+        findings
+    }
+
+    /// An error naming the first finding for `key` that isn't a warning
+    pub fn enforce(&self, key: &VerificationKey, now: u64) -> Result<(), VerencError> {
+        match self
+            .check_key(key, now)
+            .into_iter()
+            .find(|f| !f.is_warning())
+        {
+            None => Ok(()),
+            Some(finding) => Err(VerencError::Other(format!(
+                "{:?} should be replaced: {:?}",
+                finding.subject, finding.reason
+            ))),
+        }
+    }
+
+    fn check(&self, subject: ExpirySubject, lifecycle: &Lifecycle, now: u64) -> Vec<ExpiryFinding> {
+        let mut findings = Vec::new();
+        let mut push = |reason| findings.push(ExpiryFinding { subject, reason });
+        if let Some(retire_at) = lifecycle.retire_at {
+            if now >= retire_at {
+                push(ExpiryReason::Retired { retire_at });
+            } else if retire_at - now <= self.warning {
+                push(ExpiryReason::RetiringSoon { retire_at });
+            }
+        }
+        if let (Some(created_at), Some(max_age)) = (lifecycle.created_at, self.max_age) {
+            if now.saturating_sub(created_at) > max_age {
+                push(ExpiryReason::TooOld { created_at });
+            }
+        }
+        findings
+    }
+}
+
+/// The security level of a modulus of `bits` from NIST SP 800-57 part 1 table 2.
+/// A product of two primes can be a few bits short of its nominal size,
+/// e.g. 2045 bits, so moduli within 8 bits of a size are rated as that size
+pub(crate) fn estimated_security_bits(bits: usize) -> u32 {
+    match bits + 8 {
+        15360.. => 256,
+        7680.. => 192,
+        3072.. => 128,
+        2048.. => 112,
+        1024.. => 80,
+        _ => 0,
+    }
+}
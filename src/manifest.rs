@@ -0,0 +1,146 @@
+use crate::VerifiableCipherText;
+use serde::{Deserialize, Serialize};
+
+/// A Merkle tree committing to a batch of ciphertext digests so one root can
+/// be published while each escrow is stored privately and shown to be included
+/// with an `InclusionProof`.
+///
+/// Leaves and nodes are hashed with different labels and an unpaired last
+/// node is promoted to the next level instead of being duplicated
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Manifest {
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+/// Proof that a ciphertext digest is a leaf of a `Manifest`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InclusionProof {
+    index: usize,
+    leaf_count: usize,
+    siblings: Vec<[u8; 32]>,
+}
+
+impl Manifest {
+    /// Commit to `ciphertexts` in order
+    pub fn new(ciphertexts: &[VerifiableCipherText]) -> Self {
+        Self::from_hashes(ciphertexts.iter().map(|ct| ct.digest()).collect())
+    }
+
+    /// Commit to `VerifiableCipherText::digest` values in order
+    pub fn from_hashes(hashes: Vec<[u8; 32]>) -> Self {
+        let mut levels = vec![hashes.iter().map(leaf_hash).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [last] => *last,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// The number of committed ciphertexts
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// True if no ciphertexts are committed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The root to publish
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels[self.levels.len() - 1].as_slice() {
+            [root] => *root,
+            _ => empty_root(),
+        }
+    }
+
+    /// Prove the ciphertext at `index` is included
+    pub fn prove(&self, index: usize) -> Option<InclusionProof> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(i ^ 1) {
+                siblings.push(*sibling);
+            }
+            i /= 2;
+        }
+        Some(InclusionProof {
+            index,
+            leaf_count: self.len(),
+            siblings,
+        })
+    }
+}
+
+impl InclusionProof {
+    /// The position of the ciphertext in the manifest
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The number of ciphertexts in the manifest
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Check `ciphertext_hash` is at `index` in the manifest with `root`
+    pub fn verify(&self, root: &[u8; 32], ciphertext_hash: &[u8; 32]) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+        let mut hash = leaf_hash(ciphertext_hash);
+        let mut siblings = self.siblings.iter();
+        let mut i = self.index;
+        let mut size = self.leaf_count;
+        while size > 1 {
+            if i ^ 1 < size {
+                let sibling = match siblings.next() {
+                    Some(sibling) => sibling,
+                    None => return false,
+                };
+                hash = if i & 1 == 0 {
+                    node_hash(&hash, sibling)
+                } else {
+                    node_hash(sibling, &hash)
+                };
+            }
+            i /= 2;
+            size = size.div_ceil(2);
+        }
+        siblings.next().is_none() && &hash == root
+    }
+}
+
+fn leaf_hash(ciphertext_hash: &[u8; 32]) -> [u8; 32] {
+    let mut transcript = merlin::Transcript::new(b"escrow manifest leaf");
+    transcript.append_message(b"ciphertext", ciphertext_hash);
+    let mut out = [0u8; 32];
+    transcript.challenge_bytes(b"hash", &mut out);
+    out
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut transcript = merlin::Transcript::new(b"escrow manifest node");
+    transcript.append_message(b"left", left);
+    transcript.append_message(b"right", right);
+    let mut out = [0u8; 32];
+    transcript.challenge_bytes(b"hash", &mut out);
+    out
+}
+
+fn empty_root() -> [u8; 32] {
+    let mut transcript = merlin::Transcript::new(b"escrow manifest empty");
+    let mut out = [0u8; 32];
+    transcript.challenge_bytes(b"hash", &mut out);
+    out
+}
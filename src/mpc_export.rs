@@ -0,0 +1,127 @@
+use crate::{DecryptionKey, Group, VerencError};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+use zeroize::Zeroize;
+
+/// Decryption exponents split into field elements for custody
+/// in an external MPC framework, see `DecryptionKey::export_for_mpc`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MpcKeyExport {
+    /// The group the exponents belong to
+    pub group: Group,
+    /// The prime modulus of the MPC framework's field
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub field_modulus: BigNumber,
+    /// Every chunk is less than 2^chunk_bits
+    pub chunk_bits: usize,
+    /// The exponents `x1[0]`, `x1[1]`, ..., `x2` then `x3`
+    pub exponents: Vec<MpcExponent>,
+}
+
+/// One decryption exponent as little-endian chunks of `chunk_bits` bits,
+/// the exponent is the sum of chunk_i * 2^{i * chunk_bits}
+#[derive(Clone, Debug, Deserialize, Serialize, Zeroize)]
+pub struct MpcExponent {
+    /// The name of the exponent, e.g. `x1[0]` or `x2`
+    pub name: String,
+    /// The bit length every exponent is padded to
+    pub bits: usize,
+    /// The chunks, least significant first
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub chunks: Vec<BigNumber>,
+}
+
+impl Zeroize for MpcKeyExport {
+    fn zeroize(&mut self) {
+        self.exponents.iter_mut().for_each(|e| e.zeroize());
+    }
+}
+
+impl MpcKeyExport {
+    /// Reassemble the decryption key from the chunks
+    pub fn to_decryption_key(&self) -> Result<DecryptionKey, VerencError> {
+        // x2 and x3 follow however many x1 slots there are, possibly none
+        if self.exponents.len() < 2 {
+            return Err(VerencError::Other(format!(
+                "Number of exponents {} is less than 2",
+                self.exponents.len()
+            )));
+        }
+        let bound = BigNumber::one() << self.chunk_bits;
+        let mut exponents = Vec::with_capacity(self.exponents.len());
+        for exponent in &self.exponents {
+            let mut x = BigNumber::zero();
+            for (i, c) in exponent.chunks.iter().enumerate().rev() {
+                if c < &BigNumber::zero() || c >= &bound {
+                    return Err(VerencError::Other(format!(
+                        "{} chunk {} is not valid",
+                        exponent.name, i
+                    )));
+                }
+                x = (x << self.chunk_bits) + c;
+            }
+            if x >= self.group.n2d4 {
+                return Err(VerencError::Other(format!(
+                    "{} is not valid",
+                    exponent.name
+                )));
+            }
+            exponents.push(x);
+        }
+        let x3 = exponents.pop().expect("at least 2 exponents");
+        let x2 = exponents.pop().expect("at least 2 exponents");
+        Ok(DecryptionKey {
+            x1: exponents,
+            x2,
+            x3,
+            group: self.group.clone(),
+        })
+    }
+}
+
+impl DecryptionKey {
+    /// Export the decryption exponents as chunks of `chunk_bits` bits
+    /// so each chunk is an element of the field with prime `field_modulus`.
+    /// Every exponent is padded to the same number of chunks so
+    /// the export doesn't reveal their sizes.
+    /// The result holds the secret key and should be zeroized after use
+    pub fn export_for_mpc(
+        &self,
+        field_modulus: &BigNumber,
+        chunk_bits: usize,
+    ) -> Result<MpcKeyExport, VerencError> {
+        if chunk_bits == 0 || BigNumber::one() << chunk_bits > *field_modulus {
+            return Err(VerencError::Other(format!(
+                "Chunks of {} bits do not fit in the field",
+                chunk_bits
+            )));
+        }
+        // Exponents are less than n^2 / 4
+        let bits = 2 * self.group.modulus_bits();
+        let num_chunks = bits.div_ceil(chunk_bits);
+        let bound = BigNumber::one() << chunk_bits;
+        let names = (0..self.x1.len())
+            .map(|i| format!("x1[{}]", i))
+            .chain(vec!["x2".to_string(), "x3".to_string()]);
+        let exponents = names
+            .zip(self.x1.iter().chain(vec![&self.x2, &self.x3]))
+            .map(|(name, x)| {
+                let mut chunks = Vec::with_capacity(num_chunks);
+                let mut rest = x.clone();
+                for _ in 0..num_chunks {
+                    let (q, c) = rest.div_rem(&bound);
+                    chunks.push(c);
+                    rest = q;
+                }
+                rest.zeroize();
+                MpcExponent { name, bits, chunks }
+            })
+            .collect();
+        Ok(MpcKeyExport {
+            group: self.group.clone(),
+            field_modulus: field_modulus.clone(),
+            chunk_bits,
+            exponents,
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use crate::VerencError;
+use unknown_order::BigNumber;
+
+/// Pack `values` into a single message so they can share one key slot.
+/// Value `j` occupies bits `j * bits_per_value` to `(j + 1) * bits_per_value - 1`.
+/// `bits_per_value` must be between 1 and 64 and every value must fit in it
+pub fn pack_messages(values: &[u64], bits_per_value: usize) -> Result<BigNumber, VerencError> {
+    check_bits_per_value(bits_per_value)?;
+    let mut packed = BigNumber::zero();
+    for (i, v) in values.iter().enumerate().rev() {
+        if bits_per_value < 64 && *v >> bits_per_value != 0 {
+            return Err(VerencError::Other(format!(
+                "value {} does not fit in {} bits",
+                i, bits_per_value
+            )));
+        }
+        packed = (packed << bits_per_value) + BigNumber::from(*v);
+    }
+    Ok(packed)
+}
+
+/// Unpack `count` values of `bits_per_value` bits from a message
+/// created by `pack_messages`
+pub fn unpack_messages(
+    packed: &BigNumber,
+    bits_per_value: usize,
+    count: usize,
+) -> Result<Vec<u64>, VerencError> {
+    check_bits_per_value(bits_per_value)?;
+    if packed < &BigNumber::zero() || packed >= &(BigNumber::one() << (bits_per_value * count)) {
+        return Err(VerencError::Other(format!(
+            "packed message does not fit in {} values of {} bits",
+            count, bits_per_value
+        )));
+    }
+    let modulus = BigNumber::one() << bits_per_value;
+    let mut values = Vec::with_capacity(count);
+    let mut rest = packed.clone();
+    for _ in 0..count {
+        let (q, v) = rest.div_rem(&modulus);
+        values.push(
+            v.to_bytes()
+                .iter()
+                .fold(0u64, |acc, b| (acc << 8) | u64::from(*b)),
+        );
+        rest = q;
+    }
+    Ok(values)
+}
+
+/// Bit `index` of the packing of `values`
+pub(crate) fn packed_bit(values: &[u64], bits_per_value: usize, index: usize) -> bool {
+    (values[index / bits_per_value] >> (index % bits_per_value)) & 1 == 1
+}
+
+/// The lowest `width` bits of `x`, least significant first
+pub(crate) fn bits_le(x: &BigNumber, width: usize) -> Vec<bool> {
+    let bytes = x.to_bytes();
+    (0..width)
+        .map(|k| {
+            let i = k / 8;
+            i < bytes.len() && (bytes[bytes.len() - 1 - i] >> (k % 8)) & 1 == 1
+        })
+        .collect()
+}
+
+fn check_bits_per_value(bits_per_value: usize) -> Result<(), String> {
+    if bits_per_value == 0 || bits_per_value > 64 {
+        return Err(format!(
+            "bits per value {} must be between 1 and 64",
+            bits_per_value
+        ));
+    }
+    Ok(())
+}
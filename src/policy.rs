@@ -0,0 +1,367 @@
+use crate::{
+    recovery::{evaluate, interpolate, share_key, x_coordinate},
+    sampling::random_below,
+    DecryptionKey, EncryptionKey, PublicKeyGroup, SecretKeyEscrowProof, VerencError,
+    VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A monotone access structure over recipients named by their index
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum AccessPolicy {
+    /// The recipient at this index
+    Recipient(usize),
+    /// Every sub-policy must be satisfied
+    And(Vec<AccessPolicy>),
+    /// Any one sub-policy must be satisfied
+    Or(Vec<AccessPolicy>),
+    /// At least this many sub-policies must be satisfied
+    Threshold(usize, Vec<AccessPolicy>),
+}
+
+impl AccessPolicy {
+    /// True if the recipients at `indices` together satisfy this policy
+    pub fn is_satisfied_by(&self, indices: &[usize]) -> bool {
+        match self {
+            Self::Recipient(i) => indices.contains(i),
+            _ => {
+                let (threshold, children) = self.threshold();
+                children
+                    .iter()
+                    .filter(|c| c.is_satisfied_by(indices))
+                    .count()
+                    >= threshold
+            }
+        }
+    }
+
+    /// `And` and `Or` as thresholds of all or one of the sub-policies
+    fn threshold(&self) -> (usize, &[AccessPolicy]) {
+        match self {
+            Self::Recipient(_) => (1, &[]),
+            Self::And(children) => (children.len(), children),
+            Self::Or(children) => (1, children),
+            Self::Threshold(threshold, children) => (*threshold, children),
+        }
+    }
+}
+
+/// A node of a `PolicyEscrow`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) enum PolicyNode<T> {
+    Recipient {
+        recipient: usize,
+        ciphertext: VerifiableCipherText,
+        proof: SecretKeyEscrowProof,
+    },
+    Threshold {
+        commitments: Vec<T>,
+        children: Vec<PolicyNode<T>>,
+    },
+}
+
+/// A secret key escrowed so any set of recipients allowed by an
+/// `AccessPolicy` can recover it.
+///
+/// The policy is compiled to nested Shamir sharings, `And` needing every
+/// share and `Or` any one. Each node commits to its polynomial so anyone
+/// can check every share against the public key, and each share at a leaf
+/// is escrowed to its recipient with `EncryptionKey::escrow_secret_key`.
+/// Recipients decrypt their shares with `open` and anyone holding
+/// shares from an authorized set can `combine` them
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PolicyEscrow<T> {
+    pub(crate) root: PolicyNode<T>,
+}
+
+/// A recipient's share at one leaf of a `PolicyEscrow`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PolicyShare {
+    pub(crate) leaf: usize,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) share: BigNumber,
+}
+
+impl PolicyShare {
+    /// The leaf in depth first order this share is for
+    pub fn leaf(&self) -> usize {
+        self.leaf
+    }
+}
+
+impl<T: Clone + PartialEq> PolicyEscrow<T> {
+    /// Escrow `secret_key` of `public_key` to `recipients` as `policy` allows
+    pub fn create<P: PublicKeyGroup<PublicKey = T>>(
+        nonce: &[u8],
+        secret_key: &BigNumber,
+        public_key: &T,
+        policy: &AccessPolicy,
+        recipients: &[EncryptionKey],
+    ) -> Result<Self, VerencError> {
+        let order = P::order();
+        let zero = BigNumber::zero();
+        if secret_key < &zero || secret_key >= &order {
+            return Err(VerencError::Other(
+                "secret key is not less than the group order".to_string(),
+            ));
+        }
+        if P::mul_add(secret_key, &zero, public_key) != *public_key {
+            return Err(VerencError::Other(
+                "secret key does not match the public key".to_string(),
+            ));
+        }
+        let mut leaf = 0;
+        let root = Self::share::<P>(
+            nonce, secret_key, public_key, policy, recipients, &order, &mut leaf,
+        )?;
+        Ok(Self { root })
+    }
+
+    fn share<P: PublicKeyGroup<PublicKey = T>>(
+        nonce: &[u8],
+        secret: &BigNumber,
+        public_key: &T,
+        policy: &AccessPolicy,
+        recipients: &[EncryptionKey],
+        order: &BigNumber,
+        leaf: &mut usize,
+    ) -> Result<PolicyNode<T>, String> {
+        let zero = BigNumber::zero();
+        if let AccessPolicy::Recipient(recipient) = policy {
+            let key = recipients
+                .get(*recipient)
+                .ok_or_else(|| format!("Unknown recipient {}", recipient))?;
+            let share_key = P::mul_add(secret, &zero, public_key);
+            let (ciphertext, proof) =
+                key.escrow_secret_key::<P>(&leaf_nonce(nonce, *leaf), secret, &share_key)?;
+            *leaf += 1;
+            return Ok(PolicyNode::Recipient {
+                recipient: *recipient,
+                ciphertext,
+                proof,
+            });
+        }
+        let (threshold, policies) = policy.threshold();
+        check_threshold(threshold, policies.len())?;
+        let mut coefficients = vec![secret.clone()];
+        coefficients.extend((1..threshold).map(|_| random_below(order)));
+        let commitments = coefficients
+            .iter()
+            .map(|a| P::mul_add(a, &zero, public_key))
+            .collect();
+        let mut children = Vec::with_capacity(policies.len());
+        for (i, child) in policies.iter().enumerate() {
+            let share = evaluate(&coefficients, &x_coordinate(i), order);
+            children.push(Self::share::<P>(
+                nonce, &share, public_key, child, recipients, order, leaf,
+            )?);
+        }
+        Ok(PolicyNode::Threshold {
+            commitments,
+            children,
+        })
+    }
+
+    /// The access policy the shares were created for with `And` and `Or`
+    /// written as thresholds
+    pub fn policy(&self) -> AccessPolicy {
+        fn policy<T>(node: &PolicyNode<T>) -> AccessPolicy {
+            match node {
+                PolicyNode::Recipient { recipient, .. } => AccessPolicy::Recipient(*recipient),
+                PolicyNode::Threshold {
+                    commitments,
+                    children,
+                } => AccessPolicy::Threshold(
+                    commitments.len(),
+                    children.iter().map(policy).collect(),
+                ),
+            }
+        }
+        policy(&self.root)
+    }
+
+    /// Check every share is escrowed to its recipient and
+    /// consistent with `public_key`
+    pub fn verify<P: PublicKeyGroup<PublicKey = T>>(
+        &self,
+        nonce: &[u8],
+        public_key: &T,
+        recipients: &[VerificationKey],
+    ) -> Result<(), VerencError> {
+        let mut leaf = 0;
+        Self::verify_node::<P>(nonce, &self.root, public_key, recipients, &mut leaf)
+            .map_err(VerencError::from)
+    }
+
+    fn verify_node<P: PublicKeyGroup<PublicKey = T>>(
+        nonce: &[u8],
+        node: &PolicyNode<T>,
+        key: &T,
+        recipients: &[VerificationKey],
+        leaf: &mut usize,
+    ) -> Result<(), String> {
+        match node {
+            PolicyNode::Recipient {
+                recipient,
+                ciphertext,
+                proof,
+            } => {
+                let vk = recipients
+                    .get(*recipient)
+                    .ok_or_else(|| format!("Unknown recipient {}", recipient))?;
+                vk.verify_escrowed_secret_key::<P>(
+                    &leaf_nonce(nonce, *leaf),
+                    ciphertext,
+                    key,
+                    proof,
+                )?;
+                *leaf += 1;
+                Ok(())
+            }
+            PolicyNode::Threshold {
+                commitments,
+                children,
+            } => {
+                check_threshold(commitments.len(), children.len())?;
+                if commitments[0] != *key {
+                    return Err("Share commitments do not match the public key".to_string());
+                }
+                for (i, child) in children.iter().enumerate() {
+                    let child_key = share_key::<P>(commitments, i);
+                    Self::verify_node::<P>(nonce, child, &child_key, recipients, leaf)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Decrypt every share escrowed to the recipient at `index`
+    pub fn open(
+        &self,
+        nonce: &[u8],
+        index: usize,
+        key: &DecryptionKey,
+    ) -> Result<Vec<PolicyShare>, VerencError> {
+        let mut leaves = Vec::new();
+        collect_leaves(&self.root, &mut leaves);
+        let mut shares = Vec::new();
+        for (leaf, (recipient, ciphertext)) in leaves.into_iter().enumerate() {
+            if recipient != index {
+                continue;
+            }
+            let msgs = key.decrypt(leaf_nonce(nonce, leaf), ciphertext)?;
+            if msgs.len() != 1 {
+                return Err(VerencError::Other(format!(
+                    "Number of messages {} != 1 share",
+                    msgs.len()
+                )));
+            }
+            shares.push(PolicyShare {
+                leaf,
+                share: msgs[0].clone(),
+            });
+        }
+        Ok(shares)
+    }
+
+    /// Combine the shares opened by an authorized set of recipients into
+    /// the secret key of `public_key`
+    pub fn combine<P: PublicKeyGroup<PublicKey = T>>(
+        &self,
+        public_key: &T,
+        shares: &[PolicyShare],
+    ) -> Result<BigNumber, VerencError> {
+        let order = P::order();
+        let mut leaf = 0;
+        let secret = Self::combine_node::<P>(&self.root, public_key, shares, &order, &mut leaf)?
+            .ok_or_else(|| "Shares do not satisfy the access policy".to_string())?;
+        if P::mul_add(&secret, &BigNumber::zero(), public_key) == *public_key {
+            Ok(secret)
+        } else {
+            Err(VerencError::Other(
+                "Recovered secret key does not match the public key".to_string(),
+            ))
+        }
+    }
+
+    /// The secret of `node` if enough of its shares are present
+    fn combine_node<P: PublicKeyGroup<PublicKey = T>>(
+        node: &PolicyNode<T>,
+        public_key: &T,
+        shares: &[PolicyShare],
+        order: &BigNumber,
+        leaf: &mut usize,
+    ) -> Result<Option<BigNumber>, String> {
+        match node {
+            PolicyNode::Recipient { .. } => {
+                let index = *leaf;
+                *leaf += 1;
+                Ok(shares
+                    .iter()
+                    .find(|s| s.leaf == index)
+                    .map(|s| s.share.clone()))
+            }
+            PolicyNode::Threshold {
+                commitments,
+                children,
+            } => {
+                check_threshold(commitments.len(), children.len())?;
+                let mut indices = Vec::with_capacity(commitments.len());
+                let mut values = Vec::with_capacity(commitments.len());
+                for (i, child) in children.iter().enumerate() {
+                    // Visit every child so the leaf numbering stays in step
+                    let value = Self::combine_node::<P>(child, public_key, shares, order, leaf)?;
+                    if let Some(value) = value {
+                        if indices.len() < commitments.len() {
+                            if P::mul_add(&value, &BigNumber::zero(), public_key)
+                                != share_key::<P>(commitments, i)
+                            {
+                                return Err(format!("Invalid share in sub-policy {}", i));
+                            }
+                            indices.push(i);
+                            values.push(value);
+                        }
+                    }
+                }
+                if indices.len() < commitments.len() {
+                    return Ok(None);
+                }
+                interpolate(&indices, &values, order).map(Some)
+            }
+        }
+    }
+}
+
+fn collect_leaves<'a, T>(
+    node: &'a PolicyNode<T>,
+    out: &mut Vec<(usize, &'a VerifiableCipherText)>,
+) {
+    match node {
+        PolicyNode::Recipient {
+            recipient,
+            ciphertext,
+            ..
+        } => out.push((*recipient, ciphertext)),
+        PolicyNode::Threshold { children, .. } => {
+            children.iter().for_each(|c| collect_leaves(c, out))
+        }
+    }
+}
+
+fn check_threshold(threshold: usize, count: usize) -> Result<(), String> {
+    if threshold == 0 || threshold > count {
+        return Err(format!(
+            "Threshold {} must be between 1 and the number of sub-policies {}",
+            threshold, count
+        ));
+    }
+    Ok(())
+}
+
+fn leaf_nonce(nonce: &[u8], leaf: usize) -> Vec<u8> {
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(b"policy leaf");
+    out.extend_from_slice(&(leaf as u64).to_be_bytes());
+    out
+}
@@ -0,0 +1,520 @@
+use crate::{
+    encryptionkeyref::first_slots, group::bit_length, Context, Group, VerencError,
+    VerifiableCipherText, VerifiableEncryptionProof, VerificationKey,
+};
+use std::sync::Arc;
+use unknown_order::BigNumber;
+
+/// A fixed base of a `VerificationKey` that `PrecomputedKey` can build a table for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BaseId {
+    /// The group generator `g`
+    G,
+    /// `y1` of the slot
+    Y1(usize),
+}
+
+/// Most bits per window `Precompute` picks
+const MAX_WINDOW_BITS: usize = 8;
+
+/// Picks the window size of fixed-base tables from the number of
+/// exponentiations each base is expected to do and the memory available.
+///
+/// A table with w bit windows takes about one multiplication per w bits
+/// of the exponent but 2^w values per window to build and hold, so keys
+/// used a few times want narrow windows and long lived ones wide windows.
+/// Costs are counted in multiplications mod n^2, which is what the
+/// `encrypt_precomputed` and `precompute_tuned` benches spend their time on
+/// with every backend. Without a memory cap nothing limits the size,
+/// e.g. cap it in a wasm verifier and leave it off on a batch server.
+///
+/// Pass it to `PrecomputedKey::tuned` or `EncryptionKey::precompute_tuned`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Precompute {
+    num_ops: usize,
+    max_bytes: Option<usize>,
+}
+
+impl Precompute {
+    /// Tune for `num_ops` exponentiations with each base
+    pub fn tuned_for(num_ops: usize) -> Self {
+        Self {
+            num_ops,
+            max_bytes: None,
+        }
+    }
+
+    /// Cap the memory all tables of a key take together
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// The number of exponentiations with each base tuned for
+    pub fn num_ops(&self) -> usize {
+        self.num_ops
+    }
+
+    /// The memory cap, if any
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// The window bits of verifier tables for `tables` bases of `group`
+    pub(crate) fn verifier_window_bits(
+        &self,
+        group: &Group,
+        tables: usize,
+    ) -> Result<usize, String> {
+        let bits = FixedBaseTable::exponent_bits(group);
+        self.pick(group, tables, |w| {
+            let windows = bits.div_ceil(w) as u128;
+            let values = (1u128 << w) - 1;
+            // A digit is zero one time in 2^w and skipped
+            let per_op = (windows * values) << (MAX_WINDOW_BITS - w);
+            let build = (windows * (values + w as u128)) << MAX_WINDOW_BITS;
+            (build, per_op, windows * values)
+        })
+    }
+
+    /// The window bits of prover tables for `tables` bases of `group`
+    #[cfg(not(feature = "verify-only"))]
+    pub(crate) fn prover_window_bits(&self, group: &Group, tables: usize) -> Result<usize, String> {
+        self.pick(group, tables, |w| {
+            let windows = WindowedTable::window_count(group, w) as u128;
+            let values = 1u128 << w;
+            (
+                windows * (values + 2 * w as u128),
+                windows,
+                windows * values,
+            )
+        })
+    }
+
+    /// The window bits from 1 to `MAX_WINDOW_BITS` with the least total
+    /// cost whose tables fit. `cost` gives the multiplications to build a
+    /// table, the multiplications per exponentiation and the values held
+    fn pick<F>(&self, group: &Group, tables: usize, cost: F) -> Result<usize, String>
+    where
+        F: Fn(usize) -> (u128, u128, u128),
+    {
+        let value_bytes = group.nn.to_bytes().len() as u128;
+        let num_ops = self.num_ops as u128;
+        (1..=MAX_WINDOW_BITS)
+            .filter(|w| match self.max_bytes {
+                Some(max) => cost(*w).2 * value_bytes * tables as u128 <= max as u128,
+                None => true,
+            })
+            .min_by_key(|w| {
+                let (build, per_op, _) = cost(*w);
+                build + num_ops * per_op
+            })
+            .ok_or_else(|| "tables do not fit in the memory cap".to_string())
+    }
+}
+
+/// A `VerificationKey` with fixed-base tables for the bases chosen with `warm`.
+///
+/// Nothing is built on demand and nothing changes behind a shared reference:
+/// `new` builds no tables, `warm` builds the requested ones before returning
+/// and `verify` only reads them, using plain exponentiation for cold bases.
+/// Warm it when the latency is acceptable, e.g. at start up, then share it
+/// with an `Arc`. Tables are themselves behind `Arc`s so to warm more bases
+/// later clone it, warm the clone and swap it in.
+///
+/// Each table from `new` holds one value for every bit of n^2 so uses
+/// about 2 MiB per base with a 2048-bit modulus. `tuned` picks wider
+/// windows that trade memory and warm up time for faster verification
+#[derive(Clone, Debug)]
+pub struct PrecomputedKey {
+    key: VerificationKey,
+    window_bits: usize,
+    g: Option<Arc<FixedBaseTable>>,
+    y1: Vec<Option<Arc<FixedBaseTable>>>,
+}
+
+impl PrecomputedKey {
+    /// `key` with no tables
+    pub fn new(key: impl Into<VerificationKey>) -> Self {
+        let key = key.into();
+        let y1 = vec![None; key.y1.len()];
+        Self {
+            key,
+            window_bits: 1,
+            g: None,
+            y1,
+        }
+    }
+
+    /// `key` with no tables yet whose tables `warm` builds with
+    /// the window size `precompute` picks for every base of the key
+    pub fn tuned(
+        key: impl Into<VerificationKey>,
+        precompute: Precompute,
+    ) -> Result<Self, VerencError> {
+        let mut tuned = Self::new(key);
+        tuned.window_bits =
+            precompute.verifier_window_bits(&tuned.key.group, tuned.y1.len() + 1)?;
+        Ok(tuned)
+    }
+
+    /// The bits per window of the tables `warm` builds
+    pub fn window_bits(&self) -> usize {
+        self.window_bits
+    }
+
+    /// Build the tables for `bases` that aren't built yet.
+    /// Nothing is built if any base isn't part of the key
+    pub fn warm(&mut self, bases: &[BaseId]) -> Result<(), VerencError> {
+        if let Some(BaseId::Y1(i)) = bases
+            .iter()
+            .find(|b| matches!(b, BaseId::Y1(i) if *i >= self.y1.len()))
+        {
+            return Err(VerencError::Other(format!(
+                "Index {} is more than supported by this key {}",
+                i,
+                self.y1.len()
+            )));
+        }
+        let group = &self.key.group;
+        for base in bases {
+            match *base {
+                BaseId::G => {
+                    if self.g.is_none() {
+                        self.g = Some(Arc::new(FixedBaseTable::with_window(
+                            group,
+                            &group.g,
+                            self.window_bits,
+                        )));
+                    }
+                }
+                BaseId::Y1(i) => {
+                    if self.y1[i].is_none() {
+                        self.y1[i] = Some(Arc::new(FixedBaseTable::with_window(
+                            group,
+                            &self.key.y1[i],
+                            self.window_bits,
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the tables for every base
+    pub fn warm_all(&mut self) {
+        let bases = self.bases();
+        self.warm(&bases).expect("every base is part of the key");
+    }
+
+    /// Every base of the key
+    pub fn bases(&self) -> Vec<BaseId> {
+        std::iter::once(BaseId::G)
+            .chain((0..self.y1.len()).map(BaseId::Y1))
+            .collect()
+    }
+
+    /// True if the table for `base` is built
+    pub fn is_warm(&self, base: BaseId) -> bool {
+        match base {
+            BaseId::G => self.g.is_some(),
+            BaseId::Y1(i) => self.y1.get(i).is_some_and(Option::is_some),
+        }
+    }
+
+    /// The key proofs are verified with
+    pub fn verification_key(&self) -> &VerificationKey {
+        &self.key
+    }
+
+    /// Verify a proof of verifiable encryption, see `VerificationKey::verify`
+    pub fn verify<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        if proof.m.len() > self.y1.len() {
+            return Err(VerencError::TooManyMessages {
+                messages: proof.m.len(),
+                max: self.y1.len(),
+            });
+        }
+        self.key.view().verify_with_tables(
+            context.into().as_bytes(),
+            &first_slots(proof.m.len()),
+            ciphertext,
+            proof,
+            self.g.as_deref(),
+            &self.y1,
+            None,
+        )
+    }
+}
+
+/// base^(d * 2^(w * i)) mod n^2 for every window i of w bits
+/// covering n^2 and digit d from 1 to 2^w - 1
+#[derive(Debug)]
+pub(crate) struct FixedBaseTable {
+    bits: usize,
+    windows: Vec<Vec<BigNumber>>,
+}
+
+impl FixedBaseTable {
+    /// A table with one bit windows, one value for every bit of n^2
+    pub(crate) fn new(group: &Group, base: &BigNumber) -> Self {
+        Self::with_window(group, base, 1)
+    }
+
+    pub(crate) fn with_window(group: &Group, base: &BigNumber, bits: usize) -> Self {
+        let count = Self::exponent_bits(group).div_ceil(bits);
+        let mut windows = Vec::with_capacity(count);
+        let mut power = base.clone();
+        for _ in 0..count {
+            let mut window = Vec::with_capacity((1 << bits) - 1);
+            window.push(power.clone());
+            for d in 1..(1usize << bits) - 1 {
+                let next = group.mul(&window[d - 1], &power);
+                window.push(next);
+            }
+            for _ in 0..bits {
+                power = group.mul(&power, &power);
+            }
+            windows.push(window);
+        }
+        Self { bits, windows }
+    }
+
+    /// Responses are below n^2 so doubled exponents need one more bit
+    pub(crate) fn exponent_bits(group: &Group) -> usize {
+        bit_length(&group.nn) + 1
+    }
+
+    pub(crate) fn pow(&self, group: &Group, exp: &BigNumber) -> Result<BigNumber, VerencError> {
+        let negative = exp < &BigNumber::zero();
+        let magnitude = if negative { -exp.clone() } else { exp.clone() };
+        if bit_length(&magnitude) > self.windows.len() * self.bits {
+            return Ok(group.pow(&self.windows[0][0], exp));
+        }
+        let digits = window_digits(&magnitude.to_bytes(), self.bits, self.windows.len());
+        let mut result = BigNumber::one();
+        for (window, digit) in self.windows.iter().zip(digits) {
+            if digit != 0 {
+                result = group.mul(&result, &window[digit - 1]);
+            }
+        }
+        if negative {
+            result.invert(&group.nn).ok_or(VerencError::InvalidProof)
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+/// The `count` digits of `bits` bits of the big endian `bytes`,
+/// least significant first
+fn window_digits(bytes: &[u8], bits: usize, count: usize) -> Vec<usize> {
+    let mut digits = vec![0usize; count];
+    for (i, byte) in bytes.iter().rev().enumerate() {
+        for bit in 0..8 {
+            let k = i * 8 + bit;
+            if k < count * bits {
+                digits[k / bits] |= ((byte >> bit & 1) as usize) << (k % bits);
+            }
+        }
+    }
+    digits
+}
+
+/// An `EncryptionKey` with windowed fixed-base tables for `g` and every
+/// `y1` so encrypting and proving skip most of the exponentiation work.
+///
+/// Built once with `EncryptionKey::precompute` and shared with an `Arc`
+/// or by cloning, which shares the tables. `u` and each `e` take one
+/// multiplication per 4 bits of `r` instead of a full exponentiation.
+/// `h^m` needs no table and `v` depends on the ciphertext so
+/// is computed as before. Each table uses about 4 MiB with a 2048-bit modulus.
+/// `EncryptionKey::precompute_tuned` picks another window size instead
+#[cfg(not(feature = "verify-only"))]
+#[derive(Clone, Debug)]
+pub struct PrecomputedEncryptionKey {
+    key: crate::EncryptionKey,
+    tables: Arc<ProverTables>,
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl PrecomputedEncryptionKey {
+    pub(crate) fn new(key: &crate::EncryptionKey) -> Result<Self, String> {
+        Self::with_window(key, WINDOW_BITS)
+    }
+
+    pub(crate) fn tuned(
+        key: &crate::EncryptionKey,
+        precompute: Precompute,
+    ) -> Result<Self, String> {
+        let bits = precompute.prover_window_bits(&key.vk.group, key.vk.y1.len() + 1)?;
+        Self::with_window(key, bits)
+    }
+
+    fn with_window(key: &crate::EncryptionKey, bits: usize) -> Result<Self, String> {
+        let group = &key.vk.group;
+        let tables = ProverTables {
+            g: WindowedTable::new(group, &group.g, bits)?,
+            y1: key
+                .vk
+                .y1
+                .iter()
+                .map(|y| WindowedTable::new(group, y, bits))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(Self {
+            key: key.clone(),
+            tables: Arc::new(tables),
+        })
+    }
+
+    /// The bits per window of the tables
+    pub fn window_bits(&self) -> usize {
+        self.tables.g.bits
+    }
+
+    /// The key the tables are for
+    pub fn encryption_key(&self) -> &crate::EncryptionKey {
+        &self.key
+    }
+
+    /// Borrowed view of the key that encrypts and proves with the tables
+    pub fn view(&self) -> crate::EncryptionKeyRef<'_> {
+        let mut view = self.key.view();
+        view.tables = Some(&self.tables);
+        view
+    }
+
+    /// Encrypt multiple messages, see `EncryptionKey::encrypt`
+    pub fn encrypt<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+    ) -> Result<VerifiableCipherText, VerencError> {
+        self.view().encrypt(context, msgs)
+    }
+
+    /// Encrypt and prove, see `EncryptionKey::encrypt_and_prove`
+    pub fn encrypt_and_prove<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        msgs: &[BigNumber],
+    ) -> Result<(VerifiableCipherText, VerifiableEncryptionProof), VerencError> {
+        self.view().encrypt_and_prove(context, msgs)
+    }
+}
+
+/// The tables of a `PrecomputedEncryptionKey`
+#[cfg(not(feature = "verify-only"))]
+#[derive(Debug)]
+pub(crate) struct ProverTables {
+    pub(crate) g: WindowedTable,
+    pub(crate) y1: Vec<WindowedTable>,
+}
+
+/// Bits of each window of a `WindowedTable` unless tuned
+#[cfg(not(feature = "verify-only"))]
+const WINDOW_BITS: usize = 4;
+
+/// base^(d * 2^(w * i)) for every window i of w bits and odd d
+/// from -(2^w - 1) to 2^w - 1, for exponents the prover keeps secret.
+///
+/// Exponents are recoded into odd signed digits so every window takes
+/// exactly one multiplication whatever the exponent, with no
+/// multiplications by one and no branches on its bits
+#[cfg(not(feature = "verify-only"))]
+#[derive(Debug)]
+pub(crate) struct WindowedTable {
+    bits: usize,
+    windows: Vec<Vec<BigNumber>>,
+    /// base^-1 and base^-2 to undo making the exponent odd
+    corrections: [BigNumber; 2],
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl WindowedTable {
+    /// Fails if `base` is not a unit mod n^2, e.g. a malformed key value
+    pub(crate) fn new(group: &Group, base: &BigNumber, bits: usize) -> Result<Self, String> {
+        let count = Self::window_count(group, bits);
+        let inverse = base
+            .invert(&group.nn)
+            .ok_or_else(|| "key value is not a unit mod n^2".to_string())?;
+        let mut power = base.clone();
+        let mut inverse_power = inverse.clone();
+        let mut windows = Vec::with_capacity(count);
+        for _ in 0..count {
+            // Digit 2j - (2^w - 1) is at index j
+            let mut window = odd_powers(group, &inverse_power, bits);
+            window.reverse();
+            window.extend(odd_powers(group, &power, bits));
+            windows.push(window);
+            for _ in 0..bits {
+                power = group.mul(&power, &power);
+                inverse_power = group.mul(&inverse_power, &inverse_power);
+            }
+        }
+        let corrections = [inverse.clone(), group.mul(&inverse, &inverse)];
+        Ok(Self {
+            bits,
+            windows,
+            corrections,
+        })
+    }
+
+    /// Exponents are below n / 2 and made odd by adding at most 2,
+    /// whole bytes of them are recoded and one more window keeps
+    /// the top digit in range
+    pub(crate) fn window_count(group: &Group, bits: usize) -> usize {
+        (8 * (bit_length(&group.n) + 2).div_ceil(8)).div_ceil(bits) + 1
+    }
+
+    /// base^exp for `0 <= exp`. Larger exponents than the table covers fall
+    /// back to a plain exponentiation
+    pub(crate) fn pow(&self, group: &Group, exp: &BigNumber) -> BigNumber {
+        let count = self.windows.len();
+        let one = 1usize << (self.bits - 1);
+        if exp < &BigNumber::zero() {
+            return group.pow(&self.windows[0][one], exp);
+        }
+        // exp + 1 if even and exp + 2 if odd so the recoded exponent is odd
+        let odd = (exp.to_bytes().last().copied().unwrap_or(0) & 1) as usize;
+        let bytes = (exp + BigNumber::from(1 + odd as u64)).to_bytes();
+        // The top window must stay clear for the last digit to be unsigned
+        if (bytes.len() * 8).div_ceil(self.bits) >= count {
+            return group.pow(&self.windows[0][one], exp);
+        }
+        let digits = window_digits(&bytes, self.bits, count);
+        let radix = 1i64 << self.bits;
+        let mut result = self.corrections[odd].clone();
+        for (i, window) in self.windows.iter().enumerate() {
+            // digit = (d_i | 1) + 2^w * (d_(i+1) & 1) - 2^w, the last one unsigned
+            let low = (digits[i] | 1) as i64;
+            let digit = match digits.get(i + 1) {
+                Some(next) => low + radix * (*next & 1) as i64 - radix,
+                None => low,
+            };
+            result = group.mul(&result, &window[((digit + radix - 1) / 2) as usize]);
+        }
+        result
+    }
+}
+
+/// base^1, base^3, ..., base^(2^w - 1)
+#[cfg(not(feature = "verify-only"))]
+fn odd_powers(group: &Group, base: &BigNumber, bits: usize) -> Vec<BigNumber> {
+    let square = group.mul(base, base);
+    let count = 1 << (bits - 1);
+    let mut powers = Vec::with_capacity(count);
+    powers.push(base.clone());
+    for i in 1..count {
+        let next = group.mul(&powers[i - 1], &square);
+        powers.push(next);
+    }
+    powers
+}
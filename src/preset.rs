@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Named parameter sets selecting the strength of a `Group`.
+/// Serialized keys record their modulus size so a group created with any
+/// preset can be read back without knowing which one was used.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterPreset {
+    /// `n` is 2048 bits
+    Rsa2048,
+    /// `n` is 3072 bits
+    Rsa3072,
+    /// `n` is 4096 bits
+    Rsa4096,
+}
+
+impl ParameterPreset {
+    /// Every preset from weakest to strongest
+    pub const ALL: [ParameterPreset; 3] = [Self::Rsa2048, Self::Rsa3072, Self::Rsa4096];
+
+    /// The preset with a modulus of `bits` if there is one
+    pub fn for_modulus_bits(bits: usize) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.modulus_bits() == bits)
+    }
+
+    /// The bit length of `n`
+    pub fn modulus_bits(&self) -> usize {
+        match self {
+            Self::Rsa2048 => 2048,
+            Self::Rsa3072 => 3072,
+            Self::Rsa4096 => 4096,
+        }
+    }
+
+    /// The bit length of each safe prime factor of `n`
+    pub fn prime_bits(&self) -> usize {
+        self.modulus_bits() / 2
+    }
+}
+
+impl Display for ParameterPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rsa{}", self.modulus_bits())
+    }
+}
+
+impl FromStr for ParameterPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|p| p.to_string() == s)
+            .ok_or_else(|| format!("Unknown parameter preset {}", s))
+    }
+}
@@ -0,0 +1,40 @@
+use crate::VerifiableEncryptionProof;
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof of verifiable encryption that also outputs a Pedersen commitment
+/// `m_i * G + s_i * H` to each message in a `PublicKeyGroup`.
+///
+/// The message responses are shared by both groups and not reduced,
+/// so the commitments are known to hold the encrypted messages
+/// and can be linked into other proofs with the blindings `s_i`
+/// returned by `EncryptionKey::encrypt_and_prove_committed`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommittedEncryptionProof<T> {
+    pub(crate) proof: VerifiableEncryptionProof,
+    pub(crate) commitments: Vec<T>,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) s: Vec<BigNumber>,
+}
+
+impl<T> CommittedEncryptionProof<T> {
+    /// The commitment to each message
+    pub fn commitments(&self) -> &[T] {
+        &self.commitments
+    }
+}
+
+/// Proof of verifiable encryption that also shows the message in one slot
+/// is the value in an existing Pedersen commitment `m * G + s * H` in a
+/// `PublicKeyGroup`, e.g. one issued by a credential system over BLS12-381
+/// or Ristretto, given its opening `(m, s)`.
+///
+/// Like `CommittedEncryptionProof` the response for that slot is not reduced
+/// so it answers the challenge in both groups. Only that message has to be
+/// less than the order of `P`, the other slots are proven as usual.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CommitmentLinkProof {
+    pub(crate) proof: VerifiableEncryptionProof,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) s: BigNumber,
+}
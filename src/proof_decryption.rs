@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that messages are the correct decryption of a ciphertext,
+/// i.e. log_g y1_i = log_u (e_i / h^m_i) for each message
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DecryptionProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) x: Vec<BigNumber>,
+}
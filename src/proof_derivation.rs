@@ -0,0 +1,110 @@
+use crate::{Group, VerificationKey};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that a key from `EncryptionKey::derive` is its master key at
+/// some slots with every value, `g` included, raised to the same tweak.
+///
+/// The tweak and the label it's hashed from stay hidden so only those
+/// given the proof can link the derived key to its master.
+/// Check it with `VerificationKey::verify_derivation`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DerivationProof {
+    pub(crate) indices: Vec<usize>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) response: BigNumber,
+}
+
+impl DerivationProof {
+    /// The slots of the master key the derived key's slots are, in order
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+}
+
+/// Tweaks are in [2^256, 2^257)
+pub(crate) const TWEAK_BITS: usize = 257;
+
+/// The blinding is this many bits longer than the tweak
+/// to hide c * t where c is 256 bits
+pub(crate) const DERIVATION_BLINDING_SLACK: usize = 256 + 128;
+
+/// The tweak for the key derived from `master` with `label` at `indices`
+pub(crate) fn derivation_tweak(
+    master: &VerificationKey,
+    label: &[u8],
+    indices: &[usize],
+) -> BigNumber {
+    let mut transcript = merlin::Transcript::new(b"camenisch-shoup public key derivation");
+    transcript.append_message(b"master", &master.fingerprint());
+    transcript.append_message(b"label", label);
+    transcript.append_u64(b"indices.count", indices.len() as u64);
+    for i in indices {
+        transcript.append_u64(b"index", *i as u64);
+    }
+    let mut bytes = [0u8; 32];
+    transcript.challenge_bytes(b"tweak", &mut bytes);
+    // The top bit is set so the tweak is never zero or small
+    BigNumber::from_slice(bytes) + (BigNumber::one() << (TWEAK_BITS - 1))
+}
+
+/// The master values a derived key's values are powers of: `g`, the
+/// `y1` at `indices`, `y2` and `y3`
+pub(crate) fn derivation_bases<'a>(
+    key: &'a VerificationKey,
+    indices: &'a [usize],
+) -> impl Iterator<Item = &'a BigNumber> {
+    std::iter::once(&key.group.g)
+        .chain(indices.iter().map(move |i| &key.y1[*i]))
+        .chain([&key.y2, &key.y3])
+}
+
+/// `derivation_bases` of a derived key, which uses all its slots
+pub(crate) fn derived_values(key: &VerificationKey) -> impl Iterator<Item = &BigNumber> {
+    std::iter::once(&key.group.g)
+        .chain(key.y1.iter())
+        .chain([&key.y2, &key.y3])
+}
+
+/// The key `master` at `indices` with every value raised to `tweak`
+pub(crate) fn derive_key(
+    master: &VerificationKey,
+    indices: &[usize],
+    tweak: &BigNumber,
+) -> Result<VerificationKey, String> {
+    let group = &master.group;
+    let g = group.pow(&group.g, tweak);
+    Ok(VerificationKey {
+        y1: indices
+            .iter()
+            .map(|i| group.pow(&master.y1[*i], tweak))
+            .collect(),
+        y2: group.pow(&master.y2, tweak),
+        y3: group.pow(&master.y3, tweak),
+        group: Group::from_parts(g, group.n.clone()).ok_or_else(|| "Invalid group".to_string())?,
+        lifecycle: None,
+        schema: None,
+    })
+}
+
+/// The Fiat-Shamir challenge binding both keys, the indices and the commitments
+pub(crate) fn derivation_challenge(
+    master: &VerificationKey,
+    derived: &VerificationKey,
+    indices: &[usize],
+    commitments: &[BigNumber],
+) -> BigNumber {
+    let mut transcript = merlin::Transcript::new(b"camenisch-shoup key derivation proof");
+    transcript.append_message(b"master", &master.fingerprint());
+    transcript.append_message(b"derived", &derived.fingerprint());
+    transcript.append_u64(b"indices.count", indices.len() as u64);
+    for i in indices {
+        transcript.append_u64(b"index", *i as u64);
+    }
+    for c in commitments {
+        transcript.append_message(b"commitment", &c.to_bytes());
+    }
+    VerificationKey::challenge(&mut transcript)
+}
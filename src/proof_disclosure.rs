@@ -0,0 +1,112 @@
+use crate::{EncryptionKeyRef, InclusionProof, Manifest, VerencError, VerifiableCipherText};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A proof of verifiable encryption whose per-message responses are
+/// committed to in a Merkle tree, created by `EncryptionKey::encrypt_and_prove_merkle`.
+///
+/// The challenge absorbs the root over every message's test value instead of
+/// the test values themselves so the prover can keep this and send each
+/// verifier a `ResponseDisclosure` with only the slots it cares about
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResponseTree {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) r: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) m: Vec<BigNumber>,
+    pub(crate) tree: Manifest,
+}
+
+/// The response for one message and the proof its test value is in the tree
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DisclosedResponse {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) m: BigNumber,
+    pub(crate) inclusion: InclusionProof,
+}
+
+/// The responses for chosen slots of a `ResponseTree`,
+/// checked by `VerificationKey::verify_disclosure`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResponseDisclosure {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) r: BigNumber,
+    pub(crate) root: [u8; 32],
+    pub(crate) responses: Vec<DisclosedResponse>,
+}
+
+impl ResponseTree {
+    /// The number of committed responses
+    pub fn len(&self) -> usize {
+        self.m.len()
+    }
+
+    /// True if no responses are committed
+    pub fn is_empty(&self) -> bool {
+        self.m.is_empty()
+    }
+
+    /// Disclose the responses for the messages at `indices`
+    pub fn disclose(&self, indices: &[usize]) -> Result<ResponseDisclosure, VerencError> {
+        let mut responses = Vec::with_capacity(indices.len());
+        for (i, index) in indices.iter().enumerate() {
+            if indices[..i].contains(index) {
+                return Err(VerencError::Other(format!(
+                    "Index {} is disclosed more than once",
+                    index
+                )));
+            }
+            let inclusion = self
+                .tree
+                .prove(*index)
+                .ok_or_else(|| format!("Index {} is more than the number of messages", index))?;
+            responses.push(DisclosedResponse {
+                m: self.m[*index].clone(),
+                inclusion,
+            });
+        }
+        Ok(ResponseDisclosure {
+            challenge: self.challenge.clone(),
+            r: self.r.clone(),
+            root: self.tree.root(),
+            responses,
+        })
+    }
+}
+
+impl ResponseDisclosure {
+    /// The indices of the disclosed messages
+    pub fn indices(&self) -> Vec<usize> {
+        self.responses.iter().map(|r| r.inclusion.index()).collect()
+    }
+}
+
+/// The leaf for the test value of the message at `index`
+pub(crate) fn response_leaf(index: usize, test_value: &BigNumber) -> [u8; 32] {
+    let mut transcript = merlin::Transcript::new(b"camenisch-shoup response leaf");
+    transcript.append_u64(b"index", index as u64);
+    transcript.append_message(b"ciphertext_test.e", &test_value.to_bytes());
+    let mut out = [0u8; 32];
+    transcript.challenge_bytes(b"hash", &mut out);
+    out
+}
+
+pub(crate) fn merkle_challenge(
+    key: &EncryptionKeyRef<'_>,
+    nonce: &[u8],
+    ciphertext: &VerifiableCipherText,
+    u: &BigNumber,
+    root: &[u8; 32],
+    v: &BigNumber,
+) -> BigNumber {
+    let mut transcript = key.proof_transcript(nonce, ciphertext);
+    transcript.append_message(b"proof", b"merkle responses");
+    transcript.append_message(b"ciphertext_test.u", &u.to_bytes());
+    transcript.append_message(b"ciphertext_test.e.root", root);
+    transcript.append_message(b"ciphertext_test.v", &v.to_bytes());
+    crate::VerificationKey::challenge(&mut transcript)
+}
@@ -0,0 +1,62 @@
+use crate::{escrow::ESCROW_BLINDING_SLACK, group::bit_length, VerifiableEncryptionProof};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that a ciphertext encrypts an element of Z_{N^2}, e.g. another
+/// ciphertext or a commitment, split into chunks of w bits, one per slot.
+///
+/// The element X is committed to as D = g^X with `g` from the encrypting
+/// key's group. The chunk responses are not reduced so they answer the
+/// challenge both for the ciphertext and for D = prod (g^{2^{w*j}})^{x_j},
+/// showing the chunks reconstruct the element behind D. Whoever later
+/// holds a candidate element can check it's the escrowed one with
+/// `VerificationKey::element_commitment`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ElementEscrowProof {
+    pub(crate) proof: VerifiableEncryptionProof,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) commitment: BigNumber,
+}
+
+impl ElementEscrowProof {
+    /// The commitment g^X to the escrowed element
+    pub fn commitment(&self) -> &BigNumber {
+        &self.commitment
+    }
+}
+
+/// The chunk width w for a key with modulus `n`. Chunks and the slack
+/// hiding c * x_j stay below n / 2 so a chunk decrypts to itself
+pub(crate) fn element_chunk_width(n: &BigNumber) -> Result<usize, String> {
+    match bit_length(n).checked_sub(ESCROW_BLINDING_SLACK + 3) {
+        Some(width) if width > 0 => Ok(width),
+        _ => Err("group is too small to escrow elements".to_string()),
+    }
+}
+
+/// The number of `width` bit chunks elements less than `modulus` take
+pub(crate) fn element_chunk_count(modulus: &BigNumber, width: usize) -> usize {
+    let bits = bit_length(&(modulus - BigNumber::one()));
+    bits.div_ceil(width).max(1)
+}
+
+/// Split `element` into `count` chunks of `width` bits, least significant first
+pub(crate) fn split_element(element: &BigNumber, width: usize, count: usize) -> Vec<BigNumber> {
+    let modulus = BigNumber::one() << width;
+    let mut rest = element.clone();
+    let mut chunks = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (q, chunk) = rest.div_rem(&modulus);
+        chunks.push(chunk);
+        rest = q;
+    }
+    chunks
+}
+
+/// Sum `chunks[j] * 2^{width * j}`, the inverse of `split_element`
+pub(crate) fn join_chunks(chunks: &[BigNumber], width: usize) -> BigNumber {
+    chunks
+        .iter()
+        .rev()
+        .fold(BigNumber::zero(), |acc, chunk| (acc << width) + chunk)
+}
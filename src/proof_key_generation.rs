@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that `y2 = g^x2` and `y3 = g^x3` for known `x2` and `x3`
+/// below n^2 / 4, as generated by `DecryptionKey::random`.
+///
+/// The chosen ciphertext security of the scheme relies on these values
+/// being honestly generated so relying parties can check it
+/// with `VerificationKey::verify_key_generation`.
+/// The responses are not reduced so their size bounds the exponents
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyGenerationProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) x2: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) x3: BigNumber,
+}
+
+/// The blindings are this many bits longer than n^2 / 4
+/// to hide c * x where c is 256 bits
+pub(crate) const KEY_GENERATION_BLINDING_SLACK: usize = 256 + 128;
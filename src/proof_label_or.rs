@@ -0,0 +1,13 @@
+use crate::VerifiableEncryptionProof;
+use serde::{Deserialize, Serialize};
+
+/// Proof that a ciphertext is a valid verifiable encryption under
+/// one of several labels without revealing which.
+///
+/// Holds one `VerifiableEncryptionProof` per label. All but the real one are
+/// simulated, and their challenges must sum to the Fiat-Shamir challenge
+/// mod 2^256 so only one can be chosen after the fact.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LabelOrProof {
+    pub(crate) branches: Vec<VerifiableEncryptionProof>,
+}
@@ -0,0 +1,18 @@
+use crate::proof_packed::BitProof;
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that an encrypted message is less than a public bound `B`.
+///
+/// With w the bit length of `B`, both m and m + 2^w - B are shown to be
+/// w bit numbers by committing to their bits like `PackedRangeProof`,
+/// so 0 <= m < B.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LessThanProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    /// The bits of m
+    pub(crate) lower: Vec<BitProof>,
+    /// The bits of m + 2^w - B
+    pub(crate) upper: Vec<BitProof>,
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Version of the Fiat-Shamir transcript for `VerifiableEncryptionProof`
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    /// Vector elements are absorbed one at a time without the vector length
+    #[default]
+    V1,
+    /// The number of elements is absorbed before each of the `y1` and `e` vectors
+    /// so no two statements serialize to the same transcript input.
+    /// Each element is length prefixed by the transcript
+    V2,
+}
+
+impl ProtocolVersion {
+    /// The newest version
+    pub const LATEST: Self = Self::V2;
+
+    /// The version as a number, 1 for `V1`
+    pub const fn number(self) -> u16 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// The version numbered `number` if this build knows it
+    pub fn from_number(number: u16) -> Option<Self> {
+        match number {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            _ => None,
+        }
+    }
+}
+
+/// Options for how a `VerifiableEncryptionProof` is made and checked.
+/// The prover and verifier must use the same options.
+/// Set with `EncryptionKeyRef::with_options`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofOptions {
+    /// Absorb a single digest of the `e` vectors into the Fiat-Shamir transcript
+    /// instead of every element. The digest frames each element and the count.
+    /// Worthwhile for wide ciphertexts
+    pub prehash_e: bool,
+    /// The transcript version
+    pub version: ProtocolVersion,
+}
+
+/// Incremental digest of a vector of values with per-element framing
+pub(crate) struct VectorDigest {
+    transcript: merlin::Transcript,
+    count: u64,
+}
+
+impl VectorDigest {
+    pub(crate) fn new(label: &'static [u8]) -> Self {
+        Self {
+            transcript: merlin::Transcript::new(label),
+            count: 0,
+        }
+    }
+
+    pub(crate) fn absorb(&mut self, value: &BigNumber) {
+        self.transcript
+            .append_message(b"element", &value.to_bytes());
+        self.count += 1;
+    }
+
+    pub(crate) fn finish(mut self) -> [u8; 32] {
+        self.transcript.append_u64(b"count", self.count);
+        let mut digest = [0u8; 32];
+        self.transcript
+            .challenge_bytes(b"vector digest", &mut digest);
+        digest
+    }
+}
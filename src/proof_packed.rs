@@ -0,0 +1,188 @@
+use crate::{sampling::random_below, Group};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that a ciphertext message is the packing of values
+/// that each fit in their sub-range, see `pack_messages`.
+///
+/// The message is committed to bit by bit as C_k = y1^s_k * h^b_k
+/// where the s_k sum to the encryption randomness so the product
+/// of C_k^{2^k} is the ciphertext's `e` value. Each commitment has
+/// a proof that b_k is 0 or 1.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PackedRangeProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    pub(crate) bits: Vec<BitProof>,
+}
+
+/// Proof that a commitment opens to 0 or 1
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct BitProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) commitment: BigNumber,
+    /// The challenge for the 0 branch, the 1 branch gets the rest
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) c0: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) z0: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) z1: BigNumber,
+}
+
+/// Prover state for committing to the bits of `x` where e = y1^r * h^x.
+/// Each bit gets a commitment and a proof it is 0 or 1 where
+/// the branch for the other value is simulated
+pub(crate) struct BitCommitments {
+    pub(crate) bits: Vec<BitProof>,
+    pub(crate) test_values: Vec<(BigNumber, BigNumber)>,
+    /// The bit, blinding, simulated challenge and commitment randomness
+    secrets: Vec<(bool, BigNumber, BigNumber, BigNumber)>,
+}
+
+impl BitCommitments {
+    /// Commit to `bits` of x, least significant first
+    pub(crate) fn new(
+        group: &Group,
+        y1: &BigNumber,
+        r: &BigNumber,
+        bits: &[bool],
+    ) -> Result<Self, String> {
+        // s_0 is chosen so the product of C_k^{2^k} is e
+        let mut s = (1..bits.len())
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<BigNumber>>();
+        let sum = s
+            .iter()
+            .enumerate()
+            .fold(BigNumber::zero(), |acc, (k, s)| acc + (s << (k + 1)));
+        s.insert(0, r - sum);
+
+        let mut proofs = Vec::with_capacity(bits.len());
+        let mut secrets = Vec::with_capacity(bits.len());
+        let mut test_values = Vec::with_capacity(bits.len());
+        for (bit, s_k) in bits.iter().zip(s) {
+            let mut commitment = group.pow(y1, &s_k);
+            if *bit {
+                commitment = group.mul(&commitment, &group.h);
+            }
+            let (x0, x1) = bit_bases(group, &commitment)?;
+            let t = group.random_value();
+            let real_a = group.pow(y1, &(&t << 1));
+            // Simulate the branch for the other bit value
+            let fake_c = random_below(&challenge_modulus());
+            let fake_z = group.random_value();
+            let fake_a = bit_test_value(group, y1, if *bit { &x0 } else { &x1 }, &fake_c, &fake_z);
+            test_values.push(if *bit {
+                (fake_a, real_a)
+            } else {
+                (real_a, fake_a)
+            });
+            proofs.push(BitProof {
+                commitment,
+                c0: fake_c.clone(),
+                z0: fake_z.clone(),
+                z1: fake_z,
+            });
+            secrets.push((*bit, t, fake_c, s_k));
+        }
+        Ok(Self {
+            bits: proofs,
+            test_values,
+            secrets,
+        })
+    }
+
+    /// Complete the real branch of each bit proof for `challenge`
+    pub(crate) fn respond(self, challenge: &BigNumber) -> Vec<BitProof> {
+        let modulus = challenge_modulus();
+        let mut bits = self.bits;
+        for (bit_proof, (bit, t, fake_c, s_k)) in bits.iter_mut().zip(self.secrets) {
+            let c = challenge.modsub(&fake_c, &modulus);
+            let z = t - &c * &s_k;
+            if bit {
+                bit_proof.z1 = z;
+            } else {
+                bit_proof.c0 = c;
+                bit_proof.z0 = z;
+            }
+        }
+        bits
+    }
+}
+
+/// Check the commitments in `bits` multiply to `e` and
+/// recompute the test values for `challenge`
+pub(crate) fn bit_test_values(
+    group: &Group,
+    y1: &BigNumber,
+    e: &BigNumber,
+    bits: &[BitProof],
+    challenge: &BigNumber,
+) -> Result<Vec<(BigNumber, BigNumber)>, String> {
+    let invalid = || "Invalid proof".to_string();
+    // e = prod C_k^{2^k}
+    let product = bits.iter().rev().fold(BigNumber::one(), |acc, b| {
+        group.mul(&group.mul(&acc, &acc), &b.commitment)
+    });
+    if &product != e {
+        return Err(invalid());
+    }
+
+    let modulus = challenge_modulus();
+    let mut test_values = Vec::with_capacity(bits.len());
+    for b in bits {
+        if b.c0 < BigNumber::zero() || b.c0 >= modulus {
+            return Err(invalid());
+        }
+        let c1 = challenge.modsub(&b.c0, &modulus);
+        let (x0, x1) = bit_bases(group, &b.commitment)?;
+        test_values.push((
+            bit_test_value(group, y1, &x0, &b.c0, &b.z0),
+            bit_test_value(group, y1, &x1, &c1, &b.z1),
+        ));
+    }
+    Ok(test_values)
+}
+
+/// Absorb the bit commitments and test values into `transcript`
+pub(crate) fn append_bits(
+    transcript: &mut merlin::Transcript,
+    bits: &[BitProof],
+    test_values: &[(BigNumber, BigNumber)],
+) {
+    transcript.append_u64(b"width", bits.len() as u64);
+    for (b, (a0, a1)) in bits.iter().zip(test_values.iter()) {
+        transcript.append_message(b"commitment", &b.commitment.to_bytes());
+        transcript.append_message(b"a0", &a0.to_bytes());
+        transcript.append_message(b"a1", &a1.to_bytes());
+    }
+}
+
+/// Challenges and the split between branches are reduced by 2^256
+pub(crate) fn challenge_modulus() -> BigNumber {
+    BigNumber::one() << 256
+}
+
+/// The bases C and C / h that a bit commitment C is a power
+/// of `y1` for when the bit is 0 or 1 respectively
+fn bit_bases(group: &Group, commitment: &BigNumber) -> Result<(BigNumber, BigNumber), String> {
+    let h_inv = group
+        .h
+        .invert(&group.nn)
+        .ok_or_else(|| "Invalid group".to_string())?;
+    Ok((commitment.clone(), group.mul(commitment, &h_inv)))
+}
+
+/// x^{2c} * y1^{2z}
+fn bit_test_value(
+    group: &Group,
+    y1: &BigNumber,
+    x: &BigNumber,
+    challenge: &BigNumber,
+    z: &BigNumber,
+) -> BigNumber {
+    let two_c: BigNumber = challenge << 1;
+    let two_z: BigNumber = z << 1;
+    group.mul(&group.pow(x, &two_c), &group.pow(y1, &two_z))
+}
@@ -0,0 +1,192 @@
+use crate::{sampling::random_below, Group, VerencError};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// The number of rounds in a `PaillierBlumProof`.
+/// A modulus that is not Paillier-Blum passes with probability at most 2^-80
+pub const PAILLIER_BLUM_ROUNDS: usize = 80;
+
+/// Proof that the group modulus `n` is a Paillier-Blum modulus,
+/// i.e. gcd(n, phi(n)) = 1 and n = pq with p = q = 3 mod 4,
+/// as in figure 16 of CGGMP21 <https://eprint.iacr.org/2021/060>.
+///
+/// Every safe prime group is a Paillier-Blum modulus so this lets
+/// tooling that already checks these proofs validate our keys.
+/// The prover needs the factors of `n`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PaillierBlumProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) w: BigNumber,
+    pub(crate) responses: Vec<PaillierBlumResponse>,
+}
+
+/// The response to one challenge `y` of a `PaillierBlumProof`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PaillierBlumResponse {
+    /// A fourth root of (-1)^a * w^b * y mod n
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub x: BigNumber,
+    /// If `y` is multiplied by -1
+    pub a: bool,
+    /// If `y` is multiplied by `w`
+    pub b: bool,
+    /// An n-th root of y mod n
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub z: BigNumber,
+}
+
+impl PaillierBlumProof {
+    /// The value `w` with Jacobi symbol -1
+    pub fn w(&self) -> &BigNumber {
+        &self.w
+    }
+
+    /// The response to each challenge
+    pub fn responses(&self) -> &[PaillierBlumResponse] {
+        &self.responses
+    }
+}
+
+impl Group {
+    /// Prove `n` is a Paillier-Blum modulus given its factors `p` and `q`
+    pub fn prove_paillier_blum(
+        &self,
+        nonce: &[u8],
+        p: &BigNumber,
+        q: &BigNumber,
+    ) -> Result<PaillierBlumProof, VerencError> {
+        if p * q != self.n {
+            return Err(VerencError::Other("p * q != n".to_string()));
+        }
+        let one = BigNumber::one();
+        let three = BigNumber::from(3);
+        let four = BigNumber::from(4);
+        if p % &four != three || q % &four != three {
+            return Err(VerencError::Other("p and q must be 3 mod 4".to_string()));
+        }
+        let n = &self.n;
+        let phi = (p - &one) * (q - &one);
+        let n_inv = n
+            .invert(&phi)
+            .ok_or_else(|| "gcd(n, (p - 1)(q - 1)) != 1".to_string())?;
+
+        // A non-residue mod q that is a residue mod p has Jacobi symbol -1
+        let mut w = random_below(n);
+        while !is_residue(&w, p) || is_residue(&w, q) {
+            w = random_below(n);
+        }
+        // Taking the square root of the square root keeps a residue
+        let root_exp = |prime: &BigNumber| {
+            let e: BigNumber = (prime + &one) >> 2;
+            e.modmul(&e, &(prime - &one))
+        };
+        let exp_p = root_exp(p);
+        let exp_q = root_exp(q);
+        let p_inv = p
+            .invert(q)
+            .ok_or_else(|| "p and q must be coprime".to_string())?;
+        let minus_one = n - &one;
+
+        let mut responses = Vec::with_capacity(PAILLIER_BLUM_ROUNDS);
+        for y in paillier_blum_challenges(nonce, n, &w) {
+            let mut found = None;
+            for (a, b) in [(false, false), (true, false), (false, true), (true, true)] {
+                let candidate = adjust(&y, a, b, &w, &minus_one, n);
+                if is_residue(&candidate, p) && is_residue(&candidate, q) {
+                    found = Some((a, b, candidate));
+                    break;
+                }
+            }
+            let (a, b, residue) =
+                found.ok_or_else(|| "n is not a Paillier-Blum modulus".to_string())?;
+            let x_p = (&residue % p).modpow(&exp_p, p);
+            let x_q = (&residue % q).modpow(&exp_q, q);
+            let x = &x_p + p * x_q.modsub(&x_p, q).modmul(&p_inv, q);
+            let z = y.modpow(&n_inv, n);
+            responses.push(PaillierBlumResponse { x, a, b, z });
+        }
+        Ok(PaillierBlumProof { w, responses })
+    }
+
+    /// Verify `proof` shows `n` is a Paillier-Blum modulus
+    pub fn verify_paillier_blum(
+        &self,
+        nonce: &[u8],
+        proof: &PaillierBlumProof,
+    ) -> Result<(), VerencError> {
+        let n = &self.n;
+        let zero = BigNumber::zero();
+        let one = BigNumber::one();
+        if n % &BigNumber::from(2) != one || n.is_prime() {
+            return Err(VerencError::Other("n must be an odd composite".to_string()));
+        }
+        if proof.w <= zero || &proof.w >= n {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        if proof.responses.len() != PAILLIER_BLUM_ROUNDS {
+            return Err(VerencError::Other(format!(
+                "Number of responses {} != {}",
+                proof.responses.len(),
+                PAILLIER_BLUM_ROUNDS
+            )));
+        }
+        let four = BigNumber::from(4);
+        let minus_one = n - &one;
+        for (y, response) in paillier_blum_challenges(nonce, n, &proof.w).zip(&proof.responses) {
+            if response.z.modpow(n, n) != y {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+            let expected = adjust(&y, response.a, response.b, &proof.w, &minus_one, n);
+            if response.x.modpow(&four, n) != expected {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// (-1)^a * w^b * y mod n
+fn adjust(
+    y: &BigNumber,
+    a: bool,
+    b: bool,
+    w: &BigNumber,
+    minus_one: &BigNumber,
+    n: &BigNumber,
+) -> BigNumber {
+    let mut value = y.clone();
+    if a {
+        value = value.modmul(minus_one, n);
+    }
+    if b {
+        value = value.modmul(w, n);
+    }
+    value
+}
+
+/// Euler's criterion for an odd prime
+fn is_residue(a: &BigNumber, prime: &BigNumber) -> bool {
+    let exp: BigNumber = (prime - BigNumber::one()) >> 1;
+    (a % prime).modpow(&exp, prime).is_one()
+}
+
+/// The challenges `y` below `n` derived from the statement
+fn paillier_blum_challenges(
+    nonce: &[u8],
+    n: &BigNumber,
+    w: &BigNumber,
+) -> impl Iterator<Item = BigNumber> {
+    let mut transcript = merlin::Transcript::new(b"paillier blum modulus proof");
+    transcript.append_message(b"nonce", nonce);
+    transcript.append_message(b"n", &n.to_bytes());
+    transcript.append_message(b"w", &w.to_bytes());
+    transcript.append_u64(b"rounds", PAILLIER_BLUM_ROUNDS as u64);
+    // Extra bytes make the reduction mod n statistically uniform
+    let len = n.to_bytes().len() + 16;
+    let n = n.clone();
+    (0..PAILLIER_BLUM_ROUNDS).map(move |_| {
+        let mut bytes = vec![0u8; len];
+        transcript.challenge_bytes(b"y", &mut bytes);
+        BigNumber::from_slice(bytes) % &n
+    })
+}
@@ -0,0 +1,115 @@
+use crate::{VerencError, VerifiableCipherText, VerificationKey};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that a ciphertext under one key encrypts the same messages
+/// as a ciphertext under another, created by `DecryptionKey::reencrypt`.
+///
+/// Proves knowledge of the old key's `x1` opening the old ciphertext and
+/// the randomness of the new ciphertext using the same message responses.
+/// The `x1` responses are not reduced so their size bounds the exponents
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReencryptionProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) x: Vec<BigNumber>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) r: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) m: Vec<BigNumber>,
+}
+
+/// One re-encryption in a `ReencryptionChain`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReencryptionLink {
+    /// The `VerificationKey::fingerprint` of the key encrypted to
+    pub key: [u8; 32],
+    /// The domain of the new ciphertext
+    pub domain: Vec<u8>,
+    /// The new ciphertext
+    pub ciphertext: VerifiableCipherText,
+    /// Proof the new ciphertext holds the same messages as the previous one
+    pub proof: ReencryptionProof,
+}
+
+/// An escrowed value followed across key rotations.
+///
+/// Starts with the original ciphertext and the key it was encrypted to
+/// and records every re-encryption with its proof so auditors holding
+/// the public keys can check the latest ciphertext holds the original messages
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReencryptionChain {
+    pub(crate) key: [u8; 32],
+    pub(crate) domain: Vec<u8>,
+    pub(crate) ciphertext: VerifiableCipherText,
+    pub(crate) links: Vec<ReencryptionLink>,
+}
+
+impl ReencryptionChain {
+    /// Start a chain with `ciphertext` encrypted to `key` under `domain`
+    pub fn new(key: &VerificationKey, domain: &[u8], ciphertext: VerifiableCipherText) -> Self {
+        Self {
+            key: key.fingerprint(),
+            domain: domain.to_vec(),
+            ciphertext,
+            links: Vec::new(),
+        }
+    }
+
+    /// Record a re-encryption of the latest ciphertext to `key`
+    pub fn push(
+        &mut self,
+        key: &VerificationKey,
+        domain: &[u8],
+        ciphertext: VerifiableCipherText,
+        proof: ReencryptionProof,
+    ) {
+        self.links.push(ReencryptionLink {
+            key: key.fingerprint(),
+            domain: domain.to_vec(),
+            ciphertext,
+            proof,
+        });
+    }
+
+    /// The recorded re-encryptions, oldest first
+    pub fn links(&self) -> &[ReencryptionLink] {
+        &self.links
+    }
+
+    /// The fingerprint of the key, domain and ciphertext that is current
+    pub fn latest(&self) -> (&[u8; 32], &[u8], &VerifiableCipherText) {
+        match self.links.last() {
+            Some(link) => (&link.key, &link.domain, &link.ciphertext),
+            None => (&self.key, &self.domain, &self.ciphertext),
+        }
+    }
+
+    /// Check every link with the keys named by fingerprint in `keys`
+    pub fn verify(&self, keys: &[VerificationKey]) -> Result<(), VerencError> {
+        let find = |fingerprint: &[u8; 32]| {
+            keys.iter()
+                .find(|k| k.fingerprint() == *fingerprint)
+                .ok_or_else(|| "Chain uses an unknown key".to_string())
+        };
+        let mut key = find(&self.key)?;
+        let mut domain = self.domain.as_slice();
+        let mut ciphertext = &self.ciphertext;
+        for link in &self.links {
+            let new_key = find(&link.key)?;
+            key.verify_reencryption(
+                domain,
+                ciphertext,
+                new_key,
+                &link.domain,
+                &link.ciphertext,
+                &link.proof,
+            )?;
+            key = new_key;
+            domain = &link.domain;
+            ciphertext = &link.ciphertext;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Proof that a ciphertext is a re-randomization of another,
+/// i.e. both encrypt the same messages, without revealing the factor
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RerandomizationProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) r: BigNumber,
+}
@@ -1,10 +1,86 @@
+#[cfg(feature = "compression")]
+use crate::compression::{compress, decompress};
+use crate::{
+    archive::{put_bignum, put_bignums, Cursor},
+    budget::charge,
+    checked_hex::{from_checked_hex, to_checked_hex},
+    VerencError,
+};
 use serde::{Deserialize, Serialize};
 use unknown_order::BigNumber;
 
+const CHECKED_HEX_TAG: &str = "veproof";
+#[cfg(feature = "compression")]
+const COMPRESSED_MAGIC: &[u8; 8] = b"VEPRZS01";
+
 /// Proof verifiable encryption for discrete log
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct VerifiableEncryptionProof {
+    #[serde(deserialize_with = "crate::budget::bignum")]
     pub(crate) challenge: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
     pub(crate) r: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
     pub(crate) m: Vec<BigNumber>,
 }
+
+impl VerifiableEncryptionProof {
+    /// A 32 byte identifier of this proof for deduplicating and referencing
+    /// proofs in stores and ledgers, the same whichever serde format
+    /// carried the proof. It hashes the signed length prefixed encoding of
+    /// the challenge and responses, which `verify` only accepts in
+    /// the ranges provers produce, so a proof has one identifier
+    pub fn id(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"verifiable encryption proof id");
+        transcript.append_message(b"proof", &self.encode());
+        let mut id = [0u8; 32];
+        transcript.challenge_bytes(b"proof id", &mut id);
+        id
+    }
+
+    /// Encode as hex with a type tag and checksum for pasting into text,
+    /// e.g. tickets, where truncation would otherwise go unnoticed
+    pub fn to_checked_hex(&self) -> String {
+        to_checked_hex(CHECKED_HEX_TAG, &self.encode())
+    }
+
+    /// Decode the output of `to_checked_hex`
+    pub fn from_checked_hex(s: &str) -> Result<Self, VerencError> {
+        Ok(Self::decode(&from_checked_hex(CHECKED_HEX_TAG, s)?)?)
+    }
+
+    /// Encode compressed with zstd for archiving.
+    /// `level` is the zstd level, 0 for the default
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    pub fn to_compressed_bytes(&self, level: i32) -> Result<Vec<u8>, VerencError> {
+        Ok(compress(COMPRESSED_MAGIC, &self.encode(), level)?)
+    }
+
+    /// Decode the output of `to_compressed_bytes`
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    pub fn from_compressed_bytes(bytes: &[u8]) -> Result<Self, VerencError> {
+        Ok(Self::decode(&decompress(COMPRESSED_MAGIC, bytes)?)?)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        put_bignum(&mut payload, &self.challenge);
+        put_bignum(&mut payload, &self.r);
+        put_bignums(&mut payload, &self.m);
+        payload
+    }
+
+    fn decode(payload: &[u8]) -> Result<Self, String> {
+        charge(payload.len())?;
+        let mut cursor = Cursor::new(payload);
+        let challenge = cursor.bignum()?;
+        let r = cursor.bignum()?;
+        let m = cursor.bignums()?;
+        if !cursor.is_finished() {
+            return Err("Proof has trailing bytes".to_string());
+        }
+        Ok(Self { challenge, r, m })
+    }
+}
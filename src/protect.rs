@@ -0,0 +1,166 @@
+use crate::{version::wire_mismatch, DecryptionKey, Group, VerencError, VerificationKey};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+use zeroize::Zeroize;
+
+const PROTECTED_KEY_MAGIC: &[u8; 8] = b"VEDKEY01";
+
+/// Encrypts key backups with an external key, e.g. AWS KMS, GCP KMS
+/// or a local AEAD, so they flow through existing envelope encryption.
+///
+/// This crate doesn't depend on any KMS or AEAD library so implement
+/// this with the one the organization already uses.
+/// `protect` must authenticate `associated_data` along with the key.
+pub trait KeyProtector {
+    /// Identifies the wrapping key, e.g. a KMS key ARN, stored with the backup
+    fn key_id(&self) -> String;
+
+    /// Encrypt and authenticate `plaintext` and `associated_data`
+    fn protect(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Decrypt the output of `protect`, failing if either input was modified
+    fn unprotect(&self, ciphertext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// A `DecryptionKey` encrypted by a `KeyProtector`, created by
+/// `DecryptionKey::export_protected`.
+///
+/// The fingerprint of the key's `VerificationKey` is stored in the clear
+/// and authenticated as associated data so backups can be matched
+/// to keys without decrypting them.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProtectedKey {
+    pub(crate) key_id: String,
+    pub(crate) fingerprint: [u8; 32],
+    pub(crate) ciphertext: Vec<u8>,
+}
+
+impl ProtectedKey {
+    /// The id of the wrapping key
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The `VerificationKey::fingerprint` of the protected key
+    pub fn fingerprint(&self) -> &[u8; 32] {
+        &self.fingerprint
+    }
+
+    fn associated_data(key_id: &str, fingerprint: &[u8; 32]) -> Vec<u8> {
+        let mut out = PROTECTED_KEY_MAGIC.to_vec();
+        write_bytes(&mut out, key_id.as_bytes());
+        out.extend_from_slice(fingerprint);
+        out
+    }
+}
+
+impl DecryptionKey {
+    /// Encrypt this key with `protector` for backup
+    pub fn export_protected<P: KeyProtector>(
+        &self,
+        protector: &P,
+    ) -> Result<ProtectedKey, VerencError> {
+        let key_id = protector.key_id();
+        let fingerprint = VerificationKey::from(self).fingerprint();
+        let mut plaintext = encode_key(self);
+        let ciphertext = protector.protect(
+            &plaintext,
+            &ProtectedKey::associated_data(&key_id, &fingerprint),
+        );
+        plaintext.zeroize();
+        Ok(ProtectedKey {
+            key_id,
+            fingerprint,
+            ciphertext: ciphertext?,
+        })
+    }
+
+    /// Decrypt a key exported with `export_protected`
+    pub fn import_protected<P: KeyProtector>(
+        protected: &ProtectedKey,
+        protector: &P,
+    ) -> Result<Self, VerencError> {
+        if protector.key_id() != protected.key_id {
+            return Err(VerencError::Other(format!(
+                "Key was protected with {} not {}",
+                protected.key_id,
+                protector.key_id()
+            )));
+        }
+        let mut plaintext = protector.unprotect(
+            &protected.ciphertext,
+            &ProtectedKey::associated_data(&protected.key_id, &protected.fingerprint),
+        )?;
+        let key = decode_key(&plaintext);
+        plaintext.zeroize();
+        let key = key?;
+        if VerificationKey::from(&key).fingerprint() != protected.fingerprint {
+            return Err(VerencError::Other(
+                "Protected key does not match its fingerprint".to_string(),
+            ));
+        }
+        Ok(key)
+    }
+}
+
+fn encode_key(key: &DecryptionKey) -> Vec<u8> {
+    let mut out = PROTECTED_KEY_MAGIC.to_vec();
+    write_bytes(&mut out, &key.group.n.to_bytes());
+    write_bytes(&mut out, &key.group.g.to_bytes());
+    out.extend_from_slice(&(key.x1.len() as u32).to_be_bytes());
+    for x in &key.x1 {
+        write_bytes(&mut out, &x.to_bytes());
+    }
+    write_bytes(&mut out, &key.x2.to_bytes());
+    write_bytes(&mut out, &key.x3.to_bytes());
+    out
+}
+
+fn decode_key(bytes: &[u8]) -> Result<DecryptionKey, String> {
+    let invalid = || "Invalid protected key encoding".to_string();
+    let mut reader = bytes;
+    let magic = take(&mut reader, PROTECTED_KEY_MAGIC.len())?;
+    if magic != PROTECTED_KEY_MAGIC {
+        return Err(wire_mismatch(magic, PROTECTED_KEY_MAGIC).unwrap_or_else(invalid));
+    }
+    let n = read_number(&mut reader)?;
+    let g = read_number(&mut reader)?;
+    let mut count = [0u8; 4];
+    count.copy_from_slice(take(&mut reader, 4)?);
+    let count = u32::from_be_bytes(count) as usize;
+    // Every value is at least its four byte length
+    if count > reader.len() / 4 {
+        return Err(invalid());
+    }
+    let x1 = (0..count)
+        .map(|_| read_number(&mut reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    let x2 = read_number(&mut reader)?;
+    let x3 = read_number(&mut reader)?;
+    if !reader.is_empty() || x1.is_empty() {
+        return Err(invalid());
+    }
+    let group = Group::from_parts(g, n).ok_or_else(invalid)?;
+    Ok(DecryptionKey { x1, x2, x3, group })
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn take<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if reader.len() < len {
+        return Err("Invalid protected key encoding".to_string());
+    }
+    let (head, tail) = reader.split_at(len);
+    *reader = tail;
+    Ok(head)
+}
+
+fn read_number(reader: &mut &[u8]) -> Result<BigNumber, String> {
+    let mut len = [0u8; 4];
+    len.copy_from_slice(take(reader, 4)?);
+    let bytes = take(reader, u32::from_be_bytes(len) as usize)?;
+    Ok(BigNumber::from_slice(bytes))
+}
@@ -0,0 +1,296 @@
+use crate::{
+    sampling::random_below, DecryptionKey, EncryptionKey, PublicKeyGroup, SecretKeyEscrowProof,
+    VerencError, VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A share escrowed to one guardian
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EscrowedShare {
+    pub(crate) ciphertext: VerifiableCipherText,
+    pub(crate) proof: SecretKeyEscrowProof,
+}
+
+/// The escrowed shares of a secret key and the commitments
+/// `a_j * G` to the coefficients of the sharing polynomial.
+///
+/// The user splits the secret with Shamir sharing so any `threshold` of
+/// the guardians can restore it, and escrows each share to a guardian with
+/// `EncryptionKey::escrow_secret_key`. The commitments let anyone check
+/// every share against the wallet's public key.
+///
+/// To recover, the new device sends its `EncryptionKey` to the guardians.
+/// Each guardian decrypts their share and escrows it again to the device
+/// as a `PartialDecryption` which the device checks before combining.
+/// Every message is serializable for sending over any channel.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecoveryPackage<T> {
+    pub(crate) commitments: Vec<T>,
+    pub(crate) shares: Vec<EscrowedShare>,
+}
+
+/// A guardian's share escrowed to the recovering device
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PartialDecryption {
+    pub(crate) index: usize,
+    pub(crate) ciphertext: VerifiableCipherText,
+    pub(crate) proof: SecretKeyEscrowProof,
+}
+
+impl PartialDecryption {
+    /// The index of the guardian that created this
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T: Clone + PartialEq> RecoveryPackage<T> {
+    /// Split `secret_key` so any `threshold` of `guardians` can recover it
+    /// and escrow a share to each guardian
+    pub fn create<P: PublicKeyGroup<PublicKey = T>>(
+        nonce: &[u8],
+        secret_key: &BigNumber,
+        public_key: &T,
+        threshold: usize,
+        guardians: &[EncryptionKey],
+    ) -> Result<Self, VerencError> {
+        check_threshold(threshold, guardians.len())?;
+        let order = P::order();
+        let zero = BigNumber::zero();
+        if secret_key < &zero || secret_key >= &order {
+            return Err(VerencError::Other(
+                "secret key is not less than the group order".to_string(),
+            ));
+        }
+        if P::mul_add(secret_key, &zero, public_key) != *public_key {
+            return Err(VerencError::Other(
+                "secret key does not match the public key".to_string(),
+            ));
+        }
+        let mut coefficients = vec![secret_key.clone()];
+        coefficients.extend((1..threshold).map(|_| random_below(&order)));
+        let commitments = coefficients
+            .iter()
+            .map(|a| P::mul_add(a, &zero, public_key))
+            .collect::<Vec<_>>();
+
+        let mut shares = Vec::with_capacity(guardians.len());
+        for (i, guardian) in guardians.iter().enumerate() {
+            let share = evaluate(&coefficients, &x_coordinate(i), &order);
+            let share_key = P::mul_add(&share, &zero, public_key);
+            let (ciphertext, proof) =
+                guardian.escrow_secret_key::<P>(&share_nonce(nonce, i), &share, &share_key)?;
+            shares.push(EscrowedShare { ciphertext, proof });
+        }
+        Ok(Self {
+            commitments,
+            shares,
+        })
+    }
+
+    /// The public key of the escrowed secret
+    pub fn public_key(&self) -> Option<&T> {
+        self.commitments.first()
+    }
+
+    /// The number of guardians needed to recover the secret
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// The number of guardians holding a share
+    pub fn num_guardians(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Check every share is escrowed to the guardian at the same index
+    /// and lies on the committed polynomial
+    pub fn verify<P: PublicKeyGroup<PublicKey = T>>(
+        &self,
+        nonce: &[u8],
+        guardians: &[VerificationKey],
+    ) -> Result<(), VerencError> {
+        check_threshold(self.threshold(), self.shares.len())?;
+        if guardians.len() != self.shares.len() {
+            return Err(VerencError::Other(format!(
+                "Number of guardians {} != number of shares {}",
+                guardians.len(),
+                self.shares.len()
+            )));
+        }
+        for (i, (guardian, share)) in guardians.iter().zip(self.shares.iter()).enumerate() {
+            guardian.verify_escrowed_secret_key::<P>(
+                &share_nonce(nonce, i),
+                &share.ciphertext,
+                &self.share_key::<P>(i),
+                &share.proof,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt the share held by the guardian at `index`
+    /// and escrow it to the recovering `device`
+    pub fn partial_decrypt<P: PublicKeyGroup<PublicKey = T>>(
+        &self,
+        nonce: &[u8],
+        index: usize,
+        guardian: &DecryptionKey,
+        device: &EncryptionKey,
+    ) -> Result<PartialDecryption, VerencError> {
+        check_threshold(self.threshold(), self.shares.len())?;
+        let share = self
+            .shares
+            .get(index)
+            .ok_or_else(|| format!("Index {} is more than the number of shares", index))?;
+        let msgs = guardian.decrypt(share_nonce(nonce, index), &share.ciphertext)?;
+        if msgs.len() != 1 {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} != 1 share",
+                msgs.len()
+            )));
+        }
+        let share_key = self.share_key::<P>(index);
+        let (ciphertext, proof) =
+            device.escrow_secret_key::<P>(&device_nonce(nonce, index), &msgs[0], &share_key)?;
+        Ok(PartialDecryption {
+            index,
+            ciphertext,
+            proof,
+        })
+    }
+
+    /// Check and decrypt `partials` with the recovering `device` key
+    /// and combine them into the secret key
+    pub fn combine<P: PublicKeyGroup<PublicKey = T>>(
+        &self,
+        nonce: &[u8],
+        device: &DecryptionKey,
+        partials: &[PartialDecryption],
+    ) -> Result<BigNumber, VerencError> {
+        let threshold = self.threshold();
+        check_threshold(threshold, self.shares.len())?;
+        if partials.len() < threshold {
+            return Err(VerencError::Other(format!(
+                "Number of partial decryptions {} is less than the threshold {}",
+                partials.len(),
+                threshold
+            )));
+        }
+        let vk = VerificationKey::from(device);
+        let mut indices = Vec::with_capacity(threshold);
+        let mut shares = Vec::with_capacity(threshold);
+        for partial in partials.iter().take(threshold) {
+            if partial.index >= self.shares.len() || indices.contains(&partial.index) {
+                return Err(VerencError::Other(format!(
+                    "Invalid partial decryption index {}",
+                    partial.index
+                )));
+            }
+            let nonce = device_nonce(nonce, partial.index);
+            vk.verify_escrowed_secret_key::<P>(
+                &nonce,
+                &partial.ciphertext,
+                &self.share_key::<P>(partial.index),
+                &partial.proof,
+            )?;
+            let msgs = device.decrypt(&nonce, &partial.ciphertext)?;
+            indices.push(partial.index);
+            shares.push(msgs[0].clone());
+        }
+
+        let secret_key = interpolate(&indices, &shares, &P::order())?;
+        let public_key = &self.commitments[0];
+        if P::mul_add(&secret_key, &BigNumber::zero(), public_key) == *public_key {
+            Ok(secret_key)
+        } else {
+            Err(VerencError::Other(
+                "Recovered secret key does not match the public key".to_string(),
+            ))
+        }
+    }
+
+    /// The public key of the share at `index` computed from the commitments
+    fn share_key<P: PublicKeyGroup<PublicKey = T>>(&self, index: usize) -> T {
+        share_key::<P>(&self.commitments, index)
+    }
+}
+
+/// The public key of the share at `index` from the commitments to the
+/// coefficients of the sharing polynomial, of which there is at least one
+pub(crate) fn share_key<P: PublicKeyGroup>(
+    commitments: &[P::PublicKey],
+    index: usize,
+) -> P::PublicKey {
+    let x = x_coordinate(index);
+    let zero = BigNumber::zero();
+    let mut commitments = commitments.iter().rev();
+    let mut key = commitments
+        .next()
+        .expect("threshold is checked to be positive")
+        .clone();
+    for a in commitments {
+        key = P::add(&P::mul_add(&zero, &x, &key), a);
+    }
+    key
+}
+
+fn check_threshold(threshold: usize, guardians: usize) -> Result<(), String> {
+    if threshold == 0 || threshold > guardians {
+        return Err(format!(
+            "Threshold {} must be between 1 and the number of guardians {}",
+            threshold, guardians
+        ));
+    }
+    Ok(())
+}
+
+/// Shares are evaluated at 1, 2, ... so no share reveals the secret
+pub(crate) fn x_coordinate(index: usize) -> BigNumber {
+    BigNumber::from(index + 1)
+}
+
+/// The secret from the shares at `indices` by Lagrange interpolation at 0
+pub(crate) fn interpolate(
+    indices: &[usize],
+    shares: &[BigNumber],
+    order: &BigNumber,
+) -> Result<BigNumber, String> {
+    let mut secret = BigNumber::zero();
+    for (i, share) in indices.iter().zip(shares.iter()) {
+        let xi = x_coordinate(*i);
+        let mut lambda = BigNumber::one();
+        for j in indices.iter().filter(|j| *j != i) {
+            let xj = x_coordinate(*j);
+            let denominator = xj
+                .modsub(&xi, order)
+                .invert(order)
+                .ok_or_else(|| "Invalid group order".to_string())?;
+            lambda = lambda.modmul(&xj.modmul(&denominator, order), order);
+        }
+        secret = secret.modadd(&share.modmul(&lambda, order), order);
+    }
+    Ok(secret)
+}
+
+pub(crate) fn evaluate(coefficients: &[BigNumber], x: &BigNumber, order: &BigNumber) -> BigNumber {
+    coefficients.iter().rev().fold(BigNumber::zero(), |acc, a| {
+        acc.modmul(x, order).modadd(a, order)
+    })
+}
+
+fn share_nonce(nonce: &[u8], index: usize) -> Vec<u8> {
+    indexed_nonce(nonce, b"guardian", index)
+}
+
+fn device_nonce(nonce: &[u8], index: usize) -> Vec<u8> {
+    indexed_nonce(nonce, b"device", index)
+}
+
+fn indexed_nonce(nonce: &[u8], role: &[u8], index: usize) -> Vec<u8> {
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(role);
+    out.extend_from_slice(&(index as u64).to_be_bytes());
+    out
+}
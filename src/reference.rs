@@ -0,0 +1,221 @@
+use crate::{
+    DecryptionKey, EncryptionKey, VerifiableCipherText, VerifiableEncryptionProof, VerificationKey,
+};
+use unknown_order::BigNumber;
+
+/// Encrypt `msgs` using randomness `r` exactly as written in section 3.2 of
+/// <https://shoup.net/papers/verenc.pdf>
+///
+/// u = g^r, e_i = y1_i^r * h^m_i, v = abs((y2 * y3^H(u, e, L))^r)
+pub fn encrypt(
+    ek: &EncryptionKey,
+    domain: &[u8],
+    msgs: &[BigNumber],
+    r: &BigNumber,
+) -> Option<VerifiableCipherText> {
+    let vk = &ek.vk;
+    if msgs.len() > vk.y1.len() || msgs.iter().any(|m| m > &vk.group.n) {
+        return None;
+    }
+    let nn = &vk.group.nn;
+    let u = vk.group.g.modpow(r, nn);
+    let e = msgs
+        .iter()
+        .zip(vk.y1.iter())
+        .map(|(m, y)| y.modpow(r, nn).modmul(&vk.group.h.modpow(m, nn), nn))
+        .collect::<Vec<_>>();
+    let hash = hash(&u, &e, domain);
+    let v = abs(
+        &vk.y2.modmul(&vk.y3.modpow(&hash, nn), nn).modpow(r, nn),
+        nn,
+    );
+    Some(VerifiableCipherText { u, e, v })
+}
+
+/// Encrypt `msgs` using randomness `r` and prove it using
+/// randomness `r_tilde` and message blindings `m_tilde`
+/// as in section 5.2 of <https://shoup.net/papers/verenc.pdf>
+pub fn encrypt_and_prove(
+    ek: &EncryptionKey,
+    nonce: &[u8],
+    msgs: &[BigNumber],
+    r: &BigNumber,
+    r_tilde: &BigNumber,
+    m_tilde: &[BigNumber],
+) -> Option<(VerifiableCipherText, VerifiableEncryptionProof)> {
+    if msgs.len() != m_tilde.len() {
+        return None;
+    }
+    let vk = &ek.vk;
+    let nn = &vk.group.nn;
+    let ciphertext = encrypt(ek, nonce, msgs, r)?;
+    let hash = hash(&ciphertext.u, &ciphertext.e, nonce);
+
+    let two_r_tilde: BigNumber = r_tilde << 1;
+    let t_u = vk.group.g.modpow(&two_r_tilde, nn);
+    let t_e = m_tilde
+        .iter()
+        .zip(vk.y1.iter())
+        .map(|(m, y)| {
+            let two_m: BigNumber = m << 1;
+            y.modpow(&two_r_tilde, nn)
+                .modmul(&vk.group.h.modpow(&two_m, nn), nn)
+        })
+        .collect::<Vec<_>>();
+    let t_v = vk
+        .y2
+        .modmul(&vk.y3.modpow(&hash, nn), nn)
+        .modpow(&two_r_tilde, nn);
+
+    let challenge = challenge(vk, nonce, &ciphertext, &t_u, &t_e, &t_v);
+    let r_hat = r_tilde - &challenge * r;
+    let m_hat = m_tilde
+        .iter()
+        .zip(msgs.iter())
+        .map(|(mt, m)| mt - &challenge * m)
+        .collect();
+    Some((
+        ciphertext,
+        VerifiableEncryptionProof {
+            challenge,
+            r: r_hat,
+            m: m_hat,
+        },
+    ))
+}
+
+/// Verify `proof` by recomputing every commitment from the responses
+/// as in section 6.2.19 of
+/// <https://dominoweb.draco.res.ibm.com/reports/rz3730_revised.pdf>
+pub fn verify(
+    vk: &VerificationKey,
+    nonce: &[u8],
+    ciphertext: &VerifiableCipherText,
+    proof: &VerifiableEncryptionProof,
+) -> bool {
+    if proof.m.len() != ciphertext.e.len() || proof.m.len() > vk.y1.len() {
+        return false;
+    }
+    let nn = &vk.group.nn;
+    let hash = hash(&ciphertext.u, &ciphertext.e, nonce);
+    let two_c: BigNumber = &proof.challenge << 1;
+    let two_r: BigNumber = &proof.r << 1;
+
+    // u^{2c} * g^{2r}
+    let t_u = ciphertext
+        .u
+        .modpow(&two_c, nn)
+        .modmul(&vk.group.g.modpow(&two_r, nn), nn);
+    // e_i^{2c} * y1_i^{2r} * h^{2m_i}
+    let t_e = ciphertext
+        .e
+        .iter()
+        .zip(vk.y1.iter())
+        .zip(proof.m.iter())
+        .map(|((e, y), m)| {
+            let two_m: BigNumber = m << 1;
+            e.modpow(&two_c, nn)
+                .modmul(&y.modpow(&two_r, nn), nn)
+                .modmul(&vk.group.h.modpow(&two_m, nn), nn)
+        })
+        .collect::<Vec<_>>();
+    // v^{2c} * (y2 * y3^H)^{2r}
+    let t_v = ciphertext.v.modpow(&two_c, nn).modmul(
+        &vk.y2
+            .modmul(&vk.y3.modpow(&hash, nn), nn)
+            .modpow(&two_r, nn),
+        nn,
+    );
+    challenge(vk, nonce, ciphertext, &t_u, &t_e, &t_v) == proof.challenge
+}
+
+/// Decrypt `ciphertext` as in section 3.2 of <https://shoup.net/papers/verenc.pdf>
+///
+/// Checks abs(v) = v and u^{2(x2 + x3 * H(u, e, L))} = v^2
+/// then m_i = ((e_i / u^x1_i)^{2t} - 1) / n where t = 2^-1 mod n
+pub fn decrypt(
+    dk: &DecryptionKey,
+    domain: &[u8],
+    ciphertext: &VerifiableCipherText,
+) -> Option<Vec<BigNumber>> {
+    if ciphertext.e.len() > dk.x1.len() {
+        return None;
+    }
+    let group = &dk.group;
+    let nn = &group.nn;
+    let n = &group.n;
+    if abs(&ciphertext.v, nn) != ciphertext.v {
+        return None;
+    }
+    let hash = hash(&ciphertext.u, &ciphertext.e, domain);
+    let exp: BigNumber = (&dk.x2 + &dk.x3 * &hash) << 1;
+    if ciphertext.u.modpow(&exp, nn) != ciphertext.v.modpow(&BigNumber::from(2), nn) {
+        return None;
+    }
+
+    let t = BigNumber::from(2).invert(n)?;
+    let two_t: BigNumber = t << 1;
+    let mut msgs = Vec::with_capacity(ciphertext.e.len());
+    for (e, x) in ciphertext.e.iter().zip(dk.x1.iter()) {
+        let u_x = ciphertext.u.modpow(x, nn).invert(nn)?;
+        let m_hat = e.modmul(&u_x, nn).modpow(&two_t, nn);
+        let (m, rem) = (m_hat - BigNumber::one()).div_rem(n);
+        if !rem.is_zero() {
+            return None;
+        }
+        msgs.push(m);
+    }
+    Some(msgs)
+}
+
+fn abs(a: &BigNumber, nn: &BigNumber) -> BigNumber {
+    let a = a % nn;
+    if a > (nn >> 1) {
+        nn - a
+    } else {
+        a
+    }
+}
+
+fn hash(u: &BigNumber, e: &[BigNumber], domain: &[u8]) -> BigNumber {
+    let mut transcript = merlin::Transcript::new(b"encryption hash generation");
+    transcript.append_message(b"u", &u.to_bytes());
+    let e_bytes = e.iter().flat_map(|e| e.to_bytes()).collect::<Vec<u8>>();
+    transcript.append_message(b"e", &e_bytes);
+    transcript.append_message(b"domain", domain);
+    let mut output = [0u8; 64];
+    transcript.challenge_bytes(b"encryption hash output", &mut output);
+    BigNumber::from_slice(output)
+}
+
+fn challenge(
+    vk: &VerificationKey,
+    nonce: &[u8],
+    ciphertext: &VerifiableCipherText,
+    t_u: &BigNumber,
+    t_e: &[BigNumber],
+    t_v: &BigNumber,
+) -> BigNumber {
+    let mut transcript = merlin::Transcript::new(b"camenisch-shoup verifiable encryption proof");
+    transcript.append_message(b"nonce", nonce);
+    transcript.append_message(b"n", &vk.group.n.to_bytes());
+    transcript.append_message(b"g", &vk.group.g.to_bytes());
+    transcript.append_message(b"y2", &vk.y2.to_bytes());
+    transcript.append_message(b"y3", &vk.y3.to_bytes());
+    for y in &vk.y1 {
+        transcript.append_message(b"y1", &y.to_bytes());
+    }
+    transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
+    for e in &ciphertext.e {
+        transcript.append_message(b"ciphertext.e", &e.to_bytes());
+    }
+    transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
+    transcript.append_message(b"ciphertext_test.u", &t_u.to_bytes());
+    for e in t_e {
+        transcript.append_message(b"ciphertext_test.e", &e.to_bytes());
+    }
+    transcript.append_message(b"ciphertext_test.v", &t_v.to_bytes());
+    let mut output = [0u8; 32];
+    transcript.challenge_bytes(b"verifiable encryption proof challenge", &mut output);
+    BigNumber::from_slice(output)
+}
@@ -0,0 +1,213 @@
+use crate::{
+    checked_hex::{decode_hex, encode_hex},
+    EncryptionKey, Group, SearchTag, VerencError, VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use unknown_order::BigNumber;
+
+/// Groups known to a deployment indexed by `Group::fingerprint`.
+///
+/// Services that load the same registry can exchange `CompactKey`s and
+/// `CipherTextEnvelope`s which name their parameters by fingerprint
+/// instead of carrying the full modulus.
+///
+/// The text format has one group per line as hex `fingerprint n g`
+/// separated by whitespace. Blank lines and lines starting with `#` are skipped.
+/// Each fingerprint is checked when loading so a registry file
+/// embedded with `include_str!` or read from disk with `load_file`
+/// cannot silently change the parameters it names.
+#[derive(Clone, Debug, Default)]
+pub struct ParameterRegistry {
+    groups: BTreeMap<[u8; 32], Group>,
+}
+
+/// The public values of a key with the group named by fingerprint
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CompactKey {
+    parameters: [u8; 32],
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    y1: Vec<BigNumber>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    y2: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    y3: BigNumber,
+}
+
+/// A ciphertext with the fingerprint of the group it was encrypted in
+/// and any `SearchTag`s locating it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CipherTextEnvelope {
+    parameters: [u8; 32],
+    pub(crate) ciphertext: VerifiableCipherText,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<SearchTag>,
+}
+
+impl ParameterRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a registry in the text format
+    pub fn from_text(text: &str) -> Result<Self, VerencError> {
+        let mut registry = Self::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                VerencError::Other(format!(
+                    "Invalid parameter registry entry on line {}",
+                    line_number + 1
+                ))
+            };
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != 3 {
+                return Err(invalid());
+            }
+            let fingerprint = decode_hex(fields[0]).ok_or_else(invalid)?;
+            let n = decode_hex(fields[1]).ok_or_else(invalid)?;
+            let g = decode_hex(fields[2]).ok_or_else(invalid)?;
+            let group = Group::from_parts(BigNumber::from_slice(g), BigNumber::from_slice(n))
+                .ok_or_else(invalid)?;
+            if group.fingerprint()[..] != fingerprint[..] {
+                return Err(VerencError::Other(format!(
+                    "Fingerprint does not match the parameters on line {}",
+                    line_number + 1
+                )));
+            }
+            registry.insert(group);
+        }
+        Ok(registry)
+    }
+
+    /// Read a registry in the text format from `path`
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, VerencError> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_text(&text)
+    }
+
+    /// Write the registry in the text format
+    pub fn to_text(&self) -> String {
+        self.groups
+            .iter()
+            .map(|(fingerprint, group)| {
+                format!(
+                    "{} {} {}\n",
+                    encode_hex(fingerprint),
+                    encode_hex(&group.n.to_bytes()),
+                    encode_hex(&group.g.to_bytes())
+                )
+            })
+            .collect()
+    }
+
+    /// Add `group` returning its fingerprint
+    pub fn insert(&mut self, group: Group) -> [u8; 32] {
+        let fingerprint = group.fingerprint();
+        self.groups.insert(fingerprint, group);
+        fingerprint
+    }
+
+    /// The group with `fingerprint` if it is registered
+    pub fn get(&self, fingerprint: &[u8; 32]) -> Option<&Group> {
+        self.groups.get(fingerprint)
+    }
+
+    /// The fingerprints of every registered group
+    pub fn fingerprints(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.groups.keys()
+    }
+
+    /// The number of registered groups
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// True if no group is registered
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Restore a verification key from its compact form
+    pub fn verification_key(&self, key: &CompactKey) -> Result<VerificationKey, VerencError> {
+        let group = self.group(&key.parameters)?;
+        Ok(VerificationKey {
+            y1: key.y1.clone(),
+            y2: key.y2.clone(),
+            y3: key.y3.clone(),
+            group: group.clone(),
+            lifecycle: None,
+            schema: None,
+        })
+    }
+
+    /// Restore an encryption key from its compact form
+    pub fn encryption_key(&self, key: &CompactKey) -> Result<EncryptionKey, VerencError> {
+        Ok(EncryptionKey {
+            vk: self.verification_key(key)?,
+        })
+    }
+
+    /// The group `envelope` was encrypted in
+    pub fn group_for(&self, envelope: &CipherTextEnvelope) -> Result<&Group, VerencError> {
+        self.group(&envelope.parameters)
+    }
+
+    fn group(&self, fingerprint: &[u8; 32]) -> Result<&Group, VerencError> {
+        self.get(fingerprint).ok_or_else(|| {
+            VerencError::Other(format!("Unknown parameters {}", encode_hex(fingerprint)))
+        })
+    }
+}
+
+impl CompactKey {
+    /// The fingerprint of the key's group
+    pub fn parameters(&self) -> &[u8; 32] {
+        &self.parameters
+    }
+}
+
+impl CipherTextEnvelope {
+    /// Wrap `ciphertext` encrypted in `group`
+    pub fn new(group: &Group, ciphertext: VerifiableCipherText) -> Self {
+        Self {
+            parameters: group.fingerprint(),
+            ciphertext,
+            tags: Vec::new(),
+        }
+    }
+
+    /// The fingerprint of the ciphertext's group
+    pub fn parameters(&self) -> &[u8; 32] {
+        &self.parameters
+    }
+
+    /// The wrapped ciphertext
+    pub fn ciphertext(&self) -> &VerifiableCipherText {
+        &self.ciphertext
+    }
+}
+
+impl VerificationKey {
+    /// The public values of this key naming the group by fingerprint
+    pub fn to_compact(&self) -> CompactKey {
+        CompactKey {
+            parameters: self.group.fingerprint(),
+            y1: self.y1.clone(),
+            y2: self.y2.clone(),
+            y3: self.y3.clone(),
+        }
+    }
+}
+
+impl EncryptionKey {
+    /// The public values of this key naming the group by fingerprint
+    pub fn to_compact(&self) -> CompactKey {
+        self.vk.to_compact()
+    }
+}
@@ -0,0 +1,345 @@
+//! A relation is a list of equations over the group mod n^2, each
+//! `image = base_1^w_i1 * base_2^w_i2 * ...` where the exponents are entries
+//! of one witness vector, so the same witness can appear in many equations.
+//!
+//! The proof is the Fiat-Shamir transform of the sigma protocol the crate
+//! uses for verifiable encryption:
+//!
+//! 1. pick a blinding `t_j` for each witness `w_j` and commit to every
+//!    equation as `T = prod base^t_j`
+//! 2. hash the relation, the context and the commitments to the challenge `c`
+//! 3. respond with `s_j = t_j - c * w_j` over the integers
+//!
+//! The verifier recomputes each commitment as `T = image^c * prod base^s_j`
+//! and accepts if the hash gives back `c`.
+//!
+//! `VerificationKey::encryption_relation` returns the relation behind
+//! `VerifiableEncryptionProof` with witnesses `[r, m_0, m_1, ...]`.
+//! `Relation::extend` puts the relations of ciphertexts under different keys
+//! side by side with shared witnesses, e.g. to prove two ciphertexts
+//! encrypt the same message.
+//!
+//! Nothing here checks that a relation is sound to prove.
+//! Use bases of unknown order, such as squares mod n^2, and keep
+//! witnesses below `2^witness_bits` or the proof may leak them
+use crate::{
+    group::{bit_length, ct_eq, unreduced_responses},
+    sampling::random_below,
+    Context, Group, VerencError, VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Bits of the challenge
+const CHALLENGE_BITS: usize = 256;
+/// Statistical hiding of the witnesses in the responses
+const SLACK_BITS: usize = 128;
+
+/// One factor `base^w` of an equation where `w` is the witness at `witness`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RelationTerm {
+    /// The base
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub base: BigNumber,
+    /// Index into the witness vector
+    pub witness: usize,
+}
+
+/// `image = prod base^w` over the terms
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RelationEquation {
+    /// The public value
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub image: BigNumber,
+    /// The factors
+    pub terms: Vec<RelationTerm>,
+}
+
+/// A list of equations over one group sharing a witness vector
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Relation {
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    modulus: BigNumber,
+    num_witnesses: usize,
+    witness_bits: usize,
+    equations: Vec<RelationEquation>,
+}
+
+/// Proof of knowledge of the witnesses of a `Relation`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RelationProof {
+    /// The challenge `c`
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub challenge: BigNumber,
+    /// The responses `s_j = t_j - c * w_j`
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub responses: Vec<BigNumber>,
+}
+
+impl Relation {
+    /// An empty relation over `group` with `num_witnesses` witnesses
+    /// each less than `2^witness_bits` in absolute value
+    pub fn new(group: &Group, num_witnesses: usize, witness_bits: usize) -> Self {
+        Self {
+            modulus: group.nn.clone(),
+            num_witnesses,
+            witness_bits,
+            equations: Vec::new(),
+        }
+    }
+
+    /// Add the equation `image = prod base^w` over `terms` of `(base, witness index)`
+    pub fn add_equation(
+        &mut self,
+        image: BigNumber,
+        terms: Vec<(BigNumber, usize)>,
+    ) -> Result<(), VerencError> {
+        if terms.is_empty() {
+            return Err(VerencError::Other("Equation has no terms".to_string()));
+        }
+        if let Some((_, w)) = terms.iter().find(|(_, w)| *w >= self.num_witnesses) {
+            return Err(VerencError::Other(format!(
+                "Witness {} is out of range of {} witnesses",
+                w, self.num_witnesses
+            )));
+        }
+        self.equations.push(RelationEquation {
+            image,
+            terms: terms
+                .into_iter()
+                .map(|(base, witness)| RelationTerm { base, witness })
+                .collect(),
+        });
+        Ok(())
+    }
+
+    /// Add every equation of `other` with its witness `j` renamed to `witness_map[j]`.
+    /// Map two witnesses to the same index to prove they are equal
+    pub fn extend(&mut self, other: &Relation, witness_map: &[usize]) -> Result<(), VerencError> {
+        if self.modulus != other.modulus {
+            return Err(VerencError::Other(
+                "Relations are over different groups".to_string(),
+            ));
+        }
+        if witness_map.len() != other.num_witnesses {
+            return Err(VerencError::Other(format!(
+                "Witness map has {} entries but the relation has {} witnesses",
+                witness_map.len(),
+                other.num_witnesses
+            )));
+        }
+        if other.witness_bits > self.witness_bits {
+            return Err(VerencError::Other(
+                "Witnesses are larger than this relation allows".to_string(),
+            ));
+        }
+        let mut extended = self.clone();
+        for eq in &other.equations {
+            extended.add_equation(
+                eq.image.clone(),
+                eq.terms
+                    .iter()
+                    .map(|t| (t.base.clone(), witness_map[t.witness]))
+                    .collect(),
+            )?;
+        }
+        *self = extended;
+        Ok(())
+    }
+
+    /// The equations
+    pub fn equations(&self) -> &[RelationEquation] {
+        &self.equations
+    }
+
+    /// The length of the witness vector
+    pub fn num_witnesses(&self) -> usize {
+        self.num_witnesses
+    }
+
+    /// The bound on the witnesses in bits
+    pub fn witness_bits(&self) -> usize {
+        self.witness_bits
+    }
+
+    /// True if `witnesses` satisfy every equation
+    pub fn is_satisfied_by(&self, witnesses: &[BigNumber]) -> bool {
+        witnesses.len() == self.num_witnesses
+            && self.equations.iter().all(|eq| {
+                let lhs = eq.terms.iter().fold(BigNumber::one(), |acc, t| {
+                    acc.modmul(
+                        &t.base.modpow(&witnesses[t.witness], &self.modulus),
+                        &self.modulus,
+                    )
+                });
+                lhs == eq.image.modadd(&BigNumber::zero(), &self.modulus)
+            })
+    }
+
+    /// Prove knowledge of `witnesses` bound to `context`
+    pub fn prove<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        witnesses: &[BigNumber],
+    ) -> Result<RelationProof, VerencError> {
+        if witnesses.len() != self.num_witnesses {
+            return Err(VerencError::Other(format!(
+                "Expected {} witnesses but got {}",
+                self.num_witnesses,
+                witnesses.len()
+            )));
+        }
+        if witnesses
+            .iter()
+            .any(|w| magnitude_bits(w) > self.witness_bits)
+        {
+            return Err(VerencError::Other(format!(
+                "Witness is longer than {} bits",
+                self.witness_bits
+            )));
+        }
+        if !self.is_satisfied_by(witnesses) {
+            return Err(VerencError::Other(
+                "Witnesses do not satisfy the relation".to_string(),
+            ));
+        }
+        let blinding_bound = BigNumber::one() << (self.witness_bits + CHALLENGE_BITS + SLACK_BITS);
+        let blindings = (0..self.num_witnesses)
+            .map(|_| random_below(&blinding_bound))
+            .collect::<Vec<_>>();
+        let commitments = self
+            .equations
+            .iter()
+            .map(|eq| {
+                eq.terms.iter().fold(BigNumber::one(), |acc, t| {
+                    acc.modmul(
+                        &t.base.modpow(&blindings[t.witness], &self.modulus),
+                        &self.modulus,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        let challenge = self.challenge(context.into().as_bytes(), &commitments);
+        let responses = unreduced_responses(&challenge, &blindings, witnesses);
+        Ok(RelationProof {
+            challenge,
+            responses,
+        })
+    }
+
+    /// Verify `proof` for this relation and `context`
+    pub fn verify<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        proof: &RelationProof,
+    ) -> Result<(), VerencError> {
+        if proof.responses.len() != self.num_witnesses {
+            return Err(VerencError::Other(format!(
+                "Expected {} responses but got {}",
+                self.num_witnesses,
+                proof.responses.len()
+            )));
+        }
+        if proof.challenge < BigNumber::zero() || bit_length(&proof.challenge) > CHALLENGE_BITS {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        // s = t - c * w lies in (-2^(W + 256), 2^(W + 384))
+        let response_bits = self.witness_bits + CHALLENGE_BITS + SLACK_BITS + 1;
+        if proof
+            .responses
+            .iter()
+            .any(|s| magnitude_bits(s) > response_bits)
+        {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let mut commitments = Vec::with_capacity(self.equations.len());
+        for eq in &self.equations {
+            if eq.image.invert(&self.modulus).is_none() {
+                return Err(VerencError::Other("Invalid relation".to_string()));
+            }
+            let mut t = eq.image.modpow(&proof.challenge, &self.modulus);
+            for term in &eq.terms {
+                if term.base.invert(&self.modulus).is_none() {
+                    return Err(VerencError::Other("Invalid relation".to_string()));
+                }
+                t = t.modmul(
+                    &term
+                        .base
+                        .modpow(&proof.responses[term.witness], &self.modulus),
+                    &self.modulus,
+                );
+            }
+            commitments.push(t);
+        }
+        if ct_eq(
+            &self.challenge(context.into().as_bytes(), &commitments),
+            &proof.challenge,
+        ) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    fn challenge(&self, context: &[u8], commitments: &[BigNumber]) -> BigNumber {
+        let mut transcript = merlin::Transcript::new(b"verifiable encryption relation");
+        transcript.append_message(b"context", context);
+        transcript.append_message(b"modulus", &self.modulus.to_bytes());
+        transcript.append_u64(b"witnesses", self.num_witnesses as u64);
+        transcript.append_u64(b"witness bits", self.witness_bits as u64);
+        for (eq, t) in self.equations.iter().zip(commitments.iter()) {
+            transcript.append_message(b"image", &eq.image.to_bytes());
+            for term in &eq.terms {
+                transcript.append_message(b"base", &term.base.to_bytes());
+                transcript.append_u64(b"witness", term.witness as u64);
+            }
+            transcript.append_message(b"commitment", &t.to_bytes());
+        }
+        VerificationKey::challenge(&mut transcript)
+    }
+}
+
+impl VerificationKey {
+    /// The relation `VerifiableEncryptionProof` proves for `ciphertext`,
+    /// with witnesses `[r, m_0, m_1, ...]`:
+    ///
+    /// - `u^2 = (g^2)^r`
+    /// - `e_i^2 = (y1_i^2)^r * (h^2)^m_i`
+    /// - `v^2 = ((y2 * y3^H)^2)^r` where `H` is the ciphertext hash
+    ///
+    /// Every value is squared to stay in the subgroup where an absolute `v` is well defined
+    pub fn encryption_relation<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<Relation, VerencError> {
+        if ciphertext.e.len() > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                ciphertext.e.len(),
+                self.y1.len()
+            )));
+        }
+        let group = &self.group;
+        let context = context.into();
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), context.as_bytes());
+        let square = |a: &BigNumber| a.modmul(a, &group.nn);
+        let mut relation = Relation::new(group, ciphertext.e.len() + 1, bit_length(&group.n));
+        let h = square(&group.h);
+        relation.add_equation(square(&ciphertext.u), vec![(square(&group.g), 0)])?;
+        for (i, (e, y1)) in ciphertext.e.iter().zip(self.y1.iter()).enumerate() {
+            relation.add_equation(square(e), vec![(square(y1), 0), (h.clone(), i + 1)])?;
+        }
+        let y = group.mul(&self.y2, &group.pow(&self.y3, &hash));
+        relation.add_equation(square(&ciphertext.v), vec![(square(&y), 0)])?;
+        Ok(relation)
+    }
+}
+
+fn magnitude_bits(a: &BigNumber) -> usize {
+    if a < &BigNumber::zero() {
+        bit_length(&-a.clone())
+    } else {
+        bit_length(a)
+    }
+}
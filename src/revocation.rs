@@ -0,0 +1,140 @@
+use crate::{
+    sampling::random_below, CommittedEncryptionProof, DecryptionKey, EncryptionKey, PublicKeyGroup,
+    VerencError, VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A credential's revocation handle escrowed to an auditor and linked
+/// to the commitment `handle * G + s * H` the holder shows in a presentation.
+///
+/// The verifier of the presentation checks the escrow against the
+/// presentation's commitment with `verify` and stores it. If the presentation
+/// is disputed the auditor recovers the handle with `open` and looks it up in
+/// the issuer's revocation records. Nothing here depends on how the credential
+/// or its revocation registry works, only on the commitment.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RevocationEscrow<T> {
+    pub(crate) ciphertext: VerifiableCipherText,
+    pub(crate) proof: CommittedEncryptionProof<T>,
+    pub(crate) link: T,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) z: BigNumber,
+}
+
+impl<T: Clone + PartialEq> RevocationEscrow<T> {
+    /// Escrow `handle` to `auditor` and prove it's the handle in
+    /// `presentation_commitment`, opened by `handle` and `blinding` with `h`
+    pub fn create<P: PublicKeyGroup<PublicKey = T>>(
+        nonce: &[u8],
+        auditor: &EncryptionKey,
+        handle: &BigNumber,
+        h: &T,
+        presentation_commitment: &T,
+        blinding: &BigNumber,
+    ) -> Result<Self, VerencError> {
+        let order = P::order();
+        if P::mul_add(handle, &(blinding % &order), h) != *presentation_commitment {
+            return Err(VerencError::Other(
+                "handle and blinding do not open the commitment".to_string(),
+            ));
+        }
+        let (ciphertext, proof, blindings) =
+            auditor.encrypt_and_prove_committed::<P>(nonce, std::slice::from_ref(handle), h)?;
+        // Both commitments hold the handle so they differ by delta * H
+        let delta = blinding.modsub(&blindings[0], &order);
+        let k = random_below(&order);
+        let link = P::mul_add(&BigNumber::zero(), &k, h);
+        let c = link_challenge::<P>(
+            nonce,
+            &ciphertext,
+            h,
+            presentation_commitment,
+            &proof.commitments[0],
+            &link,
+        );
+        let z = k.modadd(&c.modmul(&delta, &order), &order);
+        Ok(Self {
+            ciphertext,
+            proof,
+            link,
+            z,
+        })
+    }
+
+    /// Check the ciphertext encrypts the handle in `presentation_commitment` for `auditor`
+    pub fn verify<P: PublicKeyGroup<PublicKey = T>>(
+        &self,
+        nonce: &[u8],
+        auditor: &VerificationKey,
+        h: &T,
+        presentation_commitment: &T,
+    ) -> Result<(), VerencError> {
+        auditor.verify_committed::<P>(nonce, &self.ciphertext, h, &self.proof)?;
+        if self.proof.commitments.len() != 1 {
+            return Err(VerencError::Other(
+                "Revocation escrow must hold one handle".to_string(),
+            ));
+        }
+        let order = P::order();
+        let zero = BigNumber::zero();
+        let escrowed = &self.proof.commitments[0];
+        let c = link_challenge::<P>(
+            nonce,
+            &self.ciphertext,
+            h,
+            presentation_commitment,
+            escrowed,
+            &self.link,
+        );
+        // z * H + c * C_escrow = link + c * C_presentation
+        let lhs = P::add(
+            &P::mul_add(&zero, &(&self.z % &order), h),
+            &P::mul_add(&zero, &c, escrowed),
+        );
+        let rhs = P::add(&self.link, &P::mul_add(&zero, &c, presentation_commitment));
+        if lhs == rhs {
+            Ok(())
+        } else {
+            Err(VerencError::Other(
+                "Escrowed handle does not match the presentation".to_string(),
+            ))
+        }
+    }
+
+    /// Decrypt the revocation handle with the auditor's key
+    pub fn open(&self, nonce: &[u8], auditor: &DecryptionKey) -> Result<BigNumber, VerencError> {
+        let mut msgs = auditor.decrypt(nonce, &self.ciphertext)?;
+        if msgs.len() != 1 {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} != 1 handle",
+                msgs.len()
+            )));
+        }
+        Ok(msgs.remove(0))
+    }
+
+    /// The ciphertext of the handle
+    pub fn ciphertext(&self) -> &VerifiableCipherText {
+        &self.ciphertext
+    }
+}
+
+fn link_challenge<P: PublicKeyGroup>(
+    nonce: &[u8],
+    ciphertext: &VerifiableCipherText,
+    h: &P::PublicKey,
+    presentation_commitment: &P::PublicKey,
+    escrowed: &P::PublicKey,
+    link: &P::PublicKey,
+) -> BigNumber {
+    let mut transcript = merlin::Transcript::new(b"camenisch-shoup revocation handle link");
+    transcript.append_message(b"nonce", nonce);
+    transcript.append_message(b"ciphertext", &ciphertext.digest());
+    transcript.append_message(b"order", &P::order().to_bytes());
+    transcript.append_message(b"h", &P::to_bytes(h));
+    transcript.append_message(b"presentation", &P::to_bytes(presentation_commitment));
+    transcript.append_message(b"escrowed", &P::to_bytes(escrowed));
+    transcript.append_message(b"link", &P::to_bytes(link));
+    VerificationKey::challenge(&mut transcript) % P::order()
+}
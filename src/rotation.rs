@@ -0,0 +1,230 @@
+use crate::{
+    ArchiveRecord, DecryptionKey, EncryptionKey, ReencryptionProof, VerencError,
+    VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+
+/// What the archive knows about a record that the record itself doesn't hold
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordMetadata {
+    /// The `VerificationKey::fingerprint` of the key the record is encrypted to
+    pub key_fingerprint: [u8; 32],
+    /// The domain the record was encrypted under, kept when it's rotated
+    pub domain: Vec<u8>,
+    /// When the record was encrypted in seconds
+    pub created_at: u64,
+}
+
+/// Why a record is rotated
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RotationReason {
+    /// Its key is deprecated
+    DeprecatedKey,
+    /// It's older than the maximum age
+    Expired,
+}
+
+/// A record encrypted again to the target key with a proof it holds the
+/// same messages, see `VerificationKey::verify_reencryption`
+#[derive(Clone, Debug)]
+pub struct RotatedRecord {
+    /// The record's position in the archive
+    pub position: u64,
+    /// Why it was rotated
+    pub reason: RotationReason,
+    /// The record's domain, the same before and after
+    pub domain: Vec<u8>,
+    /// The new ciphertext
+    pub ciphertext: VerifiableCipherText,
+    /// The proof the old and new ciphertexts hold the same messages
+    pub proof: ReencryptionProof,
+}
+
+/// Where to resume a rotation. Store it with the batch's results
+/// so a stopped rotation continues after the last stored batch
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RotationCursor {
+    next: u64,
+}
+
+impl RotationCursor {
+    /// Start at the beginning of the archive
+    pub fn start() -> Self {
+        Self::default()
+    }
+
+    /// The position of the next record to look at
+    pub fn position(&self) -> u64 {
+        self.next
+    }
+}
+
+/// The result of one batch
+#[derive(Clone, Debug)]
+pub struct RotationBatch {
+    /// The records rotated in this batch in archive order
+    pub rotated: Vec<RotatedRecord>,
+    /// Where the next batch starts
+    pub cursor: RotationCursor,
+    /// True if the end of the archive was reached
+    pub finished: bool,
+}
+
+/// Re-encrypts the records of an archive that are under a deprecated key
+/// or older than a maximum age to a new key, a batch at a time.
+///
+/// Records come from any iterator of `ArchiveRecord` results such as a
+/// `CiphertextArchiveReader`, with a visitor giving each record's
+/// `RecordMetadata` since archives don't store the key, domain or time.
+/// Rotated records are returned rather than written so the caller
+/// decides where they go, e.g. a new archive, and persists the cursor
+/// once they are stored
+#[derive(Clone, Debug)]
+pub struct Rotation {
+    target: EncryptionKey,
+    target_fingerprint: [u8; 32],
+    keys: Vec<([u8; 32], DecryptionKey)>,
+    deprecated: Vec<[u8; 32]>,
+    max_age: Option<u64>,
+    batch_size: usize,
+}
+
+impl Rotation {
+    /// Rotate to `target` at most `batch_size` records per batch
+    pub fn new(target: EncryptionKey, batch_size: usize) -> Result<Self, VerencError> {
+        if batch_size == 0 {
+            return Err(VerencError::Other(
+                "Batch size must be at least 1".to_string(),
+            ));
+        }
+        Ok(Self {
+            target_fingerprint: target.verification_key().fingerprint(),
+            target,
+            keys: Vec::new(),
+            deprecated: Vec::new(),
+            max_age: None,
+            batch_size,
+        })
+    }
+
+    /// Decrypt records under the key of `key` with it. Every key a
+    /// record may need rotating from must be added, including the target's
+    pub fn with_key(mut self, key: DecryptionKey) -> Self {
+        let fingerprint = VerificationKey::from(&key).fingerprint();
+        self.keys.retain(|(f, _)| *f != fingerprint);
+        self.keys.push((fingerprint, key));
+        self
+    }
+
+    /// Rotate every record under the key with `fingerprint`
+    pub fn deprecate(mut self, fingerprint: [u8; 32]) -> Result<Self, VerencError> {
+        if fingerprint == self.target_fingerprint {
+            return Err(VerencError::Other(
+                "The target key can't be deprecated".to_string(),
+            ));
+        }
+        if !self.deprecated.contains(&fingerprint) {
+            self.deprecated.push(fingerprint);
+        }
+        Ok(self)
+    }
+
+    /// Rotate records more than `seconds` old, refreshing them under the target key
+    pub fn with_max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Why the record described by `metadata` needs rotating at time `now`, if it does
+    pub fn reason(&self, metadata: &RecordMetadata, now: u64) -> Option<RotationReason> {
+        if self.deprecated.contains(&metadata.key_fingerprint) {
+            return Some(RotationReason::DeprecatedKey);
+        }
+        match self.max_age {
+            Some(max_age) if now.saturating_sub(metadata.created_at) > max_age => {
+                Some(RotationReason::Expired)
+            }
+            _ => None,
+        }
+    }
+
+    /// The position and reason of every record that needs rotating
+    /// without decrypting anything
+    pub fn plan<I, F>(
+        &self,
+        records: I,
+        mut metadata: F,
+        now: u64,
+    ) -> Result<Vec<(u64, RotationReason)>, VerencError>
+    where
+        I: IntoIterator<Item = Result<ArchiveRecord, VerencError>>,
+        F: FnMut(u64, &ArchiveRecord) -> Result<RecordMetadata, VerencError>,
+    {
+        let mut plan = Vec::new();
+        for (position, record) in records.into_iter().enumerate() {
+            let position = position as u64;
+            let record = record?;
+            if let Some(reason) = self.reason(&metadata(position, &record)?, now) {
+                plan.push((position, reason));
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Rotate the next batch of records from `cursor`. `records` must
+    /// yield the archive from its start every time, records before the
+    /// cursor are skipped without calling `metadata`
+    pub fn run_batch<I, F>(
+        &self,
+        records: I,
+        mut metadata: F,
+        now: u64,
+        cursor: RotationCursor,
+    ) -> Result<RotationBatch, VerencError>
+    where
+        I: IntoIterator<Item = Result<ArchiveRecord, VerencError>>,
+        F: FnMut(u64, &ArchiveRecord) -> Result<RecordMetadata, VerencError>,
+    {
+        let mut rotated = Vec::with_capacity(self.batch_size);
+        let mut next = cursor.next;
+        let mut records = records.into_iter().enumerate().skip(cursor.next as usize);
+        while rotated.len() < self.batch_size {
+            let (position, record) = match records.next() {
+                Some((position, record)) => (position as u64, record?),
+                None => {
+                    return Ok(RotationBatch {
+                        rotated,
+                        cursor: RotationCursor { next },
+                        finished: true,
+                    })
+                }
+            };
+            let info = metadata(position, &record)?;
+            if let Some(reason) = self.reason(&info, now) {
+                let (_, key) = self
+                    .keys
+                    .iter()
+                    .find(|(f, _)| *f == info.key_fingerprint)
+                    .ok_or_else(|| {
+                        VerencError::Other(format!("No decryption key for record {}", position))
+                    })?;
+                let (ciphertext, proof) = key
+                    .reencrypt(&info.domain, &record.ciphertext, &self.target, &info.domain)
+                    .map_err(|e| VerencError::Other(format!("Record {}: {}", position, e)))?;
+                rotated.push(RotatedRecord {
+                    position,
+                    reason,
+                    domain: info.domain,
+                    ciphertext,
+                    proof,
+                });
+            }
+            next = position + 1;
+        }
+        Ok(RotationBatch {
+            rotated,
+            cursor: RotationCursor { next },
+            finished: false,
+        })
+    }
+}
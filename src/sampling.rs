@@ -0,0 +1,29 @@
+use rand_core::{CryptoRng, OsRng, RngCore};
+use unknown_order::BigNumber;
+
+/// Sample uniformly from [0, bound) with `rng`.
+///
+/// Candidates have the bit length of `bound` and are rejected until one
+/// is below it so every value is equally likely, each try succeeds more
+/// than half the time. This is how every secret and blinding in this crate
+/// is sampled so composed proofs can match it exactly.
+/// Panics if `bound` is not positive
+pub fn sample_below(bound: &BigNumber, rng: &mut (impl RngCore + CryptoRng)) -> BigNumber {
+    assert!(bound > &BigNumber::zero(), "bound must be positive");
+    let bound_bytes = bound.to_bytes();
+    let mask = u8::MAX >> bound_bytes[0].leading_zeros();
+    let mut bytes = vec![0u8; bound_bytes.len()];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        bytes[0] &= mask;
+        let value = BigNumber::from_slice(&bytes);
+        if &value < bound {
+            return value;
+        }
+    }
+}
+
+/// `sample_below` with the operating system's RNG
+pub(crate) fn random_below(bound: &BigNumber) -> BigNumber {
+    sample_below(bound, &mut OsRng)
+}
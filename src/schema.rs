@@ -0,0 +1,331 @@
+use crate::VerencError;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use unknown_order::BigNumber;
+
+/// How a slot's value is encoded as a message
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SlotType {
+    /// The message itself
+    Number,
+    /// An unsigned integer below 2^64
+    U64,
+    /// 0 for false and 1 for true
+    Bool,
+    /// Bytes after a 0x01 marker byte so leading zeros are kept
+    Bytes,
+    /// UTF-8 text encoded as `Bytes`
+    Text,
+}
+
+/// The name and type of one slot
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SlotField {
+    /// The name records refer to the slot by
+    pub name: String,
+    /// How the value is encoded
+    pub slot_type: SlotType,
+}
+
+/// A value of a slot before encoding or after decoding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlotValue {
+    /// For `SlotType::Number`
+    Number(BigNumber),
+    /// For `SlotType::U64`
+    U64(u64),
+    /// For `SlotType::Bool`
+    Bool(bool),
+    /// For `SlotType::Bytes`
+    Bytes(Vec<u8>),
+    /// For `SlotType::Text`
+    Text(String),
+}
+
+/// An application type whose fields are stored in the slots of a
+/// `SlotSchema` by name, so the issuer and auditor don't have to
+/// agree on an order in code
+pub trait SlotRecord: Sized {
+    /// The value of every field with the name of its slot
+    fn to_slots(&self) -> Vec<(&'static str, SlotValue)>;
+
+    /// Rebuild the record from decoded values
+    fn from_slots(values: &SlotValues) -> Result<Self, String>;
+}
+
+/// What each key slot holds, attached with `VerificationKey::with_schema`
+/// so it's published with the key and messages are encoded and decoded
+/// by name with `encode` and `decode`.
+///
+/// Like `Lifecycle` it's kept by the serde encoding of the key but isn't
+/// part of its fingerprint or proofs, so pin `SlotSchema::digest` where
+/// the issuer and auditor must be sure they use the same one
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "Vec<SlotField>", into = "Vec<SlotField>")]
+pub struct SlotSchema {
+    fields: Vec<SlotField>,
+}
+
+impl TryFrom<Vec<SlotField>> for SlotSchema {
+    type Error = String;
+
+    fn try_from(fields: Vec<SlotField>) -> Result<Self, String> {
+        for (i, field) in fields.iter().enumerate() {
+            if field.name.is_empty() {
+                return Err(format!("Slot {} has no name", i));
+            }
+            if fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(format!("Slot name {} is used twice", field.name));
+            }
+        }
+        Ok(Self { fields })
+    }
+}
+
+impl From<SlotSchema> for Vec<SlotField> {
+    fn from(schema: SlotSchema) -> Self {
+        schema.fields
+    }
+}
+
+impl SlotSchema {
+    /// A schema with a slot for each name and type in order
+    pub fn new<'a>(
+        fields: impl IntoIterator<Item = (&'a str, SlotType)>,
+    ) -> Result<Self, VerencError> {
+        Self::try_from(
+            fields
+                .into_iter()
+                .map(|(name, slot_type)| SlotField {
+                    name: name.to_string(),
+                    slot_type,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .map_err(VerencError::from)
+    }
+
+    /// The slots in order
+    pub fn fields(&self) -> &[SlotField] {
+        &self.fields
+    }
+
+    /// The number of slots
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// True if the schema has no slots
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The slot named `name`
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
+
+    /// The schema of a key projected to `indices`, see `VerificationKey::project`
+    pub(crate) fn project(&self, indices: &[usize]) -> Option<Self> {
+        indices
+            .iter()
+            .map(|i| self.fields.get(*i).cloned())
+            .collect::<Option<Vec<_>>>()
+            .map(|fields| Self { fields })
+    }
+
+    /// A 32 byte digest of the names and types
+    pub fn digest(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"verenc slot schema");
+        transcript.append_u64(b"fields.count", self.fields.len() as u64);
+        for field in &self.fields {
+            transcript.append_message(b"name", field.name.as_bytes());
+            transcript.append_message(b"type", format!("{:?}", field.slot_type).as_bytes());
+        }
+        let mut digest = [0u8; 32];
+        transcript.challenge_bytes(b"digest", &mut digest);
+        digest
+    }
+
+    /// The messages for `values` in slot order. Every slot must be given
+    /// exactly once with a value of its type
+    pub fn encode(&self, values: &[(&str, SlotValue)]) -> Result<Vec<BigNumber>, VerencError> {
+        let mut messages = vec![None; self.fields.len()];
+        for (name, value) in values {
+            let i = self
+                .position(name)
+                .ok_or_else(|| format!("No slot named {}", name))?;
+            if messages[i].is_some() {
+                return Err(VerencError::Other(format!("Slot {} is given twice", name)));
+            }
+            messages[i] = Some(encode_value(&self.fields[i], value)?);
+        }
+        messages
+            .into_iter()
+            .zip(self.fields.iter())
+            .map(|(m, field)| {
+                m.ok_or_else(|| VerencError::Other(format!("Slot {} has no value", field.name)))
+            })
+            .collect()
+    }
+
+    /// The values of `messages`, e.g. from decryption, by slot name
+    pub fn decode(&self, messages: &[BigNumber]) -> Result<SlotValues, VerencError> {
+        if messages.len() != self.fields.len() {
+            return Err(VerencError::Other(format!(
+                "Expected {} messages for the schema but found {}",
+                self.fields.len(),
+                messages.len()
+            )));
+        }
+        let values = self
+            .fields
+            .iter()
+            .zip(messages.iter())
+            .map(|(field, m)| decode_value(field, m))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SlotValues {
+            schema: self.clone(),
+            values,
+        })
+    }
+
+    /// The messages for `record`, see `encode`
+    pub fn encode_record<R: SlotRecord>(&self, record: &R) -> Result<Vec<BigNumber>, VerencError> {
+        self.encode(&record.to_slots())
+    }
+
+    /// The record `messages` encode, see `decode`
+    pub fn decode_record<R: SlotRecord>(&self, messages: &[BigNumber]) -> Result<R, VerencError> {
+        Ok(R::from_slots(&self.decode(messages)?)?)
+    }
+}
+
+/// Decoded slot values looked up by name
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotValues {
+    schema: SlotSchema,
+    values: Vec<SlotValue>,
+}
+
+impl SlotValues {
+    /// The value of the slot named `name`
+    pub fn get(&self, name: &str) -> Option<&SlotValue> {
+        self.schema.position(name).map(|i| &self.values[i])
+    }
+
+    /// The values in slot order
+    pub fn values(&self) -> &[SlotValue] {
+        &self.values
+    }
+
+    /// The number of the `SlotType::Number` slot named `name`
+    pub fn number(&self, name: &str) -> Result<&BigNumber, VerencError> {
+        match self.expect(name)? {
+            SlotValue::Number(n) => Ok(n),
+            _ => Err(wrong_type(name)),
+        }
+    }
+
+    /// The integer of the `SlotType::U64` slot named `name`
+    pub fn u64(&self, name: &str) -> Result<u64, VerencError> {
+        match self.expect(name)? {
+            SlotValue::U64(n) => Ok(*n),
+            _ => Err(wrong_type(name)),
+        }
+    }
+
+    /// The flag of the `SlotType::Bool` slot named `name`
+    pub fn bool(&self, name: &str) -> Result<bool, VerencError> {
+        match self.expect(name)? {
+            SlotValue::Bool(b) => Ok(*b),
+            _ => Err(wrong_type(name)),
+        }
+    }
+
+    /// The bytes of the `SlotType::Bytes` slot named `name`
+    pub fn bytes(&self, name: &str) -> Result<&[u8], VerencError> {
+        match self.expect(name)? {
+            SlotValue::Bytes(b) => Ok(b),
+            _ => Err(wrong_type(name)),
+        }
+    }
+
+    /// The text of the `SlotType::Text` slot named `name`
+    pub fn text(&self, name: &str) -> Result<&str, VerencError> {
+        match self.expect(name)? {
+            SlotValue::Text(s) => Ok(s),
+            _ => Err(wrong_type(name)),
+        }
+    }
+
+    fn expect(&self, name: &str) -> Result<&SlotValue, String> {
+        self.get(name)
+            .ok_or_else(|| format!("No slot named {}", name))
+    }
+}
+
+fn wrong_type(name: &str) -> VerencError {
+    VerencError::Other(format!("Slot {} has a different type", name))
+}
+
+fn encode_value(field: &SlotField, value: &SlotValue) -> Result<BigNumber, String> {
+    match (field.slot_type, value) {
+        (SlotType::Number, SlotValue::Number(n)) => Ok(n.clone()),
+        (SlotType::U64, SlotValue::U64(n)) => Ok(BigNumber::from(*n)),
+        (SlotType::Bool, SlotValue::Bool(b)) => Ok(BigNumber::from(u64::from(*b))),
+        (SlotType::Bytes, SlotValue::Bytes(b)) => Ok(marked_bytes(b)),
+        (SlotType::Text, SlotValue::Text(s)) => Ok(marked_bytes(s.as_bytes())),
+        _ => Err(format!(
+            "Slot {} holds {:?} values",
+            field.name, field.slot_type
+        )),
+    }
+}
+
+fn decode_value(field: &SlotField, m: &BigNumber) -> Result<SlotValue, String> {
+    let invalid = || format!("Slot {} isn't a valid {:?}", field.name, field.slot_type);
+    match field.slot_type {
+        SlotType::Number => Ok(SlotValue::Number(m.clone())),
+        SlotType::U64 => {
+            if m < &BigNumber::zero() || m >= &(BigNumber::one() << 64) {
+                return Err(invalid());
+            }
+            let bytes = m.to_bytes();
+            Ok(SlotValue::U64(
+                bytes.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b)),
+            ))
+        }
+        SlotType::Bool => {
+            if m == &BigNumber::zero() {
+                Ok(SlotValue::Bool(false))
+            } else if m == &BigNumber::one() {
+                Ok(SlotValue::Bool(true))
+            } else {
+                Err(invalid())
+            }
+        }
+        SlotType::Bytes => unmarked_bytes(m).map(SlotValue::Bytes).ok_or_else(invalid),
+        SlotType::Text => unmarked_bytes(m)
+            .and_then(|b| String::from_utf8(b).ok())
+            .map(SlotValue::Text)
+            .ok_or_else(invalid),
+    }
+}
+
+fn marked_bytes(bytes: &[u8]) -> BigNumber {
+    let mut marked = Vec::with_capacity(bytes.len() + 1);
+    marked.push(1);
+    marked.extend_from_slice(bytes);
+    BigNumber::from_slice(marked)
+}
+
+fn unmarked_bytes(m: &BigNumber) -> Option<Vec<u8>> {
+    if m <= &BigNumber::zero() {
+        return None;
+    }
+    match m.to_bytes().split_first() {
+        Some((1, rest)) => Some(rest.to_vec()),
+        _ => None,
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{Context, DecryptionKey, VerencError, VerifiableCipherText};
+use unknown_order::BigNumber;
+
+/// Which labels a `ScopedDecryptionKey` may decrypt under
+#[derive(Clone, Debug)]
+pub enum LabelScope {
+    /// Labels that start with these bytes
+    Prefix(Vec<u8>),
+    /// Only this label
+    Exact(Vec<u8>),
+    /// Labels matching this regular expression
+    #[cfg(feature = "regex")]
+    Pattern(regex::bytes::Regex),
+}
+
+impl LabelScope {
+    /// True if `label` is in scope
+    pub fn allows(&self, label: &[u8]) -> bool {
+        match self {
+            Self::Prefix(prefix) => label.starts_with(prefix),
+            Self::Exact(exact) => label == exact.as_slice(),
+            #[cfg(feature = "regex")]
+            Self::Pattern(pattern) => pattern.is_match(label),
+        }
+    }
+}
+
+/// A `DecryptionKey` that only decrypts ciphertexts whose label,
+/// the domain passed when encrypting, is allowed by one of its scopes.
+///
+/// The label is checked before any work is done with the key so
+/// openings are bound to their purpose. The inner key is never exposed
+pub struct ScopedDecryptionKey {
+    key: DecryptionKey,
+    scopes: Vec<LabelScope>,
+}
+
+impl ScopedDecryptionKey {
+    /// Restrict `key` to labels allowed by any of `scopes`
+    pub fn new(key: DecryptionKey, scopes: Vec<LabelScope>) -> Self {
+        Self { key, scopes }
+    }
+
+    /// The scopes this key enforces
+    pub fn scopes(&self) -> &[LabelScope] {
+        &self.scopes
+    }
+
+    /// True if this key may decrypt under `domain`
+    pub fn allows(&self, domain: &[u8]) -> bool {
+        self.scopes.iter().any(|scope| scope.allows(domain))
+    }
+
+    /// Decrypt `ciphertext` if `context` is in scope
+    pub fn decrypt<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<Vec<BigNumber>, VerencError> {
+        let context = context.into();
+        let domain = context.as_bytes();
+        if !self.allows(domain) {
+            return Err(VerencError::Other(
+                "Label is not in the scope of this key".to_string(),
+            ));
+        }
+        self.key.decrypt(domain, ciphertext)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    ArchiveRecord, Context, CustodySigner, VerencError, VerifiableCipherText,
+    VerifiableEncryptionProof, VerificationKey, WarrantAuthority,
+};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// A ciphertext and its proof bound to the label they were made under,
+/// the fingerprint of the key they are encrypted to and an authenticator
+/// from the storage operator, for keeping in cold storage.
+///
+/// Check it with `SealedRecord::check` when it's read back so bit-rot or
+/// tampering is found then rather than as a failed decryption years later.
+/// The operator signs `SealedRecord::message` with a `CustodySigner`,
+/// e.g. a key in an HSM, or uses a `StorageMac`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SealedRecord {
+    ciphertext: VerifiableCipherText,
+    proof: Option<VerifiableEncryptionProof>,
+    label: Vec<u8>,
+    key_fingerprint: [u8; 32],
+    authenticator: Vec<u8>,
+}
+
+impl SealedRecord {
+    /// Seal `record` made under `context` for the key with `key_fingerprint`
+    pub fn seal<'c, S: CustodySigner>(
+        record: ArchiveRecord,
+        context: impl Into<Context<'c>>,
+        key_fingerprint: [u8; 32],
+        operator: &S,
+    ) -> Self {
+        let mut sealed = Self {
+            ciphertext: record.ciphertext,
+            proof: record.proof,
+            label: context.into().as_bytes().to_vec(),
+            key_fingerprint,
+            authenticator: Vec::new(),
+        };
+        sealed.authenticator = operator.sign(&sealed.message());
+        sealed
+    }
+
+    /// Check the operator's authenticator covers everything in the record
+    pub fn check<A: WarrantAuthority>(&self, operator: &A) -> Result<(), VerencError> {
+        if operator.verify_warrant(&self.message(), &self.authenticator) {
+            Ok(())
+        } else {
+            Err(VerencError::Other(
+                "Sealed record authenticator is invalid".to_string(),
+            ))
+        }
+    }
+
+    /// `check` the record then that it's for `key` and its proof,
+    /// if it has one, verifies under the label
+    pub fn verify<A: WarrantAuthority>(
+        &self,
+        operator: &A,
+        key: &VerificationKey,
+    ) -> Result<(), VerencError> {
+        self.check(operator)?;
+        if key.fingerprint() != self.key_fingerprint {
+            return Err(VerencError::Other(
+                "Sealed record is for a different key".to_string(),
+            ));
+        }
+        match &self.proof {
+            Some(proof) => Ok(key.verify(&self.label, &self.ciphertext, proof)?),
+            None => Ok(()),
+        }
+    }
+
+    /// The ciphertext and proof, once checked
+    pub fn record(&self) -> ArchiveRecord {
+        ArchiveRecord {
+            ciphertext: self.ciphertext.clone(),
+            proof: self.proof.clone(),
+        }
+    }
+
+    /// The ciphertext
+    pub fn ciphertext(&self) -> &VerifiableCipherText {
+        &self.ciphertext
+    }
+
+    /// The proof if one was stored
+    pub fn proof(&self) -> Option<&VerifiableEncryptionProof> {
+        self.proof.as_ref()
+    }
+
+    /// The label the ciphertext and proof were made under
+    pub fn label(&self) -> &[u8] {
+        &self.label
+    }
+
+    /// The `VerificationKey::fingerprint` of the key the ciphertext is encrypted to
+    pub fn key_fingerprint(&self) -> &[u8; 32] {
+        &self.key_fingerprint
+    }
+
+    /// The operator's signature or MAC
+    pub fn authenticator(&self) -> &[u8] {
+        &self.authenticator
+    }
+
+    /// The message the operator authenticates
+    pub fn message(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"sealed archive record");
+        transcript.append_message(b"ciphertext_hash", &self.ciphertext.digest());
+        match &self.proof {
+            None => transcript.append_u64(b"proof", 0),
+            Some(proof) => {
+                transcript.append_u64(b"proof", 1);
+                transcript.append_message(b"challenge", &proof.challenge.to_bytes());
+                transcript.append_message(b"r", &proof.r.to_bytes());
+                transcript.append_u64(b"m.count", proof.m.len() as u64);
+                for m in &proof.m {
+                    transcript.append_message(b"m", &m.to_bytes());
+                }
+            }
+        }
+        transcript.append_message(b"label", &self.label);
+        transcript.append_message(b"key_fingerprint", &self.key_fingerprint);
+        let mut message = [0u8; 32];
+        transcript.challenge_bytes(b"sealed record message", &mut message);
+        message
+    }
+}
+
+/// A secret key the storage operator authenticates `SealedRecord`s with
+/// when a signature isn't needed, i.e. only the operator checks them.
+/// Keep it apart from the archive it protects
+#[derive(Clone)]
+pub struct StorageMac([u8; 32]);
+
+impl fmt::Debug for StorageMac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StorageMac(..)")
+    }
+}
+
+impl Drop for StorageMac {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl StorageMac {
+    /// A new random key
+    pub fn random() -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// A key from its bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// The key bytes
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    fn tag(&self, message: &[u8; 32]) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"sealed archive record mac");
+        transcript.append_message(b"key", &self.0);
+        transcript.append_message(b"message", message);
+        let mut tag = [0u8; 32];
+        transcript.challenge_bytes(b"tag", &mut tag);
+        tag
+    }
+}
+
+impl CustodySigner for StorageMac {
+    fn sign(&self, message: &[u8; 32]) -> Vec<u8> {
+        self.tag(message).to_vec()
+    }
+}
+
+impl WarrantAuthority for StorageMac {
+    fn verify_warrant(&self, message: &[u8; 32], signature: &[u8]) -> bool {
+        bool::from(self.tag(message)[..].ct_eq(signature))
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{CipherTextEnvelope, Warrant};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use zeroize::Zeroize;
+
+/// Deterministic tag locating the ciphertext for a label and slot,
+/// see `SearchKey::tag`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct SearchTag([u8; 32]);
+
+impl SearchTag {
+    /// The tag bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Secret key of the keyed function deriving `SearchTag`s.
+///
+/// Whoever encrypts stores the tags of each ciphertext next to it and
+/// the escrow agent, holding the same key, derives the tag for a warrant's
+/// label to find the ciphertext without decrypting or scanning anything.
+/// Without the key the tags reveal nothing about the labels
+#[derive(Clone)]
+pub struct SearchKey([u8; 32]);
+
+impl fmt::Debug for SearchKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SearchKey(..)")
+    }
+}
+
+impl Drop for SearchKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl SearchKey {
+    /// A new random key
+    pub fn random() -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// A key from its bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// The key bytes
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// The tag of slot `slot` of a ciphertext encrypted under `label`
+    pub fn tag(&self, label: &[u8], slot: usize) -> SearchTag {
+        let mut transcript = merlin::Transcript::new(b"verifiable encryption search tag");
+        transcript.append_message(b"key", &self.0);
+        transcript.append_message(b"label", label);
+        transcript.append_u64(b"slot", slot as u64);
+        let mut tag = [0u8; 32];
+        transcript.challenge_bytes(b"tag", &mut tag);
+        SearchTag(tag)
+    }
+
+    /// The tag of slot `slot` under the label `warrant` authorizes
+    pub fn tag_for_warrant(&self, warrant: &Warrant, slot: usize) -> SearchTag {
+        self.tag(warrant.label(), slot)
+    }
+}
+
+impl CipherTextEnvelope {
+    /// Attach the tag of every slot of the ciphertext encrypted under `label`
+    pub fn with_search_tags(mut self, key: &SearchKey, label: &[u8]) -> Self {
+        let slots = self.ciphertext.e.len();
+        self.tags
+            .extend((0..slots).map(|slot| key.tag(label, slot)));
+        self
+    }
+
+    /// Attach a tag, e.g. for a ciphertext encrypted at chosen slots
+    pub fn with_search_tag(mut self, tag: SearchTag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// The attached tags
+    pub fn search_tags(&self) -> &[SearchTag] {
+        &self.tags
+    }
+
+    /// True if `tag` is attached
+    pub fn has_search_tag(&self, tag: &SearchTag) -> bool {
+        self.tags.contains(tag)
+    }
+}
+
+/// Envelopes indexed by their search tags
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    envelopes: Vec<CipherTextEnvelope>,
+    tags: BTreeMap<SearchTag, Vec<usize>>,
+}
+
+impl SearchIndex {
+    /// An empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `envelope` under each of its tags
+    pub fn insert(&mut self, envelope: CipherTextEnvelope) {
+        let index = self.envelopes.len();
+        for tag in envelope.search_tags() {
+            let entries = self.tags.entry(*tag).or_default();
+            if entries.last() != Some(&index) {
+                entries.push(index);
+            }
+        }
+        self.envelopes.push(envelope);
+    }
+
+    /// The envelopes with `tag` in the order they were inserted
+    pub fn find(&self, tag: &SearchTag) -> impl Iterator<Item = &CipherTextEnvelope> {
+        self.tags
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .map(move |i| &self.envelopes[*i])
+    }
+
+    /// The envelopes holding slot `slot` under the label `warrant` authorizes
+    pub fn find_for_warrant<'a>(
+        &'a self,
+        key: &SearchKey,
+        warrant: &Warrant,
+        slot: usize,
+    ) -> impl Iterator<Item = &'a CipherTextEnvelope> {
+        self.find(&key.tag_for_warrant(warrant, slot))
+    }
+
+    /// The number of envelopes
+    pub fn len(&self) -> usize {
+        self.envelopes.len()
+    }
+
+    /// True if there are no envelopes
+    pub fn is_empty(&self) -> bool {
+        self.envelopes.is_empty()
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{budget::charge, DecryptionKey, Group};
+use serde::{
+    de::{Error, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+use unknown_order::BigNumber;
+use zeroize::{Zeroize, Zeroizing};
+
+impl DecryptionKey {
+    /// Serialize this key writing every secret value as bytes from a
+    /// zeroizing buffer instead of through `BigNumber`'s serde impl,
+    /// which leaves copies of the secret in freed memory.
+    ///
+    /// Works with `#[serde(serialize_with = "DecryptionKey::serialize_secret")]`
+    pub fn serialize_secret<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SecretKeyRef {
+            x1: &self.x1,
+            x2: &self.x2,
+            x3: &self.x3,
+            group: &self.group,
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserialize a key written by `serialize_secret`.
+    /// Secret values are read into zeroizing buffers and partially read
+    /// keys are zeroized when deserialization fails.
+    ///
+    /// Works with `#[serde(deserialize_with = "DecryptionKey::deserialize_secret")]`
+    pub fn deserialize_secret<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut key = SecretKeySerdes::deserialize(deserializer)?;
+        Ok(DecryptionKey {
+            x1: key.x1.iter_mut().map(Secret::take).collect(),
+            x2: key.x2.take(),
+            x3: key.x3.take(),
+            group: key.group,
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "DecryptionKey")]
+struct SecretKeyRef<'a> {
+    #[serde(serialize_with = "secrets")]
+    x1: &'a [BigNumber],
+    #[serde(serialize_with = "secret")]
+    x2: &'a BigNumber,
+    #[serde(serialize_with = "secret")]
+    x3: &'a BigNumber,
+    group: &'a Group,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "DecryptionKey")]
+struct SecretKeySerdes {
+    x1: Vec<Secret>,
+    x2: Secret,
+    x3: Secret,
+    group: Group,
+}
+
+fn secret<S>(value: &BigNumber, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let bytes = Zeroizing::new(value.to_bytes());
+    serializer.serialize_bytes(&bytes)
+}
+
+fn secrets<S>(values: &[BigNumber], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(values.iter().map(SecretBytes))
+}
+
+struct SecretBytes<'a>(&'a BigNumber);
+
+impl Serialize for SecretBytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        secret(self.0, serializer)
+    }
+}
+
+/// A secret value that is zeroized when dropped unless taken
+struct Secret(BigNumber);
+
+impl Secret {
+    fn take(&mut self) -> BigNumber {
+        std::mem::replace(&mut self.0, BigNumber::zero())
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(SecretVisitor)
+    }
+}
+
+struct SecretVisitor;
+
+impl<'de> Visitor<'de> for SecretVisitor {
+    type Value = Secret;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "secret bytes")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        charge(v.len()).map_err(E::custom)?;
+        Ok(Secret(BigNumber::from_slice(v)))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let v = Zeroizing::new(v);
+        self.visit_bytes(&v)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // Grow by hand so no reallocation leaves a copy behind
+        let mut bytes = Zeroizing::new(Vec::<u8>::new());
+        while let Some(b) = seq.next_element::<u8>()? {
+            if bytes.len() == bytes.capacity() {
+                let capacity = (bytes.capacity() * 2).max(64);
+                charge(capacity - bytes.capacity()).map_err(A::Error::custom)?;
+                let mut grown = Zeroizing::new(Vec::with_capacity(capacity));
+                grown.extend_from_slice(&bytes);
+                bytes = grown;
+            }
+            bytes.push(b);
+        }
+        Ok(Secret(BigNumber::from_slice(&*bytes)))
+    }
+}
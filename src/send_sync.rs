@@ -0,0 +1,99 @@
+//! Every public type is `Send + Sync` with every backend so keys, proofs
+//! and verifiers can be shared between threads, e.g. with rayon or behind
+//! an `Arc` in a service. Nothing holds interior mutability, the only
+//! thread bound state is the `MemoryBudget` which is per thread by design.
+//! These assertions fail to compile if that ever changes.
+
+use crate::{recovery::*, *};
+use unknown_order::BigNumber;
+
+struct Authority;
+
+impl WarrantAuthority for Authority {
+    fn verify_warrant(&self, _message: &[u8; 32], _signature: &[u8]) -> bool {
+        false
+    }
+}
+
+const fn assert_send_sync<T: Send + Sync>() {}
+
+const _: () = {
+    assert_send_sync::<BigNumber>();
+    assert_send_sync::<Group>();
+    assert_send_sync::<GroupRef<'static>>();
+    assert_send_sync::<ReducedElement>();
+    assert_send_sync::<ParameterPreset>();
+    assert_send_sync::<ParameterRegistry>();
+    assert_send_sync::<EncryptionKey>();
+    assert_send_sync::<EncryptionKeyRef<'static>>();
+    assert_send_sync::<DecryptedMessages>();
+    assert_send_sync::<DecryptionKey>();
+    assert_send_sync::<VerificationKey>();
+    assert_send_sync::<CompactKey>();
+    assert_send_sync::<KeyRing>();
+    assert_send_sync::<KeyBoundProof>();
+    assert_send_sync::<TagKeyShare>();
+    assert_send_sync::<SlotKeyShare>();
+    assert_send_sync::<TagAttestation>();
+    assert_send_sync::<SlotDecryption>();
+    assert_send_sync::<MpcKeyExport>();
+    assert_send_sync::<ProtectedKey>();
+    assert_send_sync::<GuardedDecryptionKey<Authority>>();
+    assert_send_sync::<GuardState>();
+    assert_send_sync::<VerifiableCipherText>();
+    assert_send_sync::<CipherTextEnvelope>();
+    assert_send_sync::<CiphertextArchiveWriter<Vec<u8>>>();
+    assert_send_sync::<CiphertextArchiveReader<&'static [u8]>>();
+    assert_send_sync::<Manifest>();
+    assert_send_sync::<InclusionProof>();
+    assert_send_sync::<VerifiableEncryptionProof>();
+    assert_send_sync::<CommittedEncryptionProof<BigNumber>>();
+    assert_send_sync::<DecryptionProof>();
+    assert_send_sync::<KeyGenerationProof>();
+    assert_send_sync::<DerivationProof>();
+    assert_send_sync::<SealedRecord>();
+    assert_send_sync::<StorageMac>();
+    assert_send_sync::<LabelOrProof>();
+    assert_send_sync::<LessThanProof>();
+    assert_send_sync::<PackedRangeProof>();
+    assert_send_sync::<AccessPolicy>();
+    assert_send_sync::<PolicyEscrow<BigNumber>>();
+    assert_send_sync::<PolicyShare>();
+    assert_send_sync::<PaillierBlumProof>();
+    assert_send_sync::<DisclosedResponse>();
+    assert_send_sync::<ReencryptionChain>();
+    assert_send_sync::<ReencryptionLink>();
+    assert_send_sync::<ReencryptionProof>();
+    assert_send_sync::<RerandomizationProof>();
+    assert_send_sync::<ResponseDisclosure>();
+    assert_send_sync::<ResponseTree>();
+    assert_send_sync::<ScopedDecryptionKey>();
+    assert_send_sync::<LabelScope>();
+    assert_send_sync::<SecretKeyEscrowProof>();
+    assert_send_sync::<RecoveryPackage<BigNumber>>();
+    assert_send_sync::<PartialDecryption>();
+    assert_send_sync::<threshold::DecryptionKeyShare>();
+    assert_send_sync::<threshold::PartialDecryption>();
+    assert_send_sync::<dkg::DkgParty>();
+    assert_send_sync::<dkg::DkgOutput>();
+    assert_send_sync::<AuditEvent<'static>>();
+    assert_send_sync::<Rotation>();
+    assert_send_sync::<RotationBatch>();
+    assert_send_sync::<PrecomputedEncryptionKey>();
+    assert_send_sync::<ProofStatement>();
+    assert_send_sync::<VerificationTrace>();
+    assert_send_sync::<TranscriptEntry>();
+    assert_send_sync::<ProofOptions>();
+    assert_send_sync::<Verifier<'static>>();
+    assert_send_sync::<Label>();
+    assert_send_sync::<Lifecycle>();
+    assert_send_sync::<ExpiryPolicy>();
+    assert_send_sync::<SlotSchema>();
+    assert_send_sync::<SlotValues>();
+    assert_send_sync::<Warrant>();
+    assert_send_sync::<DecryptionRecord>();
+    assert_send_sync::<ChildDecryptionRecord>();
+    assert_send_sync::<MemoryBudget>();
+    #[cfg(feature = "parallel")]
+    assert_send_sync::<VerifierPool>();
+};
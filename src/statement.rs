@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// A public base raised to an exponent derived from the proof
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StatementTerm {
+    /// Name of the base, e.g. `g`, `y1[0]` or `ciphertext.e[0]`
+    pub base_name: String,
+    /// The value of the base
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub base: BigNumber,
+    /// How the exponent is computed from the proof, e.g. `2c` or `2m[0]`
+    pub exponent_name: String,
+    /// The value of the exponent
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub exponent: BigNumber,
+}
+
+/// A commitment the verifier recomputes as the product of its terms mod n^2
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StatementEquation {
+    /// The transcript label the result is absorbed under
+    pub name: String,
+    /// The terms multiplied together
+    pub terms: Vec<StatementTerm>,
+}
+
+/// The verification equations of a `VerifiableEncryptionProof` with every
+/// public value and exponent the verifier uses, for embedding in external
+/// constraint systems.
+///
+/// The verifier computes each equation in order, absorbs the statement and results
+/// into the Fiat-Shamir transcript and checks the output matches `challenge`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProofStatement {
+    /// The modulus all equations are reduced by, n^2
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub modulus: BigNumber,
+    /// The nonce the proof was created with
+    pub nonce: Vec<u8>,
+    /// H(u, e, L) used in the `v` equation
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub hash: BigNumber,
+    /// The challenge from the proof
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub challenge: BigNumber,
+    /// The equations in transcript order: `u`, each `e[i]`, then `v`
+    pub equations: Vec<StatementEquation>,
+}
+
+impl ProofStatement {
+    /// Compute the product of each equation's terms.
+    /// Valid proofs produce the prover's commitments
+    pub fn evaluate(&self) -> Vec<BigNumber> {
+        self.equations
+            .iter()
+            .map(|eq| {
+                eq.terms.iter().fold(BigNumber::one(), |acc, t| {
+                    acc.modmul(&t.base.modpow(&t.exponent, &self.modulus), &self.modulus)
+                })
+            })
+            .collect()
+    }
+}
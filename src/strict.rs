@@ -0,0 +1,140 @@
+use crate::{
+    EncryptionKey, EncryptionKeyRef, VerencError, VerifiableCipherText, VerifiableEncryptionProof,
+    VerificationKey,
+};
+use std::fmt::{self, Display};
+
+/// Why `verify_strict` rejected a proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StrictVerifyError {
+    /// The declared number of messages is more than the key has slots
+    TooManyMessages {
+        /// The declared number of messages
+        declared: usize,
+        /// The number of key slots
+        supported: usize,
+    },
+    /// The ciphertext has a different number of `e` values than declared
+    CiphertextLength {
+        /// The declared number of messages
+        declared: usize,
+        /// The number of `e` values in the ciphertext
+        actual: usize,
+    },
+    /// The proof has a different number of responses than declared
+    ResponseCount {
+        /// The declared number of messages
+        declared: usize,
+        /// The number of responses in the proof
+        actual: usize,
+    },
+    /// Every length agrees but the proof does not verify
+    InvalidProof,
+}
+
+impl Display for StrictVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyMessages {
+                declared,
+                supported,
+            } => write!(
+                f,
+                "{} messages declared but the key supports {}",
+                declared, supported
+            ),
+            Self::CiphertextLength { declared, actual } => write!(
+                f,
+                "{} messages declared but the ciphertext has {}",
+                declared, actual
+            ),
+            Self::ResponseCount { declared, actual } => write!(
+                f,
+                "{} messages declared but the proof has {} responses",
+                declared, actual
+            ),
+            Self::InvalidProof => write!(f, "Invalid proof"),
+        }
+    }
+}
+
+impl std::error::Error for StrictVerifyError {}
+
+impl From<StrictVerifyError> for String {
+    fn from(e: StrictVerifyError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<StrictVerifyError> for VerencError {
+    fn from(e: StrictVerifyError) -> Self {
+        Self::Other(e.to_string())
+    }
+}
+
+impl EncryptionKeyRef<'_> {
+    /// Verify a proof of verifiable encryption of exactly `messages` messages
+    /// into the first slots of this key.
+    ///
+    /// `verify` takes the number of messages from the proof so has to accept
+    /// whatever count it carries. This requires the declared count, the
+    /// ciphertext and the proof to agree and reports which doesn't.
+    /// It will be how `verify` behaves in the next major version
+    pub fn verify_strict(
+        &self,
+        nonce: &[u8],
+        messages: usize,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), StrictVerifyError> {
+        if messages > self.y1.len() {
+            return Err(StrictVerifyError::TooManyMessages {
+                declared: messages,
+                supported: self.y1.len(),
+            });
+        }
+        if ciphertext.e.len() != messages {
+            return Err(StrictVerifyError::CiphertextLength {
+                declared: messages,
+                actual: ciphertext.e.len(),
+            });
+        }
+        if proof.m.len() != messages {
+            return Err(StrictVerifyError::ResponseCount {
+                declared: messages,
+                actual: proof.m.len(),
+            });
+        }
+        self.verify(nonce, ciphertext, proof)
+            .map_err(|_| StrictVerifyError::InvalidProof)
+    }
+}
+
+impl VerificationKey {
+    /// Verify a proof of verifiable encryption of exactly `messages` messages,
+    /// see `EncryptionKeyRef::verify_strict`
+    pub fn verify_strict(
+        &self,
+        nonce: &[u8],
+        messages: usize,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), StrictVerifyError> {
+        self.view()
+            .verify_strict(nonce, messages, ciphertext, proof)
+    }
+}
+
+impl EncryptionKey {
+    /// Verify a proof of verifiable encryption of exactly `messages` messages,
+    /// see `EncryptionKeyRef::verify_strict`
+    pub fn verify_strict(
+        &self,
+        nonce: &[u8],
+        messages: usize,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), StrictVerifyError> {
+        self.vk.verify_strict(nonce, messages, ciphertext, proof)
+    }
+}
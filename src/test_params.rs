@@ -0,0 +1,100 @@
+//! **Not for production use.** Every prime here is public so anyone can
+//! factor the modulus and decrypt anything encrypted to these keys.
+//!
+//! The primes are fixed safe primes so test suites spend no time in
+//! safe-prime generation, and each group and key pair is built once per
+//! process and shared. Every function panics in builds without debug
+//! assertions unless `VERENC_ALLOW_TEST_PARAMS` is set in the environment,
+//! so a release binary that enabled the feature by mistake fails loudly
+//! instead of encrypting to a known key
+use crate::fixtures::{from_hex, SAFE_PRIMES_1024, SAFE_PRIMES_256, SAFE_PRIMES_512};
+use crate::{DecryptionKey, EncryptionKey, Group};
+use std::sync::OnceLock;
+use unknown_order::BigNumber;
+
+/// The environment variable that allows test parameters in release builds,
+/// e.g. for `cargo test --release`
+pub const ALLOW_ENV: &str = "VERENC_ALLOW_TEST_PARAMS";
+
+/// The number of messages the pre-built key pairs support
+pub const KEY_SLOTS: usize = 4;
+
+/// Size of the cached safe primes used to create a test group.
+/// These are public and only meant for tests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PrimeSize {
+    /// 256-bit safe primes, 512-bit modulus
+    Bits256,
+    /// 512-bit safe primes, 1024-bit modulus
+    Bits512,
+    /// 1024-bit safe primes, 2048-bit modulus like a production group
+    Bits1024,
+}
+
+impl PrimeSize {
+    /// Every size
+    pub const ALL: [PrimeSize; 3] = [Self::Bits256, Self::Bits512, Self::Bits1024];
+
+    pub(crate) fn primes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Bits256 => &SAFE_PRIMES_256,
+            Self::Bits512 => &SAFE_PRIMES_512,
+            Self::Bits1024 => &SAFE_PRIMES_1024,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Self::Bits256 => 0,
+            Self::Bits512 => 1,
+            Self::Bits1024 => 2,
+        }
+    }
+}
+
+/// The pair of safe primes the group of `size` is made from
+pub fn safe_primes(size: PrimeSize) -> (BigNumber, BigNumber) {
+    guard();
+    let primes = size.primes();
+    (from_hex(primes[0]), from_hex(primes[1]))
+}
+
+/// The group of `size`, built on first use
+pub fn group(size: PrimeSize) -> &'static Group {
+    static GROUPS: [OnceLock<Group>; 3] = [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+    guard();
+    GROUPS[size.index()].get_or_init(|| {
+        let (p, q) = safe_primes(size);
+        Group::with_safe_primes_unchecked(&p, &q).expect("cached primes are valid")
+    })
+}
+
+/// A key pair for `KEY_SLOTS` messages in the group of `size`, built on first use.
+/// Use `EncryptionKey::project` for a key with fewer slots
+pub fn keys(size: PrimeSize) -> &'static (EncryptionKey, DecryptionKey) {
+    static KEYS: [OnceLock<(EncryptionKey, DecryptionKey)>; 3] =
+        [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+    guard();
+    KEYS[size.index()].get_or_init(|| {
+        group(size)
+            .new_keys(KEY_SLOTS)
+            .expect("KEY_SLOTS is at least 1")
+    })
+}
+
+/// A fresh key pair for `max_messages` messages in the group of `size`
+pub fn new_keys(size: PrimeSize, max_messages: usize) -> (EncryptionKey, DecryptionKey) {
+    group(size)
+        .new_keys(max_messages.max(1))
+        .expect("max_messages is at least 1")
+}
+
+fn guard() {
+    if !cfg!(debug_assertions) && std::env::var_os(ALLOW_ENV).is_none() {
+        panic!(
+            "verenc test parameters are public and insecure; they are only available \
+             in debug builds or with {} set",
+            ALLOW_ENV
+        );
+    }
+}
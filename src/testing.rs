@@ -0,0 +1,221 @@
+use crate::fixtures::from_hex;
+pub use crate::test_params::PrimeSize;
+use crate::{
+    CustodySigner, DecryptionKey, DecryptionRecord, EncryptionKey, Group, VerencError,
+    VerifiableCipherText, VerificationKey, Warrant, WarrantAuthority,
+};
+use rand_core::RngCore;
+use std::sync::{Mutex, MutexGuard};
+use unknown_order::BigNumber;
+
+/// Create a random group from a pair of the cached 256-bit safe primes
+pub fn group(rng: &mut impl RngCore) -> Group {
+    group_with_size(PrimeSize::Bits256, rng)
+}
+
+/// Create a random group from a pair of cached safe primes of the specified size
+pub fn group_with_size(size: PrimeSize, rng: &mut impl RngCore) -> Group {
+    let primes = size.primes();
+    let i = index(primes.len(), rng);
+    let mut j = index(primes.len() - 1, rng);
+    if j >= i {
+        j += 1;
+    }
+    Group::with_safe_primes_unchecked(&from_hex(primes[i]), &from_hex(primes[j]))
+        .expect("cached primes are valid")
+}
+
+/// Create a key pair in `group` supporting between 1 and `max_messages` messages
+pub fn keys(
+    group: &Group,
+    max_messages: usize,
+    rng: &mut impl RngCore,
+) -> (EncryptionKey, DecryptionKey) {
+    let num_messages = 1 + index(max_messages.max(1), rng);
+    group
+        .new_keys(num_messages)
+        .expect("num_messages is at least 1")
+}
+
+/// Create `count` random messages that can be encrypted in `group`
+pub fn messages(group: &Group, count: usize, rng: &mut impl RngCore) -> Vec<BigNumber> {
+    (0..count).map(|_| below(&group.n, rng)).collect()
+}
+
+/// Create a random label of up to `max_len` bytes for use as a domain or nonce
+pub fn label(max_len: usize, rng: &mut impl RngCore) -> Vec<u8> {
+    let mut label = vec![0u8; index(max_len + 1, rng)];
+    rng.fill_bytes(&mut label);
+    label
+}
+
+/// What a `MockEscrowAgent` was asked to do
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EscrowInteraction {
+    /// Signed `message` as a `CustodySigner` or when issuing a warrant
+    Signed {
+        /// The message signed
+        message: [u8; 32],
+    },
+    /// Checked a signature on `message` as a `WarrantAuthority`
+    CheckedSignature {
+        /// The message the signature was checked on
+        message: [u8; 32],
+        /// Whether the signature was valid
+        valid: bool,
+    },
+    /// Was asked to decrypt the ciphertext with `ciphertext_hash` under a warrant
+    Decrypted {
+        /// The digest of the ciphertext
+        ciphertext_hash: [u8; 32],
+        /// The label of the warrant
+        label: Vec<u8>,
+        /// Whether decryption succeeded
+        success: bool,
+    },
+}
+
+/// Stands in for an escrow agent in integration tests.
+///
+/// Holds keys in a small cached group instead of fresh 2048-bit parameters,
+/// signs with a keyed hash only it can check and acts as both the
+/// `WarrantAuthority` and the `CustodySigner`, so it can be passed wherever
+/// the real services are. Every call is recorded in order for assertions
+#[derive(Debug)]
+pub struct MockEscrowAgent {
+    encryption_key: EncryptionKey,
+    decryption_key: DecryptionKey,
+    signing_key: [u8; 32],
+    interactions: Mutex<Vec<EscrowInteraction>>,
+}
+
+impl MockEscrowAgent {
+    /// An agent with keys for `max_messages` messages in a 256-bit prime group
+    pub fn new(max_messages: usize, rng: &mut impl RngCore) -> Self {
+        Self::with_size(PrimeSize::Bits256, max_messages, rng)
+    }
+
+    /// An agent with keys for `max_messages` messages in a group of `size` primes
+    pub fn with_size(size: PrimeSize, max_messages: usize, rng: &mut impl RngCore) -> Self {
+        let (encryption_key, decryption_key) = group_with_size(size, rng)
+            .new_keys(max_messages.max(1))
+            .expect("max_messages is at least 1");
+        let mut signing_key = [0u8; 32];
+        rng.fill_bytes(&mut signing_key);
+        Self {
+            encryption_key,
+            decryption_key,
+            signing_key,
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The key to encrypt to the agent with
+    pub fn encryption_key(&self) -> &EncryptionKey {
+        &self.encryption_key
+    }
+
+    /// The key to verify proofs for the agent with
+    pub fn verification_key(&self) -> VerificationKey {
+        VerificationKey::from(&self.encryption_key)
+    }
+
+    /// The agent's decryption key, e.g. to split it with `DecryptionKey::split_custody`
+    pub fn decryption_key(&self) -> &DecryptionKey {
+        &self.decryption_key
+    }
+
+    /// A warrant for `ciphertext` under `label` signed by the agent
+    pub fn issue_warrant(
+        &self,
+        ciphertext: &VerifiableCipherText,
+        label: &[u8],
+        statement: &[u8],
+    ) -> Warrant {
+        let warrant = Warrant::new(ciphertext, label, statement);
+        let signature = self.sign(&warrant.message());
+        warrant.with_signature(&signature)
+    }
+
+    /// Decrypt `ciphertext` if `warrant` names it and is signed by the agent
+    pub fn decrypt_with_warrant(
+        &self,
+        warrant: &Warrant,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<DecryptionRecord, VerencError> {
+        let record = self
+            .decryption_key
+            .decrypt_with_warrant(self, warrant, ciphertext);
+        self.record(EscrowInteraction::Decrypted {
+            ciphertext_hash: ciphertext.digest(),
+            label: warrant.label().to_vec(),
+            success: record.is_ok(),
+        });
+        record
+    }
+
+    /// Every interaction so far in the order they happened
+    pub fn interactions(&self) -> Vec<EscrowInteraction> {
+        self.log().clone()
+    }
+
+    /// Forget the recorded interactions
+    pub fn clear_interactions(&self) {
+        self.log().clear();
+    }
+
+    fn signature(&self, message: &[u8; 32]) -> Vec<u8> {
+        let mut transcript = merlin::Transcript::new(b"mock escrow agent signature");
+        transcript.append_message(b"key", &self.signing_key);
+        transcript.append_message(b"message", message);
+        let mut signature = vec![0u8; 32];
+        transcript.challenge_bytes(b"signature", &mut signature);
+        signature
+    }
+
+    fn record(&self, interaction: EscrowInteraction) {
+        self.log().push(interaction);
+    }
+
+    fn log(&self) -> MutexGuard<'_, Vec<EscrowInteraction>> {
+        self.interactions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl WarrantAuthority for MockEscrowAgent {
+    fn verify_warrant(&self, message: &[u8; 32], signature: &[u8]) -> bool {
+        let valid = self.signature(message) == signature;
+        self.record(EscrowInteraction::CheckedSignature {
+            message: *message,
+            valid,
+        });
+        valid
+    }
+}
+
+impl CustodySigner for MockEscrowAgent {
+    fn sign(&self, message: &[u8; 32]) -> Vec<u8> {
+        self.record(EscrowInteraction::Signed { message: *message });
+        self.signature(message)
+    }
+}
+
+fn index(len: usize, rng: &mut impl RngCore) -> usize {
+    (rng.next_u64() % len as u64) as usize
+}
+
+fn below(bound: &BigNumber, rng: &mut impl RngCore) -> BigNumber {
+    let bound_bytes = bound.to_bytes();
+    let mask = u8::MAX >> bound_bytes[0].leading_zeros();
+    let mut bytes = vec![0u8; bound_bytes.len()];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        bytes[0] &= mask;
+        let value = BigNumber::from_slice(&bytes);
+        if &value < bound {
+            return value;
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! A `DecryptionKey` split with Shamir sharing over the integers so any
+//! `threshold` of `trustees` can decrypt together while no one holds the key.
+//!
+//! The group order is unknown so shares can't be interpolated mod the order.
+//! With `D = trustees!` every Lagrange coefficient times `D` is an integer, so
+//! each trustee raises `u` to `2 * D * share` and the combiner raises the
+//! partials to `2 * D * lambda_j`, giving `u^(4 * D^2 * x)`. The tag is checked
+//! against `v^(4 * D^2)` and each `e^(4 * D^2) / u^(4 * D^2 * x1)` is
+//! `h^(4 * D^2 * m)` from which `m` is recovered by inverting `4 * D^2` mod n.
+//!
+//! Partials carry no proof they were computed honestly. A wrong partial
+//! makes `combine` fail the tag or decryption check without naming
+//! the trustee, so combine another set of partials
+use crate::{
+    checked_hex::encode_hex, sampling::random_below, Context, DecryptionKey, Group, VerencError,
+    VerifiableCipherText, VerificationKey,
+};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+use zeroize::Zeroize;
+
+/// Statistical hiding of the secrets in the sharing polynomials
+pub(crate) const SLACK_BITS: usize = 128;
+
+/// One trustee's share of a `DecryptionKey`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DecryptionKeyShare {
+    pub(crate) index: usize,
+    pub(crate) threshold: usize,
+    pub(crate) trustees: usize,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) x1: Vec<BigNumber>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) x2: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) x3: BigNumber,
+    pub(crate) group: Group,
+}
+
+impl Zeroize for DecryptionKeyShare {
+    fn zeroize(&mut self) {
+        self.x2.zeroize();
+        self.x3.zeroize();
+        self.x1.iter_mut().for_each(|x| x.zeroize());
+    }
+}
+
+/// A trustee's contribution to decrypting one ciphertext
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PartialDecryption {
+    pub(crate) index: usize,
+    pub(crate) threshold: usize,
+    pub(crate) trustees: usize,
+    pub(crate) ciphertext_hash: [u8; 32],
+    pub(crate) domain: Vec<u8>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) tag: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) values: Vec<BigNumber>,
+}
+
+impl DecryptionKey {
+    /// Split the key into `trustees` shares so any `threshold` of them can
+    /// decrypt with `threshold::combine`. Share `i` is for trustee `i + 1`
+    pub fn split_threshold(
+        &self,
+        threshold: usize,
+        trustees: usize,
+    ) -> Result<Vec<DecryptionKeyShare>, VerencError> {
+        if threshold == 0 || threshold > trustees {
+            return Err(VerencError::Other(format!(
+                "Threshold {} must be between 1 and the number of trustees {}",
+                threshold, trustees
+            )));
+        }
+        let delta = factorial(trustees);
+        let bound = (&delta * &delta * &self.group.n2d4) << SLACK_BITS;
+        let mut secrets = self.x1.clone();
+        secrets.push(self.x2.clone());
+        secrets.push(self.x3.clone());
+        let mut polynomials = secrets
+            .into_iter()
+            .map(|secret| {
+                std::iter::once(secret)
+                    .chain((1..threshold).map(|_| random_below(&bound)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let shares = (1..=trustees)
+            .map(|j| {
+                let x = BigNumber::from(j as u64);
+                let mut values = polynomials
+                    .iter()
+                    .map(|coefficients| evaluate(coefficients, &x))
+                    .collect::<Vec<_>>();
+                let x3 = values.pop().expect("x3 is shared");
+                let x2 = values.pop().expect("x2 is shared");
+                DecryptionKeyShare {
+                    index: j,
+                    threshold,
+                    trustees,
+                    x1: values,
+                    x2,
+                    x3,
+                    group: self.group.clone(),
+                }
+            })
+            .collect();
+        polynomials.iter_mut().flatten().for_each(|a| a.zeroize());
+        Ok(shares)
+    }
+}
+
+impl DecryptionKeyShare {
+    /// The trustee's index, from 1
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The number of shares needed to decrypt
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The number of shares the key was split into
+    pub fn trustees(&self) -> usize {
+        self.trustees
+    }
+
+    /// Compute this trustee's part of decrypting `ciphertext` under `context`
+    pub fn partial_decrypt<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<PartialDecryption, VerencError> {
+        if ciphertext.e.len() > self.x1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                ciphertext.e.len(),
+                self.x1.len()
+            )));
+        }
+        let context = context.into();
+        let group = &self.group;
+        let two_delta = factorial(self.trustees) << 1;
+        let hash = group.hash(&ciphertext.u, &ciphertext.e, context.as_bytes());
+        let tag = group.pow(&ciphertext.u, &(&two_delta * (hash * &self.x3 + &self.x2)));
+        let values = self
+            .x1
+            .iter()
+            .take(ciphertext.e.len())
+            .map(|x| group.pow(&ciphertext.u, &(&two_delta * x)))
+            .collect();
+        Ok(PartialDecryption {
+            index: self.index,
+            threshold: self.threshold,
+            trustees: self.trustees,
+            ciphertext_hash: ciphertext.digest(),
+            domain: context.as_bytes().to_vec(),
+            tag,
+            values,
+        })
+    }
+}
+
+impl PartialDecryption {
+    /// The index of the trustee that created this
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The digest of the ciphertext this is for
+    pub fn ciphertext_hash(&self) -> &[u8; 32] {
+        &self.ciphertext_hash
+    }
+
+    /// The context the tag was computed under
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+}
+
+/// Decrypt `ciphertext` under `context` from the partials of at least
+/// `threshold` distinct trustees. Only the first `threshold` are used
+pub fn combine<'c>(
+    key: &VerificationKey,
+    context: impl Into<Context<'c>>,
+    ciphertext: &VerifiableCipherText,
+    partials: &[PartialDecryption],
+) -> Result<Vec<BigNumber>, VerencError> {
+    let first = partials
+        .first()
+        .ok_or_else(|| "No partial decryptions".to_string())?;
+    let (threshold, trustees) = (first.threshold, first.trustees);
+    if partials.len() < threshold {
+        return Err(VerencError::Other(format!(
+            "Need {} partial decryptions but got {}",
+            threshold,
+            partials.len()
+        )));
+    }
+    let partials = &partials[..threshold];
+    let context = context.into();
+    let digest = ciphertext.digest();
+    for (i, partial) in partials.iter().enumerate() {
+        if partial.threshold != threshold || partial.trustees != trustees {
+            return Err(VerencError::Other(
+                "Partial decryptions are from different sharings".to_string(),
+            ));
+        }
+        if partial.index == 0 || partial.index > trustees {
+            return Err(VerencError::Other(format!(
+                "Trustee {} is out of range",
+                partial.index
+            )));
+        }
+        if partials[..i].iter().any(|p| p.index == partial.index) {
+            return Err(VerencError::Other(format!(
+                "Trustee {} appears twice",
+                partial.index
+            )));
+        }
+        if partial.ciphertext_hash != digest {
+            return Err(VerencError::Other(format!(
+                "Partial decryption of trustee {} is for ciphertext {}",
+                partial.index,
+                encode_hex(&partial.ciphertext_hash)
+            )));
+        }
+        if partial.domain != context.as_bytes() {
+            return Err(VerencError::Other(format!(
+                "Partial decryption of trustee {} is for a different context",
+                partial.index
+            )));
+        }
+        if partial.values.len() != ciphertext.e.len() {
+            return Err(VerencError::Other(format!(
+                "Number of values {} != number of messages {}",
+                partial.values.len(),
+                ciphertext.e.len()
+            )));
+        }
+    }
+
+    let group = &key.group;
+    let delta = factorial(trustees);
+    let indices = partials.iter().map(|p| p.index).collect::<Vec<_>>();
+    let exponents = indices
+        .iter()
+        .map(|j| lagrange(&delta, &indices, *j) << 1)
+        .collect::<Vec<_>>();
+    let interpolate = |values: &mut dyn Iterator<Item = &BigNumber>| {
+        values
+            .zip(exponents.iter())
+            .fold(BigNumber::one(), |acc, (value, exp)| {
+                group.mul(&acc, &group.pow(value, exp))
+            })
+    };
+
+    // u^(4 D^2 (H x3 + x2)) = v^(4 D^2)
+    let v = group.reduced(&ciphertext.v)?;
+    let four_delta_squared = (&delta * &delta) << 2;
+    let tag = interpolate(&mut partials.iter().map(|p| &p.tag));
+    if tag != group.pow(&v, &four_delta_squared) {
+        return Err(VerencError::Other("u^2 != v^2".to_string()));
+    }
+
+    let scale = four_delta_squared
+        .invert(&group.n)
+        .ok_or_else(|| "Number of trustees is too large for this group".to_string())?;
+    ciphertext
+        .e
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            let u_x = interpolate(&mut partials.iter().map(|p| &p.values[i]));
+            let inv = u_x
+                .invert(&group.nn)
+                .ok_or_else(|| "invalid ciphertext".to_string())?;
+            let scaled = group
+                .paillier_extract(&group.mul(&group.pow(e, &four_delta_squared), &inv))
+                .ok_or_else(|| format!("decryption failed for message {}", i))?;
+            Ok(scaled.modmul(&scale, &group.n))
+        })
+        .collect()
+}
+
+/// `D * prod_{k != j} k / (k - j)` over `indices`, an integer since `D = trustees!`
+fn lagrange(delta: &BigNumber, indices: &[usize], j: usize) -> BigNumber {
+    let mut numerator = delta.clone();
+    let mut denominator = BigNumber::one();
+    let mut negative = false;
+    for &k in indices.iter().filter(|k| **k != j) {
+        numerator *= BigNumber::from(k as u64);
+        negative ^= k < j;
+        denominator *= BigNumber::from(k.abs_diff(j) as u64);
+    }
+    let lambda = numerator / denominator;
+    if negative {
+        -lambda
+    } else {
+        lambda
+    }
+}
+
+pub(crate) fn evaluate(coefficients: &[BigNumber], x: &BigNumber) -> BigNumber {
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigNumber::zero(), |acc, a| acc * x + a)
+}
+
+pub(crate) fn factorial(n: usize) -> BigNumber {
+    (2..=n as u64).fold(BigNumber::one(), |acc, i| acc * BigNumber::from(i))
+}
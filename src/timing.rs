@@ -0,0 +1,156 @@
+use crate::{
+    sampling::random_below, DecryptionKey, EncryptionKey, VerifiableCipherText,
+    VerifiableEncryptionProof,
+};
+use rand_core::RngCore;
+use std::time::Instant;
+use unknown_order::BigNumber;
+
+/// |t| above this is strong evidence the two classes take different time,
+/// the threshold used by dudect
+pub const LEAKAGE_THRESHOLD: f64 = 4.5;
+
+/// Statistics of the measured durations for one input class
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ClassStats {
+    /// The number of measurements
+    pub samples: usize,
+    /// Mean duration in nanoseconds
+    pub mean: f64,
+    /// Sample variance in nanoseconds squared
+    pub variance: f64,
+}
+
+impl ClassStats {
+    fn from_durations(durations: &[f64]) -> Self {
+        let samples = durations.len();
+        if samples == 0 {
+            return Self::default();
+        }
+        let mean = durations.iter().sum::<f64>() / samples as f64;
+        let variance = if samples > 1 {
+            durations.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (samples - 1) as f64
+        } else {
+            0.0
+        };
+        Self {
+            samples,
+            mean,
+            variance,
+        }
+    }
+}
+
+/// The result of comparing the run time of fixed and random inputs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimingReport {
+    /// Durations for the fixed input class
+    pub fixed: ClassStats,
+    /// Durations for the random input class
+    pub random: ClassStats,
+    /// Welch's t-statistic between the two classes
+    pub t_statistic: f64,
+}
+
+impl TimingReport {
+    /// True if |t| is above `LEAKAGE_THRESHOLD`
+    pub fn leaks(&self) -> bool {
+        self.t_statistic.abs() > LEAKAGE_THRESHOLD
+    }
+}
+
+/// Time `op` over `samples` runs, choosing the fixed or random class
+/// at random for each run like dudect.
+///
+/// `op` is called with `true` for the fixed class and the index of the run
+/// and should only do the operation being measured. Prepare inputs beforehand.
+/// Measurements above the 95th percentile are dropped to remove interruptions
+pub fn measure<F>(samples: usize, rng: &mut impl RngCore, mut op: F) -> TimingReport
+where
+    F: FnMut(bool, usize),
+{
+    let mut measurements = Vec::with_capacity(samples);
+    for i in 0..samples {
+        let fixed = rng.next_u32() & 1 == 1;
+        let start = Instant::now();
+        op(fixed, i);
+        measurements.push((fixed, start.elapsed().as_nanos() as f64));
+    }
+
+    let mut sorted = measurements.iter().map(|(_, d)| *d).collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let cutoff = sorted
+        .get(samples * 95 / 100)
+        .copied()
+        .unwrap_or(f64::INFINITY);
+    let class = |fixed: bool| {
+        measurements
+            .iter()
+            .filter(|(f, d)| *f == fixed && *d <= cutoff)
+            .map(|(_, d)| *d)
+            .collect::<Vec<_>>()
+    };
+    let fixed = ClassStats::from_durations(&class(true));
+    let random = ClassStats::from_durations(&class(false));
+    let se = (fixed.variance / fixed.samples.max(1) as f64
+        + random.variance / random.samples.max(1) as f64)
+        .sqrt();
+    let t_statistic = if se > 0.0 {
+        (fixed.mean - random.mean) / se
+    } else {
+        0.0
+    };
+    TimingReport {
+        fixed,
+        random,
+        t_statistic,
+    }
+}
+
+/// Compare decrypting a fixed ciphertext of zeros with ciphertexts of random messages
+pub fn evaluate_decrypt(
+    ek: &EncryptionKey,
+    dk: &DecryptionKey,
+    samples: usize,
+    rng: &mut impl RngCore,
+) -> Result<TimingReport, VerencError> {
+    let domain = b"timing evaluation";
+    let (fixed, random) = inputs(ek, samples, |msgs| {
+        ek.encrypt(domain, msgs).map_err(String::from)
+    })?;
+    Ok(measure(samples, rng, |is_fixed, i| {
+        let ct = if is_fixed { &fixed } else { &random[i] };
+        let _ = dk.decrypt(domain, ct);
+    }))
+}
+
+/// Compare verifying a fixed proof for zeros with proofs for random messages
+pub fn evaluate_verify(
+    ek: &EncryptionKey,
+    samples: usize,
+    rng: &mut impl RngCore,
+) -> Result<TimingReport, VerencError> {
+    let nonce = b"timing evaluation";
+    let (fixed, random) = inputs(ek, samples, |msgs| {
+        ek.encrypt_and_prove(nonce, msgs).map_err(String::from)
+    })?;
+    Ok(measure(samples, rng, |is_fixed, i| {
+        let (ct, proof): &(VerifiableCipherText, VerifiableEncryptionProof) =
+            if is_fixed { &fixed } else { &random[i] };
+        let _ = ek.verify(nonce, ct, proof);
+    }))
+}
+
+/// One input for zero messages and one for random messages per sample
+fn inputs<T, F>(ek: &EncryptionKey, samples: usize, make: F) -> Result<(T, Vec<T>), String>
+where
+    F: Fn(&[BigNumber]) -> Result<T, String>,
+{
+    let count = ek.vk.y1.len();
+    let n = &ek.vk.group.n;
+    let fixed = make(&vec![BigNumber::zero(); count])?;
+    let random = (0..samples)
+        .map(|_| make(&(0..count).map(|_| random_below(n)).collect::<Vec<_>>()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((fixed, random))
+}
@@ -0,0 +1,107 @@
+use crate::{
+    EncryptionKey, Label, VerencError, VerifiableCipherText, VerifiableEncryptionProof,
+    VerificationKey,
+};
+#[cfg(not(feature = "verify-only"))]
+use crate::{KeyBoundProof, KeyRing};
+
+/// What a `VerificationPolicy` is shown about a proof before it's checked
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerificationRequest<'a> {
+    key_fingerprint: [u8; 32],
+    nonce: &'a [u8],
+    num_messages: usize,
+}
+
+impl<'a> VerificationRequest<'a> {
+    /// The `VerificationKey::fingerprint` of the key verifying the proof
+    pub fn key_fingerprint(&self) -> &[u8; 32] {
+        &self.key_fingerprint
+    }
+
+    /// The nonce the proof is verified with
+    pub fn nonce(&self) -> &'a [u8] {
+        self.nonce
+    }
+
+    /// The nonce decoded as a `Label` if it is one
+    pub fn label(&self) -> Option<Label> {
+        Label::from_bytes(self.nonce).ok()
+    }
+
+    /// The number of messages in the ciphertext
+    pub fn num_messages(&self) -> usize {
+        self.num_messages
+    }
+}
+
+/// Decides whether a proof may be verified before any exponentiation runs,
+/// e.g. to enforce each tenant's allowed keys, labels and message counts
+/// in a shared verifier.
+///
+/// Implemented for closures taking a `VerificationRequest`
+pub trait VerificationPolicy {
+    /// Allow the request or return why it's denied
+    fn check(&self, request: &VerificationRequest<'_>) -> Result<(), String>;
+}
+
+impl<F> VerificationPolicy for F
+where
+    F: Fn(&VerificationRequest<'_>) -> Result<(), String>,
+{
+    fn check(&self, request: &VerificationRequest<'_>) -> Result<(), String> {
+        self(request)
+    }
+}
+
+impl VerificationKey {
+    /// Verify a proof like `verify` if `policy` allows it
+    pub fn verify_with_policy<P: VerificationPolicy + ?Sized>(
+        &self,
+        policy: &P,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        policy.check(&VerificationRequest {
+            key_fingerprint: self.fingerprint(),
+            nonce,
+            num_messages: ciphertext.e.len(),
+        })?;
+        self.verify(nonce, ciphertext, proof)
+    }
+}
+
+impl EncryptionKey {
+    /// Verify a proof like `verify` if `policy` allows it
+    pub fn verify_with_policy<P: VerificationPolicy + ?Sized>(
+        &self,
+        policy: &P,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.vk.verify_with_policy(policy, nonce, ciphertext, proof)
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl KeyRing {
+    /// Verify a proof like `verify_any` if `policy` allows it
+    /// for the key the proof names
+    pub fn verify_any_with_policy<P: VerificationPolicy + ?Sized>(
+        &self,
+        policy: &P,
+        at: u64,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &KeyBoundProof,
+    ) -> Result<(), VerencError> {
+        policy.check(&VerificationRequest {
+            key_fingerprint: *proof.key_fingerprint(),
+            nonce,
+            num_messages: ciphertext.e.len(),
+        })?;
+        self.verify_any(at, nonce, ciphertext, proof)
+    }
+}
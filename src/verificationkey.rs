@@ -0,0 +1,1664 @@
+use crate::{
+    archive::{put_bignum, put_bignums, Cursor},
+    budget::charge,
+    checked_hex::{from_checked_hex, to_checked_hex},
+    BatchItemError, Cancellation, Context, EncryptionKey, EncryptionKeyRef, Group, GroupRef, Label,
+    Lifecycle, ProofStatement, SlotSchema, StatementEquation, StatementTerm, VerencError,
+    VerifiableCipherText, VerifiableEncryptionProof, VerificationTrace,
+};
+#[cfg(not(feature = "verify-only"))]
+use crate::{
+    escrow::ESCROW_BLINDING_SLACK,
+    group::{bit_length, ct_eq},
+    proof_derivation::{
+        derivation_bases, derivation_challenge, derivation_tweak, derive_key, derived_values,
+        DERIVATION_BLINDING_SLACK, TWEAK_BITS,
+    },
+    proof_disclosure::{merkle_challenge, response_leaf},
+    proof_element::{element_chunk_count, element_chunk_width, join_chunks},
+    proof_key_generation::KEY_GENERATION_BLINDING_SLACK,
+    proof_packed::{append_bits, bit_test_values, challenge_modulus, BitProof},
+    BatchDecryptionRecord, CommitmentLinkProof, CommittedEncryptionProof, DecryptionKey,
+    DecryptionRecord, DerivationProof, ElementEscrowProof, KeyGenerationProof, LabelOrProof,
+    LessThanProof, PackedRangeProof, PublicKeyGroup, ReencryptionProof, RerandomizationProof,
+    ResponseDisclosure, SecretKeyEscrowProof, Warrant, WarrantAuthority,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use unknown_order::BigNumber;
+
+const CHECKED_HEX_TAG: &str = "veverificationkey";
+
+/// Key for verifying `VerifiableEncryptionProof`s.
+/// Holds only the public values relying parties need,
+/// provers should use `EncryptionKey` instead.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VerificationKey {
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) y1: Vec<BigNumber>,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) y2: BigNumber,
+    #[serde(deserialize_with = "crate::budget::bignum")]
+    pub(crate) y3: BigNumber,
+    pub(crate) group: Group,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) lifecycle: Option<Lifecycle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) schema: Option<SlotSchema>,
+}
+
+impl Display for VerificationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "VerificationKey {{ y1: [{}], y2: {}, y3: {}, group: {} }}",
+            self.y1
+                .iter()
+                .map(|y| format!("{}", y))
+                .collect::<Vec<String>>()
+                .join(", "),
+            self.y2,
+            self.y3,
+            self.group,
+        )
+    }
+}
+
+impl From<&EncryptionKey> for VerificationKey {
+    fn from(ek: &EncryptionKey) -> Self {
+        ek.verification_key().clone()
+    }
+}
+
+impl From<EncryptionKey> for VerificationKey {
+    fn from(ek: EncryptionKey) -> Self {
+        ek.vk
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl From<&DecryptionKey> for VerificationKey {
+    fn from(dk: &DecryptionKey) -> Self {
+        let y1 = dk.x1.iter().map(|x| dk.group.g_pow(x)).collect();
+        let y2 = dk.group.g_pow(&dk.x2);
+        let y3 = dk.group.g_pow(&dk.x3);
+        Self {
+            y1,
+            y2,
+            y3,
+            group: dk.group.clone(),
+            lifecycle: None,
+            schema: None,
+        }
+    }
+}
+
+impl VerificationKey {
+    /// The group used by this key
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    /// The maximum number of messages proofs can be verified for with this key
+    pub fn max_messages(&self) -> usize {
+        self.y1.len()
+    }
+
+    /// The per message public values
+    pub fn y1(&self) -> &[BigNumber] {
+        &self.y1
+    }
+
+    /// The public value for the tag
+    pub fn y2(&self) -> &BigNumber {
+        &self.y2
+    }
+
+    /// The public value for the tag hash
+    pub fn y3(&self) -> &BigNumber {
+        &self.y3
+    }
+
+    /// This key with `lifecycle` attached
+    pub fn with_lifecycle(mut self, lifecycle: Lifecycle) -> Self {
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// The key's lifecycle metadata if any is attached, see `Group::lifecycle` for its group's
+    pub fn lifecycle(&self) -> Option<&Lifecycle> {
+        self.lifecycle.as_ref()
+    }
+
+    /// This key with `schema` attached.
+    /// It can't describe more slots than the key has
+    pub fn with_schema(mut self, schema: SlotSchema) -> Result<Self, VerencError> {
+        if schema.len() > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Schema has {} slots but the key only {}",
+                schema.len(),
+                self.y1.len()
+            )));
+        }
+        self.schema = Some(schema);
+        Ok(self)
+    }
+
+    /// What the key's slots hold if a schema is attached
+    pub fn schema(&self) -> Option<&SlotSchema> {
+        self.schema.as_ref()
+    }
+
+    /// Borrowed view of this key
+    pub fn view(&self) -> EncryptionKeyRef<'_> {
+        EncryptionKeyRef::new(&self.y1, &self.y2, &self.y3, GroupRef::from(&self.group))
+    }
+
+    /// A key restricted to the `y1` slots at `indices` in increasing order.
+    /// Slot `j` of the projection is slot `indices[j]` of this key so
+    /// proofs created with the projected key are verified with it and
+    /// ciphertexts are decrypted with `DecryptionKey::decrypt_at_indices(indices)`
+    pub fn project(&self, indices: &[usize]) -> Result<VerificationKey, VerencError> {
+        if indices.is_empty() {
+            return Err(VerencError::Other("No indices to project".to_string()));
+        }
+        self.check_slots(indices)?;
+        Ok(Self {
+            y1: indices.iter().map(|i| self.y1[*i].clone()).collect(),
+            y2: self.y2.clone(),
+            y3: self.y3.clone(),
+            group: self.group.clone(),
+            lifecycle: self.lifecycle.clone(),
+            schema: self.schema.as_ref().and_then(|s| s.project(indices)),
+        })
+    }
+
+    /// Verify a proof of verifiable encryption
+    /// See section 6.2.19 in
+    /// <https://dominoweb.draco.res.ibm.com/reports/rz3730_revised.pdf>
+    pub fn verify<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.view().verify(context, ciphertext, proof)
+    }
+
+    /// Verify a proof like `verify` until `cancellation` is cancelled,
+    /// see `EncryptionKeyRef::verify_cancellable`
+    pub fn verify_cancellable<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        cancellation: &dyn Cancellation,
+    ) -> Result<(), VerencError> {
+        self.view()
+            .verify_cancellable(context, ciphertext, proof, cancellation)
+    }
+
+    /// Verify a proof returning every recomputed value and transcript
+    /// message, see `EncryptionKeyRef::verify_explain`
+    pub fn verify_explain<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> VerificationTrace {
+        self.view().verify_explain(context, ciphertext, proof)
+    }
+
+    /// Verify each of many proofs, see `EncryptionKeyRef::verify_all`
+    pub fn verify_all(
+        &self,
+        items: &[(&[u8], &VerifiableCipherText, &VerifiableEncryptionProof)],
+    ) -> Result<(), Vec<BatchItemError>> {
+        self.view().verify_all(items)
+    }
+
+    /// Verify a proof made with `label.to_bytes()` as the nonce,
+    /// first checking the label has not expired at time `now` in seconds
+    pub fn verify_with_label(
+        &self,
+        label: &Label,
+        now: u64,
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        label.check_fresh(now)?;
+        self.verify(label, ciphertext, proof)
+    }
+
+    /// Verify a proof of verifiable encryption into the key slots at `indices`
+    pub fn verify_at_indices<'c>(
+        &self,
+        context: impl Into<Context<'c>>,
+        indices: &[usize],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.view()
+            .verify_at_indices(context, indices, ciphertext, proof)
+    }
+
+    pub(crate) fn check_slots(&self, slots: &[usize]) -> Result<(), VerencError> {
+        self.view().check_slots(slots)
+    }
+
+    /// Export the instance `verify` checks for `proof`
+    pub fn statement(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<ProofStatement, VerencError> {
+        if proof.m.len() > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                proof.m.len(),
+                self.y1.len()
+            )));
+        }
+        if proof.m.len() != ciphertext.e.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is equal to ciphertext {}",
+                proof.m.len(),
+                ciphertext.e.len()
+            )));
+        }
+        let group = &self.group;
+        let hash = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let two_c: BigNumber = &proof.challenge << 1;
+        let two_r: BigNumber = &proof.r << 1;
+
+        let term =
+            |base_name: String, base: &BigNumber, exponent_name: &str, exponent: &BigNumber| {
+                StatementTerm {
+                    base_name,
+                    base: base.clone(),
+                    exponent_name: exponent_name.to_string(),
+                    exponent: exponent.clone(),
+                }
+            };
+
+        let mut equations = Vec::with_capacity(ciphertext.e.len() + 2);
+        equations.push(StatementEquation {
+            name: "ciphertext_test.u".to_string(),
+            terms: vec![
+                term("ciphertext.u".to_string(), &ciphertext.u, "2c", &two_c),
+                term("g".to_string(), &group.g, "2r", &two_r),
+            ],
+        });
+        for (i, ((e, y1), m)) in ciphertext
+            .e
+            .iter()
+            .zip(self.y1.iter())
+            .zip(proof.m.iter())
+            .enumerate()
+        {
+            let two_m: BigNumber = m << 1;
+            equations.push(StatementEquation {
+                name: "ciphertext_test.e".to_string(),
+                terms: vec![
+                    term(format!("ciphertext.e[{}]", i), e, "2c", &two_c),
+                    term(format!("y1[{}]", i), y1, "2r", &two_r),
+                    term("h".to_string(), &group.h, &format!("2m[{}]", i), &two_m),
+                ],
+            });
+        }
+        let two_r_hash = &two_r * &hash;
+        equations.push(StatementEquation {
+            name: "ciphertext_test.v".to_string(),
+            terms: vec![
+                term("ciphertext.v".to_string(), &ciphertext.v, "2c", &two_c),
+                term("y2".to_string(), &self.y2, "2r", &two_r),
+                term("y3".to_string(), &self.y3, "2rH", &two_r_hash),
+            ],
+        });
+
+        Ok(ProofStatement {
+            modulus: group.nn.clone(),
+            nonce: nonce.to_vec(),
+            hash,
+            challenge: proof.challenge.clone(),
+            equations,
+        })
+    }
+
+    /// Encode as hex with a type tag and checksum like `VerifiableCipherText::to_checked_hex`.
+    /// Decoding it needs no serde format, which keeps small verifier builds small
+    pub fn to_checked_hex(&self) -> String {
+        to_checked_hex(CHECKED_HEX_TAG, &self.to_payload())
+    }
+
+    /// Decode the output of `to_checked_hex`
+    pub fn from_checked_hex(s: &str) -> Result<Self, VerencError> {
+        Ok(Self::from_payload(&from_checked_hex(CHECKED_HEX_TAG, s)?)?)
+    }
+
+    /// The modulus, generator and key values as length-prefixed numbers
+    pub(crate) fn to_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        put_bignum(&mut payload, &self.group.n);
+        put_bignum(&mut payload, &self.group.g);
+        put_bignums(&mut payload, &self.y1);
+        put_bignum(&mut payload, &self.y2);
+        put_bignum(&mut payload, &self.y3);
+        payload
+    }
+
+    pub(crate) fn from_payload(payload: &[u8]) -> Result<Self, String> {
+        charge(payload.len())?;
+        let mut cursor = Cursor::new(payload);
+        let n = cursor.bignum()?;
+        let g = cursor.bignum()?;
+        let y1 = cursor.bignums()?;
+        let y2 = cursor.bignum()?;
+        let y3 = cursor.bignum()?;
+        if !cursor.is_finished() {
+            return Err("Verification key has trailing bytes".to_string());
+        }
+        let group = Group::from_parts(g, n).ok_or_else(|| "Invalid group".to_string())?;
+        Ok(Self {
+            y1,
+            y2,
+            y3,
+            group,
+            lifecycle: None,
+            schema: None,
+        })
+    }
+
+    /// A 32 byte digest identifying this key and its group
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.view().fingerprint()
+    }
+
+    pub(crate) fn challenge(transcript: &mut merlin::Transcript) -> BigNumber {
+        let mut challenge_bytes = [0u8; 32];
+        transcript.challenge_bytes(
+            b"verifiable encryption proof challenge",
+            &mut challenge_bytes,
+        );
+        BigNumber::from_slice(challenge_bytes)
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl VerificationKey {
+    /// The key derived from this master key for `label` at the slots
+    /// `indices`, as returned by `EncryptionKey::derive`.
+    /// Anyone with this key and the label can recompute it
+    pub fn derive(&self, label: &[u8], indices: &[usize]) -> Result<VerificationKey, VerencError> {
+        if indices.is_empty() {
+            return Err(VerencError::Other("No indices to derive".to_string()));
+        }
+        self.check_slots(indices)?;
+        derive_key(self, indices, &derivation_tweak(self, label, indices))
+            .map_err(VerencError::from)
+    }
+
+    /// Verify a proof created by `EncryptionKey::derive` that `derived`
+    /// is derived from this key
+    pub fn verify_derivation(
+        &self,
+        derived: &VerificationKey,
+        proof: &DerivationProof,
+    ) -> Result<(), VerencError> {
+        let indices = proof.indices.as_slice();
+        if indices.is_empty()
+            || derived.y1.len() != indices.len()
+            || derived.group.n != self.group.n
+            || self.check_slots(indices).is_err()
+        {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let bound = BigNumber::one() << (TWEAK_BITS + DERIVATION_BLINDING_SLACK);
+        if proof.response >= bound || -proof.response.clone() >= bound {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let group = &self.group;
+        let two_c = &proof.challenge << 1;
+        let two_s = &proof.response << 1;
+        // derived^{2c} * master^{2s} for each value
+        let commitments = derivation_bases(self, indices)
+            .zip(derived_values(derived))
+            .map(|(base, value)| group.multi_pow(&[(value, &two_c), (base, &two_s)]))
+            .collect::<Vec<_>>();
+        if ct_eq(
+            &derivation_challenge(self, derived, indices, &commitments),
+            &proof.challenge,
+        ) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof that `rerandomized` is a re-randomization of `original`
+    pub fn verify_rerandomization(
+        &self,
+        original: &VerifiableCipherText,
+        rerandomized: &VerifiableCipherText,
+        proof: &RerandomizationProof,
+    ) -> Result<(), VerencError> {
+        self.check_rerandomized(original, rerandomized)?;
+        let group = &self.group;
+        let invalid = || VerencError::InvalidProof;
+        let two_c: BigNumber = &proof.challenge << 1;
+        let two_r: BigNumber = &proof.r << 1;
+
+        // (u'/u)^{2c} * g^{2r}
+        let u_inv = original.u.invert(&group.nn).ok_or_else(invalid)?;
+        let mut commitments = Vec::with_capacity(original.e.len() + 1);
+        commitments.push(group.mul(
+            &group.pow(&group.mul(&rerandomized.u, &u_inv), &two_c),
+            &group.g_pow(&two_r),
+        ));
+        // (e_i'/e_i)^{2c} * y1_i^{2r}
+        for ((e, e_tick), y1) in original
+            .e
+            .iter()
+            .zip(rerandomized.e.iter())
+            .zip(self.y1.iter())
+        {
+            let e_inv = e.invert(&group.nn).ok_or_else(invalid)?;
+            commitments.push(group.mul(
+                &group.pow(&group.mul(e_tick, &e_inv), &two_c),
+                &group.pow(y1, &two_r),
+            ));
+        }
+        let challenge = self.rerandomization_challenge(original, rerandomized, &commitments);
+        if ct_eq(&challenge, &proof.challenge) {
+            Ok(())
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// Verify a proof created by `DecryptionKey::prove_key_generation`
+    pub fn verify_key_generation(
+        &self,
+        nonce: &[u8],
+        proof: &KeyGenerationProof,
+    ) -> Result<(), VerencError> {
+        let group = &self.group;
+        // |x_hat| is below n^2 / 4 * 2^slack when x < n^2 / 4
+        let bound = &group.n2d4 << KEY_GENERATION_BLINDING_SLACK;
+        let mut commitments = Vec::with_capacity(2);
+        for (y, x_hat) in [(&self.y2, &proof.x2), (&self.y3, &proof.x3)] {
+            if x_hat >= &bound || -x_hat.clone() >= bound {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+            // y^{2c} * g^{2x_hat}
+            commitments.push(group.mul(
+                &group.pow(y, &(&proof.challenge << 1)),
+                &group.g_pow(&(x_hat << 1)),
+            ));
+        }
+        if ct_eq(
+            &self.key_generation_challenge(nonce, &commitments),
+            &proof.challenge,
+        ) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify the responses disclosed from a `ResponseTree` created by
+    /// `EncryptionKey::encrypt_and_prove_merkle`.
+    /// Only the messages at `ResponseDisclosure::indices` are checked
+    pub fn verify_disclosure(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        disclosure: &ResponseDisclosure,
+    ) -> Result<(), VerencError> {
+        let count = ciphertext.e.len();
+        if count > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                count,
+                self.y1.len()
+            )));
+        }
+        let view = self.view();
+        let group = &self.group;
+        let two_c = &disclosure.challenge << 1;
+        let two_r = &disclosure.r << 1;
+        for (i, response) in disclosure.responses.iter().enumerate() {
+            let index = response.inclusion.index();
+            if index >= count
+                || response.inclusion.leaf_count() != count
+                || disclosure.responses[..i]
+                    .iter()
+                    .any(|r| r.inclusion.index() == index)
+            {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+            let e = view.test_e(&ciphertext.e[index], index, &two_c, &two_r, &response.m);
+            if !response
+                .inclusion
+                .verify(&disclosure.root, &response_leaf(index, &e))
+            {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+        }
+        let u = view.test_u(&ciphertext.u, &two_c, &two_r);
+        let hs = group.hash(&ciphertext.u, ciphertext.e.as_slice(), nonce);
+        let v = view.test_v(&ciphertext.v, &two_c, &two_r, &hs);
+        let challenge = merkle_challenge(&view, nonce, ciphertext, &u, &disclosure.root, &v);
+        if ct_eq(&challenge, &disclosure.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `DecryptionKey::reencrypt` that `new_ciphertext`
+    /// under `new_key` holds the same messages as `ciphertext` under this key
+    pub fn verify_reencryption(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        new_key: &VerificationKey,
+        new_domain: &[u8],
+        new_ciphertext: &VerifiableCipherText,
+        proof: &ReencryptionProof,
+    ) -> Result<(), VerencError> {
+        let count = ciphertext.e.len();
+        if new_ciphertext.e.len() != count || proof.x.len() != count || proof.m.len() != count {
+            return Err(VerencError::Other(format!(
+                "Number of messages and responses != number of messages {}",
+                count
+            )));
+        }
+        if count > self.y1.len() || count > new_key.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by the keys",
+                count
+            )));
+        }
+        if new_key.group.n != self.group.n || new_key.group.g != self.group.g {
+            return Err(VerencError::Other(
+                "Both keys must use the same group".to_string(),
+            ));
+        }
+        let group = &self.group;
+        let bound = &group.n2d4 << KEY_GENERATION_BLINDING_SLACK;
+        for x in &proof.x {
+            if x >= &bound || -x.clone() >= bound {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+        }
+        let two_c: BigNumber = &proof.challenge << 1;
+        // The commitments with the responses are the commitments to the blindings
+        // times each public value to the power 2c
+        let mut commitments = self.reencryption_commitments(
+            ciphertext,
+            new_key,
+            new_domain,
+            new_ciphertext,
+            &proof.x,
+            &proof.r,
+            &proof.m,
+        );
+        let publics = self
+            .y1
+            .iter()
+            .take(count)
+            .chain(ciphertext.e.iter())
+            .chain(std::iter::once(&new_ciphertext.u))
+            .chain(new_ciphertext.e.iter())
+            .chain(std::iter::once(&new_ciphertext.v));
+        for (commitment, public) in commitments.iter_mut().zip(publics) {
+            *commitment = group.mul(commitment, &group.pow(public, &two_c));
+        }
+        let challenge = self.reencryption_challenge(
+            domain,
+            ciphertext,
+            new_key,
+            new_domain,
+            new_ciphertext,
+            &commitments,
+        );
+        if ct_eq(&challenge, &proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `EncryptionKey::encrypt_and_prove_packed`
+    /// that `ciphertext` encrypts `count` values of `bits_per_value` bits each
+    pub fn verify_packed(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        range_proof: &PackedRangeProof,
+        count: usize,
+        bits_per_value: usize,
+    ) -> Result<(), VerencError> {
+        if ciphertext.e.len() != 1 {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} != 1 packed message",
+                ciphertext.e.len()
+            )));
+        }
+        self.verify(nonce, ciphertext, proof)?;
+        let width = self.check_packed_width(count, bits_per_value)?;
+        if range_proof.bits.len() != width {
+            return Err(VerencError::Other(format!(
+                "Number of bit proofs {} != packed width {}",
+                range_proof.bits.len(),
+                width
+            )));
+        }
+        let test_values = bit_test_values(
+            &self.group,
+            &self.y1[0],
+            &ciphertext.e[0],
+            &range_proof.bits,
+            &range_proof.challenge,
+        )?;
+        let challenge = self.packed_challenge(nonce, ciphertext, &range_proof.bits, &test_values);
+        if ct_eq(&challenge, &range_proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `EncryptionKey::encrypt_and_prove_less_than`
+    /// that `ciphertext` encrypts a message less than `bound`
+    pub fn verify_less_than(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+        less_than_proof: &LessThanProof,
+        bound: &BigNumber,
+    ) -> Result<(), VerencError> {
+        if ciphertext.e.len() != 1 {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} != 1 bounded message",
+                ciphertext.e.len()
+            )));
+        }
+        self.verify(nonce, ciphertext, proof)?;
+        let width = self.check_less_than_bound(bound)?;
+        if less_than_proof.lower.len() != width || less_than_proof.upper.len() != width {
+            return Err(VerencError::Other(format!(
+                "Number of bit proofs {} and {} != bound width {}",
+                less_than_proof.lower.len(),
+                less_than_proof.upper.len(),
+                width
+            )));
+        }
+        let group = &self.group;
+        let y1 = &self.y1[0];
+        // e * h^{2^w - B} encrypts m + 2^w - B
+        let shift = (BigNumber::one() << width) - bound;
+        let shifted = group.mul(&ciphertext.e[0], &group.h_pow(&shift));
+        let lower = bit_test_values(
+            group,
+            y1,
+            &ciphertext.e[0],
+            &less_than_proof.lower,
+            &less_than_proof.challenge,
+        )?;
+        let upper = bit_test_values(
+            group,
+            y1,
+            &shifted,
+            &less_than_proof.upper,
+            &less_than_proof.challenge,
+        )?;
+        let challenge = self.less_than_challenge(
+            nonce,
+            ciphertext,
+            bound,
+            (&less_than_proof.lower, &lower),
+            (&less_than_proof.upper, &upper),
+        );
+        if ct_eq(&challenge, &less_than_proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `EncryptionKey::encrypt_and_prove_label_or`
+    /// that `ciphertext` is a valid verifiable encryption under one of `labels`
+    pub fn verify_label_or(
+        &self,
+        labels: &[&[u8]],
+        ciphertext: &VerifiableCipherText,
+        proof: &LabelOrProof,
+    ) -> Result<(), VerencError> {
+        if proof.branches.len() != labels.len() {
+            return Err(VerencError::Other(format!(
+                "Number of branches {} != number of labels {}",
+                proof.branches.len(),
+                labels.len()
+            )));
+        }
+        if ciphertext.e.len() > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                ciphertext.e.len(),
+                self.y1.len()
+            )));
+        }
+        let modulus = challenge_modulus();
+        let view = self.view();
+        let slots = (0..ciphertext.e.len()).collect::<Vec<usize>>();
+        let mut test_values = Vec::with_capacity(labels.len());
+        let mut sum = BigNumber::zero();
+        for (label, branch) in labels.iter().zip(proof.branches.iter()) {
+            if branch.m.len() != ciphertext.e.len() {
+                return Err(VerencError::Other(format!(
+                    "Number of messages {} is not equal to ciphertext {}",
+                    branch.m.len(),
+                    ciphertext.e.len()
+                )));
+            }
+            if branch.challenge < BigNumber::zero() || branch.challenge >= modulus {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+            view.check_challenge_and_r(&branch.challenge, &branch.r)?;
+            for m in &branch.m {
+                view.check_response(m)?;
+            }
+            sum += &branch.challenge;
+            test_values.push(view.test_values_from_responses(label, &slots, ciphertext, branch));
+        }
+        let challenge = self.label_or_challenge(labels, ciphertext, &test_values);
+        if ct_eq(&(sum % &modulus), &challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `EncryptionKey::escrow_secret_key`
+    /// that `ciphertext` encrypts the secret key of `public_key`
+    pub fn verify_escrowed_secret_key<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        public_key: &P::PublicKey,
+        proof: &SecretKeyEscrowProof,
+    ) -> Result<(), VerencError> {
+        let order = self.check_escrow_order::<P>()?;
+        let proof = &proof.proof;
+        if ciphertext.e.len() != 1 || proof.m.len() != 1 {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} != 1 secret key",
+                ciphertext.e.len()
+            )));
+        }
+        // |m_hat| is below order * 2^slack when the secret is less than the order
+        let bound = &order << ESCROW_BLINDING_SLACK;
+        let m_hat = &proof.m[0];
+        if m_hat >= &bound || -m_hat.clone() >= bound {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let test_values = self
+            .view()
+            .test_values_from_responses(nonce, &[0], ciphertext, proof);
+        // m_hat * G + c * P = m_tilde * G
+        let commitment = P::mul_add(
+            &m_hat.modsub(&BigNumber::zero(), &order),
+            &(&proof.challenge % &order),
+            public_key,
+        );
+        let challenge =
+            self.escrow_challenge::<P>(nonce, ciphertext, public_key, &test_values, &commitment);
+        if ct_eq(&challenge, &proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `EncryptionKey::encrypt_and_prove_element`
+    /// that `ciphertext` encrypts the chunks of an element less than `modulus`
+    /// committed to in `proof`
+    pub fn verify_element(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        modulus: &BigNumber,
+        proof: &ElementEscrowProof,
+    ) -> Result<(), VerencError> {
+        let (width, count) = self.check_element_modulus(modulus)?;
+        let commitment = &proof.commitment;
+        let proof = &proof.proof;
+        if ciphertext.e.len() != count || proof.m.len() != count {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} != {} element chunks",
+                ciphertext.e.len(),
+                count
+            )));
+        }
+        // |m_hat| is below 2^{w + slack} when the chunk is less than 2^w
+        let bound = BigNumber::one() << (width + ESCROW_BLINDING_SLACK);
+        for m_hat in &proof.m {
+            if m_hat >= &bound || -m_hat.clone() >= bound {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+        }
+        let group = &self.group;
+        if commitment <= &BigNumber::zero() || commitment >= &group.nn {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let slots = (0..count).collect::<Vec<usize>>();
+        let test_values = self
+            .view()
+            .test_values_from_responses(nonce, &slots, ciphertext, proof);
+        // D^c * g^{sum m_hat_j 2^{w*j}} = g^{sum m_tilde_j 2^{w*j}}
+        let test_commitment = group.multi_pow(&[
+            (commitment, &proof.challenge),
+            (&group.g, &join_chunks(&proof.m, width)),
+        ]);
+        let challenge = self.element_challenge(
+            nonce,
+            ciphertext,
+            modulus,
+            commitment,
+            &test_values,
+            &test_commitment,
+        );
+        if ct_eq(&challenge, &proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// The commitment g^X an `ElementEscrowProof` for `element` holds,
+    /// to check a candidate element is the one that was escrowed
+    pub fn element_commitment(&self, element: &BigNumber) -> BigNumber {
+        self.group.g_pow(element)
+    }
+
+    /// Verify a proof created by `EncryptionKey::encrypt_and_prove_committed`
+    pub fn verify_committed<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        h: &P::PublicKey,
+        proof: &CommittedEncryptionProof<P::PublicKey>,
+    ) -> Result<(), VerencError> {
+        let order = self.check_escrow_order::<P>()?;
+        let count = ciphertext.e.len();
+        if proof.proof.m.len() != count
+            || proof.commitments.len() != count
+            || proof.s.len() != count
+        {
+            return Err(VerencError::Other(format!(
+                "Number of responses and commitments != number of messages {}",
+                count
+            )));
+        }
+        if count > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                count,
+                self.y1.len()
+            )));
+        }
+        // |m_hat| is below order * 2^slack when the message is less than the order
+        let bound = &order << ESCROW_BLINDING_SLACK;
+        for m_hat in &proof.proof.m {
+            if m_hat >= &bound || -m_hat.clone() >= bound {
+                return Err(VerencError::Other("Invalid proof".to_string()));
+            }
+        }
+        let slots = (0..count).collect::<Vec<usize>>();
+        let test_values =
+            self.view()
+                .test_values_from_responses(nonce, &slots, ciphertext, &proof.proof);
+        // m_hat * G + s_hat * H + c * C = m_tilde * G + s_tilde * H
+        let zero = BigNumber::zero();
+        let c = &proof.proof.challenge % &order;
+        let test_commitments = proof
+            .proof
+            .m
+            .iter()
+            .zip(proof.s.iter())
+            .zip(proof.commitments.iter())
+            .map(|((m_hat, s_hat), commitment)| {
+                P::add(
+                    &P::mul_add(&m_hat.modsub(&zero, &order), &(s_hat % &order), h),
+                    &P::mul_add(&zero, &c, commitment),
+                )
+            })
+            .collect::<Vec<_>>();
+        let challenge = self.committed_challenge::<P>(
+            nonce,
+            ciphertext,
+            h,
+            &proof.commitments,
+            &test_values,
+            &test_commitments,
+        );
+        if ct_eq(&challenge, &proof.proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a proof created by `EncryptionKey::encrypt_and_prove_linked`
+    /// that `ciphertext` is a valid verifiable encryption whose message at
+    /// `index` is the value in `commitment`
+    pub fn verify_linked<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        index: usize,
+        h: &P::PublicKey,
+        commitment: &P::PublicKey,
+        proof: &CommitmentLinkProof,
+    ) -> Result<(), VerencError> {
+        let count = ciphertext.e.len();
+        let order = self.check_linked_slot::<P>(count, index)?;
+        let s_hat = &proof.s;
+        let proof = &proof.proof;
+        if proof.m.len() != count {
+            return Err(VerencError::Other(format!(
+                "Number of responses {} != number of messages {}",
+                proof.m.len(),
+                count
+            )));
+        }
+        // |m_hat| is below order * 2^slack when the message is less than the order
+        let bound = &order << ESCROW_BLINDING_SLACK;
+        let m_hat = &proof.m[index];
+        if m_hat >= &bound || -m_hat.clone() >= bound {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let slots = (0..count).collect::<Vec<usize>>();
+        let test_values = self
+            .view()
+            .test_values_from_responses(nonce, &slots, ciphertext, proof);
+        // m_hat * G + s_hat * H + c * C = m_tilde * G + s_tilde * H
+        let zero = BigNumber::zero();
+        let test_commitment = P::add(
+            &P::mul_add(&m_hat.modsub(&zero, &order), &(s_hat % &order), h),
+            &P::mul_add(&zero, &(&proof.challenge % &order), commitment),
+        );
+        let challenge = self.linked_challenge::<P>(
+            nonce,
+            ciphertext,
+            index,
+            h,
+            commitment,
+            &test_values,
+            &test_commitment,
+        );
+        if ct_eq(&challenge, &proof.challenge) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// The order of `P`, checking secret keys fit in a message
+    pub(crate) fn check_escrow_order<P: PublicKeyGroup>(&self) -> Result<BigNumber, String> {
+        if self.y1.is_empty() {
+            return Err("Key does not support any messages".to_string());
+        }
+        let order = P::order();
+        if order <= BigNumber::one() || order >= self.group.n {
+            return Err("public key group order is too large for the group".to_string());
+        }
+        Ok(order)
+    }
+
+    /// The chunk width and count for elements less than `modulus`,
+    /// checking the key has a slot for every chunk
+    pub(crate) fn check_element_modulus(
+        &self,
+        modulus: &BigNumber,
+    ) -> Result<(usize, usize), String> {
+        if modulus <= &BigNumber::one() {
+            return Err("modulus must be more than one".to_string());
+        }
+        let width = element_chunk_width(&self.group.n)?;
+        let count = element_chunk_count(modulus, width);
+        if count > self.y1.len() {
+            return Err(format!(
+                "Element chunks {} is more than supported by this key {}",
+                count,
+                self.y1.len()
+            ));
+        }
+        Ok((width, count))
+    }
+
+    pub(crate) fn element_challenge(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        modulus: &BigNumber,
+        commitment: &BigNumber,
+        test_values: &VerifiableCipherText,
+        test_commitment: &BigNumber,
+    ) -> BigNumber {
+        let mut transcript = self.view().proof_transcript(nonce, ciphertext);
+        transcript.append_message(b"element", b"camenisch-shoup element escrow proof");
+        transcript.append_message(b"modulus", &modulus.to_bytes());
+        transcript.append_message(b"commitment", &commitment.to_bytes());
+        transcript.append_message(b"ciphertext_test.u", &test_values.u.to_bytes());
+        for e in &test_values.e {
+            transcript.append_message(b"ciphertext_test.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext_test.v", &test_values.v.to_bytes());
+        transcript.append_message(b"commitment_test", &test_commitment.to_bytes());
+        Self::challenge(&mut transcript)
+    }
+
+    /// The order of `P`, checking `count` messages fit the key
+    /// and `index` is one of them
+    pub(crate) fn check_linked_slot<P: PublicKeyGroup>(
+        &self,
+        count: usize,
+        index: usize,
+    ) -> Result<BigNumber, String> {
+        let order = self.check_escrow_order::<P>()?;
+        if count > self.y1.len() {
+            return Err(format!(
+                "Number of messages {} is more than supported by this key {}",
+                count,
+                self.y1.len()
+            ));
+        }
+        if index >= count {
+            return Err(format!(
+                "Index {} is not one of the {} messages",
+                index, count
+            ));
+        }
+        Ok(order)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn linked_challenge<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        index: usize,
+        h: &P::PublicKey,
+        commitment: &P::PublicKey,
+        test_values: &VerifiableCipherText,
+        test_commitment: &P::PublicKey,
+    ) -> BigNumber {
+        let mut transcript = self.view().proof_transcript(nonce, ciphertext);
+        transcript.append_message(b"linked", b"camenisch-shoup commitment link proof");
+        transcript.append_message(b"order", &P::order().to_bytes());
+        transcript.append_message(b"h", &P::to_bytes(h));
+        transcript.append_u64(b"index", index as u64);
+        transcript.append_message(b"commitment", &P::to_bytes(commitment));
+        transcript.append_message(b"ciphertext_test.u", &test_values.u.to_bytes());
+        for e in &test_values.e {
+            transcript.append_message(b"ciphertext_test.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext_test.v", &test_values.v.to_bytes());
+        transcript.append_message(b"commitment_test", &P::to_bytes(test_commitment));
+        Self::challenge(&mut transcript)
+    }
+
+    pub(crate) fn committed_challenge<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        h: &P::PublicKey,
+        commitments: &[P::PublicKey],
+        test_values: &VerifiableCipherText,
+        test_commitments: &[P::PublicKey],
+    ) -> BigNumber {
+        let mut transcript = self.view().proof_transcript(nonce, ciphertext);
+        transcript.append_message(b"committed", b"camenisch-shoup committed encryption proof");
+        transcript.append_message(b"order", &P::order().to_bytes());
+        transcript.append_message(b"h", &P::to_bytes(h));
+        for commitment in commitments {
+            transcript.append_message(b"commitment", &P::to_bytes(commitment));
+        }
+        transcript.append_message(b"ciphertext_test.u", &test_values.u.to_bytes());
+        for e in &test_values.e {
+            transcript.append_message(b"ciphertext_test.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext_test.v", &test_values.v.to_bytes());
+        for commitment in test_commitments {
+            transcript.append_message(b"commitment_test", &P::to_bytes(commitment));
+        }
+        Self::challenge(&mut transcript)
+    }
+
+    pub(crate) fn escrow_challenge<P: PublicKeyGroup>(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        public_key: &P::PublicKey,
+        test_values: &VerifiableCipherText,
+        commitment: &P::PublicKey,
+    ) -> BigNumber {
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup secret key escrow proof");
+        transcript.append_message(b"nonce", nonce);
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        transcript.append_message(b"y2", &self.y2.to_bytes());
+        transcript.append_message(b"y3", &self.y3.to_bytes());
+        transcript.append_message(b"y1", &self.y1[0].to_bytes());
+        transcript.append_message(b"order", &P::order().to_bytes());
+        transcript.append_message(b"public_key", &P::to_bytes(public_key));
+        transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
+        for e in &ciphertext.e {
+            transcript.append_message(b"ciphertext.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
+        transcript.append_message(b"ciphertext_test.u", &test_values.u.to_bytes());
+        for e in &test_values.e {
+            transcript.append_message(b"ciphertext_test.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext_test.v", &test_values.v.to_bytes());
+        transcript.append_message(b"commitment", &P::to_bytes(commitment));
+        Self::challenge(&mut transcript)
+    }
+
+    /// Verify a record created by `DecryptionKey::decrypt_with_warrant`: the warrant
+    /// names `ciphertext` and is signed by `authority` and the messages are its decryption
+    pub fn verify_decryption_record<A: WarrantAuthority>(
+        &self,
+        authority: &A,
+        ciphertext: &VerifiableCipherText,
+        record: &DecryptionRecord,
+    ) -> Result<(), VerencError> {
+        record.warrant.check(authority, ciphertext)?;
+        let proof = &record.proof;
+        if record.messages.len() != ciphertext.e.len() || proof.x.len() != ciphertext.e.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is not equal to ciphertext {}",
+                record.messages.len(),
+                ciphertext.e.len()
+            )));
+        }
+        if ciphertext.e.len() > self.y1.len() {
+            return Err(VerencError::Other(format!(
+                "Number of messages {} is more than supported by this key {}",
+                ciphertext.e.len(),
+                self.y1.len()
+            )));
+        }
+        let group = &self.group;
+        let c = &proof.challenge;
+        let mut test_values = Vec::with_capacity(proof.x.len());
+        for (((e, m), x), y) in ciphertext
+            .e
+            .iter()
+            .zip(record.messages.iter())
+            .zip(proof.x.iter())
+            .zip(self.y1.iter())
+        {
+            // g^x * y1^c and u^x * (e / h^m)^c
+            let opened = group.mul(e, &group.h_pow(&-m.clone()));
+            test_values.push((
+                group.mul(&group.g_pow(x), &group.pow(y, c)),
+                group.mul(&group.pow(&ciphertext.u, x), &group.pow(&opened, c)),
+            ));
+        }
+        let challenge =
+            self.decryption_challenge(&record.warrant, ciphertext, &record.messages, &test_values);
+        if ct_eq(&challenge, c) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// Verify a record created by `DecryptionKey::decrypt_batch_with_warrants`:
+    /// each warrant names the ciphertext at its position in `ciphertexts` and is
+    /// signed by `authority` and the messages are their decryptions
+    pub fn verify_batch_decryption_record<A: WarrantAuthority>(
+        &self,
+        authority: &A,
+        ciphertexts: &[VerifiableCipherText],
+        record: &BatchDecryptionRecord,
+    ) -> Result<(), VerencError> {
+        if record.warrants.len() != ciphertexts.len() {
+            return Err(VerencError::Other(format!(
+                "Number of warrants {} != number of ciphertexts {}",
+                record.warrants.len(),
+                ciphertexts.len()
+            )));
+        }
+        for (i, (warrant, ciphertext)) in record.warrants.iter().zip(ciphertexts).enumerate() {
+            warrant
+                .check(authority, ciphertext)
+                .map_err(|e| BatchItemError::new(i, ciphertext.digest(), &warrant.label, e))?;
+        }
+        let (mut transcript, aggregates) =
+            self.batch_decryption_statement(&record.warrants, ciphertexts, &record.messages)?;
+        let proof = &record.proof;
+        if proof.x.len() != aggregates.len() {
+            return Err(VerencError::Other("Invalid proof".to_string()));
+        }
+        let group = &self.group;
+        let c = &proof.challenge;
+        // g^x * y1^c and U^x * E^c
+        let test_values = aggregates
+            .iter()
+            .zip(proof.x.iter())
+            .zip(self.y1.iter())
+            .map(|(((u, e), x), y)| {
+                (
+                    group.mul(&group.g_pow(x), &group.pow(y, c)),
+                    group.mul(&group.pow(u, x), &group.pow(e, c)),
+                )
+            })
+            .collect::<Vec<_>>();
+        if ct_eq(
+            &Self::batch_decryption_challenge(&mut transcript, &test_values),
+            c,
+        ) {
+            Ok(())
+        } else {
+            Err(VerencError::Other("Invalid proof".to_string()))
+        }
+    }
+
+    /// The bit length of `bound`, checking m + 2^w - B can't wrap around `n`
+    pub(crate) fn check_less_than_bound(&self, bound: &BigNumber) -> Result<usize, String> {
+        if self.y1.is_empty() {
+            return Err("Key does not support any messages".to_string());
+        }
+        if bound <= &BigNumber::zero() {
+            return Err("bound must be positive".to_string());
+        }
+        let width = bit_length(bound);
+        if BigNumber::one() << (width + 1) > self.group.n {
+            return Err("bound is too large for the group".to_string());
+        }
+        Ok(width)
+    }
+
+    pub(crate) fn less_than_challenge(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        bound: &BigNumber,
+        lower: (&[BitProof], &[(BigNumber, BigNumber)]),
+        upper: (&[BitProof], &[(BigNumber, BigNumber)]),
+    ) -> BigNumber {
+        let mut transcript =
+            self.bits_transcript(b"camenisch-shoup less than proof", nonce, ciphertext);
+        transcript.append_message(b"bound", &bound.to_bytes());
+        append_bits(&mut transcript, lower.0, lower.1);
+        append_bits(&mut transcript, upper.0, upper.1);
+        Self::challenge(&mut transcript)
+    }
+
+    pub(crate) fn label_or_challenge(
+        &self,
+        labels: &[&[u8]],
+        ciphertext: &VerifiableCipherText,
+        test_values: &[VerifiableCipherText],
+    ) -> BigNumber {
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup label or proof");
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        transcript.append_message(b"y2", &self.y2.to_bytes());
+        transcript.append_message(b"y3", &self.y3.to_bytes());
+        for y in &self.y1 {
+            transcript.append_message(b"y1", &y.to_bytes());
+        }
+        transcript.append_u64(b"labels", labels.len() as u64);
+        for label in labels {
+            transcript.append_message(b"label", label);
+        }
+        transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
+        for e in &ciphertext.e {
+            transcript.append_message(b"ciphertext.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
+        for tv in test_values {
+            transcript.append_message(b"ciphertext_test.u", &tv.u.to_bytes());
+            for e in &tv.e {
+                transcript.append_message(b"ciphertext_test.e", &e.to_bytes());
+            }
+            transcript.append_message(b"ciphertext_test.v", &tv.v.to_bytes());
+        }
+        Self::challenge(&mut transcript)
+    }
+
+    /// Absorb a batch of decryptions and combine it into one statement per key slot
+    /// `log_g y1 = log_U E` where `U = prod u^(2w)` and `E = prod (e / h^m)^(2w)` over
+    /// the ciphertexts with random 128-bit weights `w` drawn from the transcript.
+    /// Squaring keeps the factors in the subgroup without elements of small order
+    /// so a wrong message can't cancel against another
+    pub(crate) fn batch_decryption_statement(
+        &self,
+        warrants: &[Warrant],
+        ciphertexts: &[VerifiableCipherText],
+        messages: &[Vec<BigNumber>],
+    ) -> Result<(merlin::Transcript, Vec<(BigNumber, BigNumber)>), String> {
+        if ciphertexts.is_empty() {
+            return Err("Batch has no ciphertexts".to_string());
+        }
+        if messages.len() != ciphertexts.len() {
+            return Err(format!(
+                "Number of decryptions {} != number of ciphertexts {}",
+                messages.len(),
+                ciphertexts.len()
+            ));
+        }
+        for (m, ciphertext) in messages.iter().zip(ciphertexts.iter()) {
+            if m.len() != ciphertext.e.len() {
+                return Err(format!(
+                    "Number of messages {} is not equal to ciphertext {}",
+                    m.len(),
+                    ciphertext.e.len()
+                ));
+            }
+            if m.len() > self.y1.len() {
+                return Err(format!(
+                    "Number of messages {} is more than supported by this key {}",
+                    m.len(),
+                    self.y1.len()
+                ));
+            }
+        }
+        let slots = messages.iter().map(Vec::len).max().unwrap_or(0);
+
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup batch decryption proof");
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        for y in self.y1.iter().take(slots) {
+            transcript.append_message(b"y1", &y.to_bytes());
+        }
+        transcript.append_u64(b"count", ciphertexts.len() as u64);
+        for ((warrant, ciphertext), m) in warrants.iter().zip(ciphertexts.iter()).zip(messages) {
+            transcript.append_message(b"warrant", &warrant.message());
+            transcript.append_message(b"signature", &warrant.signature);
+            transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
+            for e in &ciphertext.e {
+                transcript.append_message(b"ciphertext.e", &e.to_bytes());
+            }
+            transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
+            for m in m {
+                transcript.append_message(b"message", &m.to_bytes());
+            }
+        }
+
+        let mut aggregates = vec![(BigNumber::one(), BigNumber::one()); slots];
+        for (ciphertext, m) in ciphertexts.iter().zip(messages) {
+            let mut weight = [0u8; 16];
+            transcript.challenge_bytes(b"weight", &mut weight);
+            let two_w = BigNumber::from_slice(weight) << 1;
+            let u = group.pow(&ciphertext.u, &two_w);
+            for ((aggregate, e), m) in aggregates.iter_mut().zip(ciphertext.e.iter()).zip(m) {
+                let opened = group.mul(e, &group.h_pow(&-m.clone()));
+                aggregate.0 = group.mul(&aggregate.0, &u);
+                aggregate.1 = group.mul(&aggregate.1, &group.pow(&opened, &two_w));
+            }
+        }
+        for (u, e) in &aggregates {
+            transcript.append_message(b"aggregate.u", &u.to_bytes());
+            transcript.append_message(b"aggregate.e", &e.to_bytes());
+        }
+        Ok((transcript, aggregates))
+    }
+
+    pub(crate) fn batch_decryption_challenge(
+        transcript: &mut merlin::Transcript,
+        test_values: &[(BigNumber, BigNumber)],
+    ) -> BigNumber {
+        for (t1, t2) in test_values {
+            transcript.append_message(b"commitment.g", &t1.to_bytes());
+            transcript.append_message(b"commitment.u", &t2.to_bytes());
+        }
+        Self::challenge(transcript)
+    }
+
+    pub(crate) fn decryption_challenge(
+        &self,
+        warrant: &Warrant,
+        ciphertext: &VerifiableCipherText,
+        messages: &[BigNumber],
+        test_values: &[(BigNumber, BigNumber)],
+    ) -> BigNumber {
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup decryption proof");
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        for y in self.y1.iter().take(messages.len()) {
+            transcript.append_message(b"y1", &y.to_bytes());
+        }
+        transcript.append_message(b"warrant", &warrant.message());
+        transcript.append_message(b"signature", &warrant.signature);
+        transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
+        for e in &ciphertext.e {
+            transcript.append_message(b"ciphertext.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
+        for m in messages {
+            transcript.append_message(b"message", &m.to_bytes());
+        }
+        for (t1, t2) in test_values {
+            transcript.append_message(b"commitment.g", &t1.to_bytes());
+            transcript.append_message(b"commitment.u", &t2.to_bytes());
+        }
+        Self::challenge(&mut transcript)
+    }
+
+    /// The number of bits `count` packed values take,
+    /// checking they fit in a message
+    pub(crate) fn check_packed_width(
+        &self,
+        count: usize,
+        bits_per_value: usize,
+    ) -> Result<usize, String> {
+        if self.y1.is_empty() {
+            return Err("Key does not support any messages".to_string());
+        }
+        let width = count * bits_per_value;
+        if count == 0 || BigNumber::one() << width > self.group.n {
+            return Err(format!(
+                "{} values of {} bits do not fit in a message",
+                count, bits_per_value
+            ));
+        }
+        Ok(width)
+    }
+
+    pub(crate) fn packed_challenge(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        bits: &[BitProof],
+        test_values: &[(BigNumber, BigNumber)],
+    ) -> BigNumber {
+        let mut transcript =
+            self.bits_transcript(b"camenisch-shoup packed range proof", nonce, ciphertext);
+        append_bits(&mut transcript, bits, test_values);
+        Self::challenge(&mut transcript)
+    }
+
+    pub(crate) fn check_rerandomized(
+        &self,
+        original: &VerifiableCipherText,
+        rerandomized: &VerifiableCipherText,
+    ) -> Result<(), String> {
+        if original.e.len() != rerandomized.e.len() {
+            return Err(format!(
+                "Number of messages {} != number of re-randomized messages {}",
+                original.e.len(),
+                rerandomized.e.len()
+            ));
+        }
+        if original.e.len() > self.y1.len() {
+            return Err(format!(
+                "Number of messages {} is more than supported by this key {}",
+                original.e.len(),
+                self.y1.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// g^{2b} followed by y1_i^{2b} for each message
+    pub(crate) fn rerandomization_commitments(
+        &self,
+        two_b: &BigNumber,
+        num_messages: usize,
+    ) -> Vec<BigNumber> {
+        let mut commitments = Vec::with_capacity(num_messages + 1);
+        commitments.push(self.group.g_pow(two_b));
+        commitments.extend(
+            self.y1
+                .iter()
+                .take(num_messages)
+                .map(|y| self.group.pow(y, two_b)),
+        );
+        commitments
+    }
+
+    pub(crate) fn rerandomization_challenge(
+        &self,
+        original: &VerifiableCipherText,
+        rerandomized: &VerifiableCipherText,
+        commitments: &[BigNumber],
+    ) -> BigNumber {
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup rerandomization proof");
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        for y in &self.y1 {
+            transcript.append_message(b"y1", &y.to_bytes());
+        }
+        transcript.append_message(b"original.u", &original.u.to_bytes());
+        for e in &original.e {
+            transcript.append_message(b"original.e", &e.to_bytes());
+        }
+        transcript.append_message(b"rerandomized.u", &rerandomized.u.to_bytes());
+        for e in &rerandomized.e {
+            transcript.append_message(b"rerandomized.e", &e.to_bytes());
+        }
+        for c in commitments {
+            transcript.append_message(b"commitment", &c.to_bytes());
+        }
+        Self::challenge(&mut transcript)
+    }
+
+    pub(crate) fn key_generation_challenge(
+        &self,
+        nonce: &[u8],
+        commitments: &[BigNumber],
+    ) -> BigNumber {
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup key generation proof");
+        transcript.append_message(b"nonce", nonce);
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        for y in &self.y1 {
+            transcript.append_message(b"y1", &y.to_bytes());
+        }
+        transcript.append_message(b"y2", &self.y2.to_bytes());
+        transcript.append_message(b"y3", &self.y3.to_bytes());
+        for c in commitments {
+            transcript.append_message(b"commitment", &c.to_bytes());
+        }
+        Self::challenge(&mut transcript)
+    }
+
+    /// g^{2x}, u^{2x} h^{2m}, g^{2r}, y1'^{2r} h^{2m} and (y2' y3'^H')^{2r}
+    /// for each message in order
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn reencryption_commitments(
+        &self,
+        ciphertext: &VerifiableCipherText,
+        new_key: &VerificationKey,
+        new_domain: &[u8],
+        new_ciphertext: &VerifiableCipherText,
+        x: &[BigNumber],
+        r: &BigNumber,
+        m: &[BigNumber],
+    ) -> Vec<BigNumber> {
+        let group = &self.group;
+        let two_r: BigNumber = r << 1;
+        let two_m = m.iter().map(|m| m << 1).collect::<Vec<_>>();
+        let two_x = x.iter().map(|x| x << 1).collect::<Vec<_>>();
+        let mut commitments = Vec::with_capacity(3 * x.len() + 2);
+        commitments.extend(two_x.iter().map(|x| group.g_pow(x)));
+        commitments.extend(
+            two_x
+                .iter()
+                .zip(two_m.iter())
+                .map(|(x, m)| group.mul(&group.pow(&ciphertext.u, x), &group.h_pow(m))),
+        );
+        commitments.push(group.g_pow(&two_r));
+        commitments.extend(
+            new_key
+                .y1
+                .iter()
+                .zip(two_m.iter())
+                .map(|(y, m)| group.mul(&group.pow(y, &two_r), &group.h_pow(m))),
+        );
+        let hash = group.hash(&new_ciphertext.u, &new_ciphertext.e, new_domain);
+        commitments.push(new_key.view().compute_v(&two_r, &hash, false));
+        commitments
+    }
+
+    pub(crate) fn reencryption_challenge(
+        &self,
+        domain: &[u8],
+        ciphertext: &VerifiableCipherText,
+        new_key: &VerificationKey,
+        new_domain: &[u8],
+        new_ciphertext: &VerifiableCipherText,
+        commitments: &[BigNumber],
+    ) -> BigNumber {
+        let mut transcript = merlin::Transcript::new(b"camenisch-shoup reencryption proof");
+        transcript.append_message(b"old_key", &self.fingerprint());
+        transcript.append_message(b"new_key", &new_key.fingerprint());
+        transcript.append_message(b"domain", domain);
+        transcript.append_message(b"new_domain", new_domain);
+        transcript.append_message(b"ciphertext", &ciphertext.digest());
+        transcript.append_message(b"new_ciphertext", &new_ciphertext.digest());
+        for c in commitments {
+            transcript.append_message(b"commitment", &c.to_bytes());
+        }
+        Self::challenge(&mut transcript)
+    }
+
+    /// Start a transcript for a proof about the bits of the message
+    /// in a single message `ciphertext`
+    fn bits_transcript(
+        &self,
+        label: &'static [u8],
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+    ) -> merlin::Transcript {
+        let group = &self.group;
+        let mut transcript = merlin::Transcript::new(label);
+        transcript.append_message(b"nonce", nonce);
+        transcript.append_message(b"n", &group.n.to_bytes());
+        transcript.append_message(b"g", &group.g.to_bytes());
+        transcript.append_message(b"y1", &self.y1[0].to_bytes());
+        transcript.append_message(b"ciphertext.u", &ciphertext.u.to_bytes());
+        for e in &ciphertext.e {
+            transcript.append_message(b"ciphertext.e", &e.to_bytes());
+        }
+        transcript.append_message(b"ciphertext.v", &ciphertext.v.to_bytes());
+        transcript
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    audit::{self, AuditEvent},
+    group::ct_eq,
+    proof_options::VectorDigest,
+    EncryptionKeyRef, VerencError, VerifiableCipherText, VerificationKey,
+};
+use unknown_order::BigNumber;
+
+/// Verifies a `VerifiableEncryptionProof` from its parts as they arrive,
+/// e.g. off a network stream, instead of a fully deserialized proof.
+///
+/// Call `begin` with `u`, `v`, the challenge and `r`, then `push` each `e[i]`
+/// and its response `m[i]` in order and `finish` for the verdict.
+/// The responses are not kept. With `ProofOptions::prehash_e` neither are the
+/// reconstructed test values. The `e` values are kept since H(u, e, L) needs them all.
+/// A part that fails verification is reported to the audit sink like `verify`
+/// with the digest of the ciphertext parts provided so far
+#[derive(Debug)]
+pub struct Verifier<'a> {
+    key: EncryptionKeyRef<'a>,
+    nonce: Vec<u8>,
+    header: Option<Header>,
+    e: Vec<BigNumber>,
+    test_e: TestValues,
+}
+
+#[derive(Debug)]
+struct Header {
+    u: BigNumber,
+    v: BigNumber,
+    challenge: BigNumber,
+    two_c: BigNumber,
+    two_r: BigNumber,
+    test_u: BigNumber,
+}
+
+enum TestValues {
+    Direct(Vec<Vec<u8>>),
+    Digest(Box<VectorDigest>),
+}
+
+impl std::fmt::Debug for TestValues {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Direct(values) => write!(f, "Direct({})", values.len()),
+            Self::Digest(_) => write!(f, "Digest"),
+        }
+    }
+}
+
+impl<'a> Verifier<'a> {
+    /// Start verifying a proof made with `nonce` for `key`
+    pub fn new(key: EncryptionKeyRef<'a>, nonce: &[u8]) -> Self {
+        let test_e = if key.options.prehash_e {
+            TestValues::Digest(Box::new(VectorDigest::new(b"ciphertext_test.e")))
+        } else {
+            TestValues::Direct(Vec::new())
+        };
+        Self {
+            key,
+            nonce: nonce.to_vec(),
+            header: None,
+            e: Vec::new(),
+            test_e,
+        }
+    }
+
+    /// Provide the ciphertext's `u` and `v` and the proof's challenge and `r`
+    pub fn begin(
+        &mut self,
+        u: BigNumber,
+        v: BigNumber,
+        challenge: BigNumber,
+        r: BigNumber,
+    ) -> Result<(), VerencError> {
+        if self.header.is_some() {
+            return Err(VerencError::Other("Verifier has already begun".to_string()));
+        }
+        if let Err(error) = self.key.check_challenge_and_r(&challenge, &r) {
+            self.record_failure(&u, &v, &[], &error);
+            return Err(error);
+        }
+        let two_c = &challenge << 1;
+        let two_r = &r << 1;
+        let test_u = self.key.test_u(&u, &two_c, &two_r);
+        self.header = Some(Header {
+            u,
+            v,
+            challenge,
+            two_c,
+            two_r,
+            test_u,
+        });
+        Ok(())
+    }
+
+    /// Provide the next ciphertext value `e` and its response `m`
+    pub fn push(&mut self, e: BigNumber, m: &BigNumber) -> Result<(), VerencError> {
+        let header = self
+            .header
+            .as_ref()
+            .ok_or_else(|| VerencError::Other("Verifier has not begun".to_string()))?;
+        let slot = self.e.len();
+        let checked = if slot >= self.key.y1.len() {
+            Err(VerencError::TooManyMessages {
+                messages: slot + 1,
+                max: self.key.y1.len(),
+            })
+        } else {
+            self.key.check_response(m)
+        };
+        if let Err(error) = checked {
+            let mut parts = self.e.clone();
+            parts.push(e);
+            self.record_failure(&header.u, &header.v, &parts, &error);
+            return Err(error);
+        }
+        let test_e = self.key.test_e(&e, slot, &header.two_c, &header.two_r, m);
+        match &mut self.test_e {
+            TestValues::Direct(values) => values.push(test_e.to_bytes()),
+            TestValues::Digest(digest) => digest.absorb(&test_e),
+        }
+        self.e.push(e);
+        Ok(())
+    }
+
+    /// Check the proof once every part has been provided
+    pub fn finish(self) -> Result<(), VerencError> {
+        let header = self
+            .header
+            .ok_or_else(|| VerencError::Other("Verifier has not begun".to_string()))?;
+        let key = &self.key;
+        let group = &key.group;
+        let count = self.e.len();
+        let ciphertext = VerifiableCipherText {
+            u: header.u,
+            v: header.v,
+            e: self.e,
+        };
+        let mut transcript = key.proof_transcript(&self.nonce, &ciphertext);
+        transcript.append_message(b"ciphertext_test.u", &header.test_u.to_bytes());
+        key.append_count(&mut transcript, b"ciphertext_test.e.count", count);
+        match self.test_e {
+            TestValues::Direct(values) => {
+                for value in values {
+                    transcript.append_message(b"ciphertext_test.e", &value);
+                }
+            }
+            TestValues::Digest(digest) => {
+                transcript.append_message(b"ciphertext_test.e", &digest.finish())
+            }
+        }
+        let hash = group.hash(&ciphertext.u, &ciphertext.e, &self.nonce);
+        let test_v = key.test_v(&ciphertext.v, &header.two_c, &header.two_r, &hash);
+        transcript.append_message(b"ciphertext_test.v", &test_v.to_bytes());
+        if ct_eq(
+            &VerificationKey::challenge(&mut transcript),
+            &header.challenge,
+        ) {
+            Ok(())
+        } else {
+            let error = VerencError::InvalidProof;
+            let nonce = &self.nonce;
+            audit::record(|| AuditEvent::VerificationFailed {
+                key_fingerprint: key.fingerprint(),
+                ciphertext_hash: ciphertext.digest(),
+                nonce,
+                error: &error,
+            });
+            Err(error)
+        }
+    }
+
+    fn record_failure(&self, u: &BigNumber, v: &BigNumber, e: &[BigNumber], error: &VerencError) {
+        audit::record(|| AuditEvent::VerificationFailed {
+            key_fingerprint: self.key.fingerprint(),
+            ciphertext_hash: VerifiableCipherText {
+                u: u.clone(),
+                v: v.clone(),
+                e: e.to_vec(),
+            }
+            .digest(),
+            nonce: &self.nonce,
+            error,
+        });
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{
+    group::ct_eq, precompute::FixedBaseTable, BatchItemError, ProofOptions, VerencError,
+    VerifiableCipherText, VerifiableEncryptionProof, VerificationKey,
+};
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use std::collections::BTreeMap;
+
+/// A prepared verifier to share between request handlers.
+///
+/// Owns the key, fixed-base tables for `g` and each `y1` and a thread pool.
+/// Verification splits the work per message across the pool and
+/// `verify_batch` spreads proofs across it. All methods take `&self`
+/// and the type is `Send + Sync` so it can be shared with an `Arc`.
+///
+/// Each table holds one value for every bit of n^2 so uses
+/// about 2 MiB per base with a 2048-bit modulus
+#[derive(Debug)]
+pub struct VerifierPool {
+    key: VerificationKey,
+    options: ProofOptions,
+    g: FixedBaseTable,
+    y1: Vec<FixedBaseTable>,
+    pool: ThreadPool,
+}
+
+impl VerifierPool {
+    /// Prepare `key` for verifying with `threads` worker threads,
+    /// or one per CPU if `threads` is zero
+    pub fn new(key: impl Into<VerificationKey>, threads: usize) -> Result<Self, VerencError> {
+        let key = key.into();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let group = &key.group;
+        let (g, y1) = pool.install(|| {
+            rayon::join(
+                || FixedBaseTable::new(group, &group.g),
+                || {
+                    key.y1
+                        .par_iter()
+                        .map(|y| FixedBaseTable::new(group, y))
+                        .collect()
+                },
+            )
+        });
+        Ok(Self {
+            key,
+            options: ProofOptions::default(),
+            g,
+            y1,
+            pool,
+        })
+    }
+
+    /// Use `options` for every proof verified by this pool
+    pub fn with_options(mut self, options: ProofOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The key proofs are verified with
+    pub fn verification_key(&self) -> &VerificationKey {
+        &self.key
+    }
+
+    /// Verify a proof of verifiable encryption, see `VerificationKey::verify`
+    pub fn verify(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.pool
+            .install(|| self.verify_in_pool(nonce, ciphertext, proof))
+    }
+
+    /// Verify many proofs in parallel, returning each result in order.
+    /// A failure carries the item's position, ciphertext digest and nonce
+    pub fn verify_batch(
+        &self,
+        items: &[(&[u8], &VerifiableCipherText, &VerifiableEncryptionProof)],
+    ) -> Vec<Result<(), BatchItemError>> {
+        self.pool.install(|| {
+            items
+                .par_iter()
+                .enumerate()
+                .map(|(i, (nonce, ciphertext, proof))| {
+                    self.verify_in_pool(nonce, ciphertext, proof)
+                        .map_err(|e| BatchItemError::new(i, ciphertext.digest(), nonce, e))
+                })
+                .collect()
+        })
+    }
+
+    fn verify_in_pool(
+        &self,
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        verify_with_tables(
+            &self.key,
+            self.options,
+            &self.g,
+            &self.y1,
+            nonce,
+            ciphertext,
+            proof,
+        )
+    }
+}
+
+/// A prepared verifier for many keys, e.g. one per tenant.
+///
+/// Keys are grouped by modulus so keys sharing a group share its
+/// fixed-base table for `g` across `verify_batch`. Each key still has
+/// its own tables for `y1`.
+/// The challenge is a hash of every test value so each proof's
+/// exponentiations are still computed on their own
+#[derive(Debug)]
+pub struct MultiKeyVerifierPool {
+    groups: BTreeMap<[u8; 32], FixedBaseTable>,
+    keys: BTreeMap<[u8; 32], PreparedKey>,
+    options: ProofOptions,
+    pool: ThreadPool,
+}
+
+#[derive(Debug)]
+struct PreparedKey {
+    key: VerificationKey,
+    group: [u8; 32],
+    y1: Vec<FixedBaseTable>,
+}
+
+impl MultiKeyVerifierPool {
+    /// An empty pool with `threads` worker threads,
+    /// or one per CPU if `threads` is zero
+    pub fn new(threads: usize) -> Result<Self, VerencError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            groups: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            options: ProofOptions::default(),
+            pool,
+        })
+    }
+
+    /// Use `options` for every proof verified by this pool
+    pub fn with_options(mut self, options: ProofOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Prepare `key` returning its `VerificationKey::fingerprint`
+    /// which `verify` and `verify_batch` name it by
+    pub fn insert(&mut self, key: impl Into<VerificationKey>) -> [u8; 32] {
+        let key = key.into();
+        let fingerprint = key.fingerprint();
+        let group_fingerprint = key.group.fingerprint();
+        let group = &key.group;
+        let groups = &self.groups;
+        let (g, y1) = self.pool.install(|| {
+            rayon::join(
+                || {
+                    (!groups.contains_key(&group_fingerprint))
+                        .then(|| FixedBaseTable::new(group, &group.g))
+                },
+                || {
+                    key.y1
+                        .par_iter()
+                        .map(|y| FixedBaseTable::new(group, y))
+                        .collect()
+                },
+            )
+        });
+        if let Some(g) = g {
+            self.groups.insert(group_fingerprint, g);
+        }
+        self.keys.insert(
+            fingerprint,
+            PreparedKey {
+                key,
+                group: group_fingerprint,
+                y1,
+            },
+        );
+        fingerprint
+    }
+
+    /// The key with `fingerprint` if it was inserted
+    pub fn get(&self, fingerprint: &[u8; 32]) -> Option<&VerificationKey> {
+        self.keys.get(fingerprint).map(|k| &k.key)
+    }
+
+    /// The number of keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// True if no key was inserted
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The number of distinct moduli of the keys
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Verify a proof for the key with `fingerprint`, see `VerificationKey::verify`
+    pub fn verify(
+        &self,
+        fingerprint: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        self.pool
+            .install(|| self.verify_in_pool(fingerprint, nonce, ciphertext, proof))
+    }
+
+    /// Verify proofs for any of the keys in parallel, returning each result
+    /// in order like `VerifierPool::verify_batch`. Each item names its key by fingerprint
+    pub fn verify_batch(
+        &self,
+        items: &[(
+            &[u8; 32],
+            &[u8],
+            &VerifiableCipherText,
+            &VerifiableEncryptionProof,
+        )],
+    ) -> Vec<Result<(), BatchItemError>> {
+        // Verify the proofs of one modulus together
+        let mut order = (0..items.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| self.keys.get(items[*i].0).map(|k| k.group));
+        let results = self.pool.install(|| {
+            order
+                .par_iter()
+                .map(|i| {
+                    let (fingerprint, nonce, ciphertext, proof) = items[*i];
+                    self.verify_in_pool(fingerprint, nonce, ciphertext, proof)
+                        .map_err(|e| BatchItemError::new(*i, ciphertext.digest(), nonce, e))
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut ordered = vec![Ok(()); items.len()];
+        for (i, result) in order.into_iter().zip(results) {
+            ordered[i] = result;
+        }
+        ordered
+    }
+
+    fn verify_in_pool(
+        &self,
+        fingerprint: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &VerifiableCipherText,
+        proof: &VerifiableEncryptionProof,
+    ) -> Result<(), VerencError> {
+        let prepared = self
+            .keys
+            .get(fingerprint)
+            .ok_or_else(|| VerencError::Other("Unknown verification key".to_string()))?;
+        verify_with_tables(
+            &prepared.key,
+            self.options,
+            &self.groups[&prepared.group],
+            &prepared.y1,
+            nonce,
+            ciphertext,
+            proof,
+        )
+    }
+}
+
+fn verify_with_tables(
+    key: &VerificationKey,
+    options: ProofOptions,
+    g: &FixedBaseTable,
+    y1: &[FixedBaseTable],
+    nonce: &[u8],
+    ciphertext: &VerifiableCipherText,
+    proof: &VerifiableEncryptionProof,
+) -> Result<(), VerencError> {
+    if proof.m.len() != ciphertext.e.len() {
+        return Err(VerencError::MessageCountMismatch {
+            messages: proof.m.len(),
+            ciphertext: ciphertext.e.len(),
+        });
+    }
+    if proof.m.len() > y1.len() {
+        return Err(VerencError::TooManyMessages {
+            messages: proof.m.len(),
+            max: y1.len(),
+        });
+    }
+    let view = key.view().with_options(options);
+    view.check_challenge_and_r(&proof.challenge, &proof.r)?;
+    for m in &proof.m {
+        view.check_response(m)?;
+    }
+    let group = &key.group;
+    let two_c = &proof.challenge << 1;
+    let two_r = &proof.r << 1;
+
+    // u^{2c} * g^{2r}
+    let u = group.mul(&group.pow(&ciphertext.u, &two_c), &g.pow(group, &two_r)?);
+    // e^{2c} * y1^{2r} * h^{2m}
+    let e = ciphertext
+        .e
+        .par_iter()
+        .zip(proof.m.par_iter())
+        .zip(y1.par_iter())
+        .map(|((ee, m), y)| {
+            let ec = group.pow(ee, &two_c);
+            let yr = y.pow(group, &two_r)?;
+            Ok(group.mul(&group.mul(&ec, &yr), &group.h_pow(&(m << 1))))
+        })
+        .collect::<Result<Vec<_>, VerencError>>()?;
+    // v^{2c} * (y2 * y3^H)^{2r}
+    let hash = group.hash(&ciphertext.u, &ciphertext.e, nonce);
+    let v = view.test_v(&ciphertext.v, &two_c, &two_r, &hash);
+    let test_values = VerifiableCipherText { u, e, v };
+    if ct_eq(
+        &view.fiat_shamir(nonce, ciphertext, &test_values),
+        &proof.challenge,
+    ) {
+        Ok(())
+    } else {
+        Err(VerencError::InvalidProof)
+    }
+}
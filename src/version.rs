@@ -0,0 +1,113 @@
+use crate::{ProofOptions, ProtocolVersion};
+use serde::{Deserialize, Serialize};
+
+/// The newest Fiat-Shamir transcript version of `VerifiableEncryptionProof`
+/// this build proves and verifies with, see `ProtocolVersion`.
+/// The other proofs have a single transcript and aren't versioned
+pub const PROTOCOL_VERSION: u16 = ProtocolVersion::LATEST.number();
+
+/// The version of the byte encodings with a magic header this build
+/// writes: archives, compressed values, labels and protected keys. It's
+/// the number at the end of each magic header. Checked hex and serde
+/// encodings carry no version
+pub const WIRE_VERSION: u16 = 1;
+
+/// The versions a build supports, exchanged with peers before sending
+/// `VerifiableEncryptionProof`s or values in the encodings `WIRE_VERSION`
+/// covers, so a mismatch shows up as a `Compat` instead of an invalid
+/// proof or unreadable value
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Version {
+    /// The newest transcript version
+    pub protocol: u16,
+    /// The oldest transcript version still verified
+    pub min_protocol: u16,
+    /// The newest wire version
+    pub wire: u16,
+    /// The oldest wire version still read
+    pub min_wire: u16,
+}
+
+impl Version {
+    /// The versions of this build
+    pub const CURRENT: Self = Self {
+        protocol: PROTOCOL_VERSION,
+        min_protocol: 1,
+        wire: WIRE_VERSION,
+        min_wire: 1,
+    };
+}
+
+/// How this build and a peer can talk
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compat {
+    /// Use these versions, the newest both sides support
+    Compatible {
+        /// The transcript version to prove and verify with
+        protocol: ProtocolVersion,
+        /// The wire version to encode with
+        wire: u16,
+    },
+    /// No transcript version is supported by both sides
+    IncompatibleProtocol,
+    /// No wire version is supported by both sides
+    IncompatibleWire,
+}
+
+impl Compat {
+    /// The proof options to use with the peer if it's compatible
+    pub fn proof_options(&self) -> Option<ProofOptions> {
+        match self {
+            Self::Compatible { protocol, .. } => Some(ProofOptions {
+                version: *protocol,
+                ..ProofOptions::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Compare this build's `Version::CURRENT` with a peer's `other`
+pub fn compatibility(other: Version) -> Compat {
+    let ours = Version::CURRENT;
+    let protocol = newest_common(
+        ours.min_protocol,
+        ours.protocol,
+        other.min_protocol,
+        other.protocol,
+    )
+    .and_then(ProtocolVersion::from_number);
+    let wire = newest_common(ours.min_wire, ours.wire, other.min_wire, other.wire);
+    match (protocol, wire) {
+        (None, _) => Compat::IncompatibleProtocol,
+        (_, None) => Compat::IncompatibleWire,
+        (Some(protocol), Some(wire)) => Compat::Compatible { protocol, wire },
+    }
+}
+
+fn newest_common(our_min: u16, ours: u16, their_min: u16, theirs: u16) -> Option<u16> {
+    let newest = ours.min(theirs);
+    (newest >= our_min.max(their_min)).then_some(newest)
+}
+
+/// If `found` is the magic header of another wire version of the
+/// encoding with header `magic`, the error saying so
+pub(crate) fn wire_mismatch(found: &[u8], magic: &[u8]) -> Option<String> {
+    let digits = magic
+        .iter()
+        .rev()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let prefix = &magic[..magic.len() - digits];
+    if found.len() != magic.len() || !found.starts_with(prefix) {
+        return None;
+    }
+    let version = std::str::from_utf8(&found[prefix.len()..])
+        .ok()?
+        .parse::<u16>()
+        .ok()?;
+    Some(format!(
+        "Wire version {} is not supported, this build reads version {}",
+        version, WIRE_VERSION
+    ))
+}
@@ -0,0 +1,146 @@
+use crate::{DecryptionProof, VerifiableCipherText};
+use serde::{Deserialize, Serialize};
+use unknown_order::BigNumber;
+
+/// Checks signatures on a `Warrant` with whichever
+/// signature scheme the authorizing party uses
+pub trait WarrantAuthority {
+    /// Return true if `signature` is a valid signature on `message`
+    fn verify_warrant(&self, message: &[u8; 32], signature: &[u8]) -> bool;
+}
+
+/// A signed authorization to decrypt one ciphertext under one label.
+///
+/// The authority signs `Warrant::message` and attaches the signature
+/// with `Warrant::with_signature`. `DecryptionKey::decrypt_with_warrant`
+/// only opens the ciphertext the warrant names and binds the warrant into
+/// the resulting `DecryptionRecord`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Warrant {
+    pub(crate) ciphertext_hash: [u8; 32],
+    pub(crate) label: Vec<u8>,
+    pub(crate) statement: Vec<u8>,
+    pub(crate) signature: Vec<u8>,
+}
+
+impl Warrant {
+    /// Create an unsigned warrant for decrypting `ciphertext` under `label`.
+    /// `statement` holds any details of the authorization, e.g. a case number
+    pub fn new(ciphertext: &VerifiableCipherText, label: &[u8], statement: &[u8]) -> Self {
+        Self {
+            ciphertext_hash: ciphertext.digest(),
+            label: label.to_vec(),
+            statement: statement.to_vec(),
+            signature: Vec::new(),
+        }
+    }
+
+    /// Attach the authority's signature on `message`
+    pub fn with_signature(mut self, signature: &[u8]) -> Self {
+        self.signature = signature.to_vec();
+        self
+    }
+
+    /// The digest of the ciphertext this warrant authorizes opening
+    pub fn ciphertext_hash(&self) -> &[u8; 32] {
+        &self.ciphertext_hash
+    }
+
+    /// The label the ciphertext is decrypted under
+    pub fn label(&self) -> &[u8] {
+        &self.label
+    }
+
+    /// The details of the authorization
+    pub fn statement(&self) -> &[u8] {
+        &self.statement
+    }
+
+    /// The authority's signature
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// The message the authority signs
+    pub fn message(&self) -> [u8; 32] {
+        let mut transcript = merlin::Transcript::new(b"verifiable encryption warrant");
+        transcript.append_message(b"ciphertext_hash", &self.ciphertext_hash);
+        transcript.append_message(b"label", &self.label);
+        transcript.append_message(b"statement", &self.statement);
+        let mut message = [0u8; 32];
+        transcript.challenge_bytes(b"warrant message", &mut message);
+        message
+    }
+
+    /// Check the warrant names `ciphertext` and is signed by `authority`
+    pub(crate) fn check<A: WarrantAuthority>(
+        &self,
+        authority: &A,
+        ciphertext: &VerifiableCipherText,
+    ) -> Result<(), String> {
+        if self.ciphertext_hash != ciphertext.digest() {
+            return Err("Warrant does not name this ciphertext".to_string());
+        }
+        if !authority.verify_warrant(&self.message(), &self.signature) {
+            return Err("Warrant signature is invalid".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The record of an authorized decryption: the warrant, the messages
+/// and a proof they are the correct decryption bound to the warrant.
+/// Check it with `VerificationKey::verify_decryption_record`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DecryptionRecord {
+    pub(crate) warrant: Warrant,
+    #[serde(deserialize_with = "crate::budget::bignums")]
+    pub(crate) messages: Vec<BigNumber>,
+    pub(crate) proof: DecryptionProof,
+}
+
+impl DecryptionRecord {
+    /// The warrant that authorized the decryption
+    pub fn warrant(&self) -> &Warrant {
+        &self.warrant
+    }
+
+    /// The decrypted messages
+    pub fn messages(&self) -> &[BigNumber] {
+        &self.messages
+    }
+}
+
+/// The record of authorized decryptions of many ciphertexts under one key
+/// with a single proof every message is the correct decryption.
+/// The proof is the size of one `DecryptionRecord`'s however many
+/// ciphertexts it covers. Check it with `VerificationKey::verify_batch_decryption_record`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BatchDecryptionRecord {
+    pub(crate) warrants: Vec<Warrant>,
+    #[serde(deserialize_with = "crate::budget::bignum_rows")]
+    pub(crate) messages: Vec<Vec<BigNumber>>,
+    pub(crate) proof: DecryptionProof,
+}
+
+impl BatchDecryptionRecord {
+    /// The warrants in the order the ciphertexts were given
+    pub fn warrants(&self) -> &[Warrant] {
+        &self.warrants
+    }
+
+    /// The decrypted messages of each ciphertext
+    pub fn messages(&self) -> &[Vec<BigNumber>] {
+        &self.messages
+    }
+
+    /// The number of ciphertexts
+    pub fn len(&self) -> usize {
+        self.warrants.len()
+    }
+
+    /// True if no ciphertext was decrypted
+    pub fn is_empty(&self) -> bool {
+        self.warrants.is_empty()
+    }
+}
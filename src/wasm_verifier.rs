@@ -0,0 +1,8 @@
+/// `rand_core` and `unknown_order` link `getrandom` but verification never
+/// draws randomness, so on targets it doesn't support every request fails
+/// instead of pulling in a JavaScript binding
+fn no_randomness(_: &mut [u8]) -> Result<(), getrandom::Error> {
+    Err(getrandom::Error::UNSUPPORTED)
+}
+
+getrandom::register_custom_getrandom!(no_randomness);
@@ -0,0 +1,119 @@
+#![cfg(not(feature = "verify-only"))]
+
+// The sink is process wide so this file has a single test
+
+mod common;
+
+use common::test_group;
+use std::sync::Mutex;
+use unknown_order::BigNumber;
+use verenc::*;
+
+static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static ENCRYPTED: Mutex<Option<([u8; 32], [u8; 32])>> = Mutex::new(None);
+
+fn take() -> Vec<String> {
+    std::mem::take(&mut *EVENTS.lock().unwrap())
+}
+
+#[test]
+fn audit_sink() {
+    let group = test_group();
+    // Nothing is recorded before a sink is set
+    let (ek, dk) = group.new_keys(2).unwrap();
+    assert!(!audit_enabled());
+
+    let sink = |event: &AuditEvent<'_>| {
+        let line = match event {
+            AuditEvent::KeyGenerated { max_messages, .. } => format!("keygen {}", max_messages),
+            AuditEvent::Encrypted {
+                key_fingerprint,
+                ciphertext_hash,
+                domain,
+                messages,
+            } => {
+                *ENCRYPTED.lock().unwrap() = Some((*key_fingerprint, *ciphertext_hash));
+                format!("encrypt {} {}", String::from_utf8_lossy(domain), messages)
+            }
+            AuditEvent::ProofIssued { nonce, .. } => {
+                format!("prove {}", String::from_utf8_lossy(nonce))
+            }
+            AuditEvent::VerificationFailed { error, .. } => format!("verify failed {}", error),
+            AuditEvent::Decrypted {
+                messages, error, ..
+            } => format!("decrypt {} {:?}", messages, error.map(|e| e.to_string())),
+        };
+        EVENTS.lock().unwrap().push(line);
+    };
+    set_audit_sink(Box::new(sink)).unwrap();
+    assert!(audit_enabled());
+    assert!(set_audit_sink(Box::new(|_: &AuditEvent<'_>| {})).is_err());
+
+    let vk = VerificationKey::from(&ek);
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+    let (ct, proof) = ek.encrypt_and_prove(b"audit", &msgs).unwrap();
+    assert_eq!(take(), ["encrypt audit 2", "prove audit"]);
+
+    vk.verify(b"audit", &ct, &proof).unwrap();
+    assert!(take().is_empty());
+    assert!(vk.verify(b"other", &ct, &proof).is_err());
+    assert_eq!(take(), ["verify failed Invalid proof"]);
+
+    // The streaming verifier reports failures the same way
+    let ct_json = serde_json::to_value(&ct).unwrap();
+    let proof_json = serde_json::to_value(&proof).unwrap();
+    let part = |v: &serde_json::Value| -> BigNumber { serde_json::from_value(v.clone()).unwrap() };
+    let begin = |nonce: &[u8]| {
+        let mut verifier = Verifier::new(vk.view(), nonce);
+        verifier
+            .begin(
+                part(&ct_json["u"]),
+                part(&ct_json["v"]),
+                part(&proof_json["challenge"]),
+                part(&proof_json["r"]),
+            )
+            .unwrap();
+        verifier
+    };
+    let mut verifier = begin(b"other");
+    for (e, m) in ct_json["e"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .zip(proof_json["m"].as_array().unwrap())
+    {
+        verifier.push(part(e), &part(m)).unwrap();
+    }
+    assert_eq!(verifier.finish(), Err(VerencError::InvalidProof));
+    assert_eq!(take(), ["verify failed Invalid proof"]);
+    let mut verifier = begin(b"audit");
+    assert_eq!(
+        verifier.push(part(&ct_json["e"][0]), &(group.nn() * BigNumber::from(2))),
+        Err(VerencError::ProofOutOfRange)
+    );
+    assert_eq!(
+        take(),
+        [format!("verify failed {}", VerencError::ProofOutOfRange)]
+    );
+
+    assert_eq!(dk.decrypt(b"audit", &ct).unwrap(), msgs);
+    assert!(dk.decrypt(b"other", &ct).is_err());
+    assert_eq!(
+        take(),
+        [
+            "decrypt 2 None".to_string(),
+            format!("decrypt 2 Some({:?})", VerencError::InvalidTag.to_string()),
+        ]
+    );
+
+    let _ = group.new_keys(1).unwrap();
+    assert_eq!(take(), ["keygen 1"]);
+
+    // Events name the key and ciphertext by their digests
+    let ct = ek.encrypt(b"audit", &msgs).unwrap();
+    take();
+    assert_eq!(
+        *ENCRYPTED.lock().unwrap(),
+        Some((vk.fingerprint(), ct.digest()))
+    );
+}
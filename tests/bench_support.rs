@@ -0,0 +1,44 @@
+#![cfg(feature = "bench_support")]
+
+// Run with and without the `parallel` feature
+
+use unknown_order::BigNumber;
+use verenc::bench_support::*;
+
+#[test]
+fn schnorr_responses_match_inline() {
+    let group = ModulusSize::Bits2048.group();
+    let nn = group.nn();
+    let challenge = BigNumber::random(&(BigNumber::one() << 256));
+    let tildes = (0..20).map(|_| group.random_value()).collect::<Vec<_>>();
+    let values = (0..20).map(|_| group.random_value()).collect::<Vec<_>>();
+
+    let reduced = tildes
+        .iter()
+        .zip(values.iter())
+        .map(|(tilde, value)| tilde - (&challenge % nn).modmul(value, nn))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        schnorr_responses(&group, &challenge, &tildes, &values),
+        reduced
+    );
+
+    let unreduced = tildes
+        .iter()
+        .zip(values.iter())
+        .map(|(tilde, value)| tilde - &challenge * value)
+        .collect::<Vec<_>>();
+    assert_eq!(unreduced_responses(&challenge, &tildes, &values), unreduced);
+
+    let order = BigNumber::from(1_000_003);
+    let modular = tildes
+        .iter()
+        .zip(values.iter())
+        .map(|(tilde, value)| tilde.modsub(&(&challenge % &order).modmul(value, &order), &order))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        modular_responses(&challenge, &tildes, &values, &order),
+        modular
+    );
+    assert!(unreduced_responses(&challenge, &[], &[]).is_empty());
+}
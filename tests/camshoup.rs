@@ -1,21 +1,15 @@
-#[cfg(feature = "std")]
-mod tests {
-    use unknown_order::BigNumber;
-    use verenc::camshoup::*;
+#![cfg(not(feature = "verify-only"))]
 
-    fn test_p() -> BigNumber {
-        BigNumber::from_slice(hex::decode("3522d66070bc9a6857796dc78adae186f96ab8ddea108400c103cfc73be0ce19e1bc00e0ec2307377086ab687bb90e28edf7e4a2ca3c723a5023d5b62916fe955ef376ee14a4c4521753b17c836d360794a0ad6e05d605a53d912dd624e8cc23036adc964f2f35148e471924bf22ca6ecdf650db067b63fb72702db004e3b4c5").unwrap())
-    }
+mod common;
 
-    fn test_q() -> BigNumber {
-        BigNumber::from_slice(hex::decode("80000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000af53b313").unwrap())
-    }
+mod tests {
+    use crate::common::*;
+    use unknown_order::BigNumber;
+    use verenc::*;
 
     #[test]
     fn abs() {
-        let opt_group = Group::with_safe_primes_unchecked(&test_p(), &test_q());
-        assert!(opt_group.is_some());
-        let group = opt_group.unwrap();
+        let group = test_group();
         let n2d2 = group.nn() >> 1;
         // known tests
         let one = BigNumber::one();
@@ -49,23 +43,19 @@ mod tests {
 
     #[test]
     fn hash() {
-        let opt_group = Group::with_safe_primes_unchecked(&test_p(), &test_q());
-        assert!(opt_group.is_some());
-        let group = opt_group.unwrap();
+        let group = test_group();
 
         let u = BigNumber::one();
         let e = vec![BigNumber::one()];
-        assert_eq!(group.hash(&u, &e, &[1u8, 1u8]), BigNumber::from_slice(&hex::decode("a5f73f4bad2a587a1afba3237f5604a3d329c9b2e13feee922f643a349352f799f94dc7a3e68db9f6837627076540628828d8260a3b78230b1ad85e2ee161b1e").unwrap()));
+        assert_eq!(group.hash(&u, &e, &[1u8, 1u8]), BigNumber::from_slice(hex::decode("a5f73f4bad2a587a1afba3237f5604a3d329c9b2e13feee922f643a349352f799f94dc7a3e68db9f6837627076540628828d8260a3b78230b1ad85e2ee161b1e").unwrap()));
         let u = BigNumber::from(2);
         let e = vec![BigNumber::from(2)];
-        assert_eq!(group.hash(&u, &e, &[2u8, 2u8]), BigNumber::from_slice(&hex::decode("572b6535c0c9d23b73403e2e5778513626e68c1c013a83e66d98e6e9f4fb8d128839a1508d029512a75886c2c38715f68aa60d5d04f9557bacd3c26e747bdf95").unwrap()));
+        assert_eq!(group.hash(&u, &e, &[2u8, 2u8]), BigNumber::from_slice(hex::decode("572b6535c0c9d23b73403e2e5778513626e68c1c013a83e66d98e6e9f4fb8d128839a1508d029512a75886c2c38715f68aa60d5d04f9557bacd3c26e747bdf95").unwrap()));
     }
 
     #[test]
     fn encrypt_single() {
-        let opt_group = Group::with_safe_primes_unchecked(&test_p(), &test_q());
-        assert!(opt_group.is_some());
-        let group = opt_group.unwrap();
+        let group = test_group();
 
         let opt_keys = group.new_keys(1);
         assert!(opt_keys.is_some());
@@ -86,9 +76,7 @@ mod tests {
 
     #[test]
     fn encrypt_multi() {
-        let opt_group = Group::with_safe_primes_unchecked(&test_p(), &test_q());
-        assert!(opt_group.is_some());
-        let group = opt_group.unwrap();
+        let group = test_group();
 
         let opt_keys = group.new_keys(10);
         assert!(opt_keys.is_some());
@@ -117,9 +105,7 @@ mod tests {
 
     #[test]
     fn encrypt_and_prove_single() {
-        let opt_group = Group::with_safe_primes_unchecked(&test_p(), &test_q());
-        assert!(opt_group.is_some());
-        let group = opt_group.unwrap();
+        let group = test_group();
 
         let opt_keys = group.new_keys(1);
         assert!(opt_keys.is_some());
@@ -139,4 +125,692 @@ mod tests {
             assert!(dk.decrypt(b"a different domain", &ct).is_err());
         }
     }
+
+    #[test]
+    fn verification_key() {
+        let group = test_group();
+        let (ek, _) = group.new_keys(2).unwrap();
+        let vk = VerificationKey::from(&ek);
+        assert_eq!(vk.max_messages(), 2);
+
+        let domain = b"verification_key_test";
+        let msgs = vec![BigNumber::from(7), BigNumber::from(11)];
+        let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+        assert!(vk.verify(domain, &ct, &proof).is_ok());
+        assert!(vk.verify(b"a different domain", &ct, &proof).is_err());
+
+        // Encryption and verification keys share the same wire format
+        let ek_json = serde_json::to_string(&ek).unwrap();
+        let vk2: VerificationKey = serde_json::from_str(&ek_json).unwrap();
+        assert!(vk2.verify(domain, &ct, &proof).is_ok());
+        assert_eq!(ek_json, serde_json::to_string(&vk).unwrap());
+    }
+
+    #[test]
+    fn encrypt_and_prove_multi() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(10).unwrap();
+
+        let domain = b"encrypt_and_prove_multi_test";
+        let msgs = (0..10)
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<BigNumber>>();
+        let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+        assert!(ek.verify(domain, &ct, &proof).is_ok());
+        assert_eq!(dk.decrypt(domain, &ct).unwrap(), msgs);
+
+        // Messages swapped between slots must not verify
+        let (mut ct2, proof2) = ek.encrypt_and_prove(domain, &msgs[..2]).unwrap();
+        assert!(ek.verify(domain, &ct2, &proof2).is_ok());
+        let mut ct_json: serde_json::Value = serde_json::to_value(&ct2).unwrap();
+        ct_json["e"].as_array_mut().unwrap().swap(0, 1);
+        ct2 = serde_json::from_value(ct_json).unwrap();
+        assert!(ek.verify(domain, &ct2, &proof2).is_err());
+    }
+
+    #[test]
+    fn homomorphic_tally() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(2).unwrap();
+        let vk = ek.verification_key();
+
+        let domain = b"homomorphic_tally_test";
+        let votes = [[1u64, 0], [0, 1], [1, 0]];
+        let mut tally: Option<VerifiableCipherText> = None;
+        for vote in &votes {
+            let msgs = vote.iter().map(|v| BigNumber::from(*v)).collect::<Vec<_>>();
+            let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+            assert!(vk.verify(domain, &ct, &proof).is_ok());
+            tally = Some(match tally {
+                None => ct,
+                Some(sum) => sum.add(&ct, &group).unwrap(),
+            });
+        }
+        let tally = tally.unwrap();
+        // The tag is dropped so only untagged decryption opens it
+        assert!(dk.decrypt(domain, &tally).is_err());
+        assert_eq!(
+            dk.decrypt_untagged(&tally).unwrap(),
+            vec![BigNumber::from(2), BigNumber::from(1)]
+        );
+
+        let tripled = tally.scalar_mul(&BigNumber::from(3), &group);
+        assert_eq!(
+            dk.decrypt_untagged(&tripled).unwrap(),
+            vec![BigNumber::from(6), BigNumber::from(3)]
+        );
+        let negated = tally.scalar_mul(&-BigNumber::one(), &group);
+        assert_eq!(
+            dk.decrypt_untagged(&negated.add(&tally, &group).unwrap())
+                .unwrap(),
+            vec![BigNumber::zero(), BigNumber::zero()]
+        );
+
+        let narrow = ek.encrypt(domain, &[BigNumber::one()]).unwrap();
+        assert!(tally.add(&narrow, &group).is_err());
+    }
+
+    #[test]
+    fn encrypt_at_indices() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(4).unwrap();
+
+        let domain = b"encrypt_at_indices_test";
+        let msgs = vec![(1, BigNumber::from(21)), (3, BigNumber::from(43))];
+        let indices = [1, 3];
+        let ct = ek.encrypt_at_indices(domain, &msgs).unwrap();
+        assert_eq!(
+            dk.decrypt_at_indices(domain, &indices, &ct)
+                .unwrap()
+                .into_messages()
+                .unwrap(),
+            vec![BigNumber::from(21), BigNumber::from(43)]
+        );
+        assert!(dk.decrypt(domain, &ct).is_err());
+        // The tag covers every slot but slot 1 read as slot 0 doesn't decrypt
+        assert!(!dk
+            .decrypt_at_indices(domain, &[0, 3], &ct)
+            .unwrap()
+            .is_complete());
+
+        let (ct, proof) = ek.encrypt_and_prove_at_indices(domain, &msgs).unwrap();
+        assert!(ek.verify_at_indices(domain, &indices, &ct, &proof).is_ok());
+        assert!(ek.verify_at_indices(domain, &[0, 1], &ct, &proof).is_err());
+        assert!(ek.verify(domain, &ct, &proof).is_err());
+        assert!(dk.decrypt_at_indices(domain, &indices, &ct).is_ok());
+
+        // Indices must be strictly increasing and within the key
+        let unordered = vec![(3, BigNumber::from(1)), (1, BigNumber::from(2))];
+        assert!(ek.encrypt_at_indices(domain, &unordered).is_err());
+        assert!(ek
+            .encrypt_at_indices(domain, &[(4, BigNumber::from(1))])
+            .is_err());
+    }
+
+    #[test]
+    fn ciphertext_num_messages() {
+        let group = test_group();
+        let (ek, _) = group.new_keys(2).unwrap();
+
+        let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+        let ct = ek.encrypt(b"num_messages_test", &msgs).unwrap();
+        assert_eq!(ct.num_messages(), 2);
+
+        // A truncated ciphertext is not equal to the original
+        let json = serde_json::to_value(&ct).unwrap();
+        let mut truncated = json.clone();
+        truncated["e"].as_array_mut().unwrap().pop();
+        let truncated: VerifiableCipherText = serde_json::from_value(truncated).unwrap();
+        assert_eq!(truncated.num_messages(), 1);
+        assert_ne!(ct, truncated);
+        assert_ne!(truncated, ct);
+    }
+
+    #[test]
+    fn key_views() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(2).unwrap();
+        let vk = VerificationKey::from(&ek);
+
+        let nonce = b"key_views_test";
+        let msgs = vec![BigNumber::from(15), BigNumber::from(16)];
+        let view = EncryptionKeyRef::new(vk.y1(), vk.y2(), vk.y3(), GroupRef::from(vk.group()));
+        assert_eq!(view.max_messages(), 2);
+        let (ct, proof) = view.encrypt_and_prove(nonce, &msgs).unwrap();
+        assert!(vk.verify(nonce, &ct, &proof).is_ok());
+        assert!(ek.view().verify(nonce, &ct, &proof).is_ok());
+        assert_eq!(dk.decrypt(nonce, &ct).unwrap(), msgs);
+
+        // A view over the first slot only
+        let view = EncryptionKeyRef::new(&vk.y1()[..1], vk.y2(), vk.y3(), view.group());
+        let ct = view.encrypt(nonce, &msgs[..1]).unwrap();
+        assert!(view.encrypt(nonce, &msgs).is_err());
+        assert_eq!(dk.decrypt(nonce, &ct).unwrap(), &msgs[..1]);
+    }
+
+    #[test]
+    fn prehash_e() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(4).unwrap();
+        let vk = VerificationKey::from(&ek);
+        let options = ProofOptions {
+            prehash_e: true,
+            ..ProofOptions::default()
+        };
+
+        let nonce = b"prehash_e_test";
+        let msgs = (0..4).map(BigNumber::from).collect::<Vec<_>>();
+        let (ct, proof) = ek
+            .view()
+            .with_options(options)
+            .encrypt_and_prove(nonce, &msgs)
+            .unwrap();
+        assert!(vk
+            .view()
+            .with_options(options)
+            .verify(nonce, &ct, &proof)
+            .is_ok());
+        assert!(vk.verify(nonce, &ct, &proof).is_err());
+        assert_eq!(dk.decrypt(nonce, &ct).unwrap(), msgs);
+
+        let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+        assert!(vk
+            .view()
+            .with_options(options)
+            .verify(nonce, &ct, &proof)
+            .is_err());
+        assert_eq!(vk.view().options(), ProofOptions::default());
+    }
+
+    #[test]
+    fn protocol_version() {
+        let group = test_group();
+        let (ek, _) = group.new_keys(2).unwrap();
+        let vk = VerificationKey::from(&ek);
+        let v2 = ProofOptions {
+            version: ProtocolVersion::V2,
+            ..ProofOptions::default()
+        };
+        assert_eq!(ProofOptions::default().version, ProtocolVersion::V1);
+
+        let nonce = b"protocol_version_test";
+        let msgs = vec![BigNumber::from(5), BigNumber::from(6)];
+        let (ct, proof) = ek
+            .view()
+            .with_options(v2)
+            .encrypt_and_prove(nonce, &msgs)
+            .unwrap();
+        assert!(vk
+            .view()
+            .with_options(v2)
+            .verify(nonce, &ct, &proof)
+            .is_ok());
+        assert!(vk.verify(nonce, &ct, &proof).is_err());
+
+        let both = ProofOptions {
+            prehash_e: true,
+            version: ProtocolVersion::V2,
+        };
+        let (ct, proof) = ek
+            .view()
+            .with_options(both)
+            .encrypt_and_prove(nonce, &msgs)
+            .unwrap();
+        assert!(vk
+            .view()
+            .with_options(both)
+            .verify(nonce, &ct, &proof)
+            .is_ok());
+        assert!(vk
+            .view()
+            .with_options(v2)
+            .verify(nonce, &ct, &proof)
+            .is_err());
+    }
+
+    #[test]
+    fn encrypt_with_randomness() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(2).unwrap();
+        let nonce = b"encrypt_with_randomness_test";
+        let msgs = vec![BigNumber::from(9), BigNumber::from(10)];
+        let r = group.random_for_encrypt();
+        let blindings = vec![group.random_for_encrypt(), group.random_for_encrypt()];
+
+        let ct = ek.encrypt_with_randomness(nonce, &msgs, &r).unwrap();
+        assert_eq!(ct, ek.encrypt_with_randomness(nonce, &msgs, &r).unwrap());
+        assert_eq!(dk.decrypt(nonce, &ct).unwrap(), msgs);
+        let (proved, proof) = ek
+            .encrypt_and_prove_with_randomness(nonce, &msgs, &r, &blindings)
+            .unwrap();
+        assert_eq!(proved, ct);
+        assert!(ek.verify(nonce, &proved, &proof).is_ok());
+
+        // r must be below n / 4 and not zero
+        let nd4: BigNumber = group.n() >> 2;
+        assert!(ek.encrypt_with_randomness(nonce, &msgs, &nd4).is_err());
+        assert!(ek
+            .encrypt_with_randomness(nonce, &msgs, &BigNumber::zero())
+            .is_err());
+        assert!(ek
+            .encrypt_and_prove_with_randomness(nonce, &msgs, &r, &blindings[..1])
+            .is_err());
+        let too_many = vec![BigNumber::one(); 3];
+        assert!(ek.encrypt_with_randomness(nonce, &too_many, &r).is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_serial() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(8).unwrap();
+        let vk = ek.verification_key();
+        let domain = b"parallel_matches_serial";
+        let msgs = (0..8u64)
+            .map(|i| BigNumber::from(100 + i))
+            .collect::<Vec<_>>();
+        let r = group.random_for_encrypt();
+
+        // The same ciphertext computed one slot after another
+        let u = group.g_pow(&r);
+        let e = vk
+            .y1()
+            .iter()
+            .zip(msgs.iter())
+            .map(|(y, m)| group.mul(&group.pow(y, &r), &group.h_pow(m)))
+            .collect::<Vec<_>>();
+        let hash = group.hash(&u, &e, domain);
+        let v = group.pow(&group.mul(&group.pow(vk.y3(), &hash), vk.y2()), &r);
+        let serial = VerifiableCipherText::new(u, e, group.abs(&v));
+
+        let ct = ek.encrypt_with_randomness(domain, &msgs, &r).unwrap();
+        assert_eq!(ct, serial);
+        // Slots open in parallel but come back in order
+        assert_eq!(dk.decrypt(domain, &serial).unwrap(), msgs);
+        let each = dk.decrypt_each(domain, &serial).unwrap();
+        assert_eq!(
+            each.iter().map(|(slot, _)| slot).collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+        assert_eq!(each.into_messages().unwrap(), msgs);
+
+        let (proved, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+        assert!(vk.verify(domain, &proved, &proof).is_ok());
+        assert_eq!(dk.decrypt(domain, &proved).unwrap(), msgs);
+    }
+
+    #[test]
+    fn reduced_element() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(1).unwrap();
+        let domain = b"reduced_element_test";
+        let msgs = vec![BigNumber::from(13)];
+        let ct = ek.encrypt(domain, &msgs).unwrap();
+
+        let value = serde_json::to_value(&ct).unwrap();
+        let u: BigNumber = serde_json::from_value(value["u"].clone()).unwrap();
+        let e: Vec<BigNumber> = serde_json::from_value(value["e"].clone()).unwrap();
+        let v: BigNumber = serde_json::from_value(value["v"].clone()).unwrap();
+        assert_eq!(group.reduced(&v).unwrap(), v);
+
+        // The negated v is equivalent but not in absolute form
+        let negated = group.nn() - &v;
+        assert!(group.reduced(&negated).is_err());
+        let mut manual = value.clone();
+        manual["v"] = serde_json::to_value(&negated).unwrap();
+        let manual: VerifiableCipherText = serde_json::from_value(manual).unwrap();
+        let err = dk.decrypt(domain, &manual).unwrap_err();
+        assert!(err.to_string().contains("Group::abs"));
+
+        let rebuilt = VerifiableCipherText::new(u, e, group.abs(&negated));
+        assert_eq!(rebuilt, ct);
+        assert_eq!(dk.decrypt(domain, &rebuilt).unwrap(), msgs);
+    }
+
+    #[test]
+    fn sample_below_exact_bound() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        // Values reach the top of the bound and the same seed gives the same values
+        let bound = BigNumber::from(1000);
+        let mut rng = StdRng::seed_from_u64(7);
+        let values = (0..2000)
+            .map(|_| sample_below(&bound, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(values.iter().all(|v| v < &bound && v >= &BigNumber::zero()));
+        assert!(values.iter().any(|v| v >= &BigNumber::from(990)));
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(sample_below(&bound, &mut rng), values[0]);
+
+        let group = test_group();
+        let nd4: BigNumber = group.n() >> 2;
+        let n2d4: BigNumber = group.nn() >> 2;
+        let mut rng = StdRng::seed_from_u64(8);
+        let r = group.random_for_encrypt_with_rng(&mut rng);
+        assert!(!r.is_zero() && r < nd4);
+        let mut rng = StdRng::seed_from_u64(8);
+        assert_eq!(group.random_for_encrypt_with_rng(&mut rng), r);
+        let b = group.random_value_with_rng(&mut rng);
+        assert!(!b.is_zero() && b < n2d4);
+    }
+
+    #[test]
+    fn commitment_only_ciphertexts() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(0).unwrap();
+        let nonce = b"session binder";
+        let (ct, proof) = ek.encrypt_and_prove(nonce, &[]).unwrap();
+        assert_eq!(ct.num_messages(), 0);
+        assert!(ek.verify(nonce, &ct, &proof).is_ok());
+        assert!(ek.verify(b"another session", &ct, &proof).is_err());
+        assert!(dk.decrypt(nonce, &ct).unwrap().is_empty());
+        assert!(dk.decrypt(b"another session", &ct).is_err());
+        let json = serde_json::to_string(&ct).unwrap();
+        assert_eq!(
+            serde_json::from_str::<VerifiableCipherText>(&json).unwrap(),
+            ct
+        );
+
+        // Keys for messages can also encrypt none
+        let (ek, dk) = group.new_keys(2).unwrap();
+        let (ct, proof) = ek.encrypt_and_prove(nonce, &[]).unwrap();
+        assert!(ek.verify(nonce, &ct, &proof).is_ok());
+        assert!(dk.decrypt(nonce, &ct).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decrypt_each_slot() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(3).unwrap();
+        let domain = b"decrypt_each_slot";
+        let msgs = vec![BigNumber::from(8), BigNumber::from(9), BigNumber::from(10)];
+        let ct = ek.encrypt(domain, &msgs).unwrap();
+        let each = dk.decrypt_each(domain, &ct).unwrap();
+        assert_eq!(each.len(), 3);
+        assert!(each.is_complete());
+        assert_eq!(each.get(1), Some(&Ok(BigNumber::from(9))));
+        assert_eq!(each.into_messages().unwrap(), msgs);
+        assert!(dk.decrypt_each(b"other", &ct).is_err());
+
+        // Reading slot 2 as slot 1 leaves the tag valid but that slot malformed
+        let ct = ek
+            .encrypt_at_indices(domain, &[(0, BigNumber::from(8)), (2, BigNumber::from(10))])
+            .unwrap();
+        let each = dk.decrypt_at_indices(domain, &[0, 1], &ct).unwrap();
+        assert!(!each.is_complete());
+        assert_eq!(each.get(0), Some(&Ok(BigNumber::from(8))));
+        assert!(matches!(each.get(1), Some(Err(_))));
+        assert!(each.get(2).is_none());
+        assert_eq!(
+            each.iter().map(|(slot, _)| slot).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert!(each.into_messages().is_err());
+    }
+
+    #[test]
+    fn extend_slots() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(1).unwrap();
+        let domain = b"extend_slots";
+        let old = ek.encrypt(domain, &[BigNumber::from(1)]).unwrap();
+
+        let (ek2, dk2) = ek.extend_slots(&dk, 2).unwrap();
+        assert_eq!(ek2.verification_key().max_messages(), 3);
+        assert_ne!(
+            ek2.verification_key().fingerprint(),
+            ek.verification_key().fingerprint()
+        );
+        assert_eq!(dk2.decrypt(domain, &old).unwrap(), vec![BigNumber::from(1)]);
+        let msgs = vec![BigNumber::from(1), BigNumber::from(2), BigNumber::from(3)];
+        let (ct, proof) = ek2.encrypt_and_prove(domain, &msgs).unwrap();
+        assert!(ek2.verify(domain, &ct, &proof).is_ok());
+        assert_eq!(dk2.decrypt(domain, &ct).unwrap(), msgs);
+
+        assert!(ek.extend_slots(&dk, 0).is_err());
+        assert!(ek.extend_slots(&dk2, 1).is_err());
+    }
+
+    #[test]
+    fn paillier_decrypt_component() {
+        let group = test_group();
+        assert_eq!(
+            group.two_inv_two() % group.n(),
+            BigNumber::one() % group.n()
+        );
+        let x = group.random_value();
+        let r = group.random_for_encrypt();
+        let u = group.g_pow(&r);
+        let encrypt = |m: u64| group.mul(&group.pow(&u, &x), &group.h_pow(&BigNumber::from(m)));
+
+        let e = encrypt(5);
+        assert_eq!(
+            group.paillier_decrypt_component(&e, &u, &x),
+            Some(BigNumber::from(5))
+        );
+        // Components under the same u and x add their messages
+        let sum = group.mul(
+            &e,
+            &group.mul(&encrypt(7), &group.pow(&u, &x).invert(group.nn()).unwrap()),
+        );
+        assert_eq!(
+            group.paillier_decrypt_component(&sum, &u, &x),
+            Some(BigNumber::from(12))
+        );
+        let negated = group.nn() - group.h_pow(&BigNumber::from(3));
+        assert_eq!(group.paillier_extract(&negated), Some(BigNumber::from(3)));
+        assert!(group
+            .paillier_decrypt_component(&e, &u, &(&x + 1))
+            .is_none());
+        assert!(group.paillier_extract(group.g()).is_none());
+    }
+
+    #[test]
+    fn entropy_health_tests() {
+        struct Cycle(u8, u8);
+        impl rand::RngCore for Cycle {
+            fn next_u32(&mut self) -> u32 {
+                let mut bytes = [0u8; 4];
+                self.fill_bytes(&mut bytes);
+                u32::from_le_bytes(bytes)
+            }
+            fn next_u64(&mut self) -> u64 {
+                u64::from(self.next_u32())
+            }
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                for b in dest {
+                    *b = self.0;
+                    self.0 = self.0.wrapping_add(1) % self.1;
+                }
+            }
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+        impl rand::CryptoRng for Cycle {}
+
+        let tests = EntropyHealthTests::default();
+        assert_eq!(tests.repetition_cutoff(), 4);
+        assert!(tests.proportion_cutoff() < 20);
+        assert!(EntropyHealthTests::new(1.0).repetition_cutoff() > 20);
+
+        let group = test_group();
+        let dk = DecryptionKey::random_with_health_tests(2, &group, tests).unwrap();
+        assert_eq!(VerificationKey::from(&dk).max_messages(), 2);
+
+        // Stuck at one byte
+        let mut stuck = HealthTestedRng::new(Cycle(7, 1), tests);
+        assert_eq!(
+            DecryptionKey::random_with_tested_rng(1, &group, &mut stuck).unwrap_err(),
+            EntropyHealthError::Repetition { run: 4 }
+        );
+        // Never repeats but only four values
+        let mut biased = HealthTestedRng::new(Cycle(0, 4), tests);
+        assert!(matches!(
+            DecryptionKey::random_with_tested_rng(1, &group, &mut biased),
+            Err(EntropyHealthError::AdaptiveProportion { .. })
+        ));
+        assert!(biased.status().is_err());
+        let mut bytes = [0u8; 4];
+        assert!(rand::RngCore::try_fill_bytes(&mut biased, &mut bytes).is_err());
+        assert_eq!(bytes, [0u8; 4]);
+
+        assert!(tests.check(&[0, 1, 2, 3, 4, 5]).is_ok());
+        assert!(tests.check(&[9, 9, 9, 9]).is_err());
+    }
+
+    #[test]
+    fn decrypt_blinded() {
+        let group = test_group();
+        let blinding = ExponentBlinding::new(&group, &test_p(), &test_q()).unwrap();
+        assert!(ExponentBlinding::new(&group, &test_p(), &test_p()).is_err());
+        let (ek, dk) = group.new_keys(3).unwrap();
+        let domain = b"decrypt_blinded";
+        let msgs = vec![
+            BigNumber::from(1),
+            BigNumber::from(20),
+            BigNumber::from(300),
+        ];
+        let ct = ek.encrypt(domain, &msgs).unwrap();
+        for _ in 0..3 {
+            assert_eq!(dk.decrypt_blinded(domain, &ct, &blinding).unwrap(), msgs);
+        }
+        assert!(dk.decrypt_blinded(b"other", &ct, &blinding).is_err());
+        let bad = VerifiableCipherText::new(
+            group.n().clone(),
+            vec![BigNumber::one(); 3],
+            group.reduced(&BigNumber::one()).unwrap(),
+        );
+        assert!(dk.decrypt_blinded(domain, &bad, &blinding).is_err());
+    }
+
+    #[test]
+    fn multi_pow() {
+        let group = test_group();
+        let bases = (0..4).map(|_| group.random_value()).collect::<Vec<_>>();
+        let exps = [
+            BigNumber::random(group.nn()),
+            -BigNumber::random(group.nn()),
+            BigNumber::random(group.n()),
+            BigNumber::from(0x1234_5678u64),
+        ];
+        let product = |terms: &[(&BigNumber, &BigNumber)]| {
+            terms.iter().fold(BigNumber::one(), |acc, (b, e)| {
+                group.mul(&acc, &group.pow(b, e))
+            })
+        };
+        // Every length mix, with and without the shared squarings
+        for count in 0..=4 {
+            let terms = bases
+                .iter()
+                .zip(exps.iter())
+                .take(count)
+                .collect::<Vec<_>>();
+            assert_eq!(group.multi_pow(&terms), product(&terms));
+        }
+        let long = [
+            (&bases[0], &exps[0]),
+            (&bases[1], &exps[1]),
+            (&bases[2], &exps[0]),
+            (&bases[3], &exps[1]),
+        ];
+        assert_eq!(group.multi_pow(&long), product(&long));
+        assert_eq!(group.multi_pow(&[]), BigNumber::one());
+
+        // Like pow a base with no inverse and a negative exponent gives zero
+        let n = group.n().clone();
+        let terms = [(&bases[0], &exps[0]), (&n, &exps[1]), (&bases[1], &exps[0])];
+        assert_eq!(group.multi_pow(&terms), BigNumber::zero());
+    }
+
+    #[test]
+    fn single_message() {
+        let group = test_group();
+        // h^k without exponentiating matches the exponentiation
+        for k in [
+            BigNumber::zero(),
+            BigNumber::from(7),
+            -BigNumber::from(7),
+            group.n() + BigNumber::from(3),
+            -(group.nn() * BigNumber::from(2) + BigNumber::one()),
+        ] {
+            assert_eq!(group.h_pow(&k), group.h().modpow(&k, group.nn()));
+        }
+
+        let (ek, dk) = group.new_keys(3).unwrap();
+        let msg = vec![BigNumber::from(77)];
+        let (ct, proof) = ek.encrypt_and_prove(b"one", &msg).unwrap();
+        ek.verify(b"one", &ct, &proof).unwrap();
+        assert_eq!(
+            ek.verify(b"two", &ct, &proof).unwrap_err(),
+            VerencError::InvalidProof
+        );
+        assert_eq!(dk.decrypt(b"one", &ct).unwrap(), msg);
+        assert_eq!(
+            dk.decrypt(b"two", &ct).unwrap_err(),
+            VerencError::InvalidTag
+        );
+        let (other, _) = group.new_keys(1).unwrap();
+        let foreign = other.encrypt(b"one", &msg).unwrap();
+        assert!(dk.decrypt(b"one", &foreign).is_err());
+
+        // Wider ciphertexts than the borrowed slots still work
+        let (ek, dk) = group.new_keys(17).unwrap();
+        let msgs = (0..17u64).map(BigNumber::from).collect::<Vec<_>>();
+        let (ct, proof) = ek.encrypt_and_prove(b"wide", &msgs).unwrap();
+        ek.verify(b"wide", &ct, &proof).unwrap();
+        assert_eq!(dk.decrypt(b"wide", &ct).unwrap(), msgs);
+    }
+
+    #[test]
+    fn typed_errors() {
+        let group = test_group();
+        let (ek, dk) = group.new_keys(2).unwrap();
+        let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+
+        let three = vec![BigNumber::one(); 3];
+        assert_eq!(
+            ek.encrypt(b"typed", &three).unwrap_err(),
+            VerencError::TooManyMessages {
+                messages: 3,
+                max: 2
+            }
+        );
+        let too_big = vec![BigNumber::one(), group.n() + BigNumber::one()];
+        assert_eq!(
+            ek.encrypt(b"typed", &too_big).unwrap_err(),
+            VerencError::MessageOutOfRange { index: 1 }
+        );
+        assert_eq!(
+            ek.encrypt_at_indices(b"typed", &[(1, BigNumber::one()), (0, BigNumber::one())])
+                .unwrap_err(),
+            VerencError::IndexNotIncreasing { index: 0 }
+        );
+        assert_eq!(
+            ek.encrypt_with_randomness(b"typed", &msgs, &BigNumber::zero())
+                .unwrap_err(),
+            VerencError::InvalidRandomness
+        );
+
+        let (ct, proof) = ek.encrypt_and_prove(b"typed", &msgs).unwrap();
+        assert_eq!(
+            ek.verify(b"other", &ct, &proof).unwrap_err(),
+            VerencError::InvalidProof
+        );
+        assert_eq!(
+            dk.decrypt(b"other", &ct).unwrap_err(),
+            VerencError::InvalidTag
+        );
+        assert_eq!(
+            dk.decrypt_at_indices(b"typed", &[0, 2], &ct).unwrap_err(),
+            VerencError::IndexOutOfRange { index: 2, max: 2 }
+        );
+
+        // Still usable where a String error is expected
+        let err: String = ek.verify(b"other", &ct, &proof).unwrap_err().into();
+        assert_eq!(err, "Invalid proof");
+        let e: Box<dyn std::error::Error> = Box::new(VerencError::InvalidTag);
+        assert_eq!(e.to_string(), VerencError::InvalidTag.to_string());
+    }
 }
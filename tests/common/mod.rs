@@ -0,0 +1,79 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use unknown_order::BigNumber;
+use verenc::*;
+
+pub fn test_p() -> BigNumber {
+    BigNumber::from_slice(hex::decode("3522d66070bc9a6857796dc78adae186f96ab8ddea108400c103cfc73be0ce19e1bc00e0ec2307377086ab687bb90e28edf7e4a2ca3c723a5023d5b62916fe955ef376ee14a4c4521753b17c836d360794a0ad6e05d605a53d912dd624e8cc23036adc964f2f35148e471924bf22ca6ecdf650db067b63fb72702db004e3b4c5").unwrap())
+}
+
+pub fn test_q() -> BigNumber {
+    BigNumber::from_slice(hex::decode("80000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000af53b313").unwrap())
+}
+
+/// The group of `test_p` and `test_q` most tests use
+pub fn test_group() -> Group {
+    Group::with_safe_primes_unchecked(&test_p(), &test_q()).unwrap()
+}
+
+/// The order q subgroup of Z*_p with p = 2q + 1 standing in for a curve
+pub struct SchnorrGroup;
+
+impl SchnorrGroup {
+    pub fn modulus() -> BigNumber {
+        BigNumber::from_slice(
+            hex::decode("c998ff967972196995c8de6284b5bf11a36ae4d26bd3767468e33bd0e61a5a7f")
+                .unwrap(),
+        )
+    }
+}
+
+impl PublicKeyGroup for SchnorrGroup {
+    type PublicKey = BigNumber;
+
+    fn order() -> BigNumber {
+        Self::modulus() >> 1
+    }
+
+    fn mul_add(a: &BigNumber, b: &BigNumber, public_key: &BigNumber) -> BigNumber {
+        let p = Self::modulus();
+        BigNumber::from(4)
+            .modpow(a, &p)
+            .modmul(&public_key.modpow(b, &p), &p)
+    }
+
+    fn add(lhs: &BigNumber, rhs: &BigNumber) -> BigNumber {
+        lhs.modmul(rhs, &Self::modulus())
+    }
+
+    fn to_bytes(public_key: &BigNumber) -> Vec<u8> {
+        public_key.to_bytes()
+    }
+}
+
+/// Signs by hashing the message with a shared secret standing in for a signature scheme
+pub struct TestAuthority(pub Vec<u8>);
+
+impl TestAuthority {
+    pub fn sign(&self, message: &[u8; 32]) -> Vec<u8> {
+        let mut transcript = merlin::Transcript::new(b"test authority");
+        transcript.append_message(b"secret", &self.0);
+        transcript.append_message(b"message", message);
+        let mut signature = vec![0u8; 32];
+        transcript.challenge_bytes(b"signature", &mut signature);
+        signature
+    }
+}
+
+impl WarrantAuthority for TestAuthority {
+    fn verify_warrant(&self, message: &[u8; 32], signature: &[u8]) -> bool {
+        self.sign(message) == signature
+    }
+}
+
+impl CustodySigner for TestAuthority {
+    fn sign(&self, message: &[u8; 32]) -> Vec<u8> {
+        TestAuthority::sign(self, message)
+    }
+}
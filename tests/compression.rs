@@ -0,0 +1,25 @@
+#![cfg(all(feature = "compression", feature = "testing"))]
+
+use verenc::{testing, VerifiableCipherText, VerifiableEncryptionProof};
+
+#[test]
+fn round_trip() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group(&mut rng);
+    let (ek, dk) = group.new_keys(4).unwrap();
+    let nonce = b"compression round trip";
+    let msgs = testing::messages(&group, 4, &mut rng);
+    let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+
+    let bytes = ct.to_compressed_bytes(0).unwrap();
+    let ct = VerifiableCipherText::from_compressed_bytes(&bytes).unwrap();
+    let bytes = proof.to_compressed_bytes(19).unwrap();
+    let proof = VerifiableEncryptionProof::from_compressed_bytes(&bytes).unwrap();
+    assert!(ek.verify(nonce, &ct, &proof).is_ok());
+    assert_eq!(dk.decrypt(nonce, &ct).unwrap(), msgs);
+
+    // A proof is not read as a ciphertext
+    assert!(VerifiableCipherText::from_compressed_bytes(&bytes).is_err());
+    assert!(VerifiableEncryptionProof::from_compressed_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(VerifiableEncryptionProof::from_compressed_bytes(&bytes[..4]).is_err());
+}
@@ -0,0 +1,63 @@
+#![cfg(all(feature = "conformance", feature = "testing"))]
+
+use verenc::conformance::{self, ConformanceVector};
+use verenc::*;
+
+#[test]
+fn run_dir() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group(&mut rng);
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let msgs = testing::messages(&group, 2, &mut rng);
+    let nonce = b"conformance";
+    let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+    let valid = ConformanceVector {
+        description: "valid proof and ciphertext".to_string(),
+        verification_key: VerificationKey::from(&ek),
+        decryption_key: Some(dk),
+        nonce: hex::encode(nonce),
+        ciphertext: ct.to_checked_hex(),
+        proof: Some(proof.to_checked_hex()),
+        expect_verify: Some(true),
+        expect_messages: Some(msgs.iter().map(conformance::encode_message).collect()),
+    };
+    let wrong_nonce = ConformanceVector {
+        description: "proof and tag bound to another nonce".to_string(),
+        nonce: hex::encode(b"other"),
+        expect_verify: Some(false),
+        expect_messages: None,
+        ..valid.clone()
+    };
+    let wrong_expectation = ConformanceVector {
+        expect_verify: Some(false),
+        ..valid.clone()
+    };
+
+    let dir = std::env::temp_dir().join(format!("verenc-conformance-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("01-valid.json"), valid.to_json()).unwrap();
+    std::fs::write(dir.join("02-wrong-nonce.json"), wrong_nonce.to_json()).unwrap();
+    std::fs::write(
+        dir.join("03-wrong-expectation.json"),
+        wrong_expectation.to_json(),
+    )
+    .unwrap();
+    std::fs::write(dir.join("04-garbage.json"), "{").unwrap();
+    std::fs::write(dir.join("README.txt"), "not a vector").unwrap();
+
+    let report = conformance::run_dir(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.vectors.len(), 4);
+    assert_eq!(report.vectors[0].name, "01-valid.json");
+    assert!(report.vectors[0].passed());
+    assert_eq!(report.vectors[0].checks.len(), 2);
+    assert!(report.vectors[1].passed());
+    assert!(!report.vectors[2].passed());
+    assert_eq!(report.vectors[3].checks[0].check, "parse");
+    assert_eq!(report.failures(), 2);
+    assert!(!report.passed());
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["vectors"][2]["checks"][0]["passed"], false);
+}
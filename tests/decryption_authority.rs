@@ -0,0 +1,569 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn social_recovery() {
+    use verenc::recovery::*;
+
+    let group = test_group();
+    let keys = (0..3)
+        .map(|_| group.new_keys(1).unwrap())
+        .collect::<Vec<_>>();
+    let guardians = keys.iter().map(|(ek, _)| ek.clone()).collect::<Vec<_>>();
+    let vks = guardians
+        .iter()
+        .map(VerificationKey::from)
+        .collect::<Vec<_>>();
+
+    let nonce = b"social_recovery_test";
+    let zero = BigNumber::zero();
+    let secret_key = BigNumber::random(&SchnorrGroup::order());
+    let public_key = SchnorrGroup::mul_add(&secret_key, &zero, &BigNumber::one());
+    let package =
+        RecoveryPackage::create::<SchnorrGroup>(nonce, &secret_key, &public_key, 2, &guardians)
+            .unwrap();
+    assert_eq!(package.threshold(), 2);
+    assert_eq!(package.num_guardians(), 3);
+    assert_eq!(package.public_key(), Some(&public_key));
+    assert!(package.verify::<SchnorrGroup>(nonce, &vks).is_ok());
+    assert!(package
+        .verify::<SchnorrGroup>(b"wrong nonce", &vks)
+        .is_err());
+    let swapped = vec![vks[1].clone(), vks[0].clone(), vks[2].clone()];
+    assert!(package.verify::<SchnorrGroup>(nonce, &swapped).is_err());
+
+    let json = serde_json::to_string(&package).unwrap();
+    let package: RecoveryPackage<BigNumber> = serde_json::from_str(&json).unwrap();
+
+    let (device_ek, device_dk) = group.new_keys(1).unwrap();
+    let partials = [2, 0]
+        .iter()
+        .map(|i| {
+            package
+                .partial_decrypt::<SchnorrGroup>(nonce, *i, &keys[*i].1, &device_ek)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(partials[0].index(), 2);
+    let json = serde_json::to_string(&partials).unwrap();
+    let partials: Vec<PartialDecryption> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        package
+            .combine::<SchnorrGroup>(nonce, &device_dk, &partials)
+            .unwrap(),
+        secret_key
+    );
+
+    // Too few, repeated or mislabeled partial decryptions fail
+    assert!(package
+        .combine::<SchnorrGroup>(nonce, &device_dk, &partials[..1])
+        .is_err());
+    let repeated = vec![partials[0].clone(), partials[0].clone()];
+    assert!(package
+        .combine::<SchnorrGroup>(nonce, &device_dk, &repeated)
+        .is_err());
+    // A guardian can't use another guardian's key
+    assert!(package
+        .partial_decrypt::<SchnorrGroup>(nonce, 1, &keys[0].1, &device_ek)
+        .is_err());
+    assert!(RecoveryPackage::create::<SchnorrGroup>(
+        nonce,
+        &secret_key,
+        &public_key,
+        4,
+        &guardians
+    )
+    .is_err());
+}
+
+#[test]
+fn decrypt_batch_with_warrants() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let authority = TestAuthority(b"court".to_vec());
+
+    let label = b"audit";
+    let batch = [
+        vec![BigNumber::from(1), BigNumber::from(2)],
+        vec![BigNumber::from(3)],
+        vec![BigNumber::from(4), BigNumber::from(5)],
+    ];
+    let ciphertexts = batch
+        .iter()
+        .map(|msgs| ek.encrypt(label, msgs).unwrap())
+        .collect::<Vec<_>>();
+    let warrants = ciphertexts
+        .iter()
+        .map(|ct| {
+            let warrant = Warrant::new(ct, label, b"monthly audit");
+            let signature = authority.sign(&warrant.message());
+            warrant.with_signature(&signature)
+        })
+        .collect::<Vec<_>>();
+    let record = dk
+        .decrypt_batch_with_warrants(&authority, &warrants, &ciphertexts)
+        .unwrap();
+    assert_eq!(record.len(), 3);
+    assert_eq!(record.messages(), &batch[..]);
+    assert_eq!(record.warrants(), &warrants[..]);
+    assert!(vk
+        .verify_batch_decryption_record(&authority, &ciphertexts, &record)
+        .is_ok());
+
+    // One response per key slot however many ciphertexts
+    let mut json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json["proof"]["x"].as_array().unwrap().len(), 2);
+    let decoded: BatchDecryptionRecord = serde_json::from_value(json.clone()).unwrap();
+    assert!(vk
+        .verify_batch_decryption_record(&authority, &ciphertexts, &decoded)
+        .is_ok());
+
+    // A wrong message, reordered ciphertexts or another authority fail
+    json["messages"][1][0] = serde_json::to_value(BigNumber::from(6)).unwrap();
+    let tampered: BatchDecryptionRecord = serde_json::from_value(json).unwrap();
+    assert!(vk
+        .verify_batch_decryption_record(&authority, &ciphertexts, &tampered)
+        .is_err());
+    let mut reordered = ciphertexts.clone();
+    reordered.swap(0, 2);
+    assert!(vk
+        .verify_batch_decryption_record(&authority, &reordered, &record)
+        .is_err());
+    let impostor = TestAuthority(b"impostor".to_vec());
+    assert!(vk
+        .verify_batch_decryption_record(&impostor, &ciphertexts, &record)
+        .is_err());
+    assert!(dk
+        .decrypt_batch_with_warrants(&authority, &warrants[..2], &ciphertexts)
+        .is_err());
+    let err = dk
+        .decrypt_batch_with_warrants(&authority, &warrants, &reordered)
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("batch item 0 with ciphertext "));
+    assert!(err.ends_with("and label \"audit\" failed: Warrant does not name this ciphertext"));
+    assert!(dk
+        .decrypt_batch_with_warrants(&authority, &[], &[])
+        .is_err());
+}
+
+#[test]
+fn decrypt_with_warrant() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let authority = TestAuthority(b"court".to_vec());
+
+    let label = b"decrypt_with_warrant_test";
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+    let ct = ek.encrypt(label, &msgs).unwrap();
+    let unsigned = Warrant::new(&ct, label, b"case 42");
+    assert_eq!(unsigned.ciphertext_hash(), &ct.digest());
+    assert!(dk.decrypt_with_warrant(&authority, &unsigned, &ct).is_err());
+
+    let signature = authority.sign(&unsigned.message());
+    let warrant = unsigned.with_signature(&signature);
+    let record = dk.decrypt_with_warrant(&authority, &warrant, &ct).unwrap();
+    assert_eq!(record.messages(), msgs.as_slice());
+    assert_eq!(record.warrant(), &warrant);
+    assert!(vk
+        .verify_decryption_record(&authority, &ct, &record)
+        .is_ok());
+    let json = serde_json::to_string(&record).unwrap();
+    let record: DecryptionRecord = serde_json::from_str(&json).unwrap();
+    assert!(vk
+        .verify_decryption_record(&authority, &ct, &record)
+        .is_ok());
+
+    // A warrant for another ciphertext, another authority or another key fail
+    let other = ek.encrypt(label, &msgs).unwrap();
+    assert!(dk
+        .decrypt_with_warrant(&authority, &warrant, &other)
+        .is_err());
+    assert!(vk
+        .verify_decryption_record(&authority, &other, &record)
+        .is_err());
+    let impostor = TestAuthority(b"impostor".to_vec());
+    assert!(vk
+        .verify_decryption_record(&impostor, &ct, &record)
+        .is_err());
+    let (other_ek, _) = group.new_keys(2).unwrap();
+    assert!(VerificationKey::from(&other_ek)
+        .verify_decryption_record(&authority, &ct, &record)
+        .is_err());
+
+    // The warrant's label must be the one the ciphertext was made with
+    let warrant = Warrant::new(&ct, b"other label", b"case 42");
+    let warrant = warrant
+        .clone()
+        .with_signature(&authority.sign(&warrant.message()));
+    assert!(dk.decrypt_with_warrant(&authority, &warrant, &ct).is_err());
+}
+
+#[test]
+fn guarded_decryption_key() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let approvers = vec![
+        TestAuthority(b"alice".to_vec()),
+        TestAuthority(b"bob".to_vec()),
+        TestAuthority(b"carol".to_vec()),
+    ];
+    let policy = DecryptionPolicy {
+        max_decryptions: 2,
+        period: 60,
+        required_approvals: 2,
+    };
+    let mut guarded = GuardedDecryptionKey::new(dk.clone(), policy, approvers);
+
+    let domain = b"guarded_decryption_key_test";
+    let msgs = vec![BigNumber::from(9)];
+    let ct = ek.encrypt(domain, &msgs).unwrap();
+    let message = GuardedDecryptionKey::<TestAuthority>::request_message(&ct, domain);
+    let approve = |i: usize, name: &[u8]| Approval {
+        approver: i,
+        signature: TestAuthority(name.to_vec()).sign(&message),
+    };
+    let approvals = vec![approve(0, b"alice"), approve(2, b"carol")];
+
+    // Too few, repeated or invalid approvals are rejected
+    assert!(guarded.decrypt(0, domain, &ct, &approvals[..1]).is_err());
+    let repeated = vec![approve(0, b"alice"), approve(0, b"alice")];
+    assert!(guarded.decrypt(0, domain, &ct, &repeated).is_err());
+    let forged = vec![approve(0, b"alice"), approve(1, b"mallory")];
+    assert!(guarded.decrypt(0, domain, &ct, &forged).is_err());
+    assert_eq!(guarded.state().total(), 0);
+
+    assert_eq!(guarded.decrypt(10, domain, &ct, &approvals).unwrap(), msgs);
+    assert_eq!(guarded.decrypt(20, domain, &ct, &approvals).unwrap(), msgs);
+    assert!(guarded.decrypt(30, domain, &ct, &approvals).is_err());
+    assert_eq!(guarded.state().count(), 2);
+    assert_eq!(guarded.decrypt(70, domain, &ct, &approvals).unwrap(), msgs);
+    assert_eq!(guarded.state().count(), 1);
+    assert_eq!(guarded.state().total(), 3);
+
+    // The state resumes only if untouched
+    let json = serde_json::to_string(guarded.state()).unwrap();
+    let state: GuardState = serde_json::from_str(&json).unwrap();
+    let approvers = || {
+        vec![
+            TestAuthority(b"alice".to_vec()),
+            TestAuthority(b"bob".to_vec()),
+            TestAuthority(b"carol".to_vec()),
+        ]
+    };
+    let resumed =
+        GuardedDecryptionKey::resume(dk.clone(), policy, approvers(), state.clone()).unwrap();
+    assert_eq!(resumed.state(), guarded.state());
+    let tampered = json.replace("\"count\":1", "\"count\":0");
+    assert_ne!(tampered, json);
+    let tampered: GuardState = serde_json::from_str(&tampered).unwrap();
+    assert!(GuardedDecryptionKey::resume(dk.clone(), policy, approvers(), tampered).is_err());
+    let looser = DecryptionPolicy {
+        max_decryptions: 100,
+        ..policy
+    };
+    assert!(GuardedDecryptionKey::resume(dk, looser, approvers(), state).is_err());
+}
+
+#[test]
+fn split_custody() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let hsm = TestAuthority(b"hsm".to_vec());
+    let software = TestAuthority(b"software".to_vec());
+    let (tag_key, slot_key) = dk.split_custody();
+
+    let domain = b"split_custody_test";
+    let msgs = vec![BigNumber::from(5), BigNumber::from(6)];
+    let ct = ek.encrypt(domain, &msgs).unwrap();
+    let attestation = tag_key.attest(&hsm, domain, &ct).unwrap();
+    let partial = slot_key.partial_decrypt(&software, &ct).unwrap();
+    assert_eq!(attestation.ciphertext_hash(), &ct.digest());
+    assert_eq!(partial.ciphertext_hash(), &ct.digest());
+    let opened = vk
+        .decrypt_from_custody(domain, &ct, &attestation, &hsm, &partial, &software)
+        .unwrap();
+    assert_eq!(opened, msgs);
+
+    // The tag is checked under the domain
+    assert!(tag_key.attest(&hsm, b"wrong domain", &ct).is_err());
+    assert!(vk
+        .decrypt_from_custody(
+            b"wrong domain",
+            &ct,
+            &attestation,
+            &hsm,
+            &partial,
+            &software
+        )
+        .is_err());
+    // Signatures must come from the expected custodians
+    assert!(vk
+        .decrypt_from_custody(domain, &ct, &attestation, &software, &partial, &software)
+        .is_err());
+    assert!(vk
+        .decrypt_from_custody(domain, &ct, &attestation, &hsm, &partial, &hsm)
+        .is_err());
+    // Results for another ciphertext are rejected
+    let other = ek.encrypt(domain, &msgs).unwrap();
+    let other_partial = slot_key.partial_decrypt(&software, &other).unwrap();
+    assert!(vk
+        .decrypt_from_custody(domain, &ct, &attestation, &hsm, &other_partial, &software)
+        .is_err());
+    // Results survive serialization
+    let json = serde_json::to_string(&partial).unwrap();
+    let partial: SlotDecryption = serde_json::from_str(&json).unwrap();
+    assert!(vk
+        .decrypt_from_custody(domain, &ct, &attestation, &hsm, &partial, &software)
+        .is_ok());
+}
+
+#[test]
+fn threshold_decryption() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let msgs = vec![BigNumber::from(42), BigNumber::from(7)];
+    let context = b"threshold";
+    let ct = ek.encrypt(context, &msgs).unwrap();
+
+    assert!(dk.split_threshold(0, 3).is_err());
+    assert!(dk.split_threshold(4, 3).is_err());
+    let shares = dk.split_threshold(3, 5).unwrap();
+    assert_eq!(shares.len(), 5);
+    assert_eq!(shares[4].index(), 5);
+    let partials = shares
+        .iter()
+        .map(|share| share.partial_decrypt(context, &ct).unwrap())
+        .collect::<Vec<_>>();
+
+    // Any three trustees decrypt
+    for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+        let chosen = subset
+            .iter()
+            .map(|i| partials[*i].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            threshold::combine(&vk, context, &ct, &chosen).unwrap(),
+            msgs
+        );
+    }
+    assert_eq!(
+        threshold::combine(&vk, context, &ct, &partials).unwrap(),
+        msgs
+    );
+
+    // A share round trips through serde
+    let json = serde_json::to_string(&shares[3]).unwrap();
+    let share: threshold::DecryptionKeyShare = serde_json::from_str(&json).unwrap();
+    let mut chosen = vec![share.partial_decrypt(context, &ct).unwrap()];
+    chosen.extend(partials[..2].iter().cloned());
+    assert_eq!(
+        threshold::combine(&vk, context, &ct, &chosen).unwrap(),
+        msgs
+    );
+
+    assert!(threshold::combine(&vk, context, &ct, &partials[..2]).is_err());
+    assert!(threshold::combine(&vk, context, &ct, &[]).is_err());
+    let duplicated = [
+        partials[0].clone(),
+        partials[1].clone(),
+        partials[0].clone(),
+    ];
+    assert!(threshold::combine(&vk, context, &ct, &duplicated).is_err());
+    assert!(threshold::combine(&vk, b"other", &ct, &partials).is_err());
+    let other_context = shares
+        .iter()
+        .map(|share| share.partial_decrypt(b"other", &ct).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        threshold::combine(&vk, b"other", &ct, &other_context)
+            .unwrap_err()
+            .to_string(),
+        "u^2 != v^2"
+    );
+    let other_ct = ek.encrypt(context, &msgs).unwrap();
+    assert!(threshold::combine(&vk, context, &other_ct, &partials).is_err());
+
+    // A wrong partial fails the tag check
+    let other_shares = dk.split_threshold(3, 5).unwrap();
+    let mixed = [
+        partials[0].clone(),
+        partials[1].clone(),
+        other_shares[2].partial_decrypt(context, &ct).unwrap(),
+    ];
+    assert!(threshold::combine(&vk, context, &ct, &mixed).is_err());
+}
+
+#[test]
+fn distributed_key_generation() {
+    use verenc::dkg::*;
+
+    assert!(DkgParameters::new(2, 2, 64, 1).is_err());
+    assert!(DkgParameters::new(3, 4, 64, 1).is_err());
+    let params = DkgParameters::new(3, 2, 64, 2).unwrap();
+    assert!(DkgParty::new(params.clone(), 0).is_err());
+    let mut parties = (1..=3)
+        .map(|i| DkgParty::new(params.clone(), i).unwrap())
+        .collect::<Vec<_>>();
+    assert!(parties[0].receive_modulus_products(&[]).is_err());
+
+    let (commitments, shares) = loop {
+        let dealt = parties
+            .iter_mut()
+            .map(|p| p.start().unwrap())
+            .collect::<Vec<_>>();
+        let products = parties
+            .iter_mut()
+            .enumerate()
+            .map(|(i, p)| {
+                let mine = dealt.iter().map(|d| d[i].clone()).collect::<Vec<_>>();
+                p.receive_modulus_shares(&mine).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let tests = parties
+            .iter_mut()
+            .map(|p| p.receive_modulus_products(&products).unwrap())
+            .collect::<Vec<_>>();
+        // Malformed shares are refused and the party can still take the right ones
+        let mut short = serde_json::to_value(&tests[1]).unwrap();
+        short["values"].as_array_mut().unwrap().pop();
+        let short = vec![
+            tests[0].clone(),
+            serde_json::from_value(short).unwrap(),
+            tests[2].clone(),
+        ];
+        assert!(parties[0].receive_biprimality_shares(&short).is_err());
+        let outcomes = parties
+            .iter_mut()
+            .map(|p| p.receive_biprimality_shares(&tests).unwrap())
+            .collect::<Vec<_>>();
+        let mut commitments = Vec::new();
+        let mut shares = Vec::new();
+        for outcome in outcomes {
+            match outcome {
+                BiprimalityOutcome::Rejected => {}
+                BiprimalityOutcome::Accepted {
+                    commitment,
+                    shares: s,
+                } => {
+                    commitments.push(commitment);
+                    shares.push(s);
+                }
+            }
+        }
+        if !commitments.is_empty() {
+            // Every party reaches the same outcome
+            assert_eq!(commitments.len(), 3);
+            break (commitments, shares);
+        }
+    };
+
+    // A tampered share fails the commitment check
+    let mut tampered = serde_json::to_value(&shares[1][0]).unwrap();
+    tampered["values"][0] = serde_json::to_value(BigNumber::from(5)).unwrap();
+    let mut mine = shares.iter().map(|s| s[0].clone()).collect::<Vec<_>>();
+    mine[1] = serde_json::from_value(tampered).unwrap();
+    assert_eq!(
+        parties[0]
+            .receive_key_material(&commitments, &mine)
+            .unwrap_err()
+            .to_string(),
+        "Key share of party 2 is invalid"
+    );
+    assert!(parties[0]
+        .receive_key_material(&commitments, &mine[..2])
+        .is_err());
+
+    let outputs = parties
+        .iter_mut()
+        .enumerate()
+        .map(|(i, p)| {
+            let mine = shares.iter().map(|s| s[i].clone()).collect::<Vec<_>>();
+            p.receive_key_material(&commitments, &mine).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let ek = &outputs[0].encryption_key;
+    let vk = VerificationKey::from(ek);
+    let other = VerificationKey::from(&outputs[2].encryption_key);
+    assert_eq!(vk.group().n(), other.group().n());
+    assert_eq!(vk.group().n().to_bytes().len(), 16);
+    // The modulus is a product of two large primes that a group can be read back with
+    let n = vk.group().n();
+    assert!(!n.is_prime());
+    assert!((3..2000u64)
+        .step_by(2)
+        .all(|d| n % BigNumber::from(d) != BigNumber::zero()));
+    let group: Group = serde_json::from_value(serde_json::to_value(vk.group()).unwrap()).unwrap();
+    assert_eq!(group.fingerprint(), vk.group().fingerprint());
+
+    let msgs = vec![BigNumber::from(42), BigNumber::from(7)];
+    let context = b"dkg";
+    let ct = ek.encrypt(context, &msgs).unwrap();
+    for subset in [[0, 1], [2, 0], [1, 2]] {
+        let partials = subset
+            .iter()
+            .map(|i| outputs[*i].share.partial_decrypt(context, &ct).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            threshold::combine(&vk, context, &ct, &partials).unwrap(),
+            msgs
+        );
+    }
+
+    // Nothing was dealt to a party that didn't take part
+    let mut fresh = DkgParty::new(params, 1).unwrap();
+    assert!(fresh.receive_key_material(&commitments, &[]).is_err());
+}
+
+#[test]
+fn distributed_key_generation_rejects_small_factor() {
+    use verenc::dkg::*;
+
+    let params = DkgParameters::new(3, 2, 64, 1).unwrap();
+    let mut parties = (1..=3)
+        .map(|i| DkgParty::new(params.clone(), i).unwrap())
+        .collect::<Vec<_>>();
+    let dealt = parties
+        .iter_mut()
+        .map(|p| p.start().unwrap())
+        .collect::<Vec<_>>();
+    let products = parties
+        .iter_mut()
+        .enumerate()
+        .map(|(i, p)| {
+            let mine = dealt.iter().map(|d| d[i].clone()).collect::<Vec<_>>();
+            p.receive_modulus_shares(&mine).unwrap()
+        })
+        .collect::<Vec<_>>();
+    // Every point on the same constant interpolates to it
+    let planted = BigNumber::from(3) * BigNumber::prime(128);
+    let products = products
+        .iter()
+        .map(|product| {
+            let mut product = serde_json::to_value(product).unwrap();
+            product["value"] = serde_json::to_value(&planted).unwrap();
+            serde_json::from_value(product).unwrap()
+        })
+        .collect::<Vec<ModulusProduct>>();
+    let tests = parties
+        .iter_mut()
+        .map(|p| p.receive_modulus_products(&products).unwrap())
+        .collect::<Vec<_>>();
+    for party in parties.iter_mut() {
+        assert!(matches!(
+            party.receive_biprimality_shares(&tests).unwrap(),
+            BiprimalityOutcome::Rejected
+        ));
+    }
+    // A rejected attempt leaves the parties ready for another
+    assert!(parties.iter_mut().all(|p| p.start().is_ok()));
+}
@@ -0,0 +1,528 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn export_for_mpc() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+
+    // BLS12-381 scalar field
+    let field = BigNumber::from_slice(
+        hex::decode("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001").unwrap(),
+    );
+    let export = dk.export_for_mpc(&field, 248).unwrap();
+    let names = export
+        .exponents
+        .iter()
+        .map(|e| e.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["x1[0]", "x1[1]", "x2", "x3"]);
+    for e in &export.exponents {
+        assert_eq!(e.chunks.len(), export.exponents[0].chunks.len());
+        assert!(e.chunks.iter().all(|c| c < &field));
+    }
+
+    let json = serde_json::to_string(&export).unwrap();
+    let export: MpcKeyExport = serde_json::from_str(&json).unwrap();
+    let dk2 = export.to_decryption_key().unwrap();
+    let msgs = vec![BigNumber::from(8), BigNumber::from(9)];
+    let ct = ek.encrypt(b"export_for_mpc_test", &msgs).unwrap();
+    assert_eq!(dk2.decrypt(b"export_for_mpc_test", &ct).unwrap(), msgs);
+
+    assert!(dk.export_for_mpc(&field, 255).is_err());
+    assert!(dk.export_for_mpc(&field, 0).is_err());
+
+    // A key with no slots has only x2 and x3 and still round trips
+    let (ek, dk) = group.new_keys(0).unwrap();
+    let export = dk.export_for_mpc(&field, 248).unwrap();
+    assert_eq!(export.exponents.len(), 2);
+    let dk2 = export.to_decryption_key().unwrap();
+    assert_eq!(
+        VerificationKey::from(&dk2).fingerprint(),
+        ek.verification_key().fingerprint()
+    );
+    let ct = ek.encrypt(b"export_for_mpc_test", &[]).unwrap();
+    assert!(dk2.decrypt(b"export_for_mpc_test", &ct).unwrap().is_empty());
+    let mut truncated = export.clone();
+    truncated.exponents.pop();
+    assert!(truncated.to_decryption_key().is_err());
+}
+
+#[test]
+fn child_keys() {
+    let group = test_group();
+    let (_, parent) = group.new_keys(1).unwrap();
+    let child = parent.derive_child(&[1, 7]);
+    assert_eq!(
+        serde_json::to_string(&child).unwrap(),
+        serde_json::to_string(&parent.derive_child(&[1]).derive_child(&[7])).unwrap()
+    );
+    let ek = EncryptionKey::from(&child);
+
+    let domain = b"child_keys_test";
+    let msgs = vec![BigNumber::from(12)];
+    let ct = ek.encrypt(domain, &msgs).unwrap();
+    assert_eq!(child.decrypt(domain, &ct).unwrap(), msgs);
+    assert!(parent.decrypt(domain, &ct).is_err());
+
+    let (opened, record) = parent.decrypt_for_child(&[1, 7], domain, &ct).unwrap();
+    assert_eq!(opened, msgs);
+    assert_eq!(record.path, vec![1, 7]);
+    assert_eq!(record.ciphertext_hash, ct.digest());
+    assert_eq!(record.domain, domain.to_vec());
+    assert!(parent.decrypt_for_child(&[1, 8], domain, &ct).is_err());
+    assert!(parent.decrypt_for_child(&[7, 1], domain, &ct).is_err());
+}
+
+#[test]
+fn manifest() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(1).unwrap();
+    let domain = b"manifest_test";
+    let cts = (0..5)
+        .map(|i| ek.encrypt(domain, &[BigNumber::from(i)]).unwrap())
+        .collect::<Vec<_>>();
+
+    for count in 1..=cts.len() {
+        let manifest = Manifest::new(&cts[..count]);
+        assert_eq!(manifest.len(), count);
+        let root = manifest.root();
+        for (i, ct) in cts[..count].iter().enumerate() {
+            let proof = manifest.prove(i).unwrap();
+            assert_eq!(proof.index(), i);
+            assert!(proof.verify(&root, &ct.digest()));
+            // Wrong leaf or root fail
+            assert!(!proof.verify(&root, &cts[(i + 1) % cts.len()].digest()));
+            assert!(!proof.verify(&[0u8; 32], &ct.digest()));
+        }
+        assert!(manifest.prove(count).is_none());
+    }
+
+    let manifest = Manifest::new(&cts);
+    let json = serde_json::to_string(&manifest.prove(4).unwrap()).unwrap();
+    let proof: InclusionProof = serde_json::from_str(&json).unwrap();
+    assert!(proof.verify(&manifest.root(), &cts[4].digest()));
+    // Reordering changes the root
+    let reversed = cts.iter().rev().cloned().collect::<Vec<_>>();
+    assert_ne!(Manifest::new(&reversed).root(), manifest.root());
+    assert!(Manifest::new(&[]).is_empty());
+    assert_ne!(Manifest::new(&[]).root(), Manifest::new(&cts[..1]).root());
+}
+
+#[test]
+fn key_ring() {
+    let group = test_group();
+    let (old, _) = group.new_keys(1).unwrap();
+    let (new, _) = group.new_keys(1).unwrap();
+    let mut ring = KeyRing::new();
+    let old_fingerprint = ring.insert(old.clone(), 100, None);
+    let new_fingerprint = ring.insert(new.clone(), 200, None);
+    assert_ne!(old_fingerprint, new_fingerprint);
+    assert_eq!(
+        ring.current(150).unwrap().verification_key().fingerprint(),
+        old_fingerprint
+    );
+    assert_eq!(
+        ring.current(250).unwrap().verification_key().fingerprint(),
+        new_fingerprint
+    );
+    assert!(ring.current(50).is_none());
+    ring.retire(&old_fingerprint, 300).unwrap();
+
+    let nonce = b"key_ring_test";
+    let msgs = vec![BigNumber::from(12)];
+    let (ct, proof) = old.encrypt_and_prove(nonce, &msgs).unwrap();
+    let bound = KeyBoundProof::new(old.verification_key(), proof.clone());
+    assert_eq!(bound.key_fingerprint(), &old_fingerprint);
+    assert!(ring.verify_any(250, nonce, &ct, &bound).is_ok());
+    // Outside the old key's window
+    assert!(ring.verify_any(301, nonce, &ct, &bound).is_err());
+    assert!(ring.verify_any(99, nonce, &ct, &bound).is_err());
+    // Naming another key doesn't help
+    let wrong = KeyBoundProof::new(new.verification_key(), proof);
+    assert!(ring.verify_any(250, nonce, &ct, &wrong).is_err());
+
+    let (ct, proof) = new.encrypt_and_prove(nonce, &msgs).unwrap();
+    let bound = KeyBoundProof::new(new.verification_key(), proof);
+    let json = serde_json::to_string(&ring).unwrap();
+    let ring: KeyRing = serde_json::from_str(&json).unwrap();
+    assert_eq!(ring.entries().len(), 2);
+    assert!(ring.verify_any(1000, nonce, &ct, &bound).is_ok());
+    assert!(KeyRing::new().verify_any(1000, nonce, &ct, &bound).is_err());
+}
+
+/// Stand in for a KMS, a keystream and tag derived from a secret
+struct TestProtector(Vec<u8>);
+
+impl TestProtector {
+    fn keystream(&self, nonce: &[u8], associated_data: &[u8], len: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut transcript = merlin::Transcript::new(b"test protector");
+        transcript.append_message(b"secret", &self.0);
+        transcript.append_message(b"nonce", nonce);
+        transcript.append_message(b"associated_data", associated_data);
+        let mut stream = vec![0u8; len];
+        transcript.challenge_bytes(b"stream", &mut stream);
+        let mut tag = vec![0u8; 32];
+        transcript.challenge_bytes(b"tag", &mut tag);
+        (stream, tag)
+    }
+
+    fn tag(&self, ciphertext: &[u8], tag_key: &[u8]) -> Vec<u8> {
+        let mut transcript = merlin::Transcript::new(b"test protector tag");
+        transcript.append_message(b"key", tag_key);
+        transcript.append_message(b"ciphertext", ciphertext);
+        let mut tag = vec![0u8; 32];
+        transcript.challenge_bytes(b"tag", &mut tag);
+        tag
+    }
+}
+
+impl KeyProtector for TestProtector {
+    fn key_id(&self) -> String {
+        "test-kms-key".to_string()
+    }
+
+    fn protect(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = BigNumber::random(&(BigNumber::one() << 128)).to_bytes();
+        let (stream, tag_key) = self.keystream(&nonce, associated_data, plaintext.len());
+        let mut out = vec![nonce.len() as u8];
+        out.extend_from_slice(&nonce);
+        let body = plaintext
+            .iter()
+            .zip(stream.iter())
+            .map(|(p, k)| p ^ k)
+            .collect::<Vec<u8>>();
+        out.extend_from_slice(&self.tag(&body, &tag_key));
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    fn unprotect(&self, ciphertext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce_len = *ciphertext.first().ok_or("empty")? as usize;
+        let nonce = &ciphertext[1..1 + nonce_len];
+        let tag = &ciphertext[1 + nonce_len..33 + nonce_len];
+        let body = &ciphertext[33 + nonce_len..];
+        let (stream, tag_key) = self.keystream(nonce, associated_data, body.len());
+        if self.tag(body, &tag_key) != tag {
+            return Err("authentication failed".to_string());
+        }
+        Ok(body.iter().zip(stream.iter()).map(|(c, k)| c ^ k).collect())
+    }
+}
+
+#[test]
+fn export_protected() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let kms = TestProtector(b"kms secret".to_vec());
+    let protected = dk.export_protected(&kms).unwrap();
+    assert_eq!(protected.key_id(), "test-kms-key");
+    assert_eq!(
+        protected.fingerprint(),
+        &ek.verification_key().fingerprint()
+    );
+
+    let json = serde_json::to_string(&protected).unwrap();
+    let protected: ProtectedKey = serde_json::from_str(&json).unwrap();
+    let restored = DecryptionKey::import_protected(&protected, &kms).unwrap();
+    let domain = b"export_protected_test";
+    let msgs = vec![BigNumber::from(14), BigNumber::from(15)];
+    let ct = ek.encrypt(domain, &msgs).unwrap();
+    assert_eq!(restored.decrypt(domain, &ct).unwrap(), msgs);
+
+    // Another wrapping key or a changed fingerprint fail
+    let other = TestProtector(b"other secret".to_vec());
+    assert!(DecryptionKey::import_protected(&protected, &other).is_err());
+    let (other_ek, _) = group.new_keys(2).unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["fingerprint"] = serde_json::to_value(other_ek.verification_key().fingerprint()).unwrap();
+    let forged: ProtectedKey = serde_json::from_value(value).unwrap();
+    assert!(DecryptionKey::import_protected(&forged, &kms).is_err());
+}
+
+#[test]
+fn scoped_decryption_key() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let msgs = vec![BigNumber::from(5)];
+    let scoped = ScopedDecryptionKey::new(
+        dk,
+        vec![
+            LabelScope::Prefix(b"tax/".to_vec()),
+            LabelScope::Exact(b"court order 17".to_vec()),
+        ],
+    );
+    let ct = ek.encrypt(b"tax/2024", &msgs).unwrap();
+    assert_eq!(scoped.decrypt(b"tax/2024", &ct).unwrap(), msgs);
+    let ct = ek.encrypt(b"court order 17", &msgs).unwrap();
+    assert_eq!(scoped.decrypt(b"court order 17", &ct).unwrap(), msgs);
+    let ct = ek.encrypt(b"court order 18", &msgs).unwrap();
+    assert!(scoped.decrypt(b"court order 18", &ct).is_err());
+    let ct = ek.encrypt(b"marketing/2024", &msgs).unwrap();
+    assert_eq!(
+        scoped.decrypt(b"marketing/2024", &ct),
+        Err(VerencError::Other(
+            "Label is not in the scope of this key".to_string()
+        ))
+    );
+    let context = Context::new(b"tax/2025");
+    let ct = ek.encrypt(&context, &msgs).unwrap();
+    assert_eq!(scoped.decrypt(&context, &ct).unwrap(), msgs);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn scoped_decryption_key_pattern() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let msgs = vec![BigNumber::from(5)];
+    let pattern = regex::bytes::Regex::new(r"^case-[0-9]+$").unwrap();
+    let scoped = ScopedDecryptionKey::new(dk, vec![LabelScope::Pattern(pattern)]);
+    let ct = ek.encrypt(b"case-42", &msgs).unwrap();
+    assert_eq!(scoped.decrypt(b"case-42", &ct).unwrap(), msgs);
+    let ct = ek.encrypt(b"case-42x", &msgs).unwrap();
+    assert!(scoped.decrypt(b"case-42x", &ct).is_err());
+}
+
+#[derive(Debug, PartialEq)]
+struct Credential {
+    age: u64,
+    name: String,
+    over_18: bool,
+    id: Vec<u8>,
+}
+
+impl SlotRecord for Credential {
+    fn to_slots(&self) -> Vec<(&'static str, SlotValue)> {
+        vec![
+            ("age", SlotValue::U64(self.age)),
+            ("name", SlotValue::Text(self.name.clone())),
+            ("over_18", SlotValue::Bool(self.over_18)),
+            ("id", SlotValue::Bytes(self.id.clone())),
+        ]
+    }
+
+    fn from_slots(values: &SlotValues) -> Result<Self, String> {
+        Ok(Self {
+            age: values.u64("age")?,
+            name: values.text("name")?.to_string(),
+            over_18: values.bool("over_18")?,
+            id: values.bytes("id")?.to_vec(),
+        })
+    }
+}
+
+#[test]
+fn slot_schema() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(4).unwrap();
+    // The schema's order differs from the record's
+    let schema = SlotSchema::new([
+        ("id", SlotType::Bytes),
+        ("over_18", SlotType::Bool),
+        ("name", SlotType::Text),
+        ("age", SlotType::U64),
+    ])
+    .unwrap();
+    let ek = ek.with_schema(schema.clone()).unwrap();
+
+    // The auditor gets the schema with the key
+    let json = serde_json::to_string(&VerificationKey::from(&ek)).unwrap();
+    let vk: VerificationKey = serde_json::from_str(&json).unwrap();
+    assert_eq!(vk.schema(), Some(&schema));
+    assert_eq!(vk.fingerprint(), VerificationKey::from(&ek).fingerprint());
+
+    let credential = Credential {
+        age: 42,
+        name: "Alice".to_string(),
+        over_18: true,
+        id: vec![0, 0, 7],
+    };
+    let msgs = ek.schema().unwrap().encode_record(&credential).unwrap();
+    assert_eq!(msgs[3], BigNumber::from(42));
+    let domain = b"slot_schema_test";
+    let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+    assert!(vk.verify(domain, &ct, &proof).is_ok());
+    let decrypted = dk.decrypt(domain, &ct).unwrap();
+    let decoded: Credential = vk.schema().unwrap().decode_record(&decrypted).unwrap();
+    assert_eq!(decoded, credential);
+    assert_eq!(
+        schema.decode(&decrypted).unwrap().get("over_18"),
+        Some(&SlotValue::Bool(true))
+    );
+
+    assert!(schema.encode(&[("age", SlotValue::U64(1))]).is_err());
+    let mut slots = credential.to_slots();
+    slots[0].1 = SlotValue::Bool(false);
+    assert!(schema.encode(&slots).is_err());
+    assert!(schema.decode(&decrypted[..3]).is_err());
+    let swapped = [
+        decrypted[3].clone(),
+        decrypted[1].clone(),
+        decrypted[2].clone(),
+        decrypted[0].clone(),
+    ];
+    assert!(schema.decode_record::<Credential>(&swapped).is_err());
+
+    assert_eq!(
+        vk.project(&[1, 3]).unwrap().schema(),
+        SlotSchema::new([("over_18", SlotType::Bool), ("age", SlotType::U64)])
+            .ok()
+            .as_ref()
+    );
+    assert_ne!(
+        schema.digest(),
+        vk.project(&[1, 3]).unwrap().schema().unwrap().digest()
+    );
+    assert!(SlotSchema::new([("a", SlotType::U64), ("a", SlotType::Bool)]).is_err());
+    assert!(serde_json::from_str::<SlotSchema>(
+        r#"[{"name":"a","slot_type":"U64"},{"name":"a","slot_type":"U64"}]"#
+    )
+    .is_err());
+    let (small, _) = group.new_keys(2).unwrap();
+    assert!(small.with_schema(schema).is_err());
+}
+
+#[test]
+fn lifecycle_metadata() {
+    let plain = test_group();
+    assert!(plain.lifecycle().is_none());
+    assert!(!serde_json::to_string(&plain).unwrap().contains("lifecycle"));
+    let params = Lifecycle {
+        created_at: Some(1_000),
+        security_bits: None,
+        retire_at: Some(10_000),
+    };
+    let group = plain.clone().with_lifecycle(params.clone());
+    let restored: Group = serde_json::from_str(&serde_json::to_string(&group).unwrap()).unwrap();
+    assert_eq!(restored.lifecycle(), Some(&params));
+    assert_eq!(restored.fingerprint(), plain.fingerprint());
+
+    let (ek, _) = group.new_keys(1).unwrap();
+    let key_lifecycle = Lifecycle {
+        created_at: Some(5_000),
+        security_bits: Some(112),
+        retire_at: Some(8_000),
+    };
+    let fingerprint = ek.verification_key().fingerprint();
+    let ek = ek.with_lifecycle(key_lifecycle.clone());
+    let vk = ek.verification_key().clone();
+    assert_eq!(vk.fingerprint(), fingerprint);
+    let restored: VerificationKey =
+        serde_json::from_str(&serde_json::to_string(&vk).unwrap()).unwrap();
+    assert_eq!(restored.lifecycle(), Some(&key_lifecycle));
+    assert_eq!(restored.group().lifecycle(), Some(&params));
+
+    // Nothing is flagged while current
+    let policy = ExpiryPolicy::new();
+    assert!(policy.check_key(&vk, 6_000).is_empty());
+    policy.enforce(&vk, 6_000).unwrap();
+
+    // The key retires before its group
+    let policy = ExpiryPolicy::new().with_warning(2_500);
+    assert_eq!(
+        policy.check_key(&vk, 6_000),
+        [ExpiryFinding {
+            subject: ExpirySubject::Key,
+            reason: ExpiryReason::RetiringSoon { retire_at: 8_000 },
+        }]
+    );
+    policy.enforce(&vk, 6_000).unwrap();
+    let findings = policy.check_key(&vk, 8_000);
+    assert_eq!(
+        findings[0].reason,
+        ExpiryReason::Retired { retire_at: 8_000 }
+    );
+    assert!(policy.enforce(&vk, 8_000).is_err());
+    assert_eq!(
+        ExpiryPolicy::new().check_group(&group, 10_000)[0].reason,
+        ExpiryReason::Retired { retire_at: 10_000 }
+    );
+
+    // 2048-bit parameters without a stated level are rated at 112 bits
+    let strict = ExpiryPolicy::new()
+        .with_min_security_bits(128)
+        .with_max_age(3_000);
+    assert_eq!(
+        strict.check_group(&plain, 0),
+        [ExpiryFinding {
+            subject: ExpirySubject::Group,
+            reason: ExpiryReason::TooWeak { security_bits: 112 },
+        }]
+    );
+    let reasons = strict
+        .check_key(&vk, 6_000)
+        .into_iter()
+        .map(|f| (f.subject, f.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        [
+            (
+                ExpirySubject::Key,
+                ExpiryReason::TooWeak { security_bits: 112 }
+            ),
+            (
+                ExpirySubject::Group,
+                ExpiryReason::TooOld { created_at: 1_000 }
+            ),
+            (
+                ExpirySubject::Group,
+                ExpiryReason::TooWeak { security_bits: 112 }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn derived_keys() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(4).unwrap();
+    let master = ek.verification_key();
+    let indices = [1, 3];
+    let (derived, proof) = ek.derive(b"party a", &indices).unwrap();
+    let vk = derived.verification_key();
+    assert_eq!(proof.indices(), &indices);
+    assert_eq!(vk.max_messages(), 2);
+    assert_ne!(vk.group().g(), group.g());
+    assert_ne!(vk.y1()[0], master.y1()[1]);
+    assert_ne!(vk.y2(), master.y2());
+
+    // Deterministic from the label and anyone can recompute it
+    let again = master.derive(b"party a", &indices).unwrap();
+    assert_eq!(again.fingerprint(), vk.fingerprint());
+    let (other, other_proof) = ek.derive(b"party b", &indices).unwrap();
+    assert_ne!(other.verification_key().fingerprint(), vk.fingerprint());
+
+    master.verify_derivation(vk, &proof).unwrap();
+    master
+        .verify_derivation(other.verification_key(), &other_proof)
+        .unwrap();
+    assert!(master.verify_derivation(vk, &other_proof).is_err());
+    let (unrelated, _) = group.new_keys(4).unwrap();
+    assert!(unrelated
+        .verification_key()
+        .verify_derivation(vk, &proof)
+        .is_err());
+
+    // The master decryption key opens it at the derived indices
+    let msgs = vec![BigNumber::from(5), BigNumber::from(6)];
+    let (ct, ct_proof) = derived.encrypt_and_prove(b"derived", &msgs).unwrap();
+    derived.verify(b"derived", &ct, &ct_proof).unwrap();
+    assert_eq!(
+        dk.decrypt_at_indices(b"derived", &indices, &ct)
+            .unwrap()
+            .into_messages()
+            .unwrap(),
+        msgs
+    );
+
+    assert!(ek.derive(b"party a", &[]).is_err());
+    assert!(ek.derive(b"party a", &[1, 4]).is_err());
+    assert!(ek.derive(b"party a", &[3, 1]).is_err());
+}
@@ -0,0 +1,168 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn label_expiry() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    let label = Label::new(b"escrow")
+        .field(b"regulator", b"x")
+        .not_after(1000);
+    let bytes = label.to_bytes();
+    assert_eq!(Label::from_bytes(&bytes).unwrap(), label);
+    assert!(Label::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    // Fields are framed so moving bytes between them changes the encoding
+    assert_ne!(
+        Label::new(b"escrow").field(b"ab", b"c").to_bytes(),
+        Label::new(b"escrow").field(b"a", b"bc").to_bytes()
+    );
+
+    let msgs = vec![BigNumber::from(1)];
+    let (ct, proof) = ek.encrypt_and_prove(&bytes, &msgs).unwrap();
+    assert!(vk.verify_with_label(&label, 999, &ct, &proof).is_ok());
+    assert!(ek.verify_with_label(&label, 1000, &ct, &proof).is_ok());
+    assert!(vk.verify_with_label(&label, 1001, &ct, &proof).is_err());
+    // Changing the expiry changes the label
+    let extended = label.clone().not_after(2000);
+    assert!(vk.verify_with_label(&extended, 1001, &ct, &proof).is_err());
+    assert_eq!(dk.decrypt(&bytes, &ct).unwrap(), msgs);
+
+    let forever = Label::new(b"escrow");
+    assert_eq!(forever.expiry(), None);
+    let (ct, proof) = ek.encrypt_and_prove(forever.to_bytes(), &msgs).unwrap();
+    assert!(vk
+        .verify_with_label(&forever, u64::MAX, &ct, &proof)
+        .is_ok());
+}
+
+#[test]
+fn session_labels() {
+    let base = Label::new(b"payments").field(b"tenant", b"a");
+    let label = Label::derive_session(&base, b"session 1", 7);
+    assert_eq!(label.session(), Some((&b"session 1"[..], 7)));
+    assert_eq!(label.context(), b"payments");
+    assert_eq!(base.session(), None);
+    assert_ne!(
+        label.to_bytes(),
+        Label::derive_session(&base, b"session 2", 7).to_bytes()
+    );
+
+    let mut labels = SessionLabels::new(base.clone(), b"session 1");
+    let first = labels.next_label().unwrap();
+    let second = labels.next_label().unwrap();
+    assert_eq!(first.session().unwrap().1, 0);
+    assert_eq!(second.session().unwrap().1, 1);
+    assert_eq!(labels.counter(), Some(2));
+    let mut last = SessionLabels::resume(base.clone(), b"session 1", u64::MAX);
+    assert!(last.next_label().is_ok());
+    assert!(last.next_label().is_err());
+
+    let mut detector = LabelReuseDetector::new();
+    assert!(detector.record(&first.to_bytes()).is_ok());
+    assert!(detector.record(&second.to_bytes()).is_ok());
+    assert!(detector.record(&second.to_bytes()).is_err());
+    // Out of order in the same session
+    assert!(detector
+        .record(&Label::derive_session(&base, b"session 1", 5).to_bytes())
+        .is_ok());
+    assert!(detector
+        .record(&Label::derive_session(&base, b"session 1", 3).to_bytes())
+        .is_err());
+    // Other sessions and plain nonces are independent
+    assert!(detector
+        .record(&Label::derive_session(&base, b"session 2", 0).to_bytes())
+        .is_ok());
+    assert!(detector.record(b"plain nonce").is_ok());
+    assert!(detector.record(b"plain nonce").is_err());
+    assert!(detector.contains(b"plain nonce"));
+    assert_eq!(detector.len(), 5);
+
+    // Session labels work as nonces
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let msgs = vec![BigNumber::from(5)];
+    let nonce = labels.next_label().unwrap().to_bytes();
+    let (ct, proof) = ek.encrypt_and_prove(&nonce, &msgs).unwrap();
+    assert!(ek.verify(&nonce, &ct, &proof).is_ok());
+    assert_eq!(dk.decrypt(&nonce, &ct).unwrap(), msgs);
+}
+
+#[test]
+fn search_tags() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let key = SearchKey::random();
+    let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+    let labels: [&[u8]; 3] = [b"subject 1", b"subject 2", b"subject 3"];
+
+    let mut index = SearchIndex::new();
+    for label in &labels {
+        let ct = ek.encrypt(*label, &msgs).unwrap();
+        index.insert(CipherTextEnvelope::new(&group, ct).with_search_tags(&key, label));
+    }
+    assert_eq!(index.len(), 3);
+
+    let tag = key.tag(b"subject 2", 1);
+    assert_eq!(tag, key.tag(b"subject 2", 1));
+    assert_ne!(tag, key.tag(b"subject 2", 0));
+    assert_ne!(tag, SearchKey::random().tag(b"subject 2", 1));
+    let found = index.find(&tag).collect::<Vec<_>>();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].has_search_tag(&tag));
+    assert_eq!(found[0].search_tags().len(), 2);
+
+    let warrant = Warrant::new(found[0].ciphertext(), b"subject 3", b"case 7");
+    let found = index
+        .find_for_warrant(&key, &warrant, 0)
+        .collect::<Vec<_>>();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].has_search_tag(&key.tag(b"subject 3", 0)));
+    assert_eq!(index.find(&key.tag(b"subject 4", 0)).count(), 0);
+
+    // Tags survive serialization and untagged envelopes still parse
+    let envelope = found[0].clone();
+    let json = serde_json::to_string(&envelope).unwrap();
+    let parsed: CipherTextEnvelope = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, envelope);
+    let untagged = CipherTextEnvelope::new(&group, envelope.ciphertext().clone());
+    let json = serde_json::to_string(&untagged).unwrap();
+    assert!(!json.contains("tags"));
+    let parsed: CipherTextEnvelope = serde_json::from_str(&json).unwrap();
+    assert!(parsed.search_tags().is_empty());
+    let restored = SearchKey::from_bytes(key.to_bytes());
+    assert_eq!(restored.tag(b"subject 1", 0), key.tag(b"subject 1", 0));
+}
+
+#[test]
+fn context() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+
+    // One context for every step
+    let label = Label::new(b"context").field(b"case", b"1");
+    let context = Context::from(&label);
+    let (ct, proof) = ek.encrypt_and_prove(&context, &msgs).unwrap();
+    assert!(vk.verify(&context, &ct, &proof).is_ok());
+    assert_eq!(dk.decrypt(&context, &ct).unwrap(), msgs);
+
+    // A label, its bytes and a context of them are the same value
+    let bytes = label.to_bytes();
+    assert_eq!(context.as_bytes(), &bytes[..]);
+    assert!(ek.verify(&label, &ct, &proof).is_ok());
+    assert!(vk.verify(&bytes, &ct, &proof).is_ok());
+    assert_eq!(dk.decrypt(Context::new(&bytes), &ct).unwrap(), msgs);
+    assert!(vk.verify(b"other", &ct, &proof).is_err());
+
+    let owned = Context::new(b"owned").into_owned();
+    let ct = ek.encrypt(&owned, &msgs).unwrap();
+    assert_eq!(dk.decrypt(b"owned", &ct).unwrap(), msgs);
+}
@@ -0,0 +1,166 @@
+#![cfg(all(feature = "parallel", feature = "testing"))]
+
+use std::sync::Arc;
+use verenc::{
+    testing, MultiKeyVerifierPool, ProofOptions, ProtocolVersion, VerencError, VerifierPool,
+};
+
+#[test]
+fn send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<VerifierPool>();
+    assert_send_sync::<MultiKeyVerifierPool>();
+}
+
+#[test]
+fn verify() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group(&mut rng);
+    let (ek, _) = group.new_keys(3).unwrap();
+    let pool = VerifierPool::new(ek.clone(), 2).unwrap();
+    let nonce = b"verifier pool";
+
+    for count in 1..=3 {
+        let msgs = testing::messages(&group, count, &mut rng);
+        let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+        assert!(pool.verify(nonce, &ct, &proof).is_ok());
+        assert!(pool.verify(b"wrong nonce", &ct, &proof).is_err());
+    }
+
+    let (other, _) = group.new_keys(3).unwrap();
+    let (ct, proof) = other
+        .encrypt_and_prove(nonce, &testing::messages(&group, 3, &mut rng))
+        .unwrap();
+    assert!(pool.verify(nonce, &ct, &proof).is_err());
+}
+
+#[test]
+fn verify_batch() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group(&mut rng);
+    let (ek, _) = group.new_keys(2).unwrap();
+    let pool = Arc::new(VerifierPool::new(ek.clone(), 0).unwrap());
+
+    let nonces = [b"first".to_vec(), b"second".to_vec(), b"third".to_vec()];
+    let proofs = nonces
+        .iter()
+        .map(|nonce| {
+            ek.encrypt_and_prove(nonce, &testing::messages(&group, 2, &mut rng))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let mut items = nonces
+        .iter()
+        .zip(proofs.iter())
+        .map(|(nonce, (ct, proof))| (nonce.as_slice(), ct, proof))
+        .collect::<Vec<_>>();
+    items[1].0 = b"wrong nonce";
+
+    let handle = {
+        let pool = pool.clone();
+        let items = items
+            .iter()
+            .map(|(n, c, p)| (n.to_vec(), (*c).clone(), (*p).clone()))
+            .collect::<Vec<_>>();
+        std::thread::spawn(move || {
+            let items = items
+                .iter()
+                .map(|(n, c, p)| (n.as_slice(), c, p))
+                .collect::<Vec<_>>();
+            pool.verify_batch(&items)
+        })
+    };
+    let results = pool.verify_batch(&items);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+    assert_eq!(handle.join().unwrap(), results);
+}
+
+#[test]
+fn with_options() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group(&mut rng);
+    let (ek, _) = group.new_keys(2).unwrap();
+    let options = ProofOptions {
+        prehash_e: true,
+        version: ProtocolVersion::V2,
+    };
+    let nonce = b"verifier pool options";
+    let msgs = testing::messages(&group, 2, &mut rng);
+    let (ct, proof) = ek
+        .view()
+        .with_options(options)
+        .encrypt_and_prove(nonce, &msgs)
+        .unwrap();
+
+    let pool = VerifierPool::new(ek, 1).unwrap();
+    assert!(pool.verify(nonce, &ct, &proof).is_err());
+    let pool = pool.with_options(options);
+    assert!(pool.verify(nonce, &ct, &proof).is_ok());
+}
+
+#[test]
+fn verify_batch_many_keys() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group(&mut rng);
+    let other_group = loop {
+        let g = testing::group(&mut rng);
+        if g.fingerprint() != group.fingerprint() {
+            break g;
+        }
+    };
+    let keys = [
+        group.new_keys(2).unwrap().0,
+        group.new_keys(1).unwrap().0,
+        other_group.new_keys(2).unwrap().0,
+    ];
+    let mut pool = MultiKeyVerifierPool::new(0).unwrap();
+    let fingerprints = keys
+        .iter()
+        .map(|ek| pool.insert(ek.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(pool.len(), 3);
+    assert_eq!(pool.num_groups(), 2);
+
+    let nonce = b"tenant traffic";
+    let proofs = keys
+        .iter()
+        .map(|ek| {
+            let group = ek.verification_key().group();
+            let count = ek.verification_key().max_messages();
+            ek.encrypt_and_prove(nonce, &testing::messages(group, count, &mut rng))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let unknown = [7u8; 32];
+    let items = [
+        (&fingerprints[2], &nonce[..], &proofs[2].0, &proofs[2].1),
+        (&fingerprints[0], &nonce[..], &proofs[0].0, &proofs[0].1),
+        // Proof for another key
+        (&fingerprints[1], &nonce[..], &proofs[0].0, &proofs[0].1),
+        (&fingerprints[1], &nonce[..], &proofs[1].0, &proofs[1].1),
+        (&unknown, &nonce[..], &proofs[1].0, &proofs[1].1),
+        (&fingerprints[2], &b"wrong"[..], &proofs[2].0, &proofs[2].1),
+    ];
+    let results = pool.verify_batch(&items);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_ok());
+    assert!(results[2].is_err());
+    assert!(results[3].is_ok());
+    let unknown_key = results[4].as_ref().unwrap_err();
+    assert_eq!(unknown_key.index, 4);
+    assert_eq!(unknown_key.ciphertext, proofs[1].0.digest());
+    assert_eq!(
+        unknown_key.source,
+        VerencError::Other("Unknown verification key".to_string())
+    );
+    assert!(results[5].is_err());
+    assert!(pool
+        .verify(&fingerprints[0], nonce, &proofs[0].0, &proofs[0].1)
+        .is_ok());
+    assert_eq!(
+        pool.get(&fingerprints[1]).unwrap().fingerprint(),
+        fingerprints[1]
+    );
+}
@@ -0,0 +1,154 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn parameter_presets() {
+    for preset in ParameterPreset::ALL.iter() {
+        let name = preset.to_string();
+        assert_eq!(name.parse::<ParameterPreset>().unwrap(), *preset);
+        assert_eq!(
+            serde_json::to_string(preset).unwrap(),
+            format!("\"{}\"", name)
+        );
+        assert_eq!(
+            ParameterPreset::for_modulus_bits(preset.modulus_bits()),
+            Some(*preset)
+        );
+    }
+    assert!("rsa1024".parse::<ParameterPreset>().is_err());
+
+    let group = test_group();
+    // The test primes are slightly short of 1024 bits
+    assert_eq!(group.modulus_bits(), 2045);
+    assert_eq!(group.preset(), None);
+
+    let mut json = serde_json::to_value(&group).unwrap();
+    assert_eq!(json["modulus_bits"], 2045);
+    let group2: Group = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(group2.n(), group.n());
+    json["modulus_bits"] = serde_json::Value::from(3072);
+    assert!(serde_json::from_value::<Group>(json).is_err());
+}
+
+#[test]
+fn from_rsa_private_key() {
+    let p = BigNumber::from(47);
+    let q = BigNumber::from(59);
+    let group = Group::from_rsa_private_key(&p, &q).unwrap();
+    assert_eq!(group.n(), &BigNumber::from(47 * 59));
+    assert!(Group::from_rsa_private_key(&p, &p).is_err());
+    // 41 is prime but not safe
+    assert!(Group::from_rsa_private_key(&p, &BigNumber::from(41)).is_err());
+    assert!(Group::from_rsa_private_key_relaxed(&p, &BigNumber::from(41)).is_ok());
+    assert!(Group::from_rsa_private_key(&p, &BigNumber::from(45)).is_err());
+
+    assert!(Group::from_rsa_private_key(&test_p(), &test_q()).is_err());
+    let group = Group::from_rsa_private_key_relaxed(&test_p(), &test_q()).unwrap();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let msgs = vec![BigNumber::from(10)];
+    let ct = ek.encrypt(b"from_rsa_private_key_test", &msgs).unwrap();
+    assert_eq!(dk.decrypt(b"from_rsa_private_key_test", &ct).unwrap(), msgs);
+}
+
+#[test]
+fn parameter_registry() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let mut registry = ParameterRegistry::new();
+    assert!(registry.is_empty());
+    let fingerprint = registry.insert(group.clone());
+    assert_eq!(fingerprint, group.fingerprint());
+    assert_eq!(registry.len(), 1);
+
+    let text = format!("# fleet parameters\n\n{}", registry.to_text());
+    let loaded = ParameterRegistry::from_text(&text).unwrap();
+    assert_eq!(
+        loaded.fingerprints().collect::<Vec<_>>(),
+        vec![&fingerprint]
+    );
+    assert_eq!(loaded.get(&fingerprint).unwrap().n(), group.n());
+
+    // Keys and ciphertexts name the group by fingerprint
+    let compact = ek.to_compact();
+    assert_eq!(compact.parameters(), &fingerprint);
+    let json = serde_json::to_string(&compact).unwrap();
+    let compact: CompactKey = serde_json::from_str(&json).unwrap();
+    let restored = loaded.encryption_key(&compact).unwrap();
+    let nonce = b"parameter_registry_test";
+    let msgs = vec![BigNumber::from(7), BigNumber::from(8)];
+    let (ct, proof) = restored.encrypt_and_prove(nonce, &msgs).unwrap();
+    assert!(ek.verify(nonce, &ct, &proof).is_ok());
+    let envelope = CipherTextEnvelope::new(&group, ct.clone());
+    assert_eq!(loaded.group_for(&envelope).unwrap().n(), group.n());
+    assert_eq!(envelope.ciphertext(), &ct);
+    assert!(ParameterRegistry::new().encryption_key(&compact).is_err());
+
+    // Entries whose fingerprint does not match are rejected
+    let mut tampered = registry.to_text();
+    tampered.pop();
+    let digit = tampered.pop().unwrap();
+    tampered.push(if digit == '3' { '5' } else { '3' });
+    assert!(ParameterRegistry::from_text(&tampered).is_err());
+    assert!(ParameterRegistry::from_text("00 11").is_err());
+    assert!(ParameterRegistry::from_text("zz 11 22").is_err());
+}
+
+#[test]
+fn paillier_blum_proof() {
+    let p = BigNumber::safe_prime(256);
+    let mut q = BigNumber::safe_prime(256);
+    while q == p {
+        q = BigNumber::safe_prime(256);
+    }
+    let group = Group::with_safe_primes_unchecked(&p, &q).unwrap();
+    let nonce = b"paillier_blum_proof_test";
+    let proof = group.prove_paillier_blum(nonce, &p, &q).unwrap();
+    assert_eq!(proof.responses().len(), PAILLIER_BLUM_ROUNDS);
+    assert!(group.verify_paillier_blum(nonce, &proof).is_ok());
+    assert!(group.verify_paillier_blum(b"wrong nonce", &proof).is_err());
+    let json = serde_json::to_string(&proof).unwrap();
+    let proof: PaillierBlumProof = serde_json::from_str(&json).unwrap();
+    assert!(group.verify_paillier_blum(nonce, &proof).is_ok());
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["responses"].as_array_mut().unwrap().pop();
+    let forged: PaillierBlumProof = serde_json::from_value(value).unwrap();
+    assert!(group.verify_paillier_blum(nonce, &forged).is_err());
+    let other = Group::with_safe_primes_unchecked(&p, &BigNumber::safe_prime(256)).unwrap();
+    assert!(other.verify_paillier_blum(nonce, &proof).is_err());
+
+    // The factors must match n and be 3 mod 4
+    assert!(group.prove_paillier_blum(nonce, &p, &p).is_err());
+    let test_group = test_group();
+    assert!(test_group
+        .prove_paillier_blum(nonce, &test_p(), &test_q())
+        .is_err());
+}
+
+#[test]
+fn key_generation_proof() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let nonce = b"key_generation_proof_test";
+    let proof = dk.prove_key_generation(nonce);
+    assert!(ek.verify_key_generation(nonce, &proof).is_ok());
+    assert!(ek.verify_key_generation(b"wrong nonce", &proof).is_err());
+    let json = serde_json::to_string(&proof).unwrap();
+    let proof: KeyGenerationProof = serde_json::from_str(&json).unwrap();
+    let vk = VerificationKey::from(&ek);
+    assert!(vk.verify_key_generation(nonce, &proof).is_ok());
+
+    // A proof for another key fails
+    let (other, _) = group.new_keys(2).unwrap();
+    assert!(other.verify_key_generation(nonce, &proof).is_err());
+    // Responses too large to come from an exponent below n^2 / 4 fail
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["x2"] = serde_json::to_value(BigNumber::one() << 8192).unwrap();
+    let forged: KeyGenerationProof = serde_json::from_value(value).unwrap();
+    assert!(vk.verify_key_generation(nonce, &forged).is_err());
+}
@@ -0,0 +1,695 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn prove_rerandomization() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+
+    let domain = b"prove_rerandomization_test";
+    let msgs = vec![BigNumber::from(5), BigNumber::from(6)];
+    let ct = ek.encrypt(domain, &msgs).unwrap();
+    let (ct2, factor) = ek.rerandomize(&ct).unwrap();
+    assert_ne!(ct, ct2);
+    assert!(dk.decrypt(domain, &ct2).is_err());
+    assert_eq!(dk.decrypt_untagged(&ct2).unwrap(), msgs);
+
+    let proof = ek.prove_rerandomization(&ct, &ct2, &factor).unwrap();
+    assert!(ek.verify_rerandomization(&ct, &ct2, &proof).is_ok());
+    let vk = VerificationKey::from(&ek);
+    assert!(vk.verify_rerandomization(&ct, &ct2, &proof).is_ok());
+
+    // A different ciphertext or wrong factor must not verify
+    let (ct3, _) = ek.rerandomize(&ek.encrypt(domain, &msgs).unwrap()).unwrap();
+    assert!(ek.verify_rerandomization(&ct, &ct3, &proof).is_err());
+    let bad = ek.prove_rerandomization(&ct, &ct2, &(&factor + 1)).unwrap();
+    assert!(ek.verify_rerandomization(&ct, &ct2, &bad).is_err());
+
+    // More values than the key has slots
+    let (wide, _) = group.new_keys(3).unwrap();
+    let ct = wide.encrypt(domain, &vec![BigNumber::from(1); 3]).unwrap();
+    assert_eq!(
+        ek.rerandomize(&ct).unwrap_err(),
+        VerencError::TooManyMessages {
+            messages: 3,
+            max: 2
+        }
+    );
+}
+
+#[test]
+fn packing() {
+    let values = [1u64, 0, 15, 7];
+    let packed = pack_messages(&values, 4).unwrap();
+    assert_eq!(packed, BigNumber::from(0x7f01));
+    assert_eq!(unpack_messages(&packed, 4, 4).unwrap(), values);
+    assert!(pack_messages(&[16], 4).is_err());
+    assert!(pack_messages(&[1], 0).is_err());
+    assert!(unpack_messages(&packed, 4, 3).is_err());
+    let max = [u64::MAX, 1];
+    assert_eq!(
+        unpack_messages(&pack_messages(&max, 64).unwrap(), 64, 2).unwrap(),
+        max
+    );
+}
+
+#[test]
+fn encrypt_and_prove_packed() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    let nonce = b"encrypt_and_prove_packed_test";
+    let values = [5u64, 0, 7];
+    let (ct, proof, range_proof) = ek.encrypt_and_prove_packed(nonce, &values, 3).unwrap();
+    assert!(vk
+        .verify_packed(nonce, &ct, &proof, &range_proof, 3, 3)
+        .is_ok());
+    assert!(ek
+        .verify_packed(nonce, &ct, &proof, &range_proof, 3, 3)
+        .is_ok());
+    let msgs = dk.decrypt(nonce, &ct).unwrap();
+    assert_eq!(unpack_messages(&msgs[0], 3, 3).unwrap(), values);
+
+    // Wrong dimensions or nonce
+    assert!(vk
+        .verify_packed(nonce, &ct, &proof, &range_proof, 2, 3)
+        .is_err());
+    assert!(vk
+        .verify_packed(nonce, &ct, &proof, &range_proof, 3, 4)
+        .is_err());
+    assert!(vk
+        .verify_packed(b"wrong nonce", &ct, &proof, &range_proof, 3, 3)
+        .is_err());
+
+    // A range proof for a different ciphertext
+    let (ct2, proof2, _) = ek.encrypt_and_prove_packed(nonce, &values, 3).unwrap();
+    assert!(vk
+        .verify_packed(nonce, &ct2, &proof2, &range_proof, 3, 3)
+        .is_err());
+
+    assert!(ek.encrypt_and_prove_packed(nonce, &[8], 3).is_err());
+}
+
+#[test]
+fn project() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(3).unwrap();
+
+    let projected = ek.project(&[0, 2]).unwrap();
+    assert_eq!(projected.verification_key().max_messages(), 2);
+    let vk = VerificationKey::from(&ek).project(&[0, 2]).unwrap();
+
+    let nonce = b"project_test";
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+    let (ct, proof) = projected.encrypt_and_prove(nonce, &msgs).unwrap();
+    assert!(vk.verify(nonce, &ct, &proof).is_ok());
+    assert!(ek.verify(nonce, &ct, &proof).is_err());
+    assert_eq!(
+        dk.decrypt_at_indices(nonce, &[0, 2], &ct)
+            .unwrap()
+            .into_messages()
+            .unwrap(),
+        msgs
+    );
+
+    assert!(ek.project(&[]).is_err());
+    assert!(ek.project(&[3]).is_err());
+    assert!(ek.project(&[2, 1]).is_err());
+}
+
+#[test]
+fn encrypt_and_prove_less_than() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    let nonce = b"encrypt_and_prove_less_than_test";
+    let bound = BigNumber::from(1000);
+    let msg = BigNumber::from(999);
+    let (ct, proof, lt_proof) = ek.encrypt_and_prove_less_than(nonce, &msg, &bound).unwrap();
+    assert!(vk
+        .verify_less_than(nonce, &ct, &proof, &lt_proof, &bound)
+        .is_ok());
+    assert!(ek
+        .verify_less_than(nonce, &ct, &proof, &lt_proof, &bound)
+        .is_ok());
+    assert_eq!(dk.decrypt(nonce, &ct).unwrap(), vec![msg.clone()]);
+
+    // A smaller bound with the same width or a different nonce fails
+    assert!(vk
+        .verify_less_than(nonce, &ct, &proof, &lt_proof, &BigNumber::from(999))
+        .is_err());
+    assert!(vk
+        .verify_less_than(b"wrong nonce", &ct, &proof, &lt_proof, &bound)
+        .is_err());
+
+    let (ct, proof, lt_proof) = ek
+        .encrypt_and_prove_less_than(nonce, &BigNumber::zero(), &BigNumber::one())
+        .unwrap();
+    assert!(vk
+        .verify_less_than(nonce, &ct, &proof, &lt_proof, &BigNumber::one())
+        .is_ok());
+
+    assert!(ek
+        .encrypt_and_prove_less_than(nonce, &bound, &bound)
+        .is_err());
+    assert!(ek
+        .encrypt_and_prove_less_than(nonce, &msg, &BigNumber::zero())
+        .is_err());
+}
+
+#[test]
+fn encrypt_and_prove_label_or() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    let msgs = vec![BigNumber::from(7), BigNumber::from(11)];
+    let labels: [&[u8]; 2] = [b"regulator x", b"regulator y"];
+    for index in 0..labels.len() {
+        let (ct, proof) = ek
+            .encrypt_and_prove_label_or(&labels, index, &msgs)
+            .unwrap();
+        assert!(vk.verify_label_or(&labels, &ct, &proof).is_ok());
+        assert!(ek.verify_label_or(&labels, &ct, &proof).is_ok());
+        assert_eq!(dk.decrypt(labels[index], &ct).unwrap(), msgs);
+        assert!(dk.decrypt(labels[1 - index], &ct).is_err());
+
+        // The proof doesn't hold for other or reordered labels
+        let wrong: [&[u8]; 2] = [labels[0], b"regulator z"];
+        assert!(vk.verify_label_or(&wrong, &ct, &proof).is_err());
+        let swapped = [labels[1], labels[0]];
+        assert!(vk.verify_label_or(&swapped, &ct, &proof).is_err());
+        assert!(vk.verify_label_or(&labels[..1], &ct, &proof).is_err());
+    }
+
+    // Any number of labels and the real one must be among them
+    let labels3: [&[u8]; 3] = [b"a", b"b", b"c"];
+    let (ct, proof) = ek.encrypt_and_prove_label_or(&labels3, 2, &msgs).unwrap();
+    assert!(vk.verify_label_or(&labels3, &ct, &proof).is_ok());
+    assert!(vk.verify_label_or(&labels3[..2], &ct, &proof).is_err());
+    assert!(ek.encrypt_and_prove_label_or(&labels, 2, &msgs).is_err());
+
+    // Branch challenges that don't sum to the challenge fail
+    let mut value = serde_json::to_value(&proof).unwrap();
+    let first = &mut value["branches"][0]["challenge"];
+    let shifted: BigNumber =
+        serde_json::from_value::<BigNumber>(first.clone()).unwrap() + BigNumber::one();
+    *first = serde_json::to_value(&shifted).unwrap();
+    let forged: LabelOrProof = serde_json::from_value(value).unwrap();
+    assert!(vk.verify_label_or(&labels3, &ct, &forged).is_err());
+
+    // Out of range responses are rejected before any exponentiation
+    for (field, value) in [
+        ("r", group.nn().clone()),
+        ("r", -group.nn().clone()),
+        ("m", group.nn().clone()),
+        ("m", -(group.nn() * BigNumber::from(2))),
+    ] {
+        let mut json = serde_json::to_value(&proof).unwrap();
+        let target = &mut json["branches"][1][field];
+        let target = if field == "m" { &mut target[0] } else { target };
+        *target = serde_json::to_value(&value).unwrap();
+        let forged: LabelOrProof = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            vk.verify_label_or(&labels3, &ct, &forged),
+            Err(VerencError::ProofOutOfRange)
+        );
+    }
+}
+
+#[test]
+fn escrow_secret_key() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    let nonce = b"escrow_secret_key_test";
+    let zero = BigNumber::zero();
+    let secret_key = BigNumber::random(&SchnorrGroup::order());
+    let public_key = SchnorrGroup::mul_add(&secret_key, &zero, &BigNumber::one());
+    let (ct, proof) = ek
+        .escrow_secret_key::<SchnorrGroup>(nonce, &secret_key, &public_key)
+        .unwrap();
+    assert!(vk
+        .verify_escrowed_secret_key::<SchnorrGroup>(nonce, &ct, &public_key, &proof)
+        .is_ok());
+    assert!(ek
+        .verify_escrowed_secret_key::<SchnorrGroup>(nonce, &ct, &public_key, &proof)
+        .is_ok());
+    assert_eq!(dk.decrypt(nonce, &ct).unwrap(), vec![secret_key.clone()]);
+
+    // A different public key or nonce fails
+    let other = SchnorrGroup::mul_add(&BigNumber::from(5), &zero, &BigNumber::one());
+    assert!(vk
+        .verify_escrowed_secret_key::<SchnorrGroup>(nonce, &ct, &other, &proof)
+        .is_err());
+    assert!(vk
+        .verify_escrowed_secret_key::<SchnorrGroup>(b"wrong nonce", &ct, &public_key, &proof)
+        .is_err());
+    // A valid encryption of something else fails
+    let (ct2, _) = ek
+        .escrow_secret_key::<SchnorrGroup>(nonce, &secret_key, &public_key)
+        .unwrap();
+    assert!(vk
+        .verify_escrowed_secret_key::<SchnorrGroup>(nonce, &ct2, &public_key, &proof)
+        .is_err());
+
+    assert!(ek
+        .escrow_secret_key::<SchnorrGroup>(nonce, &BigNumber::from(5), &public_key)
+        .is_err());
+    assert!(ek
+        .escrow_secret_key::<SchnorrGroup>(nonce, &SchnorrGroup::order(), &public_key)
+        .is_err());
+}
+
+#[test]
+fn encrypt_and_prove_element() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(3).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    // Escrow a commitment, an element mod n^2
+    let element = group.mul(
+        &group.g_pow(&group.random_value()),
+        &group.h_pow(&BigNumber::from(7)),
+    );
+    let other = group.g_pow(&group.random_value());
+    let modulus = group.nn();
+    let nonce = b"encrypt_and_prove_element_test";
+    let (ct, proof) = ek
+        .encrypt_and_prove_element(nonce, &element, modulus)
+        .unwrap();
+    assert_eq!(ct.num_messages(), 3);
+    assert!(vk.verify_element(nonce, &ct, modulus, &proof).is_ok());
+    assert!(ek.verify_element(nonce, &ct, modulus, &proof).is_ok());
+    assert_eq!(proof.commitment(), &vk.element_commitment(&element));
+    assert_ne!(proof.commitment(), &vk.element_commitment(&other));
+    assert_eq!(dk.decrypt_element(nonce, &ct, modulus).unwrap(), element);
+
+    assert!(vk
+        .verify_element(b"wrong nonce", &ct, modulus, &proof)
+        .is_err());
+    assert!(vk.verify_element(nonce, &ct, group.n(), &proof).is_err());
+    // A valid escrow of something else fails
+    let (ct2, _) = ek
+        .encrypt_and_prove_element(nonce, &other, modulus)
+        .unwrap();
+    assert!(vk.verify_element(nonce, &ct2, modulus, &proof).is_err());
+    assert!(dk.decrypt_element(nonce, &ct, group.n()).is_err());
+
+    assert!(ek
+        .encrypt_and_prove_element(nonce, modulus, modulus)
+        .is_err());
+    let (small_ek, _) = group.new_keys(2).unwrap();
+    assert!(small_ek
+        .encrypt_and_prove_element(nonce, &element, modulus)
+        .is_err());
+}
+
+#[test]
+fn encrypt_and_prove_committed() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let zero = BigNumber::zero();
+    let generator = BigNumber::from(4);
+    let h = SchnorrGroup::mul_add(&BigNumber::from(987_654_321u64), &zero, &generator);
+
+    let nonce = b"encrypt_and_prove_committed_test";
+    let msgs = vec![
+        BigNumber::from(11),
+        BigNumber::random(&SchnorrGroup::order()),
+    ];
+    let (ct, proof, blindings) = ek
+        .encrypt_and_prove_committed::<SchnorrGroup>(nonce, &msgs, &h)
+        .unwrap();
+    assert!(vk
+        .verify_committed::<SchnorrGroup>(nonce, &ct, &h, &proof)
+        .is_ok());
+    assert_eq!(dk.decrypt(nonce, &ct).unwrap(), msgs);
+    // The commitments open to the messages with the returned blindings
+    for ((m, s), c) in msgs.iter().zip(blindings.iter()).zip(proof.commitments()) {
+        assert_eq!(&SchnorrGroup::mul_add(m, s, &h), c);
+    }
+
+    let json = serde_json::to_string(&proof).unwrap();
+    let proof: CommittedEncryptionProof<BigNumber> = serde_json::from_str(&json).unwrap();
+    assert!(ek
+        .verify_committed::<SchnorrGroup>(nonce, &ct, &h, &proof)
+        .is_ok());
+    assert!(vk
+        .verify_committed::<SchnorrGroup>(b"wrong nonce", &ct, &h, &proof)
+        .is_err());
+    assert!(vk
+        .verify_committed::<SchnorrGroup>(nonce, &ct, &generator, &proof)
+        .is_err());
+    // Swapping commitments breaks the proof
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["commitments"].as_array_mut().unwrap().swap(0, 1);
+    let forged: CommittedEncryptionProof<BigNumber> = serde_json::from_value(value).unwrap();
+    assert!(vk
+        .verify_committed::<SchnorrGroup>(nonce, &ct, &h, &forged)
+        .is_err());
+
+    let too_large = vec![SchnorrGroup::order()];
+    assert!(ek
+        .encrypt_and_prove_committed::<SchnorrGroup>(nonce, &too_large, &h)
+        .is_err());
+}
+
+#[test]
+fn encrypt_and_prove_linked() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(3).unwrap();
+    let vk = VerificationKey::from(&ek);
+    let zero = BigNumber::zero();
+    let generator = BigNumber::from(4);
+    let h = SchnorrGroup::mul_add(&BigNumber::from(987_654_321u64), &zero, &generator);
+
+    // A commitment made elsewhere to the message in slot 1
+    let nonce = b"encrypt_and_prove_linked_test";
+    let order = SchnorrGroup::order();
+    let msgs = vec![
+        group.n() - BigNumber::one(),
+        BigNumber::random(&order),
+        BigNumber::from(3),
+    ];
+    let blinding = BigNumber::random(&order);
+    let commitment = SchnorrGroup::mul_add(&msgs[1], &blinding, &h);
+    let (ct, proof) = ek
+        .encrypt_and_prove_linked::<SchnorrGroup>(nonce, &msgs, 1, &h, &blinding)
+        .unwrap();
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 1, &h, &commitment, &proof)
+        .is_ok());
+    assert!(ek
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 1, &h, &commitment, &proof)
+        .is_ok());
+    assert_eq!(dk.decrypt(nonce, &ct).unwrap(), msgs);
+
+    let json = serde_json::to_string(&proof).unwrap();
+    let proof: CommitmentLinkProof = serde_json::from_str(&json).unwrap();
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 1, &h, &commitment, &proof)
+        .is_ok());
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(b"wrong nonce", &ct, 1, &h, &commitment, &proof)
+        .is_err());
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 2, &h, &commitment, &proof)
+        .is_err());
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 3, &h, &commitment, &proof)
+        .is_err());
+    // A commitment to another value or with another blinding fails
+    let other = SchnorrGroup::mul_add(&msgs[2], &blinding, &h);
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 1, &h, &other, &proof)
+        .is_err());
+    let other = SchnorrGroup::mul_add(&msgs[1], &BigNumber::from(5), &h);
+    assert!(vk
+        .verify_linked::<SchnorrGroup>(nonce, &ct, 1, &h, &other, &proof)
+        .is_err());
+
+    // Only the linked message has to be less than the order
+    assert!(ek
+        .encrypt_and_prove_linked::<SchnorrGroup>(nonce, &msgs, 0, &h, &blinding)
+        .is_err());
+    assert!(ek
+        .encrypt_and_prove_linked::<SchnorrGroup>(nonce, &msgs, 1, &h, &order)
+        .is_err());
+}
+
+#[test]
+fn reencryption_chain() {
+    let group = test_group();
+    let (ek1, dk1) = group.new_keys(2).unwrap();
+    let (ek2, dk2) = group.new_keys(2).unwrap();
+    let (ek3, dk3) = group.new_keys(2).unwrap();
+    let msgs = vec![BigNumber::from(7), BigNumber::from(1234)];
+    let ct1 = ek1.encrypt(b"2024", &msgs).unwrap();
+
+    let mut chain = ReencryptionChain::new(ek1.verification_key(), b"2024", ct1.clone());
+    let (ct2, proof2) = dk1.reencrypt(b"2024", &ct1, &ek2, b"2027").unwrap();
+    chain.push(ek2.verification_key(), b"2027", ct2.clone(), proof2.clone());
+    let (ct3, proof3) = dk2.reencrypt(b"2027", &ct2, &ek3, b"2030").unwrap();
+    chain.push(ek3.verification_key(), b"2030", ct3, proof3);
+
+    let keys = vec![
+        ek1.verification_key().clone(),
+        ek2.verification_key().clone(),
+        ek3.verification_key().clone(),
+    ];
+    assert!(chain.verify(&keys).is_ok());
+    let (fingerprint, domain, latest) = chain.latest();
+    assert_eq!(fingerprint, &ek3.verification_key().fingerprint());
+    assert_eq!(dk3.decrypt(domain, latest).unwrap(), msgs);
+    // Every key is needed
+    assert!(chain.verify(&keys[..2]).is_err());
+
+    // A ciphertext of other messages doesn't match the proof
+    let other = ek2
+        .encrypt(b"2027", &[BigNumber::from(8), BigNumber::from(1234)])
+        .unwrap();
+    assert!(ek1
+        .verification_key()
+        .verify_reencryption(
+            b"2024",
+            &ct1,
+            ek2.verification_key(),
+            b"2027",
+            &other,
+            &proof2
+        )
+        .is_err());
+    // Nor does another domain
+    assert!(ek1
+        .verification_key()
+        .verify_reencryption(
+            b"2024",
+            &ct1,
+            ek2.verification_key(),
+            b"2028",
+            &ct2,
+            &proof2
+        )
+        .is_err());
+    let mut tampered = chain.clone();
+    let (ct, proof) = dk1.reencrypt(b"2024", &ct1, &ek2, b"2027").unwrap();
+    tampered.push(ek2.verification_key(), b"2027", ct, proof);
+    assert!(tampered.verify(&keys).is_err());
+}
+
+#[test]
+fn merkle_response_disclosure() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(5).unwrap();
+    let vk = ek.verification_key();
+    let nonce = b"merkle_response_disclosure";
+    let msgs = (1..=5)
+        .map(|i| BigNumber::from(i * 100))
+        .collect::<Vec<_>>();
+    let (ct, tree) = ek.encrypt_and_prove_merkle(nonce, &msgs).unwrap();
+    assert_eq!(tree.len(), 5);
+
+    let disclosure = tree.disclose(&[1, 4]).unwrap();
+    assert_eq!(disclosure.indices(), vec![1, 4]);
+    assert!(vk.verify_disclosure(nonce, &ct, &disclosure).is_ok());
+    assert!(vk
+        .verify_disclosure(nonce, &ct, &tree.disclose(&[]).unwrap())
+        .is_ok());
+    let all = tree.disclose(&[0, 1, 2, 3, 4]).unwrap();
+    assert!(vk.verify_disclosure(nonce, &ct, &all).is_ok());
+    assert!(tree.disclose(&[5]).is_err());
+    assert!(tree.disclose(&[2, 2]).is_err());
+    assert!(vk
+        .verify_disclosure(b"other nonce", &ct, &disclosure)
+        .is_err());
+
+    // A ciphertext with a different message in a disclosed slot fails
+    let mut other = msgs.clone();
+    other[4] = BigNumber::from(1);
+    let (other_ct, _) = ek.encrypt_and_prove_merkle(nonce, &other).unwrap();
+    assert!(vk.verify_disclosure(nonce, &other_ct, &disclosure).is_err());
+
+    // A forged response doesn't match its leaf
+    let mut json = serde_json::to_value(&disclosure).unwrap();
+    json["responses"][0]["m"] = json["responses"][1]["m"].clone();
+    let forged: ResponseDisclosure = serde_json::from_value(json).unwrap();
+    assert!(vk.verify_disclosure(nonce, &ct, &forged).is_err());
+}
+
+#[test]
+fn access_policy_escrow() {
+    let group = test_group();
+    let keys = (0..4)
+        .map(|_| group.new_keys(1).unwrap())
+        .collect::<Vec<_>>();
+    let recipients = keys.iter().map(|(ek, _)| ek.clone()).collect::<Vec<_>>();
+    let vks = recipients
+        .iter()
+        .map(VerificationKey::from)
+        .collect::<Vec<_>>();
+
+    // The auditor and one of two officers, or the court alone
+    let policy = AccessPolicy::Or(vec![
+        AccessPolicy::And(vec![
+            AccessPolicy::Recipient(0),
+            AccessPolicy::Threshold(
+                1,
+                vec![AccessPolicy::Recipient(1), AccessPolicy::Recipient(2)],
+            ),
+        ]),
+        AccessPolicy::Recipient(3),
+    ]);
+    assert!(policy.is_satisfied_by(&[0, 2]));
+    assert!(policy.is_satisfied_by(&[3]));
+    assert!(!policy.is_satisfied_by(&[1, 2]));
+
+    let nonce = b"access_policy_escrow";
+    let zero = BigNumber::zero();
+    let secret_key = BigNumber::from(123_456_789u64);
+    let public_key = SchnorrGroup::mul_add(&secret_key, &zero, &BigNumber::one());
+    let escrow =
+        PolicyEscrow::create::<SchnorrGroup>(nonce, &secret_key, &public_key, &policy, &recipients)
+            .unwrap();
+    assert!(escrow
+        .verify::<SchnorrGroup>(nonce, &public_key, &vks)
+        .is_ok());
+    let other = SchnorrGroup::mul_add(&BigNumber::from(5), &zero, &BigNumber::one());
+    assert!(escrow.verify::<SchnorrGroup>(nonce, &other, &vks).is_err());
+    assert!(escrow.policy().is_satisfied_by(&[0, 1]));
+
+    let open = |i: usize| escrow.open(nonce, i, &keys[i].1).unwrap();
+    let mut shares = open(0);
+    shares.extend(open(2));
+    assert_eq!(
+        escrow
+            .combine::<SchnorrGroup>(&public_key, &shares)
+            .unwrap(),
+        secret_key
+    );
+    assert_eq!(
+        escrow
+            .combine::<SchnorrGroup>(&public_key, &open(3))
+            .unwrap(),
+        secret_key
+    );
+    let mut shares = open(1);
+    shares.extend(open(2));
+    assert!(escrow
+        .combine::<SchnorrGroup>(&public_key, &shares)
+        .is_err());
+}
+
+#[test]
+fn revocation_escrow() {
+    use verenc::revocation::*;
+
+    let group = test_group();
+    let (auditor, auditor_dk) = group.new_keys(1).unwrap();
+    let vk = auditor.verification_key();
+    let zero = BigNumber::zero();
+    let h = SchnorrGroup::mul_add(&BigNumber::from(123_456_789u64), &zero, &BigNumber::from(4));
+    let nonce = b"revocation_escrow_test";
+
+    let handle = BigNumber::random(&SchnorrGroup::order());
+    let blinding = BigNumber::random(&SchnorrGroup::order());
+    let commitment = SchnorrGroup::mul_add(&handle, &blinding, &h);
+    let escrow = RevocationEscrow::create::<SchnorrGroup>(
+        nonce,
+        &auditor,
+        &handle,
+        &h,
+        &commitment,
+        &blinding,
+    )
+    .unwrap();
+    assert!(escrow
+        .verify::<SchnorrGroup>(nonce, vk, &h, &commitment)
+        .is_ok());
+    assert_eq!(escrow.open(nonce, &auditor_dk).unwrap(), handle);
+
+    let json = serde_json::to_string(&escrow).unwrap();
+    let escrow: RevocationEscrow<BigNumber> = serde_json::from_str(&json).unwrap();
+    assert!(escrow
+        .verify::<SchnorrGroup>(nonce, vk, &h, &commitment)
+        .is_ok());
+    assert!(escrow
+        .verify::<SchnorrGroup>(b"other", vk, &h, &commitment)
+        .is_err());
+    // A presentation of another handle doesn't match
+    let other = SchnorrGroup::mul_add(&(&handle + 1), &blinding, &h);
+    assert!(escrow
+        .verify::<SchnorrGroup>(nonce, vk, &h, &other)
+        .is_err());
+    assert!(RevocationEscrow::create::<SchnorrGroup>(
+        nonce, &auditor, &handle, &h, &other, &blinding,
+    )
+    .is_err());
+}
+
+#[test]
+fn relation() {
+    use verenc::relation::Relation;
+
+    let group = test_group();
+    let (ek1, _) = group.new_keys(1).unwrap();
+    let (ek2, _) = group.new_keys(1).unwrap();
+    let vk1 = VerificationKey::from(&ek1);
+    let vk2 = VerificationKey::from(&ek2);
+    let m = BigNumber::from(42);
+    let r1 = BigNumber::random(&(group.n() >> 2));
+    let r2 = BigNumber::random(&(group.n() >> 2));
+    let ct1 = ek1
+        .encrypt_with_randomness(b"a", std::slice::from_ref(&m), &r1)
+        .unwrap();
+    let ct2 = ek2
+        .encrypt_with_randomness(b"b", std::slice::from_ref(&m), &r2)
+        .unwrap();
+
+    // The statement of the encryption proof
+    let rel1 = vk1.encryption_relation(b"a", &ct1).unwrap();
+    assert_eq!(rel1.num_witnesses(), 2);
+    assert_eq!(rel1.equations().len(), 3);
+    assert!(rel1.is_satisfied_by(&[r1.clone(), m.clone()]));
+    let proof = rel1.prove(b"ctx", &[r1.clone(), m.clone()]).unwrap();
+    assert!(rel1.verify(b"ctx", &proof).is_ok());
+    assert!(rel1.verify(b"other", &proof).is_err());
+    assert!(rel1
+        .prove(b"ctx", &[r1.clone(), BigNumber::from(43)])
+        .is_err());
+
+    // Both ciphertexts encrypt the same message: witnesses [r1, r2, m]
+    let rel2 = vk2.encryption_relation(b"b", &ct2).unwrap();
+    let mut both = Relation::new(&group, 3, rel1.witness_bits());
+    both.extend(&rel1, &[0, 2]).unwrap();
+    both.extend(&rel2, &[1, 2]).unwrap();
+    let witnesses = [r1.clone(), r2.clone(), m.clone()];
+    let proof = both.prove(b"equal", &witnesses).unwrap();
+    assert!(both.verify(b"equal", &proof).is_ok());
+    let json = serde_json::to_string(&proof).unwrap();
+    let proof: relation::RelationProof = serde_json::from_str(&json).unwrap();
+    assert!(both.verify(b"equal", &proof).is_ok());
+
+    // Different messages don't satisfy it
+    let ct3 = ek2
+        .encrypt_with_randomness(b"b", &[BigNumber::from(7)], &r2)
+        .unwrap();
+    let rel3 = vk2.encryption_relation(b"b", &ct3).unwrap();
+    let mut unequal = Relation::new(&group, 3, rel1.witness_bits());
+    unequal.extend(&rel1, &[0, 2]).unwrap();
+    unequal.extend(&rel3, &[1, 2]).unwrap();
+    assert!(unequal.prove(b"equal", &witnesses).is_err());
+    let mut tampered = proof.clone();
+    tampered.responses[2] += BigNumber::one();
+    assert!(both.verify(b"equal", &tampered).is_err());
+    assert!(both.extend(&rel1, &[0]).is_err());
+}
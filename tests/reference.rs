@@ -0,0 +1,37 @@
+#![cfg(all(feature = "reference", feature = "testing"))]
+
+use verenc::{reference, testing, VerificationKey};
+
+#[test]
+fn differential() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..10 {
+        let group = testing::group(&mut rng);
+        let (ek, dk) = testing::keys(&group, 5, &mut rng);
+        let vk = VerificationKey::from(&ek);
+        let msgs = testing::messages(&group, vk.max_messages(), &mut rng);
+        let label = testing::label(32, &mut rng);
+
+        // optimized -> reference
+        let ct = ek.encrypt(&label, &msgs).unwrap();
+        assert_eq!(reference::decrypt(&dk, &label, &ct), Some(msgs.clone()));
+        let (ct, proof) = ek.encrypt_and_prove(&label, &msgs).unwrap();
+        assert!(reference::verify(&vk, &label, &ct, &proof));
+        assert!(!reference::verify(&vk, b"wrong label", &ct, &proof));
+
+        // reference -> optimized
+        let r = group.random_for_encrypt();
+        let ct = reference::encrypt(&ek, &label, &msgs, &r).unwrap();
+        assert_eq!(dk.decrypt(&label, &ct).unwrap(), msgs);
+        let r_tilde = group.random_for_encrypt();
+        let m_tilde = (0..msgs.len())
+            .map(|_| group.random_for_encrypt())
+            .collect::<Vec<_>>();
+        let (ct, proof) =
+            reference::encrypt_and_prove(&ek, &label, &msgs, &r, &r_tilde, &m_tilde).unwrap();
+        assert_eq!(ct, reference::encrypt(&ek, &label, &msgs, &r).unwrap());
+        assert!(vk.verify(&label, &ct, &proof).is_ok());
+        assert!(vk.verify(b"wrong label", &ct, &proof).is_err());
+        assert!(reference::decrypt(&dk, b"wrong label", &ct).is_none());
+    }
+}
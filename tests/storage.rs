@@ -0,0 +1,444 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn ciphertext_archive() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+
+    let nonce = b"ciphertext_archive_test";
+    let msgs = vec![BigNumber::from(11), BigNumber::from(12)];
+    let ct1 = ek.encrypt(nonce, &msgs).unwrap();
+    let (ct2, proof2) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+
+    let mut writer = CiphertextArchiveWriter::new(Vec::new()).unwrap();
+    writer.write(&ct1, None).unwrap();
+    writer.write(&ct2, Some(&proof2)).unwrap();
+    assert_eq!(writer.records(), 2);
+    let archive = writer.finish().unwrap();
+
+    let records = CiphertextArchiveReader::new(archive.as_slice())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].ciphertext, ct1);
+    assert!(records[0].proof.is_none());
+    assert_eq!(records[1].ciphertext, ct2);
+    let proof = records[1].proof.as_ref().unwrap();
+    assert!(ek.verify(nonce, &records[1].ciphertext, proof).is_ok());
+    assert_eq!(dk.decrypt(nonce, &records[1].ciphertext).unwrap(), msgs);
+
+    // Corrupt, truncated and unrelated input
+    let mut corrupt = archive.clone();
+    corrupt[20] ^= 1;
+    let mut reader = CiphertextArchiveReader::new(corrupt.as_slice()).unwrap();
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+    let truncated = &archive[..archive.len() - 1];
+    let mut reader = CiphertextArchiveReader::new(truncated).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_err());
+    assert!(CiphertextArchiveReader::new(&b"not an archive"[..]).is_err());
+
+    // Cutting whole records or the trailer off the end is detected
+    let trailer_len = 4 + 8 + 16;
+    let without_trailer = &archive[..archive.len() - trailer_len];
+    let mut reader = CiphertextArchiveReader::new(without_trailer).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_ok());
+    assert_eq!(
+        reader.next().unwrap().unwrap_err().to_string(),
+        "Archive is truncated"
+    );
+
+    let mut writer = CiphertextArchiveWriter::new(Vec::new()).unwrap();
+    writer.write(&ct1, None).unwrap();
+    let first = writer.finish().unwrap();
+    let mut dropped = archive[..first.len() - trailer_len].to_vec();
+    dropped.extend_from_slice(&archive[archive.len() - trailer_len..]);
+    let mut reader = CiphertextArchiveReader::new(dropped.as_slice()).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert_eq!(
+        reader.next().unwrap().unwrap_err().to_string(),
+        "Archive has 1 records but the trailer counts 2"
+    );
+    let mut appended = archive.clone();
+    appended.push(0);
+    let mut reader = CiphertextArchiveReader::new(appended.as_slice()).unwrap();
+    assert_eq!(reader.by_ref().filter(Result::is_err).count(), 1);
+    let empty = CiphertextArchiveWriter::new(Vec::new())
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert_eq!(
+        CiphertextArchiveReader::new(empty.as_slice())
+            .unwrap()
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn memory_budget() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+
+    let nonce = b"memory_budget_test";
+    let msgs = vec![BigNumber::from(13), BigNumber::from(14)];
+    let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+    let json = serde_json::to_string(&ct).unwrap();
+
+    let small = MemoryBudget::new(1024);
+    let large = MemoryBudget::new(4096);
+    assert!(small
+        .enforce(|| serde_json::from_str::<VerifiableCipherText>(&json))
+        .is_err());
+    assert_eq!(
+        large
+            .enforce(|| serde_json::from_str::<VerifiableCipherText>(&json))
+            .unwrap(),
+        ct
+    );
+    // Only the operation inside `enforce` is limited
+    assert!(serde_json::from_str::<VerifiableCipherText>(&json).is_ok());
+    let ek_json = serde_json::to_string(&ek).unwrap();
+    assert!(MemoryBudget::new(2048)
+        .enforce(|| serde_json::from_str::<EncryptionKey>(&ek_json))
+        .is_err());
+
+    let mut writer = CiphertextArchiveWriter::new(Vec::new()).unwrap();
+    writer.write(&ct, Some(&proof)).unwrap();
+    let archive = writer.finish().unwrap();
+    assert!(small.enforce(|| {
+        CiphertextArchiveReader::new(archive.as_slice())
+            .unwrap()
+            .read_record()
+            .is_err()
+    }));
+    assert!(large.enforce(|| {
+        CiphertextArchiveReader::new(archive.as_slice())
+            .unwrap()
+            .read_record()
+            .is_ok()
+    }));
+
+    // Numbers are charged from their encoded length before they are decoded
+    let mut huge = serde_json::to_value(&ct).unwrap();
+    huge["u"] = serde_json::Value::String("f".repeat(1 << 20));
+    let err = small
+        .enforce(|| serde_json::from_value::<VerifiableCipherText>(huge))
+        .unwrap_err();
+    assert!(err.to_string().starts_with("Memory budget exceeded"));
+    let hex = ct.to_checked_hex();
+    assert!(small
+        .enforce(|| VerifiableCipherText::from_checked_hex(&hex))
+        .is_err());
+    assert_eq!(
+        MemoryBudget::new(8192)
+            .enforce(|| VerifiableCipherText::from_checked_hex(&hex))
+            .unwrap(),
+        ct
+    );
+    let statement = ek.verification_key().statement(nonce, &ct, &proof).unwrap();
+    let mut statement = serde_json::to_value(statement).unwrap();
+    statement["modulus"] = serde_json::Value::String("f".repeat(1 << 20));
+    assert!(small
+        .enforce(|| serde_json::from_value::<ProofStatement>(statement))
+        .is_err());
+}
+
+#[test]
+fn checked_hex() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let nonce = b"checked_hex";
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+    let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+
+    let ct_hex = ct.to_checked_hex();
+    let proof_hex = proof.to_checked_hex();
+    assert!(ct_hex.starts_with("veciphertext:"));
+    assert_eq!(VerifiableCipherText::from_checked_hex(&ct_hex).unwrap(), ct);
+    let decoded = VerifiableEncryptionProof::from_checked_hex(&proof_hex).unwrap();
+    assert!(ek.verify(nonce, &ct, &decoded).is_ok());
+
+    // Wrapped lines are fine
+    let wrapped = ct_hex
+        .as_bytes()
+        .chunks(64)
+        .map(|c| std::str::from_utf8(c).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(
+        VerifiableCipherText::from_checked_hex(&wrapped).unwrap(),
+        ct
+    );
+
+    // Truncation, edits and the wrong type are rejected
+    let truncated = format!(
+        "{}:{}",
+        &ct_hex[..ct_hex.len() - 20],
+        &ct_hex[ct_hex.len() - 8..]
+    );
+    assert!(VerifiableCipherText::from_checked_hex(&truncated).is_err());
+    assert!(VerifiableCipherText::from_checked_hex(&ct_hex[..ct_hex.len() - 2]).is_err());
+    let edited = ct_hex.replacen("veciphertext:00", "veciphertext:01", 1);
+    assert_ne!(edited, ct_hex);
+    assert!(VerifiableCipherText::from_checked_hex(&edited).is_err());
+    assert!(VerifiableCipherText::from_checked_hex(&proof_hex).is_err());
+    assert!(VerifiableEncryptionProof::from_checked_hex(&ct_hex).is_err());
+}
+
+#[test]
+fn serialize_secret() {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Stored {
+        #[serde(
+            serialize_with = "DecryptionKey::serialize_secret",
+            deserialize_with = "DecryptionKey::deserialize_secret"
+        )]
+        key: DecryptionKey,
+    }
+
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let domain = b"serialize_secret";
+    let msgs = vec![BigNumber::from(7), BigNumber::from(8)];
+    let ct = ek.encrypt(domain, &msgs).unwrap();
+
+    let json = serde_json::to_string(&Stored { key: dk }).unwrap();
+    let stored: Stored = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        VerificationKey::from(&stored.key).fingerprint(),
+        ek.verification_key().fingerprint()
+    );
+    assert_eq!(stored.key.decrypt(domain, &ct).unwrap(), msgs);
+
+    let truncated = &json[..json.find("\"x3\"").unwrap()];
+    assert!(serde_json::from_str::<Stored>(truncated).is_err());
+    let over = MemoryBudget::new(16).enforce(|| serde_json::from_str::<Stored>(&json));
+    assert!(over.is_err());
+}
+
+#[test]
+fn encrypt_bytes_iter() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let domain = b"encrypt_bytes_iter";
+    let inputs: Vec<&[u8]> = vec![b"alice", &[0x01, 0x00]];
+    let ct = ek
+        .encrypt_bytes_iter(domain, inputs.iter().copied())
+        .unwrap();
+    let decrypted = dk.decrypt(domain, &ct).unwrap();
+    assert_eq!(decrypted[0].to_bytes(), b"alice".to_vec());
+    assert_eq!(decrypted[1], BigNumber::from(256));
+
+    let too_many = vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+    assert!(ek.encrypt_bytes_iter(domain, &too_many).is_err());
+    let too_large = group.n().to_bytes();
+    assert!(ek.encrypt_bytes_iter(domain, [too_large]).is_err());
+}
+
+#[test]
+fn sealed_record() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let vk = ek.verification_key();
+    let msgs = vec![BigNumber::from(8), BigNumber::from(9)];
+    let (ciphertext, proof) = ek.encrypt_and_prove(b"cold", &msgs).unwrap();
+    let record = ArchiveRecord {
+        ciphertext,
+        proof: Some(proof),
+    };
+    let mac = StorageMac::random();
+    let sealed = SealedRecord::seal(record.clone(), b"cold", vk.fingerprint(), &mac);
+    sealed.check(&mac).unwrap();
+    sealed.verify(&mac, vk).unwrap();
+    assert_eq!(sealed.label(), b"cold");
+    assert_eq!(sealed.key_fingerprint(), &vk.fingerprint());
+
+    let json = serde_json::to_value(&sealed).unwrap();
+    let restored: SealedRecord = serde_json::from_value(json.clone()).unwrap();
+    restored.verify(&mac, vk).unwrap();
+    assert_eq!(restored.record().ciphertext, sealed.ciphertext().clone());
+
+    // Any change to a bound field is detected
+    let mut tampered = json;
+    tampered["label"] = serde_json::to_value(b"warm".to_vec()).unwrap();
+    let tampered: SealedRecord = serde_json::from_value(tampered).unwrap();
+    assert!(tampered.check(&mac).is_err());
+    assert!(sealed.check(&StorageMac::random()).is_err());
+
+    // A genuine seal of the wrong key or label still fails to verify
+    let (other, _) = group.new_keys(2).unwrap();
+    assert!(sealed.verify(&mac, other.verification_key()).is_err());
+    let mislabeled = SealedRecord::seal(record, b"warm", vk.fingerprint(), &mac);
+    mislabeled.check(&mac).unwrap();
+    assert!(mislabeled.verify(&mac, vk).is_err());
+}
+
+#[test]
+fn rotation() {
+    let group = test_group();
+    let (old_ek, old_dk) = group.new_keys(1).unwrap();
+    let (new_ek, new_dk) = group.new_keys(1).unwrap();
+    let old_vk = VerificationKey::from(&old_ek);
+    let new_vk = VerificationKey::from(&new_ek);
+
+    // Two records under the old key, one fresh and one stale under the new key
+    let records = [
+        (&old_ek, 1000, 1),
+        (&new_ek, 1000, 2),
+        (&old_ek, 1900, 3),
+        (&new_ek, 1900, 4),
+    ];
+    let mut writer = CiphertextArchiveWriter::new(Vec::new()).unwrap();
+    let mut metadata = Vec::new();
+    for (i, (ek, created_at, m)) in records.iter().enumerate() {
+        let domain = format!("record {}", i).into_bytes();
+        let ct = ek.encrypt(&domain[..], &[BigNumber::from(*m)]).unwrap();
+        writer.write(&ct, None).unwrap();
+        metadata.push(RecordMetadata {
+            key_fingerprint: ek.verification_key().fingerprint(),
+            domain,
+            created_at: *created_at,
+        });
+    }
+    let archive = writer.finish().unwrap();
+    let reader = || CiphertextArchiveReader::new(archive.as_slice()).unwrap();
+    let lookup = |position: u64, _: &ArchiveRecord| Ok(metadata[position as usize].clone());
+
+    assert!(Rotation::new(new_ek.clone(), 0).is_err());
+    let rotation = Rotation::new(new_ek.clone(), 2)
+        .unwrap()
+        .with_key(old_dk)
+        .with_key(new_dk.clone())
+        .with_max_age(500)
+        .deprecate(old_vk.fingerprint())
+        .unwrap();
+    assert!(rotation.clone().deprecate(new_vk.fingerprint()).is_err());
+    assert_eq!(
+        rotation.plan(reader(), lookup, 2000).unwrap(),
+        [
+            (0, RotationReason::DeprecatedKey),
+            (1, RotationReason::Expired),
+            (2, RotationReason::DeprecatedKey),
+        ]
+    );
+
+    let first = rotation
+        .run_batch(reader(), lookup, 2000, RotationCursor::start())
+        .unwrap();
+    assert!(!first.finished);
+    assert_eq!(first.cursor.position(), 2);
+    assert_eq!(first.rotated.len(), 2);
+    // Resuming from a stored cursor picks up where the batch stopped
+    let cursor: RotationCursor =
+        serde_json::from_str(&serde_json::to_string(&first.cursor).unwrap()).unwrap();
+    let second = rotation.run_batch(reader(), lookup, 2000, cursor).unwrap();
+    assert!(second.finished);
+    assert_eq!(second.cursor.position(), 4);
+    assert_eq!(second.rotated.len(), 1);
+
+    let old_records = reader().collect::<Result<Vec<_>, _>>().unwrap();
+    for rotated in first.rotated.iter().chain(second.rotated.iter()) {
+        let position = rotated.position as usize;
+        let old_key = if position == 1 { &new_vk } else { &old_vk };
+        old_key
+            .verify_reencryption(
+                &rotated.domain,
+                &old_records[position].ciphertext,
+                &new_vk,
+                &rotated.domain,
+                &rotated.ciphertext,
+                &rotated.proof,
+            )
+            .unwrap();
+        assert_eq!(
+            new_dk
+                .decrypt(&rotated.domain[..], &rotated.ciphertext)
+                .unwrap(),
+            [BigNumber::from(records[position].2)]
+        );
+    }
+
+    // A record whose key wasn't added can't be rotated
+    let missing = Rotation::new(new_ek, 2)
+        .unwrap()
+        .deprecate(old_vk.fingerprint())
+        .unwrap();
+    assert!(missing
+        .run_batch(reader(), lookup, 2000, RotationCursor::start())
+        .is_err());
+}
+
+#[test]
+fn versions() {
+    assert_eq!(PROTOCOL_VERSION, ProtocolVersion::LATEST.number());
+    assert_eq!(
+        ProtocolVersion::from_number(PROTOCOL_VERSION),
+        Some(ProtocolVersion::LATEST)
+    );
+    assert_eq!(ProtocolVersion::from_number(0), None);
+    assert_eq!(
+        compatibility(Version::CURRENT),
+        Compat::Compatible {
+            protocol: ProtocolVersion::LATEST,
+            wire: WIRE_VERSION
+        }
+    );
+
+    // An older peer gets the newest versions both sides know
+    let old = Version {
+        protocol: 1,
+        ..Version::CURRENT
+    };
+    let compat = compatibility(old);
+    assert_eq!(compat.proof_options().unwrap().version, ProtocolVersion::V1);
+    // A newer peer that dropped everything this build speaks
+    let newer = Version {
+        protocol: 5,
+        min_protocol: 4,
+        ..Version::CURRENT
+    };
+    assert_eq!(compatibility(newer), Compat::IncompatibleProtocol);
+    assert!(compatibility(newer).proof_options().is_none());
+    let newer = Version {
+        wire: 3,
+        min_wire: 2,
+        ..Version::CURRENT
+    };
+    assert_eq!(compatibility(newer), Compat::IncompatibleWire);
+
+    // Encodings from another wire version say so
+    let mut archive = CiphertextArchiveWriter::new(Vec::new())
+        .unwrap()
+        .finish()
+        .unwrap();
+    archive[7] = b'2';
+    assert_eq!(
+        CiphertextArchiveReader::new(archive.as_slice())
+            .unwrap_err()
+            .to_string(),
+        "Wire version 2 is not supported, this build reads version 1"
+    );
+    assert_eq!(
+        CiphertextArchiveReader::new(&b"VEXXXX01"[..])
+            .unwrap_err()
+            .to_string(),
+        "Not a ciphertext archive"
+    );
+    let mut label = Label::new(b"ctx").to_bytes();
+    label[7] = b'9';
+    assert!(Label::from_bytes(&label)
+        .unwrap_err()
+        .to_string()
+        .starts_with("Wire version 9"));
+}
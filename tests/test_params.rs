@@ -0,0 +1,30 @@
+#![cfg(feature = "test-params")]
+
+use unknown_order::BigNumber;
+use verenc::test_params::{self, PrimeSize};
+use verenc::*;
+
+#[test]
+fn shared_parameters() {
+    for size in PrimeSize::ALL {
+        let (p, q) = test_params::safe_primes(size);
+        let group = test_params::group(size);
+        assert_eq!(group.n(), &(&p * &q));
+        assert!(std::ptr::eq(group, test_params::group(size)));
+
+        let (ek, dk) = test_params::keys(size);
+        assert_eq!(ek.verification_key().max_messages(), test_params::KEY_SLOTS);
+        assert!(std::ptr::eq(ek, &test_params::keys(size).0));
+        let msgs = vec![BigNumber::from(7), BigNumber::from(8)];
+        let (ct, proof) = ek.encrypt_and_prove(b"shared_parameters", &msgs).unwrap();
+        assert!(ek.verify(b"shared_parameters", &ct, &proof).is_ok());
+        assert_eq!(dk.decrypt(b"shared_parameters", &ct).unwrap(), msgs);
+    }
+
+    let (ek, _) = test_params::new_keys(PrimeSize::Bits256, 2);
+    assert_eq!(ek.verification_key().max_messages(), 2);
+    assert_eq!(
+        ek.verification_key().group().n(),
+        test_params::group(PrimeSize::Bits256).n()
+    );
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "testing")]
+
+use verenc::testing;
+use verenc::*;
+
+#[test]
+fn round_trips() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..10 {
+        let group = testing::group(&mut rng);
+        let (ek, dk) = testing::keys(&group, 5, &mut rng);
+        let vk = VerificationKey::from(&ek);
+        let msgs = testing::messages(&group, vk.max_messages(), &mut rng);
+        let label = testing::label(32, &mut rng);
+
+        let ct = ek.encrypt(&label, &msgs).unwrap();
+        assert_eq!(dk.decrypt(&label, &ct).unwrap(), msgs);
+
+        let (ct, proof) = ek.encrypt_and_prove(&label, &msgs).unwrap();
+        assert!(vk.verify(&label, &ct, &proof).is_ok());
+
+        let ct_json = serde_json::to_string(&ct).unwrap();
+        let ct2: VerifiableCipherText = serde_json::from_str(&ct_json).unwrap();
+        assert_eq!(ct, ct2);
+        let dk_json = serde_json::to_string(&dk).unwrap();
+        let dk2: DecryptionKey = serde_json::from_str(&dk_json).unwrap();
+        assert_eq!(dk2.decrypt(&label, &ct2).unwrap(), msgs);
+    }
+}
+
+#[test]
+fn larger_groups() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group_with_size(testing::PrimeSize::Bits512, &mut rng);
+    let (ek, dk) = testing::keys(&group, 3, &mut rng);
+    let msgs = testing::messages(&group, 1, &mut rng);
+    let ct = ek.encrypt(b"larger_groups", &msgs).unwrap();
+    assert_eq!(dk.decrypt(b"larger_groups", &ct).unwrap(), msgs);
+}
+
+#[test]
+fn mock_escrow_agent() {
+    let mut rng = rand::thread_rng();
+    let agent = testing::MockEscrowAgent::new(2, &mut rng);
+    let vk = agent.verification_key();
+    let group = vk.group().clone();
+    let msgs = testing::messages(&group, 2, &mut rng);
+    let label = b"mock_escrow_agent";
+    let (ct, proof) = agent
+        .encryption_key()
+        .encrypt_and_prove(label, &msgs)
+        .unwrap();
+    assert!(vk.verify(label, &ct, &proof).is_ok());
+
+    let warrant = agent.issue_warrant(&ct, label, b"case 1");
+    let record = agent.decrypt_with_warrant(&warrant, &ct).unwrap();
+    assert_eq!(record.messages(), msgs.as_slice());
+    assert!(vk.verify_decryption_record(&agent, &ct, &record).is_ok());
+
+    let message = warrant.message();
+    assert_eq!(
+        agent.interactions(),
+        vec![
+            testing::EscrowInteraction::Signed { message },
+            testing::EscrowInteraction::CheckedSignature {
+                message,
+                valid: true
+            },
+            testing::EscrowInteraction::Decrypted {
+                ciphertext_hash: ct.digest(),
+                label: label.to_vec(),
+                success: true
+            },
+            testing::EscrowInteraction::CheckedSignature {
+                message,
+                valid: true
+            },
+        ]
+    );
+
+    agent.clear_interactions();
+    let other = testing::MockEscrowAgent::new(2, &mut rng);
+    let forged = other.issue_warrant(&ct, label, b"case 1");
+    assert!(agent.decrypt_with_warrant(&forged, &ct).is_err());
+    assert_eq!(agent.interactions().len(), 2);
+
+    let (tag_key, slot_key) = agent.decryption_key().split_custody();
+    let attestation = tag_key.attest(&agent, label, &ct).unwrap();
+    let partial = slot_key.partial_decrypt(&agent, &ct).unwrap();
+    let opened = vk
+        .decrypt_from_custody(label, &ct, &attestation, &agent, &partial, &agent)
+        .unwrap();
+    assert_eq!(opened, msgs);
+}
@@ -0,0 +1,27 @@
+#![cfg(all(feature = "timing", feature = "testing"))]
+
+use verenc::{testing, timing};
+
+#[test]
+fn measure() {
+    let mut rng = rand::thread_rng();
+    let report = timing::measure(100, &mut rng, |fixed, _| {
+        if fixed {
+            std::hint::black_box(0u64);
+        }
+    });
+    assert!(report.fixed.samples + report.random.samples <= 100);
+    assert!(report.fixed.samples > 0 && report.random.samples > 0);
+}
+
+#[test]
+#[ignore]
+fn evaluate() {
+    let mut rng = rand::thread_rng();
+    let group = testing::group_with_size(testing::PrimeSize::Bits512, &mut rng);
+    let (ek, dk) = group.new_keys(1).unwrap();
+    let report = timing::evaluate_decrypt(&ek, &dk, 10_000, &mut rng).unwrap();
+    println!("decrypt: {:?}", report);
+    let report = timing::evaluate_verify(&ek, 1_000, &mut rng).unwrap();
+    println!("verify: {:?}", report);
+}
@@ -0,0 +1,559 @@
+#![cfg(not(feature = "verify-only"))]
+
+mod common;
+
+use common::*;
+use unknown_order::BigNumber;
+use verenc::*;
+
+#[test]
+fn proof_statement() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let vk = ek.verification_key();
+
+    let domain = b"proof_statement_test";
+    let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+    let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+    let statement = vk.statement(domain, &ct, &proof).unwrap();
+    assert_eq!(statement.modulus, *group.nn());
+    assert_eq!(statement.equations.len(), msgs.len() + 2);
+    assert_eq!(statement.equations[2].terms[0].base_name, "ciphertext.e[1]");
+    assert_eq!(statement.equations[3].terms[2].exponent_name, "2rH");
+
+    // u^{2c} * g^{2r}
+    let u_eq = &statement.equations[0];
+    let expected = group.mul(
+        &group.pow(&u_eq.terms[0].base, &u_eq.terms[0].exponent),
+        &group.g_pow(&u_eq.terms[1].exponent),
+    );
+    assert_eq!(statement.evaluate()[0], expected);
+
+    let json = serde_json::to_string(&statement).unwrap();
+    assert_eq!(statement, serde_json::from_str(&json).unwrap());
+    assert!(vk.statement(domain, &ct, &proof).is_ok());
+    let (ct2, _) = ek.encrypt_and_prove(domain, &msgs[..1]).unwrap();
+    assert!(vk.statement(domain, &ct2, &proof).is_err());
+}
+
+#[test]
+fn verify_explain() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let vk = ek.verification_key();
+
+    let domain = b"verify_explain_test";
+    let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+    let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+    let trace = vk.verify_explain(domain, &ct, &proof);
+    assert!(trace.is_valid());
+    assert_eq!(trace.challenge, trace.proof_challenge);
+    assert_eq!(trace.slots, vec![0, 1]);
+
+    // The recomputed values are the statement's equations
+    let mut values = vec![trace.u.clone()];
+    values.extend(trace.e.iter().cloned());
+    values.push(trace.v.clone());
+    assert_eq!(
+        vk.statement(domain, &ct, &proof).unwrap().evaluate(),
+        values
+    );
+    assert_eq!(trace.transcript[0].label, "dom-sep");
+    let last = trace.transcript.last().unwrap();
+    assert_eq!(last.label, "ciphertext_test.v");
+    assert_eq!(last.message, trace.v.to_bytes());
+
+    let json = serde_json::to_string(&trace).unwrap();
+    assert_eq!(trace, serde_json::from_str(&json).unwrap());
+
+    // A different nonce changes the hash and every later message
+    let other = vk.verify_explain(b"another nonce", &ct, &proof);
+    assert_eq!(other.failure.as_deref(), Some("Invalid proof"));
+    assert_ne!(other.hash, trace.hash);
+    assert_eq!(other.u, trace.u);
+    assert_ne!(other.challenge, other.proof_challenge);
+    let (ct2, _) = ek.encrypt_and_prove(domain, &msgs[..1]).unwrap();
+    assert!(!vk.verify_explain(domain, &ct2, &proof).is_valid());
+}
+
+#[test]
+fn verify_cancellable() {
+    use std::cell::Cell;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let vk = ek.verification_key();
+    let domain = b"verify_cancellable_test";
+    let msgs = vec![BigNumber::from(1), BigNumber::from(2), BigNumber::from(3)];
+    let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(600);
+    assert!(vk
+        .verify_cancellable(domain, &ct, &proof, &deadline)
+        .is_ok());
+    assert_eq!(
+        vk.verify_cancellable(b"other", &ct, &proof, &deadline),
+        Err(VerencError::InvalidProof)
+    );
+    assert_eq!(
+        vk.verify_cancellable(domain, &ct, &proof, &Instant::now()),
+        Err(VerencError::Cancelled)
+    );
+    let stop = AtomicBool::new(true);
+    assert_eq!(
+        vk.verify_cancellable(domain, &ct, &proof, &stop),
+        Err(VerencError::Cancelled)
+    );
+
+    // Polled before u, each e and v
+    let polls = Cell::new(0);
+    let count = || {
+        polls.set(polls.get() + 1);
+        false
+    };
+    assert!(vk.verify_cancellable(domain, &ct, &proof, &count).is_ok());
+    assert_eq!(polls.get(), msgs.len() + 2);
+    polls.set(0);
+    let after_two = || {
+        polls.set(polls.get() + 1);
+        polls.get() > 2
+    };
+    assert_eq!(
+        vk.verify_cancellable(domain, &ct, &proof, &after_two),
+        Err(VerencError::Cancelled)
+    );
+    assert_eq!(polls.get(), 3);
+}
+
+#[test]
+fn streaming_verifier() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let vk = VerificationKey::from(&ek);
+
+    let nonce = b"streaming_verifier_test";
+    let msgs = (1..4).map(BigNumber::from).collect::<Vec<_>>();
+    for options in [
+        ProofOptions::default(),
+        ProofOptions {
+            prehash_e: true,
+            version: ProtocolVersion::V2,
+        },
+    ] {
+        let view = vk.view().with_options(options);
+        let (ct, proof) = ek
+            .view()
+            .with_options(options)
+            .encrypt_and_prove(nonce, &msgs)
+            .unwrap();
+        // Split the parts like a stream would deliver them
+        let ct_json = serde_json::to_value(&ct).unwrap();
+        let proof_json = serde_json::to_value(&proof).unwrap();
+        let part =
+            |v: &serde_json::Value| -> BigNumber { serde_json::from_value(v.clone()).unwrap() };
+        let e = ct_json["e"].as_array().unwrap();
+        let m = proof_json["m"].as_array().unwrap();
+
+        let stream = |m: &[serde_json::Value]| {
+            let mut verifier = Verifier::new(view, nonce);
+            verifier.begin(
+                part(&ct_json["u"]),
+                part(&ct_json["v"]),
+                part(&proof_json["challenge"]),
+                part(&proof_json["r"]),
+            )?;
+            for (e, m) in e.iter().zip(m.iter()) {
+                verifier.push(part(e), &part(m))?;
+            }
+            verifier.finish()
+        };
+        assert!(stream(m).is_ok());
+        let mut tampered = m.clone();
+        tampered.swap(0, 1);
+        assert!(stream(&tampered).is_err());
+        assert!(stream(&m[..2]).is_err());
+        // A challenge off by one fails the final comparison
+        let mut verifier = Verifier::new(view, nonce);
+        verifier
+            .begin(
+                part(&ct_json["u"]),
+                part(&ct_json["v"]),
+                part(&proof_json["challenge"]) + BigNumber::one(),
+                part(&proof_json["r"]),
+            )
+            .unwrap();
+        for (e, m) in e.iter().zip(m.iter()) {
+            verifier.push(part(e), &part(m)).unwrap();
+        }
+        assert!(verifier.finish().is_err());
+    }
+
+    let mut verifier = Verifier::new(vk.view(), nonce);
+    assert!(verifier.push(BigNumber::one(), &BigNumber::one()).is_err());
+    assert!(Verifier::new(vk.view(), nonce).finish().is_err());
+}
+
+#[test]
+fn verify_strict() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let vk = ek.verification_key();
+    let nonce = b"verify_strict";
+    let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+    let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+    let (_, short) = ek.encrypt_and_prove(nonce, &msgs[..1]).unwrap();
+
+    assert!(vk.verify_strict(nonce, 2, &ct, &proof).is_ok());
+    assert!(ek.verify_strict(nonce, 2, &ct, &proof).is_ok());
+    assert_eq!(
+        vk.verify_strict(nonce, 4, &ct, &proof),
+        Err(StrictVerifyError::TooManyMessages {
+            declared: 4,
+            supported: 3
+        })
+    );
+    assert_eq!(
+        vk.verify_strict(nonce, 3, &ct, &proof),
+        Err(StrictVerifyError::CiphertextLength {
+            declared: 3,
+            actual: 2
+        })
+    );
+    assert_eq!(
+        vk.verify_strict(nonce, 2, &ct, &short),
+        Err(StrictVerifyError::ResponseCount {
+            declared: 2,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        vk.verify_strict(b"other", 2, &ct, &proof),
+        Err(StrictVerifyError::InvalidProof)
+    );
+    let message: String = StrictVerifyError::InvalidProof.into();
+    assert_eq!(message, "Invalid proof");
+}
+
+#[test]
+fn proof_response_ranges() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let vk = ek.verification_key();
+    let nonce = b"proof_response_ranges";
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+    let (ct, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+    assert!(vk.verify(nonce, &ct, &proof).is_ok());
+
+    let shifted = |path: &[&str], delta: &BigNumber| {
+        let mut value = serde_json::to_value(&proof).unwrap();
+        let mut field = &mut value;
+        for p in path {
+            field = match p.parse::<usize>() {
+                Ok(i) => &mut field[i],
+                Err(_) => &mut field[*p],
+            };
+        }
+        let n: BigNumber = serde_json::from_value(field.clone()).unwrap();
+        *field = serde_json::to_value(&n + delta).unwrap();
+        serde_json::from_value::<VerifiableEncryptionProof>(value).unwrap()
+    };
+    let nn = group.nn().clone();
+    for (path, delta) in [
+        (vec!["challenge"], BigNumber::one() << 256),
+        (vec!["r"], nn.clone()),
+        (vec!["m", "1"], nn.clone()),
+        (vec!["m", "0"], BigNumber::zero() - (&nn << 1)),
+    ] {
+        let forged = shifted(&path, &delta);
+        assert!(vk.verify(nonce, &ct, &forged).is_err());
+    }
+
+    let blindings = vec![group.random_for_encrypt(), group.n() >> 2];
+    assert!(ek
+        .encrypt_and_prove_blindings(nonce, &msgs, &blindings)
+        .is_err());
+}
+
+#[test]
+fn proof_id() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let nonce = b"proof_id";
+    let msgs = vec![BigNumber::from(3), BigNumber::from(4)];
+    let (_, proof) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+    let (_, other) = ek.encrypt_and_prove(nonce, &msgs).unwrap();
+    assert_ne!(proof.id(), other.id());
+
+    let json: VerifiableEncryptionProof =
+        serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+    let hex = VerifiableEncryptionProof::from_checked_hex(&proof.to_checked_hex()).unwrap();
+    assert_eq!(json.id(), proof.id());
+    assert_eq!(hex.id(), proof.id());
+
+    // Negating a response changes the id
+    let mut value = serde_json::to_value(&proof).unwrap();
+    let m: BigNumber = serde_json::from_value(value["m"][0].clone()).unwrap();
+    value["m"][0] = serde_json::to_value(BigNumber::zero() - m).unwrap();
+    let negated: VerifiableEncryptionProof = serde_json::from_value(value).unwrap();
+    assert_ne!(negated.id(), proof.id());
+}
+
+#[test]
+fn verify_with_policy() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let tenant_key = ek.verification_key().fingerprint();
+    let label = Label::new(b"tenant-a").field(b"purpose", b"kyc");
+    let nonce = label.to_bytes();
+    let msgs = vec![BigNumber::from(1), BigNumber::from(2)];
+    let (ct, proof) = ek.encrypt_and_prove(&nonce, &msgs).unwrap();
+
+    let policy = |request: &VerificationRequest<'_>| {
+        if request.key_fingerprint() != &tenant_key {
+            return Err("Key is not allowed for this tenant".to_string());
+        }
+        match request.label() {
+            Some(label) if label.context() == b"tenant-a" => {}
+            _ => return Err("Label is not allowed for this tenant".to_string()),
+        }
+        if request.num_messages() > 2 {
+            return Err("Too many messages".to_string());
+        }
+        Ok(())
+    };
+    assert!(ek.verify_with_policy(&policy, &nonce, &ct, &proof).is_ok());
+    let bound = KeyBoundProof::new(ek.verification_key(), proof.clone());
+    let mut ring = KeyRing::new();
+    ring.insert(ek.clone(), 0, None);
+    assert!(ring
+        .verify_any_with_policy(&policy, 1, &nonce, &ct, &bound)
+        .is_ok());
+
+    let other = Label::new(b"tenant-b").to_bytes();
+    let (ct, proof) = ek.encrypt_and_prove(&other, &msgs).unwrap();
+    assert_eq!(
+        ek.verify_with_policy(&policy, &other, &ct, &proof),
+        Err(VerencError::Other(
+            "Label is not allowed for this tenant".to_string()
+        ))
+    );
+    let three = vec![BigNumber::from(1), BigNumber::from(2), BigNumber::from(3)];
+    let (ct, proof) = ek.encrypt_and_prove(&nonce, &three).unwrap();
+    assert_eq!(
+        ek.verification_key()
+            .verify_with_policy(&policy, &nonce, &ct, &proof),
+        Err(VerencError::Other("Too many messages".to_string()))
+    );
+    let (ek2, _) = group.new_keys(1).unwrap();
+    let (ct, proof) = ek2
+        .encrypt_and_prove(&nonce, &[BigNumber::from(1)])
+        .unwrap();
+    assert!(ek2
+        .verify_with_policy(&policy, &nonce, &ct, &proof)
+        .is_err());
+}
+
+#[test]
+fn precomputed_key() {
+    use std::sync::Arc;
+
+    let group = test_group();
+    let (ek, _) = group.new_keys(2).unwrap();
+    let msgs = vec![BigNumber::from(5), BigNumber::from(6)];
+    let (ct, proof) = ek.encrypt_and_prove(b"precomputed", &msgs).unwrap();
+
+    let mut key = PrecomputedKey::new(&ek);
+    assert!(!key.is_warm(BaseId::G));
+    assert!(key.verify(b"precomputed", &ct, &proof).is_ok());
+
+    // Nothing is built when one base is unknown
+    assert!(key.warm(&[BaseId::G, BaseId::Y1(2)]).is_err());
+    assert!(!key.is_warm(BaseId::G));
+
+    key.warm(&[BaseId::G, BaseId::Y1(1)]).unwrap();
+    assert!(key.is_warm(BaseId::G));
+    assert!(!key.is_warm(BaseId::Y1(0)));
+    assert!(key.is_warm(BaseId::Y1(1)));
+    assert!(key.verify(b"precomputed", &ct, &proof).is_ok());
+    assert!(key.verify(b"other", &ct, &proof).is_err());
+
+    // Warm a clone and swap it in
+    let shared = Arc::new(key);
+    let mut next = (*shared).clone();
+    next.warm_all();
+    assert_eq!(next.bases(), vec![BaseId::G, BaseId::Y1(0), BaseId::Y1(1)]);
+    assert!(next.bases().into_iter().all(|b| next.is_warm(b)));
+    assert!(!shared.is_warm(BaseId::Y1(0)));
+    let shared = Arc::new(next);
+    assert!(shared.verify(b"precomputed", &ct, &proof).is_ok());
+    let (_, other) = ek.encrypt_and_prove(b"precomputed", &msgs).unwrap();
+    assert!(shared.verify(b"precomputed", &ct, &other).is_err());
+}
+
+#[test]
+fn precomputed_encryption_key() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(2).unwrap();
+    let precomputed = ek.precompute().unwrap();
+    let msgs = vec![BigNumber::from(5), group.n() - BigNumber::one()];
+
+    // The tables give exactly the ciphertext plain exponentiation does
+    let max: BigNumber = (group.n() >> 2) - BigNumber::one();
+    let mut randomness = (1..40u64).map(BigNumber::from).collect::<Vec<_>>();
+    randomness.extend([
+        max.clone(),
+        &max - BigNumber::one(),
+        group.random_for_encrypt(),
+    ]);
+    for r in &randomness {
+        assert_eq!(
+            precomputed
+                .view()
+                .encrypt_with_randomness(b"tables", &msgs, r)
+                .unwrap(),
+            ek.encrypt_with_randomness(b"tables", &msgs, r).unwrap(),
+        );
+    }
+
+    let ct = precomputed.encrypt(b"tables", &msgs).unwrap();
+    assert_eq!(dk.decrypt(b"tables", &ct).unwrap(), msgs);
+    let (ct, proof) = precomputed.encrypt_and_prove(b"tables", &msgs).unwrap();
+    ek.verify(b"tables", &ct, &proof).unwrap();
+    assert_eq!(dk.decrypt(b"tables", &ct).unwrap(), msgs);
+    let (ct, proof) = precomputed
+        .view()
+        .encrypt_and_prove_at_indices(b"tables", &[(1, BigNumber::from(9))])
+        .unwrap();
+    ek.verify_at_indices(b"tables", &[1], &ct, &proof).unwrap();
+
+    // Clones share the tables
+    let clone = precomputed.clone();
+    assert_eq!(
+        clone.encryption_key().verification_key().fingerprint(),
+        ek.verification_key().fingerprint()
+    );
+    assert!(clone.encrypt(b"tables", &msgs).is_ok());
+
+    // A key from elsewhere whose y1 is not a unit fails instead of panicking
+    let mut json = serde_json::to_value(&ek).unwrap();
+    json["y1"][1] = serde_json::to_value(BigNumber::zero()).unwrap();
+    let malformed: EncryptionKey = serde_json::from_value(json).unwrap();
+    assert!(malformed.precompute().is_err());
+    assert!(malformed
+        .precompute_tuned(Precompute::tuned_for(1))
+        .is_err());
+}
+
+#[test]
+fn precompute_tuned() {
+    let group = test_group();
+    let (ek, dk) = group.new_keys(1).unwrap();
+
+    // More operations pick wider windows and a memory cap narrower ones
+    let few = PrecomputedKey::tuned(&ek, Precompute::tuned_for(1)).unwrap();
+    let many = PrecomputedKey::tuned(&ek, Precompute::tuned_for(100_000)).unwrap();
+    assert!(few.window_bits() < many.window_bits());
+    let capped = Precompute::tuned_for(100_000).with_max_bytes(16 << 20);
+    assert!(PrecomputedKey::tuned(&ek, capped).unwrap().window_bits() < many.window_bits());
+    let tiny = Precompute::tuned_for(1).with_max_bytes(1024);
+    assert!(PrecomputedKey::tuned(&ek, tiny).is_err());
+    assert!(ek.precompute_tuned(tiny).is_err());
+
+    // Tables of every width give the same results
+    let msgs = vec![BigNumber::from(5)];
+    let max: BigNumber = (group.n() >> 2) - BigNumber::one();
+    for num_ops in [0, 1, 10, 30] {
+        let precompute = Precompute::tuned_for(num_ops);
+        let prover = ek.precompute_tuned(precompute).unwrap();
+        for r in [group.random_for_encrypt(), max.clone()] {
+            assert_eq!(
+                prover
+                    .view()
+                    .encrypt_with_randomness(b"tuned", &msgs, &r)
+                    .unwrap(),
+                ek.encrypt_with_randomness(b"tuned", &msgs, &r).unwrap(),
+            );
+        }
+        let (ct, proof) = prover.encrypt_and_prove(b"tuned", &msgs).unwrap();
+        assert_eq!(dk.decrypt(b"tuned", &ct).unwrap(), msgs);
+
+        let mut verifier = PrecomputedKey::tuned(&ek, precompute).unwrap();
+        verifier.warm_all();
+        assert!(verifier.verify(b"tuned", &ct, &proof).is_ok());
+        assert!(verifier.verify(b"other", &ct, &proof).is_err());
+    }
+}
+
+#[test]
+fn verify_all() {
+    let group = test_group();
+    let (ek, _) = group.new_keys(3).unwrap();
+    let one = vec![BigNumber::from(1)];
+    let three = vec![BigNumber::from(2), BigNumber::from(3), BigNumber::from(4)];
+    let (ct1, proof1) = ek.encrypt_and_prove(b"batch 1", &one).unwrap();
+    let (ct2, proof2) = ek.encrypt_and_prove(b"batch 2", &three).unwrap();
+    let (ct3, proof3) = ek.encrypt_and_prove(b"batch 3", &three).unwrap();
+
+    assert!(ek.verify_all(&[]).is_ok());
+    assert!(ek.verify_all(&[(b"batch 1", &ct1, &proof1)]).is_ok());
+    assert!(ek
+        .verify_all(&[
+            (b"batch 1", &ct1, &proof1),
+            (b"batch 2", &ct2, &proof2),
+            (b"batch 3", &ct3, &proof3),
+        ])
+        .is_ok());
+
+    let failures = ek
+        .verification_key()
+        .verify_all(&[
+            (b"batch 1", &ct1, &proof1),
+            (b"batch 2", &ct2, &proof3),
+            (b"batch 3", &ct3, &proof3),
+            (b"other", &ct1, &proof1),
+        ])
+        .unwrap_err();
+    assert_eq!(
+        failures
+            .iter()
+            .map(|f| (f.index, f.source.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, VerencError::InvalidProof),
+            (3, VerencError::InvalidProof)
+        ]
+    );
+    assert_eq!(failures[0].ciphertext, ct2.digest());
+    assert_eq!(failures[1].label, b"other".to_vec());
+
+    let (narrow, _) = group.new_keys(1).unwrap();
+    let failures = narrow
+        .verify_all(&[(b"batch 1", &ct1, &proof1), (b"batch 2", &ct2, &proof2)])
+        .unwrap_err();
+    assert_eq!(failures.len(), 2);
+    assert!(matches!(
+        failures[1].source,
+        VerencError::TooManyMessages { .. }
+    ));
+}
+
+#[test]
+fn batch_item_error() {
+    use std::error::Error;
+
+    let err = BatchItemError {
+        index: 7,
+        ciphertext: [0xab; 32],
+        label: b"block\n42".to_vec(),
+        source: VerencError::InvalidProof,
+    };
+    let context = format!(
+        "batch item 7 with ciphertext {} and label \"block\\n42\" failed",
+        "ab".repeat(32)
+    );
+    assert_eq!(err.to_string(), context);
+    assert_eq!(err.source().unwrap().to_string(), "Invalid proof");
+    assert_eq!(String::from(err), format!("{}: Invalid proof", context));
+}
@@ -0,0 +1,48 @@
+use verenc::*;
+
+// A one slot key over a 512-bit modulus with a proof for the nonce `verify-only`,
+// kept as serialized values so this also runs with `--features verify-only`
+const VERIFICATION_KEY: &str = r#"{"y1":["76b7ff1cddcb448495648159b7a79dd1e2d3cc33d102b0da6a8c3f173de5f09541b6985d27d61c6ed858a773e7d1c3da35a4dd878b2196b72dca5c7ba2d5b1c7637275ee28772294900024bf44a2f725aa7b007b2c6380e49ef1563aefe3cc0818fde559b7e079e924e933af5d7934a4db1814249564bf89c222999bbfa37dd3"],"y2":"31edcd52d4f75897b997a67f7d908e10ece303bce83959cee1273ed26bc0fb4c9187cb06e2a3f2069d221dbad807d49126870300970307ce34114a55eb620aa650f8bda88a468713d9251b9cf5b1c29f86080e873090f063d208d4b96a9094ce01c14e182c81d3b42ccc5f78ad92a311daea7e1185f9a3c3d420eb9a30dd4d7a","y3":"a1251c108d7efffff432ee91bab150ccbf4c78ac7a62d78a10ef9f3627efc55082e703222bc19b77d5696a5e416a6e94028ed1675764fc4d4899aa6650467f0ac0fbca91ec4aecd18a2f2ff3d2021ccaf188c4eb1d61fec3a243652fe54c92e8f3b4987ff803d0211057b1856e33482b502e79fc054ae764b0560c9a3d863b27","group":{"g":"31dce3f5be1a5f8f47529e203834757780c42c62d55c25c8b5f65e58a916539388b13982931da46cce56bfda94dfa4f109073896fa21968981b17f1f69fa1dc589721478e44453c5da30f460f88cb4356c2d1526d646c8a0d713620b61a7e2c0341927991c5bf96c59b3f4316982cec2b25d29a7121ea783d0ef56647d1d1a5c","n":"ccf9e3b135f832923697085616b6d713e946be7f9f619920a793d39aad7fc06c488f7008ea98793243ffa51e64aaa17ec8f0222d454fe5a7078c842724255e99","modulus_bits":512}}"#;
+const CIPHERTEXT: &str = r#"{"u":"95ffefb7a912059ee377bbbc8496d98910076c155aee21b3667b08f157fdd4bde2f5c53d43914dc38b6f5969831fb710bcf0308e8f9b1c21b647a03c54dca847f745674fcf09d41eca7e159516d86036691fc367579afdac3e5f0734f9a9b56a4a4f8729f4e6d2d8af046b838a0dd3bd277a98171685bdac55cfed8df973f49e","v":"1e395e0b655b374295f45b2af2a64761ae901de79230771226fd19a1de6caf88567ab8c8fd4a458434c5a940cdc227cb1704c6421d446d682cc6ce0b5f5e0c7d46d40a8bde56a02ed0be524e9cd94053e8580ef0ff0d33a617f9456ad4abd6df1c81b189a5703d5c0476a929083219aa3c818695672e6399bd9358fba04696fd","e":["32e2875ad27210b59138392c47ab4cfe5b856e100692e7fbb34e22cdfb457086539ec82e8d855ba3505b5e5b717283e5dac8ba5759db36b755f03e1eb79fca6decb80565ba1105cb6bb23f0b43e76e58acf4edb7f071b12b97d911117a506ab2387301f4019d465d15ba26e9b6cdcc3d7c480ddf556013decd8507a6122535f4"]}"#;
+const PROOF: &str = r#"{"challenge":"bc67edadcd450222a0753a8b4533a3d4561e85143c7c7d57dc6d57433b25322e","r":"-619a984a38850d57511d71fc289872a8c2d0c6ce65d8db549b7fe22c4ccc8907b3a27605d523354305f897e212904b59f13d9bedefe18da42e6dff4185e6b0b6d1c9c1cbc4cf24ff451982d4f413d845027a2731bffc856dfbe1f0e86d813f0","m":["-1d5dc5f2b2a9228fc6dbfc01b8d82f26c8ec449bc32456ec3e2e2c9612758b3f0e83f9d948c5406c72038cd8dff528a496aca5b5855d45e580c91eb32df427aea95a58c109ff0c4487e32e9c257ebebfc23a93d09cc1063a342d14f49ab624a7"]}"#;
+
+fn fixtures() -> (
+    VerificationKey,
+    VerifiableCipherText,
+    VerifiableEncryptionProof,
+) {
+    (
+        serde_json::from_str(VERIFICATION_KEY).unwrap(),
+        serde_json::from_str(CIPHERTEXT).unwrap(),
+        serde_json::from_str(PROOF).unwrap(),
+    )
+}
+
+#[test]
+fn verify_serialized() {
+    let (vk, ct, proof) = fixtures();
+    assert!(vk.verify(b"verify-only", &ct, &proof).is_ok());
+    assert!(vk.verify_strict(b"verify-only", 1, &ct, &proof).is_ok());
+    assert!(vk.verify(b"another nonce", &ct, &proof).is_err());
+}
+
+#[test]
+fn verify_serialized_encryption_key() {
+    let (vk, ct, proof) = fixtures();
+    let ek: EncryptionKey = serde_json::from_str(VERIFICATION_KEY).unwrap();
+    assert_eq!(ek.verification_key().fingerprint(), vk.fingerprint());
+    assert!(ek.verify(b"verify-only", &ct, &proof).is_ok());
+}
+
+#[test]
+fn verify_checked_hex() {
+    let (vk, ct, proof) = fixtures();
+    let encoded = vk.to_checked_hex();
+    assert!(encoded.starts_with("veverificationkey:"));
+    let decoded = VerificationKey::from_checked_hex(&encoded).unwrap();
+    assert_eq!(decoded.fingerprint(), vk.fingerprint());
+    let ct = VerifiableCipherText::from_checked_hex(&ct.to_checked_hex()).unwrap();
+    let proof = VerifiableEncryptionProof::from_checked_hex(&proof.to_checked_hex()).unwrap();
+    assert!(decoded.verify(b"verify-only", &ct, &proof).is_ok());
+    assert!(VerificationKey::from_checked_hex(&encoded[..encoded.len() - 2]).is_err());
+}
@@ -0,0 +1,25 @@
+//! Run with `wasm-bindgen-test-runner` installed as the wasm32 runner:
+//!
+//! ```sh
+//! cargo test --test wasm --target wasm32-unknown-unknown --no-default-features --features wasm
+//! ```
+#![cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+
+mod common;
+
+use common::test_group;
+use unknown_order::BigNumber;
+use verenc::*;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn encrypt_and_prove_verify() {
+    let (ek, dk) = test_group().new_keys(2).unwrap();
+    let domain = b"wasm_test";
+    let msgs = [BigNumber::from(7), BigNumber::from(8)];
+    let (ct, proof) = ek.encrypt_and_prove(domain, &msgs).unwrap();
+    let vk = VerificationKey::from(&ek);
+    assert!(vk.verify(domain, &ct, &proof).is_ok());
+    assert!(vk.verify(b"another nonce", &ct, &proof).is_err());
+    assert_eq!(dk.decrypt(domain, &ct).unwrap(), msgs);
+}